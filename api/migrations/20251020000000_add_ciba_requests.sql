@@ -0,0 +1,26 @@
+-- ============================================================================
+-- CLIENT-INITIATED BACKCHANNEL AUTHENTICATION (CIBA)
+-- Backchannel requests reuse the device_codes state machine:
+-- device_code doubles as the auth_req_id handed to the service, and the
+-- user approves or denies from a signed-in device instead of entering a code
+-- ============================================================================
+
+-- Distinguish device flow rows from CIBA rows
+ALTER TABLE device_codes ADD COLUMN flow_type TEXT NOT NULL DEFAULT 'device'; -- 'device', 'ciba'
+
+-- The user the service asked to authenticate (resolved from login_hint)
+ALTER TABLE device_codes ADD COLUMN login_hint_user_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+
+-- Message shown to the user on the approval screen (e.g. "Call ref 4821")
+ALTER TABLE device_codes ADD COLUMN binding_message TEXT;
+
+-- Bearer token the service wants us to present on the ping callback
+ALTER TABLE device_codes ADD COLUMN client_notification_token TEXT;
+
+-- Status now also includes 'denied' for rejected CIBA requests
+ALTER TABLE device_codes ADD COLUMN created_at DATETIME;
+
+-- Services register where they receive CIBA ping callbacks (NULL = poll only)
+ALTER TABLE services ADD COLUMN ciba_notification_endpoint TEXT;
+
+CREATE INDEX idx_device_codes_login_hint_user ON device_codes(login_hint_user_id, status);
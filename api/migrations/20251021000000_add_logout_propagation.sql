@@ -0,0 +1,38 @@
+-- ============================================================================
+-- OIDC FRONT-CHANNEL & BACK-CHANNEL LOGOUT
+-- Services register logout endpoints; revoked sessions are propagated to them
+-- ============================================================================
+
+-- Logout endpoints registered per service
+ALTER TABLE services ADD COLUMN backchannel_logout_uri TEXT;
+ALTER TABLE services ADD COLUMN frontchannel_logout_uri TEXT;
+ALTER TABLE services ADD COLUMN post_logout_redirect_uris TEXT; -- JSON array
+
+-- Client metadata columns expected by the Session model
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
+ALTER TABLE sessions ADD COLUMN ip_address TEXT;
+
+-- Refresh tokens that have been rotated out, used to detect reuse and revoke the family
+CREATE TABLE rotated_refresh_tokens (
+    token_hash TEXT PRIMARY KEY, -- SHA256 of the old refresh token
+    session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    rotated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Outbox of signed logout tokens waiting to be POSTed to services
+CREATE TABLE backchannel_logout_deliveries (
+    id TEXT PRIMARY KEY,
+    service_id TEXT NOT NULL REFERENCES services(id) ON DELETE CASCADE,
+    session_id TEXT NOT NULL, -- session is already deleted, kept for tracing
+    logout_uri TEXT NOT NULL,
+    logout_token TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending', -- 'pending', 'delivered', 'failed'
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at DATETIME NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_rotated_refresh_tokens_session ON rotated_refresh_tokens(session_id);
+CREATE INDEX idx_backchannel_logout_pending ON backchannel_logout_deliveries(status, next_attempt_at);
+CREATE INDEX idx_backchannel_logout_service ON backchannel_logout_deliveries(service_id);
@@ -0,0 +1,4 @@
+-- Track when each session was last used so users can review their active sessions
+ALTER TABLE sessions ADD COLUMN last_used_at DATETIME;
+
+CREATE INDEX idx_sessions_user ON sessions(user_id);
@@ -0,0 +1,27 @@
+-- ============================================================================
+-- CROSS-SERVICE SSO SESSIONS
+-- A browser-level session (HttpOnly cookie on the API domain) that lets users
+-- sign into sibling services of the same organization without going back to
+-- the upstream provider
+-- ============================================================================
+
+-- One row per (browser, organization); the browser holds a single cookie whose
+-- hash is shared by all of its organization grants
+CREATE TABLE sso_sessions (
+    token_hash TEXT NOT NULL, -- SHA256 of the cookie value
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL, -- provider used for the original upstream login
+    ip_address TEXT,
+    user_agent TEXT,
+    created_at DATETIME NOT NULL,
+    last_used_at DATETIME,
+    expires_at DATETIME NOT NULL,
+    PRIMARY KEY (token_hash, org_id)
+);
+
+-- SSO session lifetime per organization (NULL = platform default, 0 = SSO disabled)
+ALTER TABLE organizations ADD COLUMN sso_session_lifetime_minutes INTEGER;
+
+CREATE INDEX idx_sso_sessions_user ON sso_sessions(user_id);
+CREATE INDEX idx_sso_sessions_expires ON sso_sessions(expires_at);
@@ -0,0 +1,12 @@
+-- ============================================================================
+-- LOGIN CSRF PROTECTION
+-- Ties each OAuth state to the browser that started the flow and stores the
+-- OIDC nonce expected in the provider's ID token
+-- ============================================================================
+
+-- SHA256 of the browser binding id carried in the signed oauth_binding cookie
+-- (NULL for flows started from an authenticated API call, e.g. identity linking)
+ALTER TABLE oauth_states ADD COLUMN browser_binding TEXT;
+
+-- Nonce sent to Google/Microsoft when requesting an ID token
+ALTER TABLE oauth_states ADD COLUMN nonce TEXT;
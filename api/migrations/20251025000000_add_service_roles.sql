@@ -0,0 +1,48 @@
+-- ============================================================================
+-- END-USER ROLES AND PERMISSIONS
+-- Organizations define roles (each a named set of permissions) per service and
+-- assign them to end users directly or through groups. The effective roles and
+-- permissions are embedded in service tokens as `roles` / `permissions` claims.
+-- ============================================================================
+
+-- Groups of end users within an organization
+CREATE TABLE user_groups (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at DATETIME NOT NULL,
+    UNIQUE(org_id, name)
+);
+
+CREATE TABLE user_group_members (
+    group_id TEXT NOT NULL REFERENCES user_groups(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at DATETIME NOT NULL,
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE TABLE service_roles (
+    id TEXT PRIMARY KEY,
+    service_id TEXT NOT NULL REFERENCES services(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    permissions TEXT NOT NULL DEFAULT '[]', -- JSON array of permission strings
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE(service_id, name)
+);
+
+-- A role is assigned to exactly one user or one group
+CREATE TABLE service_role_assignments (
+    id TEXT PRIMARY KEY,
+    role_id TEXT NOT NULL REFERENCES service_roles(id) ON DELETE CASCADE,
+    user_id TEXT REFERENCES users(id) ON DELETE CASCADE,
+    group_id TEXT REFERENCES user_groups(id) ON DELETE CASCADE,
+    created_at DATETIME NOT NULL,
+    CHECK ((user_id IS NULL) != (group_id IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_service_role_assignments_user ON service_role_assignments(role_id, user_id) WHERE user_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_service_role_assignments_group ON service_role_assignments(role_id, group_id) WHERE group_id IS NOT NULL;
+CREATE INDEX idx_user_group_members_user ON user_group_members(user_id);
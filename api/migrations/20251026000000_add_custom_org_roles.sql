@@ -0,0 +1,19 @@
+-- ============================================================================
+-- CUSTOM ORGANIZATION ROLES
+-- Organizations can define roles beyond the built-in owner/admin/member, each a
+-- named set of permissions from the platform catalogue. Memberships and
+-- invitations reference a role by name; built-in role names are reserved.
+-- ============================================================================
+
+CREATE TABLE org_roles (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    permissions TEXT NOT NULL DEFAULT '[]', -- JSON array of catalogue permissions
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE(org_id, name)
+);
+
+CREATE INDEX idx_memberships_org_role ON memberships(org_id, role);
@@ -0,0 +1,29 @@
+-- ============================================================================
+-- PERSONAL ACCESS TOKENS AND ORGANIZATION API KEYS
+-- Long-lived bearer credentials for automation. Only a SHA-256 hash of the
+-- token is stored; the plaintext is shown once at creation.
+--   personal: acts as the user who created it
+--   org:      acts as a dedicated service-account user holding `role` in the
+--             organization, so it keeps working when its creator leaves
+-- ============================================================================
+
+CREATE TABLE api_tokens (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL CHECK (kind IN ('personal', 'org')),
+    name TEXT NOT NULL,
+    token_prefix TEXT NOT NULL, -- leading characters of the token, for display
+    token_hash TEXT NOT NULL UNIQUE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE, -- principal the token acts as
+    org_id TEXT REFERENCES organizations(id) ON DELETE CASCADE, -- org keys only
+    role TEXT, -- org keys only: built-in or custom org role
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    scopes TEXT NOT NULL DEFAULT '[]', -- JSON array: read, write, platform
+    expires_at DATETIME NOT NULL,
+    last_used_at DATETIME,
+    revoked_at DATETIME,
+    created_at DATETIME NOT NULL,
+    CHECK ((kind = 'org') = (org_id IS NOT NULL AND role IS NOT NULL))
+);
+
+CREATE INDEX idx_api_tokens_user ON api_tokens(user_id);
+CREATE INDEX idx_api_tokens_org ON api_tokens(org_id) WHERE org_id IS NOT NULL;
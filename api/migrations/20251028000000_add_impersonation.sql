@@ -0,0 +1,11 @@
+-- ============================================================================
+-- USER IMPERSONATION
+-- Platform owners and org members holding `impersonate_users` can open a
+-- short-lived session as another user. The session records who is really
+-- acting so the impersonated user can see it in their session list.
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN impersonator_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE sessions ADD COLUMN impersonation_reason TEXT;
+
+CREATE INDEX idx_sessions_impersonator ON sessions(impersonator_id) WHERE impersonator_id IS NOT NULL;
@@ -0,0 +1,9 @@
+-- ============================================================================
+-- DATA EXPORT AND ERASURE
+-- End-user subscriptions remember their Stripe subscription so a deletion
+-- request can cancel billing before the user's data is removed.
+-- ============================================================================
+
+ALTER TABLE subscriptions ADD COLUMN stripe_subscription_id TEXT;
+
+CREATE INDEX idx_subscriptions_stripe ON subscriptions(stripe_subscription_id) WHERE stripe_subscription_id IS NOT NULL;
@@ -0,0 +1,21 @@
+-- ============================================================================
+-- USER PROFILES
+-- Each identity keeps the profile its provider reported at the last login.
+-- The user carries a primary profile, copied from `primary_identity_id` on
+-- every login with that identity, or edited directly (which detaches it).
+-- ============================================================================
+
+ALTER TABLE identities ADD COLUMN name TEXT;
+ALTER TABLE identities ADD COLUMN given_name TEXT;
+ALTER TABLE identities ADD COLUMN family_name TEXT;
+ALTER TABLE identities ADD COLUMN avatar_url TEXT;
+ALTER TABLE identities ADD COLUMN locale TEXT;
+ALTER TABLE identities ADD COLUMN username TEXT; -- provider login, e.g. GitHub handle
+ALTER TABLE identities ADD COLUMN profile_updated_at DATETIME;
+
+ALTER TABLE users ADD COLUMN name TEXT;
+ALTER TABLE users ADD COLUMN given_name TEXT;
+ALTER TABLE users ADD COLUMN family_name TEXT;
+ALTER TABLE users ADD COLUMN avatar_url TEXT;
+ALTER TABLE users ADD COLUMN locale TEXT;
+ALTER TABLE users ADD COLUMN primary_identity_id TEXT REFERENCES identities(id) ON DELETE SET NULL;
@@ -0,0 +1,11 @@
+-- ============================================================================
+-- PROVIDER EMAIL VERIFICATION
+-- Each identity records the email its provider returned and whether the
+-- provider verified it. Only verified emails can attach a login to an
+-- existing account, and services can refuse unverified emails altogether.
+-- ============================================================================
+
+ALTER TABLE identities ADD COLUMN email TEXT;
+ALTER TABLE identities ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT 0;
+
+ALTER TABLE services ADD COLUMN require_verified_email BOOLEAN NOT NULL DEFAULT 0;
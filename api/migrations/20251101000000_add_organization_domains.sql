@@ -0,0 +1,26 @@
+-- ============================================================================
+-- ORGANIZATION DOMAINS
+-- Organizations claim email domains and prove ownership with a DNS TXT
+-- record. A verified domain can auto-join users with that email domain as
+-- members, and can force them to sign in through one provider.
+-- ============================================================================
+
+CREATE TABLE organization_domains (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    domain TEXT NOT NULL, -- lowercase, e.g. customer.com
+    verification_token TEXT NOT NULL,
+    verified_at DATETIME,
+    last_checked_at DATETIME,
+    auto_join BOOLEAN NOT NULL DEFAULT 0,
+    default_role TEXT NOT NULL DEFAULT 'member',
+    enforce_sso BOOLEAN NOT NULL DEFAULT 0,
+    required_provider TEXT, -- 'github', 'google' or 'microsoft'
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_organization_domains_org_domain ON organization_domains(org_id, domain);
+-- Only one organization can hold a domain once it is verified
+CREATE UNIQUE INDEX idx_organization_domains_verified ON organization_domains(domain)
+    WHERE verified_at IS NOT NULL;
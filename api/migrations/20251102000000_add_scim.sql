@@ -0,0 +1,25 @@
+-- ============================================================================
+-- SCIM PROVISIONING
+-- An organization's identity provider provisions people through SCIM 2.0
+-- using an organization API key with the `scim` scope. Each provisioned user
+-- is either an organization member or an end user of its services.
+-- Deactivation keeps the record so the user can be reactivated later.
+-- ============================================================================
+
+CREATE TABLE scim_users (
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    external_id TEXT, -- the identity provider's own id for the user
+    user_type TEXT NOT NULL DEFAULT 'member', -- 'member' or 'end_user'
+    role TEXT, -- organization role for members, restored on reactivation
+    active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (org_id, user_id)
+);
+
+CREATE UNIQUE INDEX idx_scim_users_external_id ON scim_users(org_id, external_id)
+    WHERE external_id IS NOT NULL;
+
+-- Groups pushed by the identity provider keep its id
+ALTER TABLE user_groups ADD COLUMN external_id TEXT;
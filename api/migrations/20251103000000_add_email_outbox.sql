@@ -0,0 +1,44 @@
+-- ============================================================================
+-- TRANSACTIONAL EMAIL
+-- Messages are rendered into the outbox in the same transaction as the change
+-- that triggers them and delivered by a background job with retries
+-- ============================================================================
+
+CREATE TABLE email_outbox (
+    id TEXT PRIMARY KEY,
+    org_id TEXT REFERENCES organizations(id) ON DELETE SET NULL,
+    template TEXT NOT NULL,
+    to_address TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body_text TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'sent', 'failed', 'bounced', 'suppressed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at DATETIME NOT NULL,
+    last_error TEXT,
+    sent_at DATETIME,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_email_outbox_pending ON email_outbox(status, next_attempt_at);
+CREATE INDEX idx_email_outbox_to ON email_outbox(to_address);
+
+-- Organization overrides of the built-in templates
+CREATE TABLE email_templates (
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    template TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body_text TEXT NOT NULL,
+    updated_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (org_id, template)
+);
+
+-- Addresses that hard-bounced; nothing more is sent to them
+CREATE TABLE email_suppressions (
+    email TEXT PRIMARY KEY COLLATE NOCASE,
+    reason TEXT NOT NULL,
+    created_at DATETIME NOT NULL
+);
@@ -0,0 +1,32 @@
+-- ============================================================================
+-- INVITE LINKS
+-- Shareable join links with a role, a usage cap, an expiry and an optional
+-- email domain restriction
+-- ============================================================================
+
+CREATE TABLE organization_invite_links (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL,
+    max_uses INTEGER NOT NULL,
+    use_count INTEGER NOT NULL DEFAULT 0,
+    allowed_domain TEXT,
+    expires_at DATETIME NOT NULL,
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at DATETIME,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_invite_links_org ON organization_invite_links(org_id);
+
+-- One row per user who joined through a link
+CREATE TABLE organization_invite_link_redemptions (
+    id TEXT PRIMARY KEY,
+    link_id TEXT NOT NULL REFERENCES organization_invite_links(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at DATETIME NOT NULL,
+    UNIQUE(link_id, user_id)
+);
+
+CREATE INDEX idx_invite_link_redemptions_user ON organization_invite_link_redemptions(user_id);
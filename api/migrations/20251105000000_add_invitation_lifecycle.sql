@@ -0,0 +1,45 @@
+-- ============================================================================
+-- INVITATION LIFECYCLE
+-- Invitations past their expiry are moved to 'expired' by a background job,
+-- 'rejected' becomes 'declined', and resends and reminders are tracked.
+-- ============================================================================
+
+-- The old UNIQUE(org_id, email, status) also covered finished invitations,
+-- so a second cancelled or expired invitation for the same email failed.
+-- Only one *pending* invitation per email and organization is enforced now.
+-- No table references organization_invitations, so the table is rebuilt
+-- inside the migration's transaction with foreign keys left on.
+
+CREATE TABLE organization_invitations_new (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    role TEXT NOT NULL DEFAULT 'member',
+    invited_by TEXT NOT NULL REFERENCES users(id),
+    status TEXT NOT NULL DEFAULT 'pending', -- 'pending', 'accepted', 'declined', 'cancelled', 'expired'
+    token TEXT NOT NULL UNIQUE,
+    expires_at DATETIME NOT NULL,
+    last_sent_at DATETIME,      -- Set when the invitation is resent
+    reminder_sent_at DATETIME,  -- Set when the expiry reminder went out
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO organization_invitations_new
+    (id, org_id, email, role, invited_by, status, token, expires_at, created_at)
+SELECT id, org_id, email, role, invited_by,
+       CASE status WHEN 'rejected' THEN 'declined' ELSE status END,
+       token, expires_at, created_at
+FROM organization_invitations;
+
+DROP TABLE organization_invitations;
+
+ALTER TABLE organization_invitations_new RENAME TO organization_invitations;
+
+CREATE INDEX idx_organization_invitations_org ON organization_invitations(org_id);
+CREATE INDEX idx_organization_invitations_email ON organization_invitations(email);
+CREATE INDEX idx_organization_invitations_token ON organization_invitations(token);
+CREATE INDEX idx_organization_invitations_status ON organization_invitations(status, expires_at);
+
+CREATE UNIQUE INDEX idx_organization_invitations_pending
+ON organization_invitations(org_id, email)
+WHERE status = 'pending';
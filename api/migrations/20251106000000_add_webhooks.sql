@@ -0,0 +1,48 @@
+-- ============================================================================
+-- OUTBOUND WEBHOOKS
+-- Organizations register endpoints that receive signed event notifications
+-- ============================================================================
+
+CREATE TABLE webhook_endpoints (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    description TEXT,
+    event_types TEXT NOT NULL,              -- JSON array of event types, or ["*"]
+    secret TEXT NOT NULL,                   -- HMAC-SHA256 signing secret
+    previous_secret TEXT,                   -- Still signed with until previous_secret_expires_at
+    previous_secret_expires_at DATETIME,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+-- An event is stored once and delivered to every subscribed endpoint
+CREATE TABLE webhook_events (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,                  -- JSON body sent to endpoints
+    created_at DATETIME NOT NULL
+);
+
+CREATE TABLE webhook_deliveries (
+    id TEXT PRIMARY KEY,
+    endpoint_id TEXT NOT NULL REFERENCES webhook_endpoints(id) ON DELETE CASCADE,
+    event_id TEXT NOT NULL REFERENCES webhook_events(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending', -- 'pending', 'delivered', 'failed'
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at DATETIME NOT NULL,
+    response_status INTEGER,
+    last_error TEXT,
+    delivered_at DATETIME,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_webhook_endpoints_org ON webhook_endpoints(org_id);
+CREATE INDEX idx_webhook_events_org ON webhook_events(org_id, created_at);
+CREATE INDEX idx_webhook_deliveries_pending ON webhook_deliveries(status, next_attempt_at);
+CREATE INDEX idx_webhook_deliveries_endpoint ON webhook_deliveries(endpoint_id, created_at);
+CREATE INDEX idx_webhook_deliveries_event ON webhook_deliveries(event_id);
@@ -0,0 +1,24 @@
+-- ============================================================================
+-- ORGANIZATION AUDIT LOG
+-- Changes made by an organization's admins, readable by those admins
+-- ============================================================================
+
+CREATE TABLE organization_audit_log (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    actor_id TEXT NOT NULL REFERENCES users(id),
+    impersonator_id TEXT REFERENCES users(id),  -- Real actor when impersonating
+    api_token_id TEXT,                          -- API key or PAT the request used, if any
+    action TEXT NOT NULL,                       -- 'service.updated', 'member.removed', ...
+    target_type TEXT NOT NULL,                  -- 'service', 'member', 'oauth_credentials', ...
+    target_id TEXT NOT NULL,
+    changes TEXT,                               -- JSON: {"field": {"before": ..., "after": ...}}
+    ip_address TEXT,
+    user_agent TEXT,
+    request_id TEXT,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_org_audit_log_org ON organization_audit_log(org_id, created_at);
+CREATE INDEX idx_org_audit_log_actor ON organization_audit_log(org_id, actor_id);
+CREATE INDEX idx_org_audit_log_target ON organization_audit_log(org_id, target_type, target_id);
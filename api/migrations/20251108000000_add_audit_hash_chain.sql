@@ -0,0 +1,80 @@
+-- ============================================================================
+-- TAMPER-EVIDENT AUDIT LOGS
+-- Each audit entry carries its position in a hash chain (one chain for the
+-- platform log, one per organization) and a hash of its content plus the
+-- previous entry's hash. Audit rows are never rewritten, so they no longer
+-- reference users: an account merge leaves the original actor id in place.
+-- ============================================================================
+
+CREATE TABLE platform_audit_log_new (
+    id TEXT PRIMARY KEY,
+    platform_owner_id TEXT NOT NULL,
+    action TEXT NOT NULL, -- 'approve_org', 'reject_org', 'suspend_org', 'set_tier', etc.
+    target_type TEXT NOT NULL, -- 'organization', 'user', 'service'
+    target_id TEXT NOT NULL,
+    metadata TEXT, -- JSON: {"old_status": "pending", "new_status": "active"}
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    seq INTEGER UNIQUE,  -- Position in the chain; NULL until sealed
+    prev_hash TEXT,      -- entry_hash of the entry at seq - 1
+    entry_hash TEXT      -- SHA-256 of this entry's content and prev_hash
+);
+
+INSERT INTO platform_audit_log_new
+    (id, platform_owner_id, action, target_type, target_id, metadata, created_at)
+SELECT id, platform_owner_id, action, target_type, target_id, metadata, created_at
+FROM platform_audit_log;
+
+DROP TABLE platform_audit_log;
+ALTER TABLE platform_audit_log_new RENAME TO platform_audit_log;
+
+CREATE INDEX idx_platform_audit_log_owner ON platform_audit_log(platform_owner_id);
+CREATE INDEX idx_platform_audit_log_target ON platform_audit_log(target_type, target_id);
+CREATE INDEX idx_platform_audit_log_created ON platform_audit_log(created_at);
+
+CREATE TABLE organization_audit_log_new (
+    id TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    actor_id TEXT NOT NULL,
+    impersonator_id TEXT,                       -- Real actor when impersonating
+    api_token_id TEXT,                          -- API key or PAT the request used, if any
+    action TEXT NOT NULL,                       -- 'service_updated', 'member_removed', ...
+    target_type TEXT NOT NULL,                  -- 'service', 'member', 'oauth_credentials', ...
+    target_id TEXT NOT NULL,
+    changes TEXT,                               -- JSON: {"field": {"before": ..., "after": ...}}
+    ip_address TEXT,
+    user_agent TEXT,
+    request_id TEXT,
+    created_at DATETIME NOT NULL,
+    seq INTEGER,                                -- Position in the organization's chain
+    prev_hash TEXT,
+    entry_hash TEXT,
+    UNIQUE(org_id, seq)
+);
+
+INSERT INTO organization_audit_log_new
+    (id, org_id, actor_id, impersonator_id, api_token_id, action, target_type, target_id,
+     changes, ip_address, user_agent, request_id, created_at)
+SELECT id, org_id, actor_id, impersonator_id, api_token_id, action, target_type, target_id,
+       changes, ip_address, user_agent, request_id, created_at
+FROM organization_audit_log;
+
+DROP TABLE organization_audit_log;
+ALTER TABLE organization_audit_log_new RENAME TO organization_audit_log;
+
+CREATE INDEX idx_org_audit_log_org ON organization_audit_log(org_id, created_at);
+CREATE INDEX idx_org_audit_log_actor ON organization_audit_log(org_id, actor_id);
+CREATE INDEX idx_org_audit_log_target ON organization_audit_log(org_id, target_type, target_id);
+
+-- Signed statements of a chain's head, so a rewritten chain cannot be passed
+-- off as the original without the JWT signing key
+CREATE TABLE audit_checkpoints (
+    id TEXT PRIMARY KEY,
+    org_id TEXT REFERENCES organizations(id) ON DELETE CASCADE, -- NULL for the platform log
+    seq INTEGER NOT NULL,
+    entry_hash TEXT NOT NULL,
+    signature TEXT NOT NULL,  -- RS256 JWT over {chain, seq, hash}
+    key_id TEXT NOT NULL,     -- JWT_KID of the signing key
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_audit_checkpoints_chain ON audit_checkpoints(org_id, seq);
@@ -0,0 +1,44 @@
+-- ============================================================================
+-- SECURITY EVENTS & SIEM EXPORT
+-- Security-relevant events that are not audit entries or logins, and the
+-- sinks audit data, logins and security events are streamed to
+-- ============================================================================
+
+CREATE TABLE security_events (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,   -- 'refresh_token_reuse', 'oauth_browser_binding_mismatch', ...
+    severity TEXT NOT NULL,     -- 'low', 'medium', 'high'
+    user_id TEXT,               -- Kept as written; no FK so events outlive the user
+    org_id TEXT,
+    ip_address TEXT,
+    user_agent TEXT,
+    details TEXT,               -- JSON object
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_security_events_created ON security_events(created_at);
+CREATE INDEX idx_security_events_user ON security_events(user_id, created_at);
+CREATE INDEX idx_security_events_type ON security_events(event_type, created_at);
+
+CREATE TABLE siem_sinks (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,         -- 'file', 'syslog', 'http'
+    config TEXT NOT NULL,       -- JSON, shape depends on kind
+    sources TEXT NOT NULL,      -- JSON array: 'platform_audit', 'org_audit', 'login', 'security'
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    last_error TEXT,
+    last_exported_at DATETIME,
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+-- How far each sink has read each source, by rowid, so restarts resume
+CREATE TABLE siem_sink_cursors (
+    sink_id TEXT NOT NULL REFERENCES siem_sinks(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,  -- rowid of the last exported row
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (sink_id, source)
+);
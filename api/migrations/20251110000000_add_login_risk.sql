@@ -0,0 +1,40 @@
+-- ============================================================================
+-- LOGIN RISK
+-- Device, location and risk score on each login, the organization policy
+-- deciding what happens to risky logins, and emailed codes for logins that
+-- need a second factor
+-- ============================================================================
+
+-- Blocked and challenged attempts are kept too; analytics count 'success' only.
+-- A completed challenge adds a 'success' row rather than changing the old one.
+ALTER TABLE login_events ADD COLUMN outcome TEXT NOT NULL DEFAULT 'success'; -- 'success', 'challenged', 'blocked'
+ALTER TABLE login_events ADD COLUMN device_fingerprint TEXT; -- SHA-256 of the user agent without version numbers
+ALTER TABLE login_events ADD COLUMN country TEXT;            -- ISO 3166-1 alpha-2, from GeoIP
+ALTER TABLE login_events ADD COLUMN latitude REAL;
+ALTER TABLE login_events ADD COLUMN longitude REAL;
+ALTER TABLE login_events ADD COLUMN risk_score INTEGER NOT NULL DEFAULT 0; -- 0-100
+ALTER TABLE login_events ADD COLUMN risk_reasons TEXT;       -- JSON array: 'new_device', 'new_country', 'impossible_travel'
+ALTER TABLE login_events ADD COLUMN risk_action TEXT NOT NULL DEFAULT 'allow'; -- policy action applied
+
+CREATE INDEX idx_login_events_user_created ON login_events(user_id, created_at DESC);
+CREATE INDEX idx_login_events_risk ON login_events(risk_score, created_at DESC);
+
+-- Action per risk level: 'allow', 'notify', 'require_mfa' or 'block'.
+-- New-device emails were always sent before, so both levels start at 'notify'.
+ALTER TABLE organizations ADD COLUMN login_risk_medium_action TEXT NOT NULL DEFAULT 'notify';
+ALTER TABLE organizations ADD COLUMN login_risk_high_action TEXT NOT NULL DEFAULT 'notify';
+
+-- A login held back until the user enters the code emailed to them
+CREATE TABLE login_challenges (
+    id TEXT PRIMARY KEY,
+    login_event_id TEXT NOT NULL REFERENCES login_events(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    service_id TEXT NOT NULL REFERENCES services(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,      -- SHA256 of the emailed code
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at DATETIME NOT NULL,
+    completed_at DATETIME,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_login_challenges_user ON login_challenges(user_id);
@@ -0,0 +1,40 @@
+-- ============================================================================
+-- LOGIN FAILURES & LOCKOUT
+-- Failed sign-in attempts with a reason code, and the failure counters that
+-- throttle and temporarily lock out a user, an upstream identity or an IP
+-- ============================================================================
+
+CREATE TABLE login_failures (
+    id TEXT PRIMARY KEY,
+    flow TEXT NOT NULL,         -- 'oauth', 'device', 'refresh', 'login_challenge'
+    reason TEXT NOT NULL,       -- 'invalid_state', 'provider_error', 'redirect_uri_mismatch', ...
+    user_id TEXT,               -- Kept as written; no FK so failures outlive the user
+    provider TEXT,
+    provider_user_id TEXT,
+    org_id TEXT,
+    service_id TEXT,
+    ip_address TEXT,
+    user_agent TEXT,
+    detail TEXT,                -- Error message shown to the client
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_login_failures_created ON login_failures(created_at);
+CREATE INDEX idx_login_failures_org ON login_failures(org_id, created_at);
+CREATE INDEX idx_login_failures_user ON login_failures(user_id, created_at);
+
+-- Failures inside the current window. A row is cleared by a successful
+-- login (user and identity only) or by an admin.
+CREATE TABLE login_lockouts (
+    scope TEXT NOT NULL,        -- 'user', 'identity', 'ip'
+    subject TEXT NOT NULL,      -- user id, 'provider:provider_user_id' or IP address
+    user_id TEXT,               -- Owner of a user or identity subject, when known
+    failures INTEGER NOT NULL DEFAULT 0,
+    window_started_at DATETIME NOT NULL,
+    last_failure_at DATETIME NOT NULL,
+    locked_until DATETIME,
+    PRIMARY KEY (scope, subject)
+);
+
+CREATE INDEX idx_login_lockouts_user ON login_lockouts(user_id);
+CREATE INDEX idx_login_lockouts_locked ON login_lockouts(locked_until);
@@ -0,0 +1,15 @@
+-- ============================================================================
+-- ACCOUNT MERGE PROOFS
+-- Single-use proofs minted by the account that is about to be merged away
+-- ============================================================================
+
+CREATE TABLE account_merge_proofs (
+    id TEXT PRIMARY KEY,        -- The proof's jti
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    session_id TEXT NOT NULL,   -- Session that minted the proof
+    expires_at DATETIME NOT NULL,
+    used_at DATETIME,           -- Set when a merge consumes the proof
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX idx_account_merge_proofs_user ON account_merge_proofs(user_id);
@@ -0,0 +1,13 @@
+-- ============================================================================
+-- SIGN-IN PROVIDER
+-- Remember which provider a session or device authorization came from, so a
+-- domain's sign-in policy can be checked again on refresh and on device and
+-- CIBA token grants. A domain can also require its organization's own OAuth
+-- app (enterprise connection) instead of the platform's.
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN provider TEXT; -- NULL for sessions created before this column
+ALTER TABLE device_codes ADD COLUMN provider TEXT; -- set when the request is authorized
+
+ALTER TABLE organization_domains
+    ADD COLUMN require_enterprise_connection BOOLEAN NOT NULL DEFAULT 0;
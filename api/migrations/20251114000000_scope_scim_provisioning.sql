@@ -0,0 +1,13 @@
+-- ============================================================================
+-- SCIM PROVISIONING SCOPE
+-- A SCIM client only binds to accounts its organization already has, or to
+-- accounts it created itself. The name it sends for an account it did not
+-- create is kept on the organization's SCIM record instead of the account.
+-- ============================================================================
+
+-- Organization whose SCIM client created the account
+ALTER TABLE users ADD COLUMN scim_org_id TEXT REFERENCES organizations(id) ON DELETE SET NULL;
+
+ALTER TABLE scim_users ADD COLUMN name TEXT;
+ALTER TABLE scim_users ADD COLUMN given_name TEXT;
+ALTER TABLE scim_users ADD COLUMN family_name TEXT;
@@ -0,0 +1,8 @@
+-- ============================================================================
+-- INVITATION REMINDER LINKS
+-- Reminders resend the invitee's current link instead of issuing a new one,
+-- so an encrypted copy of the token is kept next to its hash. Invitations
+-- created before this, or without ENCRYPTION_KEY, get no reminder.
+-- ============================================================================
+
+ALTER TABLE organization_invitations ADD COLUMN token_encrypted BLOB;
@@ -0,0 +1,20 @@
+-- ============================================================================
+-- ONE-TIME AUDIT SEALING
+-- Every audit entry is linked into its hash chain when it is written. Only
+-- the entries written before chaining existed are left for the checkpoint
+-- job to seal, and they are listed here so it cannot seal anything else:
+-- an unlinked entry that is not listed was inserted outside the service and
+-- is reported as a break when the chain is verified.
+-- ============================================================================
+
+CREATE TABLE audit_legacy_entries (
+    chain_table TEXT NOT NULL, -- 'platform_audit_log' or 'organization_audit_log'
+    entry_id TEXT NOT NULL,
+    PRIMARY KEY (chain_table, entry_id)
+);
+
+INSERT INTO audit_legacy_entries (chain_table, entry_id)
+SELECT 'platform_audit_log', id FROM platform_audit_log WHERE seq IS NULL;
+
+INSERT INTO audit_legacy_entries (chain_table, entry_id)
+SELECT 'organization_audit_log', id FROM organization_audit_log WHERE seq IS NULL;
@@ -0,0 +1,13 @@
+-- ============================================================================
+-- RENAME require_mfa
+-- The risk action emails a one-time code; there are no registered second
+-- factors to ask for, so it is named for what it does.
+-- ============================================================================
+
+UPDATE organizations SET login_risk_medium_action = 'require_email_code'
+WHERE login_risk_medium_action = 'require_mfa';
+
+UPDATE organizations SET login_risk_high_action = 'require_email_code'
+WHERE login_risk_high_action = 'require_mfa';
+
+UPDATE login_events SET risk_action = 'require_email_code' WHERE risk_action = 'require_mfa';
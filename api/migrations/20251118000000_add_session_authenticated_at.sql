@@ -0,0 +1,13 @@
+-- ============================================================================
+-- SESSION AUTHENTICATION TIME
+-- When the user last proved who they are for a session. Refreshing a session
+-- keeps it, so checks for a recent login cannot be satisfied by refreshing.
+-- Device codes carry it from the sign-in or session that authorized them.
+-- NULL (impersonation and older CIBA sessions) never counts as recent.
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN authenticated_at DATETIME;
+ALTER TABLE device_codes ADD COLUMN authenticated_at DATETIME;
+
+-- Sessions are created at sign-in and never re-created on refresh
+UPDATE sessions SET authenticated_at = created_at WHERE impersonator_id IS NULL;
@@ -0,0 +1,362 @@
+use crate::auth::jwt::JwtService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::constants::ACCOUNT_MERGE_PROOF_MINUTES;
+use crate::db::models::{Membership, Subscription, User};
+use crate::error::{AppError, Result};
+use crate::handlers::platform::create_audit_log;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// What a merge moved onto the surviving user and which conflicts it resolved
+#[derive(Debug, Default, Serialize)]
+pub struct MergeSummary {
+    pub identities_moved: u64,
+    pub identities_dropped: u64,
+    pub memberships_moved: u64,
+    pub memberships_upgraded: u64,
+    pub subscriptions_moved: u64,
+    pub subscriptions_replaced: u64,
+    pub subscriptions_dropped: u64,
+    pub sessions_moved: u64,
+    pub login_events_moved: u64,
+}
+
+pub struct AccountMergeService;
+
+impl AccountMergeService {
+    /// Conflict rule for two memberships in the same organization: the source
+    /// role replaces the survivor's only if it is `owner` or grants a strict
+    /// superset of the survivor's permissions
+    pub fn source_role_wins(
+        survivor_role: &str,
+        survivor_permissions: &[String],
+        source_role: &str,
+        source_permissions: &[String],
+    ) -> bool {
+        if survivor_role == "owner" {
+            return false;
+        }
+        if source_role == "owner" {
+            return true;
+        }
+        OrgRoleService::is_within(survivor_permissions, source_permissions)
+            && source_permissions.len() > survivor_permissions.len()
+    }
+
+    /// Conflict rule for two subscriptions to the same service: an active
+    /// subscription beats an inactive one, then the later period end wins.
+    /// Ties keep the survivor's subscription.
+    pub fn source_subscription_wins(survivor: &Subscription, source: &Subscription) -> bool {
+        let survivor_active = survivor.status == "active";
+        let source_active = source.status == "active";
+        if survivor_active != source_active {
+            return source_active;
+        }
+        source.current_period_end > survivor.current_period_end
+    }
+
+    /// Mint a short-lived, single-use proof that the caller controls their
+    /// account, for merging it into another one
+    pub async fn issue_proof(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        user: &User,
+        session_id: &str,
+    ) -> Result<(String, DateTime<Utc>)> {
+        let proof_id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(ACCOUNT_MERGE_PROOF_MINUTES);
+        let token = jwt_service.create_merge_proof(
+            &user.id,
+            session_id,
+            &proof_id,
+            ACCOUNT_MERGE_PROOF_MINUTES,
+        )?;
+
+        sqlx::query(
+            "INSERT INTO account_merge_proofs (id, user_id, session_id, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&proof_id)
+        .bind(&user.id)
+        .bind(session_id)
+        .bind(expires_at)
+        .bind(now)
+        .execute(pool)
+        .await?;
+
+        Ok((token, expires_at))
+    }
+
+    /// Move everything owned by `source` onto `survivor` in one transaction,
+    /// then delete `source`. The merge consumes the proof `source` minted and
+    /// is recorded in the platform audit log.
+    pub async fn merge(
+        pool: &SqlitePool,
+        survivor: &User,
+        source: &User,
+        proof_id: &str,
+    ) -> Result<MergeSummary> {
+        if survivor.id == source.id {
+            return Err(AppError::BadRequest(
+                "Cannot merge an account into itself".to_string(),
+            ));
+        }
+        if source.is_platform_owner && !survivor.is_platform_owner {
+            return Err(AppError::BadRequest(
+                "Merge into the platform owner account instead".to_string(),
+            ));
+        }
+
+        let mut summary = MergeSummary::default();
+        let mut tx = pool.begin().await?;
+
+        let consumed = sqlx::query(
+            "UPDATE account_merge_proofs SET used_at = ?
+             WHERE id = ? AND user_id = ? AND used_at IS NULL AND expires_at > ?",
+        )
+        .bind(Utc::now())
+        .bind(proof_id)
+        .bind(&source.id)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if consumed == 0 {
+            return Err(AppError::Unauthorized(
+                "Merge token already used or expired".to_string(),
+            ));
+        }
+
+        // Resolve membership conflicts against the same snapshot the moves use
+        let mut upgrades: Vec<(String, String)> = Vec::new(); // (org_id, role)
+        let source_memberships =
+            sqlx::query_as::<_, Membership>("SELECT * FROM memberships WHERE user_id = ?")
+                .bind(&source.id)
+                .fetch_all(&mut *tx)
+                .await?;
+        for source_membership in &source_memberships {
+            let survivor_membership = sqlx::query_as::<_, Membership>(
+                "SELECT * FROM memberships WHERE user_id = ? AND org_id = ?",
+            )
+            .bind(&survivor.id)
+            .bind(&source_membership.org_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some(survivor_membership) = survivor_membership else {
+                continue;
+            };
+
+            let org_id = &source_membership.org_id;
+            let survivor_permissions =
+                OrgRoleService::permissions_for_role(&mut *tx, org_id, &survivor_membership.role)
+                    .await?;
+            let source_permissions =
+                OrgRoleService::permissions_for_role(&mut *tx, org_id, &source_membership.role)
+                    .await?;
+            if Self::source_role_wins(
+                &survivor_membership.role,
+                &survivor_permissions,
+                &source_membership.role,
+                &source_permissions,
+            ) {
+                upgrades.push((org_id.clone(), source_membership.role.clone()));
+            }
+        }
+
+        // Identities: the survivor's identity wins when both have the same
+        // provider in the same context; the source's is dropped with the user
+        summary.identities_moved =
+            sqlx::query("UPDATE OR IGNORE identities SET user_id = ? WHERE user_id = ?")
+                .bind(&survivor.id)
+                .bind(&source.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        summary.identities_dropped =
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM identities WHERE user_id = ?")
+                .bind(&source.id)
+                .fetch_one(&mut *tx)
+                .await? as u64;
+
+        // Memberships: apply the winning roles, then move the rest
+        for (org_id, role) in &upgrades {
+            sqlx::query("UPDATE memberships SET role = ? WHERE user_id = ? AND org_id = ?")
+                .bind(role)
+                .bind(&survivor.id)
+                .bind(org_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        summary.memberships_upgraded = upgrades.len() as u64;
+        summary.memberships_moved =
+            sqlx::query("UPDATE OR IGNORE memberships SET user_id = ? WHERE user_id = ?")
+                .bind(&survivor.id)
+                .bind(&source.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        sqlx::query("UPDATE organizations SET owner_user_id = ? WHERE owner_user_id = ?")
+            .bind(&survivor.id)
+            .bind(&source.id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Subscriptions: replace the survivor's where the source's wins
+        let source_subscriptions =
+            sqlx::query_as::<_, Subscription>("SELECT * FROM subscriptions WHERE user_id = ?")
+                .bind(&source.id)
+                .fetch_all(&mut *tx)
+                .await?;
+        for source_subscription in &source_subscriptions {
+            let survivor_subscription = sqlx::query_as::<_, Subscription>(
+                "SELECT * FROM subscriptions WHERE user_id = ? AND service_id = ?",
+            )
+            .bind(&survivor.id)
+            .bind(&source_subscription.service_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some(survivor_subscription) = survivor_subscription else {
+                continue;
+            };
+
+            if Self::source_subscription_wins(&survivor_subscription, source_subscription) {
+                sqlx::query("DELETE FROM subscriptions WHERE id = ?")
+                    .bind(&survivor_subscription.id)
+                    .execute(&mut *tx)
+                    .await?;
+                summary.subscriptions_replaced += 1;
+            } else {
+                summary.subscriptions_dropped += 1;
+            }
+        }
+        summary.subscriptions_moved =
+            sqlx::query("UPDATE OR IGNORE subscriptions SET user_id = ? WHERE user_id = ?")
+                .bind(&survivor.id)
+                .bind(&source.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        // Sessions keep working: refreshing one issues tokens for the survivor
+        summary.sessions_moved = sqlx::query("UPDATE sessions SET user_id = ? WHERE user_id = ?")
+            .bind(&survivor.id)
+            .bind(&source.id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        summary.login_events_moved =
+            sqlx::query("UPDATE login_events SET user_id = ? WHERE user_id = ?")
+                .bind(&survivor.id)
+                .bind(&source.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        // Remaining references; rows that would duplicate the survivor's are
+        // left behind and removed by ON DELETE CASCADE. Audit log entries keep
+        // the merged user's id: they are hash-chained and never rewritten.
+        for statement in [
+            "UPDATE sessions SET impersonator_id = ? WHERE impersonator_id = ?",
+            "UPDATE OR IGNORE sso_sessions SET user_id = ? WHERE user_id = ?",
+            "UPDATE OR IGNORE user_group_members SET user_id = ? WHERE user_id = ?",
+            "UPDATE OR IGNORE scim_users SET user_id = ? WHERE user_id = ?",
+            "UPDATE OR IGNORE service_role_assignments SET user_id = ? WHERE user_id = ?",
+            "UPDATE api_tokens SET user_id = ? WHERE user_id = ? AND kind = 'personal'",
+            "UPDATE api_tokens SET created_by = ? WHERE created_by = ?",
+            "UPDATE organization_invitations SET invited_by = ? WHERE invited_by = ?",
+            "UPDATE organization_invite_links SET created_by = ? WHERE created_by = ?",
+            "UPDATE OR IGNORE organization_invite_link_redemptions SET user_id = ? WHERE user_id = ?",
+            "UPDATE organizations SET approved_by = ? WHERE approved_by = ?",
+            "UPDATE organizations SET rejected_by = ? WHERE rejected_by = ?",
+            "UPDATE device_codes SET user_id = ? WHERE user_id = ?",
+            "UPDATE device_codes SET login_hint_user_id = ? WHERE login_hint_user_id = ?",
+        ] {
+            sqlx::query(statement)
+                .bind(&survivor.id)
+                .bind(&source.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("DELETE FROM oauth_states WHERE user_id_for_linking = ?")
+            .bind(&source.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(&source.id)
+            .execute(&mut *tx)
+            .await?;
+
+        create_audit_log(
+            &mut tx,
+            &survivor.id,
+            "account_merged",
+            "user",
+            &survivor.id,
+            Some(json!({
+                "merged_user_id": source.id,
+                "merged_email": source.email,
+                "summary": summary,
+            })),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(status: &str, days: i64) -> Subscription {
+        Subscription {
+            id: "s".to_string(),
+            user_id: "u".to_string(),
+            service_id: "svc".to_string(),
+            plan_id: "p".to_string(),
+            status: status.to_string(),
+            current_period_end: Utc::now() + Duration::days(days),
+        }
+    }
+
+    #[test]
+    fn test_conflict_rules() {
+        let admin: Vec<String> = vec!["manage_members".into(), "view_analytics".into()];
+        let member: Vec<String> = vec!["view_analytics".into()];
+        assert!(AccountMergeService::source_role_wins(
+            "member", &member, "admin", &admin
+        ));
+        assert!(!AccountMergeService::source_role_wins(
+            "admin", &admin, "member", &member
+        ));
+        assert!(!AccountMergeService::source_role_wins(
+            "owner", &admin, "owner", &admin
+        ));
+        assert!(AccountMergeService::source_role_wins(
+            "admin", &admin, "owner", &admin
+        ));
+
+        let active = subscription("active", 1);
+        let cancelled_later = subscription("cancelled", 30);
+        let active_later = subscription("active", 30);
+        assert!(!AccountMergeService::source_subscription_wins(
+            &active,
+            &cancelled_later
+        ));
+        assert!(AccountMergeService::source_subscription_wins(
+            &cancelled_later,
+            &active
+        ));
+        assert!(AccountMergeService::source_subscription_wins(
+            &active,
+            &active_later
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::auth::jwt::{Claims, JwtService, ProfileClaims};
+use crate::constants::{
+    ORG_API_KEY_PREFIX, PERSONAL_ACCESS_TOKEN_PREFIX, SESSION_LAST_USED_RESOLUTION_MINUTES,
+    VALID_API_TOKEN_SCOPES,
+};
+use crate::db::models::{ApiToken, User};
+use crate::error::{AppError, Result};
+use axum::http::Method;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// Number of characters kept in `token_prefix` so users can tell tokens apart
+const DISPLAY_PREFIX_RANDOM_CHARS: usize = 6;
+
+pub struct ApiTokenService;
+
+impl ApiTokenService {
+    /// Whether a bearer credential is an API token rather than a JWT
+    pub fn is_api_token(token: &str) -> bool {
+        token.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX) || token.starts_with(ORG_API_KEY_PREFIX)
+    }
+
+    /// Generate a new token for the given kind ('personal' or 'org')
+    pub fn generate(kind: &str) -> String {
+        let prefix = if kind == "org" {
+            ORG_API_KEY_PREFIX
+        } else {
+            PERSONAL_ACCESS_TOKEN_PREFIX
+        };
+        let bytes: [u8; 32] = rand::random();
+        format!("{}{}", prefix, URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// The non-secret leading part of a token shown in listings
+    pub fn display_prefix(token: &str) -> String {
+        let prefix_len = if token.starts_with(ORG_API_KEY_PREFIX) {
+            ORG_API_KEY_PREFIX.len()
+        } else {
+            PERSONAL_ACCESS_TOKEN_PREFIX.len()
+        };
+        token
+            .chars()
+            .take(prefix_len + DISPLAY_PREFIX_RANDOM_CHARS)
+            .collect()
+    }
+
+    /// Validate requested scopes against the catalogue, then sort and de-duplicate them
+    pub fn normalize_scopes(scopes: Vec<String>) -> Result<Vec<String>> {
+        let mut scopes: Vec<String> = scopes.into_iter().map(|s| s.trim().to_string()).collect();
+        if scopes.is_empty() {
+            return Err(AppError::BadRequest(
+                "At least one scope is required".to_string(),
+            ));
+        }
+        if let Some(unknown) = scopes
+            .iter()
+            .find(|s| !VALID_API_TOKEN_SCOPES.contains(&s.as_str()))
+        {
+            return Err(AppError::BadRequest(format!(
+                "Invalid scope '{}'. Must be one of: {}",
+                unknown,
+                VALID_API_TOKEN_SCOPES.join(", ")
+            )));
+        }
+        scopes.sort();
+        scopes.dedup();
+
+        Ok(scopes)
+    }
+
+    /// Scopes stored on a token (JSON array)
+    pub fn parse_scopes(scopes: &str) -> Vec<String> {
+        serde_json::from_str(scopes).unwrap_or_default()
+    }
+
+    /// Scope a request needs: `read` for safe methods, `write` otherwise.
+    /// `write` implies `read`.
+    pub fn check_method_scope(token: &ApiToken, method: &Method) -> Result<()> {
+        let scopes = Self::parse_scopes(&token.scopes);
+        let has = |scope: &str| scopes.iter().any(|s| s == scope);
+
+        let allowed = match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => has("read") || has("write"),
+            _ => has("write"),
+        };
+        if !allowed {
+            return Err(AppError::Forbidden(
+                "API token does not have the required scope".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Persist a new token and return it with its plaintext value (shown once)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create(
+        pool: &SqlitePool,
+        kind: &str,
+        name: &str,
+        user_id: &str,
+        org_id: Option<&str>,
+        role: Option<&str>,
+        created_by: &str,
+        scopes: &[String],
+        expires_at: DateTime<Utc>,
+    ) -> Result<(ApiToken, String)> {
+        let token = Self::generate(kind);
+
+        let api_token = sqlx::query_as::<_, ApiToken>(
+            r#"
+            INSERT INTO api_tokens (id, kind, name, token_prefix, token_hash, user_id, org_id, role,
+                                    created_by, scopes, expires_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(kind)
+        .bind(name)
+        .bind(Self::display_prefix(&token))
+        .bind(JwtService::hash_token(&token))
+        .bind(user_id)
+        .bind(org_id)
+        .bind(role)
+        .bind(created_by)
+        .bind(serde_json::to_string(scopes).unwrap_or_else(|_| "[]".to_string()))
+        .bind(expires_at)
+        .bind(Utc::now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok((api_token, token))
+    }
+
+    /// Create the user an organization API key acts as. The address uses the
+    /// reserved `.invalid` TLD so no OAuth provider can ever log in as it.
+    pub async fn create_service_account(pool: &SqlitePool, org_slug: &str) -> Result<User> {
+        let id = Uuid::new_v4().to_string();
+        let user = sqlx::query_as::<_, User>(
+            "INSERT INTO users (id, email, is_platform_owner, created_at) VALUES (?, ?, 0, ?)
+             RETURNING *",
+        )
+        .bind(&id)
+        .bind(format!("api-key-{}@{}.api-keys.invalid", id, org_slug))
+        .bind(Utc::now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// Resolve a bearer API token to the token row, the user it acts as and
+    /// JWT-shaped claims for handlers that read them
+    pub async fn authenticate(pool: &SqlitePool, token: &str) -> Result<(ApiToken, User, Claims)> {
+        let now = Utc::now();
+        let api_token = sqlx::query_as::<_, ApiToken>(
+            "SELECT * FROM api_tokens
+             WHERE token_hash = ? AND revoked_at IS NULL AND expires_at > ?",
+        )
+        .bind(JwtService::hash_token(token))
+        .bind(now)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("API token revoked or expired".to_string()))?;
+
+        // Track last use, throttled like session activity
+        sqlx::query(
+            "UPDATE api_tokens SET last_used_at = ?
+             WHERE id = ? AND (last_used_at IS NULL OR last_used_at < ?)",
+        )
+        .bind(now)
+        .bind(&api_token.id)
+        .bind(now - Duration::minutes(SESSION_LAST_USED_RESOLUTION_MINUTES))
+        .execute(pool)
+        .await?;
+
+        let user = sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE id = ?",
+        )
+        .bind(&api_token.user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+        let org_slug = match api_token.org_id {
+            Some(ref org_id) => {
+                sqlx::query_scalar::<_, String>("SELECT slug FROM organizations WHERE id = ?")
+                    .bind(org_id)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            None => None,
+        };
+
+        let claims = Claims {
+            sub: user.id.clone(),
+            email: user.email.clone(),
+            is_platform_owner: user.is_platform_owner,
+            org: org_slug,
+            service: None,
+            plan: None,
+            features: None,
+            roles: None,
+            permissions: None,
+            act: None,
+            profile: ProfileClaims::from(&user),
+            exp: api_token.expires_at.timestamp(),
+            iat: api_token.created_at.timestamp(),
+        };
+
+        Ok((api_token, user, claims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_with_scopes(scopes: &[&str]) -> ApiToken {
+        ApiToken {
+            id: "t".to_string(),
+            kind: "personal".to_string(),
+            name: "ci".to_string(),
+            token_prefix: "sso_pat_abcdef".to_string(),
+            user_id: "u".to_string(),
+            org_id: None,
+            role: None,
+            created_by: None,
+            scopes: serde_json::to_string(scopes).unwrap(),
+            expires_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_method_scopes() {
+        let read_only = token_with_scopes(&["read"]);
+        assert!(ApiTokenService::check_method_scope(&read_only, &Method::GET).is_ok());
+        assert!(ApiTokenService::check_method_scope(&read_only, &Method::POST).is_err());
+
+        let write = token_with_scopes(&["write"]);
+        assert!(ApiTokenService::check_method_scope(&write, &Method::GET).is_ok());
+        assert!(ApiTokenService::check_method_scope(&write, &Method::DELETE).is_ok());
+    }
+}
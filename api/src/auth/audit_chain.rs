@@ -0,0 +1,561 @@
+use crate::auth::jwt::{AuditCheckpointClaims, JwtService};
+use crate::db::models::{AuditCheckpoint, OrganizationAuditLog, PlatformAuditLog};
+use crate::error::Result;
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// prev_hash of the first entry in a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Attempts at appending an entry when a concurrent writer took the next seq
+const MAX_APPEND_ATTEMPTS: usize = 5;
+
+/// Entries read per query while verifying a chain
+const VERIFY_BATCH_SIZE: i64 = 1000;
+
+/// An audit hash chain: the platform log, or one organization's log
+#[derive(Debug, Clone)]
+pub enum AuditChain {
+    Platform,
+    Organization(String),
+}
+
+impl AuditChain {
+    /// Name of the chain in checkpoint claims
+    pub fn name(&self) -> String {
+        match self {
+            AuditChain::Platform => "platform".to_string(),
+            AuditChain::Organization(org_id) => format!("org:{}", org_id),
+        }
+    }
+
+    fn org_id(&self) -> Option<&str> {
+        match self {
+            AuditChain::Platform => None,
+            AuditChain::Organization(org_id) => Some(org_id),
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            AuditChain::Platform => "platform_audit_log",
+            AuditChain::Organization(_) => "organization_audit_log",
+        }
+    }
+
+    /// WHERE condition selecting the chain's entries, bound to `org_id()`
+    fn scope(&self) -> &'static str {
+        match self {
+            AuditChain::Platform => "? IS NULL",
+            AuditChain::Organization(_) => "org_id = ?",
+        }
+    }
+
+    /// WHERE condition selecting entries written before chaining existed,
+    /// the only ones that may be sealed after the fact
+    fn legacy(&self) -> String {
+        format!(
+            "id IN (SELECT entry_id FROM audit_legacy_entries WHERE chain_table = '{}')",
+            self.table()
+        )
+    }
+}
+
+/// An audit log row that is part of a hash chain
+pub trait ChainedEntry {
+    fn id(&self) -> &str;
+    fn seq(&self) -> Option<i64>;
+    fn prev_hash(&self) -> Option<&str>;
+    fn entry_hash(&self) -> Option<&str>;
+    fn set_link(&mut self, seq: i64, prev_hash: String, entry_hash: String);
+    /// Every other column, in a fixed order
+    fn content(&self) -> Value;
+}
+
+impl ChainedEntry for PlatformAuditLog {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn seq(&self) -> Option<i64> {
+        self.seq
+    }
+    fn prev_hash(&self) -> Option<&str> {
+        self.prev_hash.as_deref()
+    }
+    fn entry_hash(&self) -> Option<&str> {
+        self.entry_hash.as_deref()
+    }
+    fn set_link(&mut self, seq: i64, prev_hash: String, entry_hash: String) {
+        self.seq = Some(seq);
+        self.prev_hash = Some(prev_hash);
+        self.entry_hash = Some(entry_hash);
+    }
+    fn content(&self) -> Value {
+        json!([
+            self.id,
+            self.platform_owner_id,
+            self.action,
+            self.target_type,
+            self.target_id,
+            self.metadata,
+            timestamp(&self.created_at),
+        ])
+    }
+}
+
+impl ChainedEntry for OrganizationAuditLog {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn seq(&self) -> Option<i64> {
+        self.seq
+    }
+    fn prev_hash(&self) -> Option<&str> {
+        self.prev_hash.as_deref()
+    }
+    fn entry_hash(&self) -> Option<&str> {
+        self.entry_hash.as_deref()
+    }
+    fn set_link(&mut self, seq: i64, prev_hash: String, entry_hash: String) {
+        self.seq = Some(seq);
+        self.prev_hash = Some(prev_hash);
+        self.entry_hash = Some(entry_hash);
+    }
+    fn content(&self) -> Value {
+        json!([
+            self.id,
+            self.org_id,
+            self.actor_id,
+            self.impersonator_id,
+            self.api_token_id,
+            self.action,
+            self.target_type,
+            self.target_id,
+            self.changes,
+            self.ip_address,
+            self.user_agent,
+            self.request_id,
+            timestamp(&self.created_at),
+        ])
+    }
+}
+
+fn timestamp(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn describe_range(first: i64, last: i64) -> String {
+    if first == last {
+        format!("entry {} is", first)
+    } else {
+        format!("entries {} to {} are", first, last)
+    }
+}
+
+/// Result of walking a chain
+#[derive(Debug, Serialize)]
+pub struct ChainVerification {
+    pub chain: String,
+    pub valid: bool,
+    pub entries_checked: i64,
+    pub unsealed_entries: i64, // written before the chain existed and not yet sealed
+    pub head_seq: i64,
+    pub head_hash: String,
+    pub checkpoints_checked: i64,
+    pub checkpoints_skipped: i64, // signed with a key other than the current JWT_KID
+    pub last_checkpoint_at: Option<DateTime<Utc>>,
+    pub first_break: Option<ChainBreak>,
+}
+
+/// The earliest point at which a chain no longer verifies
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ChainBreak {
+    pub seq: i64,
+    pub entry_id: Option<String>,
+    pub reason: String,
+}
+
+pub struct AuditChainService;
+
+impl AuditChainService {
+    /// Creation time for a new entry, at the precision the hash covers
+    pub fn now() -> DateTime<Utc> {
+        Utc::now().trunc_subsecs(6)
+    }
+
+    /// SHA-256 over an entry's position, its predecessor's hash and its content
+    pub fn compute_hash<T: ChainedEntry>(entry: &T, seq: i64, prev_hash: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(
+            json!([seq, prev_hash, entry.content()])
+                .to_string()
+                .as_bytes(),
+        );
+        hex::encode(hasher.finalize())
+    }
+
+    /// Sequence number and hash of the chain's last entry; (0, GENESIS_HASH) when empty
+    pub async fn head(conn: &mut SqliteConnection, chain: &AuditChain) -> Result<(i64, String)> {
+        let query = format!(
+            "SELECT seq, entry_hash FROM {} WHERE {} AND seq IS NOT NULL
+             ORDER BY seq DESC LIMIT 1",
+            chain.table(),
+            chain.scope()
+        );
+        let head = sqlx::query_as::<_, (i64, String)>(&query)
+            .bind(chain.org_id())
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        Ok(head.unwrap_or((0, GENESIS_HASH.to_string())))
+    }
+
+    /// Link an entry to the chain's current head
+    pub async fn link<T: ChainedEntry>(
+        conn: &mut SqliteConnection,
+        chain: &AuditChain,
+        entry: &mut T,
+    ) -> Result<()> {
+        let (head_seq, head_hash) = Self::head(conn, chain).await?;
+        let seq = head_seq + 1;
+        let entry_hash = Self::compute_hash(entry, seq, &head_hash);
+        entry.set_link(seq, head_hash, entry_hash);
+        Ok(())
+    }
+
+    /// Whether an append lost the race for its seq and should be linked again
+    pub fn should_retry_append(error: &sqlx::Error, attempt: usize) -> bool {
+        attempt < MAX_APPEND_ATTEMPTS
+            && matches!(error, sqlx::Error::Database(e) if e.is_unique_violation())
+    }
+
+    /// Chains that still have entries written before chaining existed and not yet linked
+    pub async fn unsealed_chains(pool: &SqlitePool) -> Result<Vec<AuditChain>> {
+        let mut chains = Vec::new();
+
+        let platform_query = format!(
+            "SELECT COUNT(*) FROM platform_audit_log WHERE seq IS NULL AND {}",
+            AuditChain::Platform.legacy()
+        );
+        let platform_unsealed: i64 = sqlx::query_scalar(&platform_query).fetch_one(pool).await?;
+        if platform_unsealed > 0 {
+            chains.push(AuditChain::Platform);
+        }
+
+        let org_query = format!(
+            "SELECT DISTINCT org_id FROM organization_audit_log WHERE seq IS NULL AND {}",
+            AuditChain::Organization(String::new()).legacy()
+        );
+        let org_ids: Vec<String> = sqlx::query_scalar(&org_query).fetch_all(pool).await?;
+        chains.extend(org_ids.into_iter().map(AuditChain::Organization));
+
+        Ok(chains)
+    }
+
+    /// Link a chain's entries written before chaining existed, in the order
+    /// they were written. Any other unlinked entry is left for `verify` to report.
+    pub async fn seal<T>(pool: &SqlitePool, chain: &AuditChain) -> Result<u64>
+    where
+        T: ChainedEntry + for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+    {
+        let mut tx = pool.begin().await?;
+
+        let query = format!(
+            "SELECT * FROM {} WHERE {} AND seq IS NULL AND {} ORDER BY created_at ASC, rowid ASC",
+            chain.table(),
+            chain.scope(),
+            chain.legacy()
+        );
+        let entries = sqlx::query_as::<_, T>(&query)
+            .bind(chain.org_id())
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let update = format!(
+            "UPDATE {} SET seq = ?, prev_hash = ?, entry_hash = ? WHERE id = ? AND seq IS NULL",
+            chain.table()
+        );
+        let mut sealed = 0;
+        for mut entry in entries {
+            Self::link(&mut tx, chain, &mut entry).await?;
+            sealed += sqlx::query(&update)
+                .bind(entry.seq())
+                .bind(entry.prev_hash())
+                .bind(entry.entry_hash())
+                .bind(entry.id())
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        Ok(sealed)
+    }
+
+    /// Chains whose head has moved past their last checkpoint
+    pub async fn chains_needing_checkpoint(pool: &SqlitePool) -> Result<Vec<AuditChain>> {
+        let mut chains = Vec::new();
+
+        let platform_behind: bool = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(seq), 0) >
+                    (SELECT COALESCE(MAX(seq), 0) FROM audit_checkpoints WHERE org_id IS NULL)
+             FROM platform_audit_log",
+        )
+        .fetch_one(pool)
+        .await?;
+        if platform_behind {
+            chains.push(AuditChain::Platform);
+        }
+
+        let org_ids: Vec<String> = sqlx::query_scalar(
+            "SELECT l.org_id FROM organization_audit_log l
+             GROUP BY l.org_id
+             HAVING COALESCE(MAX(l.seq), 0) >
+                    (SELECT COALESCE(MAX(c.seq), 0) FROM audit_checkpoints c WHERE c.org_id = l.org_id)",
+        )
+        .fetch_all(pool)
+        .await?;
+        chains.extend(org_ids.into_iter().map(AuditChain::Organization));
+
+        Ok(chains)
+    }
+
+    /// Sign the chain's current head
+    pub async fn checkpoint(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        chain: &AuditChain,
+    ) -> Result<AuditCheckpoint> {
+        let mut conn = pool.acquire().await?;
+        let (seq, entry_hash) = Self::head(&mut conn, chain).await?;
+
+        let created_at = Self::now();
+        let signature = jwt_service.create_audit_checkpoint(&AuditCheckpointClaims {
+            chain: chain.name(),
+            seq,
+            hash: entry_hash.clone(),
+            iat: created_at.timestamp(),
+        })?;
+
+        let checkpoint = sqlx::query_as::<_, AuditCheckpoint>(
+            r#"
+            INSERT INTO audit_checkpoints (id, org_id, seq, entry_hash, signature, key_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(chain.org_id())
+        .bind(seq)
+        .bind(&entry_hash)
+        .bind(&signature)
+        .bind(jwt_service.key_id())
+        .bind(created_at)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(checkpoint)
+    }
+
+    /// Walk a chain from its first entry, checking every link and checkpoint,
+    /// and report the first break
+    pub async fn verify<T>(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        chain: &AuditChain,
+    ) -> Result<ChainVerification>
+    where
+        T: ChainedEntry + for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+    {
+        let checkpoints = sqlx::query_as::<_, AuditCheckpoint>(
+            "SELECT * FROM audit_checkpoints WHERE org_id IS ? ORDER BY seq ASC, created_at ASC",
+        )
+        .bind(chain.org_id())
+        .fetch_all(pool)
+        .await?;
+
+        let mut breaks = Vec::new();
+        let mut checkpoints_checked = 0;
+        let mut checkpoints_skipped = 0;
+        let mut signed_hashes = BTreeMap::new();
+        for checkpoint in &checkpoints {
+            if checkpoint.key_id != jwt_service.key_id() {
+                checkpoints_skipped += 1;
+                continue;
+            }
+            checkpoints_checked += 1;
+
+            let expected = AuditCheckpointClaims {
+                chain: chain.name(),
+                seq: checkpoint.seq,
+                hash: checkpoint.entry_hash.clone(),
+                iat: checkpoint.created_at.timestamp(),
+            };
+            match jwt_service.validate_audit_checkpoint(&checkpoint.signature) {
+                Ok(claims) if claims == expected => {
+                    signed_hashes.insert(checkpoint.seq, checkpoint.entry_hash.clone());
+                }
+                _ => breaks.push(ChainBreak {
+                    seq: checkpoint.seq,
+                    entry_id: None,
+                    reason: format!("checkpoint {} has an invalid signature", checkpoint.id),
+                }),
+            }
+        }
+
+        let list_query = format!(
+            "SELECT * FROM {} WHERE {} AND seq > ? ORDER BY seq ASC LIMIT ?",
+            chain.table(),
+            chain.scope()
+        );
+        let mut entries_checked = 0;
+        let mut head_seq = 0;
+        let mut head_hash = GENESIS_HASH.to_string();
+        let mut walk_broken = false;
+        'walk: loop {
+            let entries = sqlx::query_as::<_, T>(&list_query)
+                .bind(chain.org_id())
+                .bind(head_seq)
+                .bind(VERIFY_BATCH_SIZE)
+                .fetch_all(pool)
+                .await?;
+            if entries.is_empty() {
+                break;
+            }
+
+            for entry in entries {
+                let seq = entry.seq().unwrap_or_default();
+                let entry_hash = entry.entry_hash().unwrap_or_default();
+                let reason = if seq != head_seq + 1 {
+                    Some(format!("{} missing", describe_range(head_seq + 1, seq - 1)))
+                } else if entry.prev_hash() != Some(head_hash.as_str()) {
+                    Some("prev_hash does not match the previous entry".to_string())
+                } else if Self::compute_hash(&entry, seq, &head_hash) != entry_hash {
+                    Some("entry content does not match its hash".to_string())
+                } else if signed_hashes.get(&seq).is_some_and(|h| h != entry_hash) {
+                    Some("entry hash does not match the signed checkpoint".to_string())
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    breaks.push(ChainBreak {
+                        seq: seq.min(head_seq + 1),
+                        entry_id: Some(entry.id().to_string()),
+                        reason,
+                    });
+                    walk_broken = true;
+                    break 'walk;
+                }
+
+                entries_checked += 1;
+                head_seq = seq;
+                head_hash = entry_hash.to_string();
+            }
+        }
+
+        // Entries deleted from the end of the chain leave a checkpoint beyond the head
+        if let Some((&signed_seq, _)) = signed_hashes.range(head_seq + 1..).next_back() {
+            if !walk_broken {
+                breaks.push(ChainBreak {
+                    seq: head_seq + 1,
+                    entry_id: None,
+                    reason: format!(
+                        "{} missing but covered by a checkpoint",
+                        describe_range(head_seq + 1, signed_seq)
+                    ),
+                });
+            }
+        }
+
+        let unsealed_query = format!(
+            "SELECT COUNT(*) FROM {} WHERE {} AND seq IS NULL AND {}",
+            chain.table(),
+            chain.scope(),
+            chain.legacy()
+        );
+        let unsealed_entries = sqlx::query_scalar::<_, i64>(&unsealed_query)
+            .bind(chain.org_id())
+            .fetch_one(pool)
+            .await?;
+
+        // New entries are linked when written, so an unlinked one that is not
+        // awaiting sealing was inserted around the chain
+        let unlinked_query = format!(
+            "SELECT id FROM {} WHERE {} AND seq IS NULL AND NOT {}
+             ORDER BY created_at ASC, rowid ASC LIMIT 1",
+            chain.table(),
+            chain.scope(),
+            chain.legacy()
+        );
+        let unlinked = sqlx::query_scalar::<_, String>(&unlinked_query)
+            .bind(chain.org_id())
+            .fetch_optional(pool)
+            .await?;
+        if let Some(entry_id) = unlinked {
+            breaks.push(ChainBreak {
+                seq: head_seq + 1,
+                entry_id: Some(entry_id),
+                reason: "entry is not linked into the chain".to_string(),
+            });
+        }
+
+        let first_break = breaks.into_iter().min_by_key(|b| b.seq);
+
+        Ok(ChainVerification {
+            chain: chain.name(),
+            valid: first_break.is_none(),
+            entries_checked,
+            unsealed_entries,
+            head_seq,
+            head_hash,
+            checkpoints_checked,
+            checkpoints_skipped,
+            last_checkpoint_at: checkpoints.last().map(|c| c.created_at),
+            first_break,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(action: &str) -> PlatformAuditLog {
+        PlatformAuditLog {
+            id: "log-1".to_string(),
+            platform_owner_id: "owner".to_string(),
+            action: action.to_string(),
+            target_type: "organization".to_string(),
+            target_id: "org-1".to_string(),
+            metadata: Some(r#"{"tier":"pro"}"#.to_string()),
+            created_at: "2025-11-08T10:00:00.123456Z".parse().unwrap(),
+            seq: None,
+            prev_hash: None,
+            entry_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_compute_hash() {
+        let log = entry("set_tier");
+        let hash = AuditChainService::compute_hash(&log, 1, GENESIS_HASH);
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, AuditChainService::compute_hash(&log, 1, GENESIS_HASH));
+
+        // Content, position and predecessor are all covered
+        assert_ne!(
+            hash,
+            AuditChainService::compute_hash(&entry("suspend_org"), 1, GENESIS_HASH)
+        );
+        assert_ne!(hash, AuditChainService::compute_hash(&log, 2, GENESIS_HASH));
+        assert_ne!(hash, AuditChainService::compute_hash(&log, 1, &hash));
+    }
+}
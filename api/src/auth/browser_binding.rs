@@ -0,0 +1,113 @@
+use crate::auth::jwt::JwtService;
+use crate::constants::{OAUTH_BINDING_COOKIE_NAME, OAUTH_STATE_EXPIRE_MINUTES};
+use crate::error::{AppError, Result};
+use crate::middleware::read_cookie;
+use axum::http::HeaderMap;
+
+pub struct BrowserBindingService;
+
+impl BrowserBindingService {
+    /// Binding id carried by this browser's cookie, if its signature and expiry check out
+    pub fn read(jwt_service: &JwtService, headers: &HeaderMap) -> Option<String> {
+        read_cookie(headers, OAUTH_BINDING_COOKIE_NAME)
+            .and_then(|token| jwt_service.validate_browser_binding(&token).ok())
+    }
+
+    /// Bind a new OAuth flow to this browser. A browser that already carries a
+    /// binding keeps it, so logins started in several tabs all stay valid.
+    /// Returns the hash to store with the OAuth state and the Set-Cookie value.
+    pub fn bind(
+        jwt_service: &JwtService,
+        headers: &HeaderMap,
+        base_url: &str,
+    ) -> Result<(String, String)> {
+        let binding_id =
+            Self::read(jwt_service, headers).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let token = jwt_service.create_browser_binding(&binding_id, OAUTH_STATE_EXPIRE_MINUTES)?;
+
+        // Over HTTPS, SameSite=None so browsers keep a cookie set by a cross-site
+        // fetch (starting an identity link from the app). That does not weaken the
+        // binding: the callback still has to present the binding stored with its state.
+        // Otherwise SameSite=Lax, which survives the top-level redirect back from the provider.
+        let attributes = if base_url.starts_with("https://") {
+            "SameSite=None; Secure"
+        } else {
+            "SameSite=Lax"
+        };
+        let cookie = format!(
+            "{}={}; Path=/auth; Max-Age={}; HttpOnly; {}",
+            OAUTH_BINDING_COOKIE_NAME,
+            token,
+            OAUTH_STATE_EXPIRE_MINUTES * 60,
+            attributes
+        );
+
+        Ok((JwtService::hash_token(&binding_id), cookie))
+    }
+
+    /// Reject callbacks that arrive in a different browser than the one that
+    /// started the flow (login CSRF)
+    pub fn verify(
+        jwt_service: &JwtService,
+        headers: &HeaderMap,
+        expected_hash: &str,
+    ) -> Result<()> {
+        match Self::read(jwt_service, headers) {
+            Some(binding_id) if JwtService::hash_token(&binding_id) == expected_hash => Ok(()),
+            _ => Err(AppError::BadRequest(
+                "This sign-in was started in a different browser. Please try again.".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::tests::test_jwt_service;
+
+    /// Headers a browser sends back after storing the given Set-Cookie value
+    fn headers_with(set_cookie: &str) -> HeaderMap {
+        let pair = set_cookie.split(';').next().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(axum::http::header::COOKIE, pair.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_binding_verifies_only_in_the_same_browser() {
+        let jwt_service = test_jwt_service();
+        let (hash, cookie) =
+            BrowserBindingService::bind(&jwt_service, &HeaderMap::new(), "https://sso.example.com")
+                .unwrap();
+        assert!(cookie.contains("; Secure"));
+
+        assert!(BrowserBindingService::verify(&jwt_service, &headers_with(&cookie), &hash).is_ok());
+        assert!(BrowserBindingService::verify(&jwt_service, &HeaderMap::new(), &hash).is_err());
+
+        let (_, other_cookie) =
+            BrowserBindingService::bind(&jwt_service, &HeaderMap::new(), "https://sso.example.com")
+                .unwrap();
+        assert!(
+            BrowserBindingService::verify(&jwt_service, &headers_with(&other_cookie), &hash)
+                .is_err()
+        );
+
+        let forged = format!("{}=not-a-token", OAUTH_BINDING_COOKIE_NAME);
+        assert!(
+            BrowserBindingService::verify(&jwt_service, &headers_with(&forged), &hash).is_err()
+        );
+    }
+
+    #[test]
+    fn test_bind_reuses_existing_binding() {
+        let jwt_service = test_jwt_service();
+        let (hash, cookie) =
+            BrowserBindingService::bind(&jwt_service, &HeaderMap::new(), "http://localhost")
+                .unwrap();
+        let (second_hash, _) =
+            BrowserBindingService::bind(&jwt_service, &headers_with(&cookie), "http://localhost")
+                .unwrap();
+        assert_eq!(hash, second_hash);
+    }
+}
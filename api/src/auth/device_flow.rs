@@ -1,4 +1,4 @@
-use crate::constants::DEVICE_CODE_EXPIRE_MINUTES;
+use crate::constants::{CIBA_REQUEST_EXPIRE_MINUTES, DEVICE_CODE_EXPIRE_MINUTES};
 use crate::db::models::DeviceCode;
 use crate::error::{AppError, Result};
 use chrono::{Duration, Utc};
@@ -132,14 +132,43 @@ impl DeviceFlowService {
         device_code.status == "authorized" && device_code.user_id.is_some()
     }
 
-    /// Validate device code for token exchange
+    /// Check if the user rejected a backchannel request
+    pub fn is_denied(device_code: &DeviceCode) -> bool {
+        device_code.status == "denied"
+    }
+
+    /// Check if tokens were already issued for the code
+    pub fn is_consumed(device_code: &DeviceCode) -> bool {
+        device_code.status == "consumed"
+    }
+
+    /// Claim an authorized code for token issuance. Only one caller can win,
+    /// so a device code or auth_req_id yields tokens once.
+    pub async fn consume(pool: &SqlitePool, id: &str) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE device_codes SET status = 'consumed' WHERE id = ? AND status = 'authorized'",
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::BadRequest("Invalid device code".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Validate device code (or CIBA auth_req_id) for token exchange
     pub async fn validate_for_token_exchange(
         pool: &SqlitePool,
         device_code: &str,
         client_id: &str,
+        flow_type: &str,
     ) -> Result<DeviceCode> {
         let device_code_record = Self::find_by_device_code(pool, device_code)
             .await?
+            .filter(|record| record.flow_type == flow_type)
             .ok_or_else(|| AppError::BadRequest("Invalid device code".to_string()))?;
 
         // Validate client_id matches
@@ -152,6 +181,15 @@ impl DeviceFlowService {
             return Err(AppError::DeviceCodeExpired);
         }
 
+        if Self::is_consumed(&device_code_record) {
+            return Err(AppError::BadRequest("Invalid device code".to_string()));
+        }
+
+        // Check if the user rejected the request
+        if Self::is_denied(&device_code_record) {
+            return Err(AppError::AuthorizationDenied);
+        }
+
         // Check if authorized
         if !Self::is_authorized(&device_code_record) {
             return Err(AppError::DeviceCodePending);
@@ -160,6 +198,111 @@ impl DeviceFlowService {
         Ok(device_code_record)
     }
 
+    /// Create a CIBA request for a known user. The device_code is returned to
+    /// the service as the auth_req_id; the user_code is only shown for reference.
+    pub async fn create_backchannel_request(
+        pool: &SqlitePool,
+        client_id: &str,
+        org_slug: &str,
+        service_slug: &str,
+        login_hint_user_id: &str,
+        binding_message: Option<&str>,
+        client_notification_token: Option<&str>,
+    ) -> Result<DeviceCode> {
+        let id = Uuid::new_v4().to_string();
+        let auth_req_id = Self::generate_device_code();
+        let user_code = Self::generate_user_code();
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(CIBA_REQUEST_EXPIRE_MINUTES);
+
+        let request = sqlx::query_as::<_, DeviceCode>(
+            r#"
+            INSERT INTO device_codes (
+                id, device_code, user_code, client_id, org_slug, service_slug, expires_at, status,
+                flow_type, login_hint_user_id, binding_message, client_notification_token, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, 'pending', 'ciba', ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(&id)
+        .bind(&auth_req_id)
+        .bind(&user_code)
+        .bind(client_id)
+        .bind(org_slug)
+        .bind(service_slug)
+        .bind(expires_at)
+        .bind(login_hint_user_id)
+        .bind(binding_message)
+        .bind(client_notification_token)
+        .bind(now)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(request)
+    }
+
+    /// List unexpired CIBA requests awaiting the user's decision
+    pub async fn list_pending_backchannel_requests(
+        pool: &SqlitePool,
+        user_id: &str,
+    ) -> Result<Vec<DeviceCode>> {
+        let requests = sqlx::query_as::<_, DeviceCode>(
+            r#"
+            SELECT * FROM device_codes
+            WHERE login_hint_user_id = ? AND flow_type = 'ciba' AND status = 'pending' AND expires_at > ?
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(requests)
+    }
+
+    /// Approve or deny a CIBA request from one of the user's sessions. The
+    /// session's provider is recorded for the sign-in policy only when it is
+    /// signed in to a service of the requesting organization. The session's
+    /// authentication time carries over to the session the request yields.
+    pub async fn resolve_backchannel_request(
+        pool: &SqlitePool,
+        id: &str,
+        user_id: &str,
+        session_id: &str,
+        approve: bool,
+    ) -> Result<DeviceCode> {
+        let status = if approve { "authorized" } else { "denied" };
+
+        let request = sqlx::query_as::<_, DeviceCode>(
+            r#"
+            UPDATE device_codes
+            SET user_id = ?, status = ?,
+                provider = (SELECT s.provider FROM sessions s
+                            JOIN services sv ON sv.id = s.service_id
+                            JOIN organizations o ON o.id = sv.org_id
+                            WHERE s.id = ? AND o.slug = device_codes.org_slug),
+                authenticated_at = (SELECT authenticated_at FROM sessions WHERE id = ?)
+            WHERE id = ? AND login_hint_user_id = ? AND flow_type = 'ciba'
+              AND status = 'pending' AND expires_at > ?
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(status)
+        .bind(session_id)
+        .bind(session_id)
+        .bind(id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Authentication request not found".to_string()))?;
+
+        Ok(request)
+    }
+
     #[allow(dead_code)]
     pub async fn cleanup_expired(pool: &SqlitePool) -> Result<u64> {
         let now = Utc::now();
@@ -179,6 +322,7 @@ impl DeviceFlowService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::test_support::{insert_org, insert_service, insert_user, test_pool};
 
     #[test]
     fn test_user_code_generation() {
@@ -208,6 +352,13 @@ mod tests {
             expires_at: Utc::now() - Duration::hours(1),
             user_id: None,
             status: "pending".to_string(),
+            flow_type: "device".to_string(),
+            login_hint_user_id: None,
+            binding_message: None,
+            client_notification_token: None,
+            created_at: None,
+            provider: None,
+            authenticated_at: None,
         };
 
         assert!(DeviceFlowService::is_expired(&expired_code));
@@ -225,8 +376,169 @@ mod tests {
             expires_at: Utc::now() + Duration::hours(1),
             user_id: Some("user_123".to_string()),
             status: "authorized".to_string(),
+            flow_type: "device".to_string(),
+            login_hint_user_id: None,
+            binding_message: None,
+            client_notification_token: None,
+            created_at: None,
+            provider: None,
+            authenticated_at: None,
         };
 
         assert!(DeviceFlowService::is_authorized(&authorized_code));
     }
+
+    async fn ciba_request(pool: &SqlitePool) -> (String, String, DeviceCode) {
+        let owner = insert_user(pool, "owner@acme.com").await;
+        let user = insert_user(pool, "ada@acme.com").await;
+        let org = insert_org(pool, "acme", &owner).await;
+        let service = insert_service(pool, &org, "billing").await;
+        let request = DeviceFlowService::create_backchannel_request(
+            pool,
+            &service.client_id,
+            &org.slug,
+            &service.slug,
+            &user.id,
+            Some("Pay invoice 42"),
+            None,
+        )
+        .await
+        .unwrap();
+        (user.id, service.client_id, request)
+    }
+
+    #[tokio::test]
+    async fn test_ciba_auth_req_id_is_single_use() {
+        let pool = test_pool().await;
+        let (user_id, client_id, request) = ciba_request(&pool).await;
+        let exchange = |client_id: &str, flow_type: &'static str| {
+            let (pool, code, client_id) =
+                (&pool, request.device_code.clone(), client_id.to_string());
+            async move {
+                DeviceFlowService::validate_for_token_exchange(pool, &code, &client_id, flow_type)
+                    .await
+            }
+        };
+
+        assert!(matches!(
+            exchange(&client_id, "ciba").await,
+            Err(AppError::DeviceCodePending)
+        ));
+        assert!(matches!(
+            exchange(&client_id, "device").await,
+            Err(AppError::BadRequest(_))
+        ));
+
+        // Only the hinted user can answer
+        assert!(DeviceFlowService::resolve_backchannel_request(
+            &pool,
+            &request.id,
+            "someone-else",
+            "session",
+            true
+        )
+        .await
+        .is_err());
+        DeviceFlowService::resolve_backchannel_request(
+            &pool,
+            &request.id,
+            &user_id,
+            "session",
+            true,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            exchange("other-client", "ciba").await,
+            Err(AppError::Unauthorized(_))
+        ));
+        let authorized = exchange(&client_id, "ciba").await.unwrap();
+        DeviceFlowService::consume(&pool, &authorized.id)
+            .await
+            .unwrap();
+
+        assert!(DeviceFlowService::consume(&pool, &authorized.id)
+            .await
+            .is_err());
+        assert!(matches!(
+            exchange(&client_id, "ciba").await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ciba_denied_request() {
+        let pool = test_pool().await;
+        let (user_id, client_id, request) = ciba_request(&pool).await;
+
+        DeviceFlowService::resolve_backchannel_request(
+            &pool,
+            &request.id,
+            &user_id,
+            "session",
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            DeviceFlowService::validate_for_token_exchange(
+                &pool,
+                &request.device_code,
+                &client_id,
+                "ciba"
+            )
+            .await,
+            Err(AppError::AuthorizationDenied)
+        ));
+        // A decision is final
+        assert!(DeviceFlowService::resolve_backchannel_request(
+            &pool,
+            &request.id,
+            &user_id,
+            "session",
+            true
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ciba_expired_request() {
+        let pool = test_pool().await;
+        let (user_id, client_id, request) = ciba_request(&pool).await;
+        sqlx::query("UPDATE device_codes SET expires_at = ? WHERE id = ?")
+            .bind(Utc::now() - Duration::minutes(1))
+            .bind(&request.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(
+            DeviceFlowService::list_pending_backchannel_requests(&pool, &user_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(DeviceFlowService::resolve_backchannel_request(
+            &pool,
+            &request.id,
+            &user_id,
+            "session",
+            true
+        )
+        .await
+        .is_err());
+        assert!(matches!(
+            DeviceFlowService::validate_for_token_exchange(
+                &pool,
+                &request.device_code,
+                &client_id,
+                "ciba"
+            )
+            .await,
+            Err(AppError::DeviceCodeExpired)
+        ));
+    }
 }
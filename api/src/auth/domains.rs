@@ -0,0 +1,396 @@
+use crate::auth::org_roles::OrgRoleService;
+use crate::auth::scim::ScimService;
+use crate::auth::sso::{Provider, UserInfo};
+use crate::billing::tiers::TierService;
+use crate::db::models::{Organization, OrganizationDomain, User};
+use crate::dns::TxtResolver;
+use crate::error::{AppError, Result};
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::AuditContext;
+use chrono::Utc;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// DNS name prefixed to a claimed domain to hold its verification record
+pub const VERIFICATION_RECORD_PREFIX: &str = "_sso-verification";
+/// Prefix of the TXT value that proves ownership
+pub const VERIFICATION_VALUE_PREFIX: &str = "sso-domain-verification=";
+
+pub struct DomainService;
+
+impl DomainService {
+    /// Lowercase a domain and check that it is a plausible DNS name
+    pub fn normalize(domain: &str) -> Result<String> {
+        let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+        let valid_label = |label: &str| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        };
+        if domain.len() > 253 || !domain.contains('.') || !domain.split('.').all(valid_label) {
+            return Err(AppError::BadRequest(format!("Invalid domain: {}", domain)));
+        }
+        Ok(domain)
+    }
+
+    /// Domain part of an email address, lowercased
+    pub fn email_domain(email: &str) -> Option<String> {
+        email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_ascii_lowercase())
+    }
+
+    pub fn generate_token() -> String {
+        let bytes: [u8; 16] = rand::random();
+        hex::encode(bytes)
+    }
+
+    /// DNS name the verification TXT record must be published at
+    pub fn record_name(domain: &str) -> String {
+        format!("{}.{}", VERIFICATION_RECORD_PREFIX, domain)
+    }
+
+    /// TXT value the verification record must contain
+    pub fn record_value(token: &str) -> String {
+        format!("{}{}", VERIFICATION_VALUE_PREFIX, token)
+    }
+
+    /// Look up the verification record and mark the domain verified when it
+    /// is published. A domain can be verified by one organization only.
+    pub async fn verify(
+        pool: &SqlitePool,
+        resolver: &dyn TxtResolver,
+        domain: &OrganizationDomain,
+    ) -> Result<OrganizationDomain> {
+        let claimed_elsewhere: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM organization_domains
+             WHERE domain = ? AND org_id != ? AND verified_at IS NOT NULL",
+        )
+        .bind(&domain.domain)
+        .bind(&domain.org_id)
+        .fetch_one(pool)
+        .await?;
+        if claimed_elsewhere > 0 {
+            return Err(AppError::BadRequest(
+                "This domain is already verified by another organization".to_string(),
+            ));
+        }
+
+        let expected = Self::record_value(&domain.verification_token);
+        let records = resolver
+            .txt_records(&Self::record_name(&domain.domain))
+            .await?;
+        let found = records.iter().any(|record| record.trim() == expected);
+
+        let now = Utc::now();
+        let updated = sqlx::query_as::<_, OrganizationDomain>(
+            "UPDATE organization_domains
+             SET last_checked_at = ?,
+                 verified_at = CASE WHEN ? THEN COALESCE(verified_at, ?) ELSE verified_at END,
+                 updated_at = ?
+             WHERE id = ?
+             RETURNING *",
+        )
+        .bind(now)
+        .bind(found)
+        .bind(now)
+        .bind(now)
+        .bind(&domain.id)
+        .fetch_one(pool)
+        .await?;
+
+        if !found {
+            return Err(AppError::BadRequest(format!(
+                "TXT record {}=\"{}\" not found",
+                Self::record_name(&domain.domain),
+                expected
+            )));
+        }
+
+        Ok(updated)
+    }
+
+    /// The verified domain claim covering an email address, if any
+    pub async fn verified_for_email(
+        pool: &SqlitePool,
+        email: &str,
+    ) -> Result<Option<OrganizationDomain>> {
+        let Some(domain) = Self::email_domain(email) else {
+            return Ok(None);
+        };
+
+        let claim = sqlx::query_as::<_, OrganizationDomain>(
+            "SELECT * FROM organization_domains WHERE domain = ? AND verified_at IS NOT NULL",
+        )
+        .bind(domain)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(claim)
+    }
+
+    /// Refuse a sign-in the owner of any of the user's email domains does not
+    /// allow: through any provider but the required one, or, when it requires
+    /// its enterprise connection, through anything but its own OAuth app.
+    /// `provider` is None when the sign-in's provider is not known, which fails
+    /// an enforced domain. `org_id` is the organization whose service is signed
+    /// in to, None for the admin console, which always uses the platform's apps.
+    /// Platform owners are never locked out by a tenant.
+    pub async fn check_sign_in(
+        pool: &SqlitePool,
+        user: &User,
+        provider: Option<Provider>,
+        org_id: Option<&str>,
+    ) -> Result<()> {
+        if user.is_platform_owner {
+            return Ok(());
+        }
+
+        for claim in Self::enforced_claims(pool, user).await? {
+            Self::check_claim(pool, &claim, provider, org_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Verified claims with enforce_sso on, for the domain of the user's email
+    /// or of any email a linked provider has verified for them. Switching the
+    /// primary email to another address does not escape the policy.
+    async fn enforced_claims(pool: &SqlitePool, user: &User) -> Result<Vec<OrganizationDomain>> {
+        let mut emails: Vec<String> = sqlx::query_scalar(
+            "SELECT email FROM identities
+             WHERE user_id = ? AND email IS NOT NULL AND email_verified = 1",
+        )
+        .bind(&user.id)
+        .fetch_all(pool)
+        .await?;
+        emails.push(user.email.clone());
+
+        let mut domains: Vec<String> = emails
+            .iter()
+            .filter_map(|email| Self::email_domain(email))
+            .collect();
+        domains.sort();
+        domains.dedup();
+
+        let mut claims = Vec::new();
+        for domain in domains {
+            let claim = sqlx::query_as::<_, OrganizationDomain>(
+                "SELECT * FROM organization_domains
+                 WHERE domain = ? AND verified_at IS NOT NULL AND enforce_sso = 1",
+            )
+            .bind(domain)
+            .fetch_optional(pool)
+            .await?;
+            claims.extend(claim);
+        }
+
+        Ok(claims)
+    }
+
+    async fn check_claim(
+        pool: &SqlitePool,
+        claim: &OrganizationDomain,
+        provider: Option<Provider>,
+        org_id: Option<&str>,
+    ) -> Result<()> {
+        if let Some(ref required) = claim.required_provider {
+            if provider.map(|p| p.as_str()) != Some(required.as_str()) {
+                return Err(AppError::Forbidden(format!(
+                    "Your organization requires signing in with {}",
+                    required
+                )));
+            }
+        }
+
+        // Service sign-ins use the organization's OAuth app whenever it has one
+        // for the provider, so the connection is the claim owner's own when the
+        // service is one of its own and it has credentials for the provider
+        if let (true, Some(org_id), Some(provider)) =
+            (claim.require_enterprise_connection, org_id, provider)
+        {
+            let connected: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM organization_oauth_credentials WHERE org_id = ? AND provider = ?",
+            )
+            .bind(&claim.org_id)
+            .bind(provider.as_str())
+            .fetch_one(pool)
+            .await?;
+            if org_id != claim.org_id || connected == 0 {
+                return Err(AppError::Forbidden(
+                    "Your organization requires signing in through its own connection".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the user to the organization that verified their email domain,
+    /// when it has auto-join on. The provider must have verified the email.
+    /// Returns the organization joined, if any.
+    pub async fn auto_join(
+        pool: &SqlitePool,
+        audit: &AuditContext,
+        user: &User,
+        user_info: &UserInfo,
+    ) -> Result<Option<Organization>> {
+        if !user_info.email_verified || !user_info.email.eq_ignore_ascii_case(&user.email) {
+            return Ok(None);
+        }
+
+        let Some(claim) = Self::verified_for_email(pool, &user.email).await? else {
+            return Ok(None);
+        };
+        if !claim.auto_join {
+            return Ok(None);
+        }
+
+        let org = sqlx::query_as::<_, Organization>(
+            "SELECT * FROM organizations WHERE id = ? AND status = 'active'",
+        )
+        .bind(&claim.org_id)
+        .fetch_optional(pool)
+        .await?;
+        let Some(org) = org else {
+            return Ok(None);
+        };
+
+        let already_member: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ? AND user_id = ?")
+                .bind(&org.id)
+                .bind(&user.id)
+                .fetch_one(pool)
+                .await?;
+        if already_member > 0 {
+            return Ok(None);
+        }
+        // Someone the organization's identity provider deprovisioned stays out
+        if ScimService::is_deactivated(pool, &org.id, &user.id).await? {
+            return Ok(None);
+        }
+
+        let member_count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ?")
+                .bind(&org.id)
+                .fetch_one(pool)
+                .await?;
+        if member_count >= TierService::member_limit(pool, &org).await? {
+            tracing::warn!(
+                "Auto-join of {} into {} skipped: team limit reached",
+                user.id,
+                org.slug
+            );
+            return Ok(None);
+        }
+
+        // The role may have been deleted since it was configured
+        let role = if OrgRoleService::role_exists(pool, &org.id, &claim.default_role).await? {
+            claim.default_role.as_str()
+        } else {
+            "member"
+        };
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO memberships (id, org_id, user_id, role, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&org.id)
+        .bind(&user.id)
+        .bind(role)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+        create_org_audit_log(
+            &mut tx,
+            audit,
+            &org.id,
+            "domain_auto_joined",
+            "domain",
+            &claim.domain,
+            diff(
+                &serde_json::Value::Null,
+                &serde_json::json!({ "user_id": user.id, "role": role }),
+            ),
+        )
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!("User {} auto-joined {} as {}", user.id, org.slug, role);
+
+        Ok(Some(org))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    #[test]
+    fn test_normalize_and_records() {
+        assert_eq!(
+            DomainService::normalize(" Customer.COM. ").unwrap(),
+            "customer.com"
+        );
+        assert!(DomainService::normalize("localhost").is_err());
+        assert!(DomainService::normalize("bad_domain.com").is_err());
+        assert!(DomainService::normalize("-customer.com").is_err());
+
+        assert_eq!(
+            DomainService::email_domain("Ada@Customer.com"),
+            Some("customer.com".to_string())
+        );
+        assert_eq!(
+            DomainService::record_name("customer.com"),
+            "_sso-verification.customer.com"
+        );
+        assert_eq!(
+            DomainService::record_value("abc"),
+            "sso-domain-verification=abc"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enforce_sso_follows_verified_identity_emails() {
+        let pool = test_support::test_pool().await;
+        let owner = test_support::insert_user(&pool, "owner@customer.com").await;
+        let org = test_support::insert_org(&pool, "customer", &owner).await;
+        sqlx::query(
+            "INSERT INTO organization_domains
+                 (id, org_id, domain, verification_token, verified_at, enforce_sso, required_provider)
+             VALUES (?, ?, 'customer.com', 'token', CURRENT_TIMESTAMP, 1, 'microsoft')",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&org.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // The primary email points elsewhere, but the work address is verified
+        let user = test_support::insert_user(&pool, "ada@personal.example").await;
+        DomainService::check_sign_in(&pool, &user, Some(Provider::Github), None)
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "INSERT INTO identities (id, user_id, provider, provider_user_id, email, email_verified)
+             VALUES (?, ?, 'microsoft', 'ms-ada', 'Ada@Customer.com', 1)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&user.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(matches!(
+            DomainService::check_sign_in(&pool, &user, Some(Provider::Github), None).await,
+            Err(AppError::Forbidden(_))
+        ));
+        DomainService::check_sign_in(&pool, &user, Some(Provider::Microsoft), None)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+use crate::auth::sso::{Provider, UserInfo};
+use crate::error::{AppError, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+
+const GOOGLE_JWKS_URI: &str = "https://www.googleapis.com/oauth2/v3/certs";
+const GOOGLE_ISSUERS: &[&str] = &["https://accounts.google.com", "accounts.google.com"];
+const MICROSOFT_JWKS_URI: &str = "https://login.microsoftonline.com/common/discovery/v2.0/keys";
+
+/// Claims we rely on from a Google or Microsoft ID token
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub nonce: Option<String>,
+    /// Microsoft: object id of the user, which Graph returns as `id`
+    pub oid: Option<String>,
+    /// Microsoft: tenant the token was issued for (part of the issuer)
+    pub tid: Option<String>,
+    pub email: Option<String>,
+    /// Google: the standard OIDC claim
+    pub email_verified: Option<bool>,
+    /// Microsoft: the email domain is verified by the tenant (optional claim)
+    pub xms_edov: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kid: Option<String>,
+    kty: String,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+pub struct IdTokenVerifier;
+
+impl IdTokenVerifier {
+    /// Nonce to send with the authorization request, for providers that will
+    /// return an ID token for these scopes
+    pub fn generate_nonce(provider: Provider, scopes: &[String]) -> Option<String> {
+        let issues_id_token = matches!(provider, Provider::Google | Provider::Microsoft);
+        if issues_id_token && scopes.iter().any(|scope| scope == "openid") {
+            let bytes: [u8; 32] = rand::random();
+            Some(URL_SAFE_NO_PAD.encode(bytes))
+        } else {
+            None
+        }
+    }
+
+    /// Verify an ID token's signature against the provider's published keys,
+    /// and check its issuer, audience, expiry and nonce
+    pub async fn verify(
+        provider: Provider,
+        id_token: Option<&str>,
+        client_id: &str,
+        expected_nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let id_token = id_token
+            .ok_or_else(|| AppError::OAuth("Provider did not return an ID token".to_string()))?;
+
+        let jwks_uri = match provider {
+            Provider::Google => GOOGLE_JWKS_URI,
+            Provider::Microsoft => MICROSOFT_JWKS_URI,
+            Provider::Github => {
+                return Err(AppError::OAuth(
+                    "GitHub does not issue ID tokens".to_string(),
+                ))
+            }
+        };
+
+        let jwks: Jwks = reqwest::Client::new()
+            .get(jwks_uri)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| AppError::OAuth(format!("Failed to fetch provider keys: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::OAuth(format!("Failed to parse provider keys: {}", e)))?;
+
+        Self::validate(provider, id_token, client_id, expected_nonce, &jwks)
+    }
+
+    /// Check an ID token against an already fetched key set
+    fn validate(
+        provider: Provider,
+        id_token: &str,
+        client_id: &str,
+        expected_nonce: &str,
+        jwks: &Jwks,
+    ) -> Result<IdTokenClaims> {
+        let header = decode_header(id_token)
+            .map_err(|e| AppError::OAuth(format!("Invalid ID token: {}", e)))?;
+        if header.alg != Algorithm::RS256 {
+            return Err(AppError::OAuth(
+                "Unsupported ID token algorithm".to_string(),
+            ));
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| AppError::OAuth("ID token has no key id".to_string()))?;
+
+        let (n, e) = jwks
+            .keys
+            .iter()
+            .filter(|key| key.kty == "RSA" && key.kid.as_deref() == Some(kid.as_str()))
+            .find_map(|key| Some((key.n.as_deref()?, key.e.as_deref()?)))
+            .ok_or_else(|| AppError::OAuth("ID token signed with an unknown key".to_string()))?;
+
+        let decoding_key = DecodingKey::from_rsa_components(n, e)
+            .map_err(|e| AppError::OAuth(format!("Invalid provider key: {}", e)))?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[client_id]);
+        if provider == Provider::Google {
+            validation.set_issuer(GOOGLE_ISSUERS);
+        }
+
+        let claims = decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(|e| AppError::OAuth(format!("ID token validation failed: {}", e)))?
+            .claims;
+
+        // The common endpoint serves every tenant, so the issuer must match the token's own tenant
+        if provider == Provider::Microsoft {
+            let tid = claims.tid.as_deref().unwrap_or_default();
+            if tid.is_empty()
+                || claims.iss != format!("https://login.microsoftonline.com/{}/v2.0", tid)
+            {
+                return Err(AppError::OAuth("ID token issuer is invalid".to_string()));
+            }
+        }
+
+        if claims.nonce.as_deref() != Some(expected_nonce) {
+            return Err(AppError::OAuth("ID token nonce mismatch".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    /// Make sure the userinfo response describes the same account as the ID token
+    pub fn check_subject(
+        provider: Provider,
+        claims: &IdTokenClaims,
+        user_info: &UserInfo,
+    ) -> Result<()> {
+        let subject = match provider {
+            Provider::Google => Some(claims.sub.as_str()),
+            // Graph ids are object ids; `sub` is pairwise per application
+            Provider::Microsoft => claims.oid.as_deref(),
+            Provider::Github => None,
+        };
+
+        match subject {
+            Some(subject) if subject != user_info.provider_user_id => Err(AppError::OAuth(
+                "ID token does not match the provider account".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the ID token vouches for the email the userinfo response returned
+    pub fn email_verified(claims: &IdTokenClaims, user_info: &UserInfo) -> bool {
+        let same_email = claims
+            .email
+            .as_deref()
+            .is_some_and(|email| email.eq_ignore_ascii_case(&user_info.email));
+        same_email && (claims.email_verified == Some(true) || claims.xms_edov == Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::tests::{TEST_PRIVATE_KEY, TEST_PUBLIC_KEY};
+    use base64::engine::general_purpose::STANDARD;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use rsa::pkcs8::DecodePublicKey;
+    use rsa::traits::PublicKeyParts;
+
+    const CLIENT_ID: &str = "client-123";
+    const NONCE: &str = "nonce-abc";
+
+    fn test_jwks() -> Jwks {
+        let pem = String::from_utf8(STANDARD.decode(TEST_PUBLIC_KEY).unwrap()).unwrap();
+        let key = rsa::RsaPublicKey::from_public_key_pem(&pem).unwrap();
+        Jwks {
+            keys: vec![Jwk {
+                kid: Some("provider-key".to_string()),
+                kty: "RSA".to_string(),
+                n: Some(URL_SAFE_NO_PAD.encode(key.n().to_bytes_be())),
+                e: Some(URL_SAFE_NO_PAD.encode(key.e().to_bytes_be())),
+            }],
+        }
+    }
+
+    fn sign(kid: &str, claims: serde_json::Value) -> String {
+        let pem = STANDARD.decode(TEST_PRIVATE_KEY).unwrap();
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        encode(&header, &claims, &EncodingKey::from_rsa_pem(&pem).unwrap()).unwrap()
+    }
+
+    fn google_claims() -> serde_json::Value {
+        serde_json::json!({
+            "iss": "https://accounts.google.com",
+            "sub": "google-sub",
+            "aud": CLIENT_ID,
+            "exp": chrono::Utc::now().timestamp() + 300,
+            "nonce": NONCE,
+            "email": "ada@example.com",
+            "email_verified": true,
+        })
+    }
+
+    fn validate(provider: Provider, token: &str) -> Result<IdTokenClaims> {
+        IdTokenVerifier::validate(provider, token, CLIENT_ID, NONCE, &test_jwks())
+    }
+
+    #[test]
+    fn test_validate_accepts_signed_token() {
+        let claims = validate(Provider::Google, &sign("provider-key", google_claims())).unwrap();
+        assert_eq!(claims.sub, "google-sub");
+        assert_eq!(claims.email_verified, Some(true));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_claims() {
+        let mut wrong_nonce = google_claims();
+        wrong_nonce["nonce"] = serde_json::json!("other");
+        assert!(validate(Provider::Google, &sign("provider-key", wrong_nonce)).is_err());
+
+        let mut wrong_audience = google_claims();
+        wrong_audience["aud"] = serde_json::json!("someone-else");
+        assert!(validate(Provider::Google, &sign("provider-key", wrong_audience)).is_err());
+
+        let mut wrong_issuer = google_claims();
+        wrong_issuer["iss"] = serde_json::json!("https://evil.example.com");
+        assert!(validate(Provider::Google, &sign("provider-key", wrong_issuer)).is_err());
+
+        let mut expired = google_claims();
+        expired["exp"] = serde_json::json!(chrono::Utc::now().timestamp() - 3600);
+        assert!(validate(Provider::Google, &sign("provider-key", expired)).is_err());
+
+        assert!(validate(Provider::Google, &sign("unknown-key", google_claims())).is_err());
+    }
+
+    #[test]
+    fn test_validate_microsoft_issuer_must_match_tenant() {
+        let mut claims = google_claims();
+        claims["iss"] = serde_json::json!("https://login.microsoftonline.com/tenant-a/v2.0");
+        claims["tid"] = serde_json::json!("tenant-a");
+        assert!(validate(Provider::Microsoft, &sign("provider-key", claims.clone())).is_ok());
+
+        claims["tid"] = serde_json::json!("tenant-b");
+        assert!(validate(Provider::Microsoft, &sign("provider-key", claims.clone())).is_err());
+
+        claims.as_object_mut().unwrap().remove("tid");
+        assert!(validate(Provider::Microsoft, &sign("provider-key", claims)).is_err());
+    }
+
+    #[test]
+    fn test_email_verified_requires_matching_email() {
+        let user_info = UserInfo {
+            provider_user_id: "oid".to_string(),
+            email: "Ada@Example.com".to_string(),
+            email_verified: false,
+            name: None,
+            given_name: None,
+            family_name: None,
+            avatar_url: None,
+            locale: None,
+            username: None,
+        };
+        let mut claims = IdTokenClaims {
+            iss: "https://login.microsoftonline.com/tid/v2.0".to_string(),
+            sub: "sub".to_string(),
+            nonce: None,
+            oid: Some("oid".to_string()),
+            tid: Some("tid".to_string()),
+            email: Some("ada@example.com".to_string()),
+            email_verified: None,
+            xms_edov: Some(true),
+        };
+        assert!(IdTokenVerifier::email_verified(&claims, &user_info));
+
+        claims.email = Some("someone@else.com".to_string());
+        assert!(!IdTokenVerifier::email_verified(&claims, &user_info));
+
+        claims.email = Some("ada@example.com".to_string());
+        claims.xms_edov = None;
+        assert!(!IdTokenVerifier::email_verified(&claims, &user_info));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::auth::jwt::{ActorClaim, Claims, JwtService, ProfileClaims};
+use crate::auth::service_roles::ServiceRoleService;
+use crate::constants::{IMPERSONATION_DEFAULT_MINUTES, MAX_IMPERSONATION_MINUTES};
+use crate::db::models::{Service, Session, User};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::get_service_plan;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::handlers::platform::create_audit_log;
+use crate::middleware::{AuditContext, ClientInfo};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// Where an impersonation session is scoped: the platform dashboard, or one
+/// organization's service as the target end-user sees it
+pub struct ImpersonationScope<'a> {
+    pub org_slug: Option<&'a str>,
+    pub service: Option<&'a Service>,
+    pub via: &'a str, // 'platform' or 'organization'
+}
+
+/// A freshly issued impersonation session
+pub struct ImpersonationSession {
+    pub session_id: String,
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct ImpersonationService;
+
+impl ImpersonationService {
+    /// Validate the requested duration against the allowed window
+    pub fn duration(minutes: Option<i64>) -> Result<Duration> {
+        let minutes = minutes.unwrap_or(IMPERSONATION_DEFAULT_MINUTES);
+        if !(1..=MAX_IMPERSONATION_MINUTES).contains(&minutes) {
+            return Err(AppError::BadRequest(format!(
+                "duration_minutes must be between 1 and {}",
+                MAX_IMPERSONATION_MINUTES
+            )));
+        }
+        Ok(Duration::minutes(minutes))
+    }
+
+    /// A reason is mandatory so every session can be justified later
+    pub fn validate_reason(reason: &str) -> Result<String> {
+        let reason = reason.trim();
+        if reason.is_empty() || reason.len() > 500 {
+            return Err(AppError::BadRequest(
+                "A reason (1-500 characters) is required to impersonate a user".to_string(),
+            ));
+        }
+        Ok(reason.to_string())
+    }
+
+    /// Issue an access token for `target` carrying an `act` claim for `actor`.
+    /// The session has no refresh token, so it ends when the token expires.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        actor: &User,
+        target: &User,
+        scope: ImpersonationScope<'_>,
+        reason: &str,
+        duration: Duration,
+        client: &ClientInfo,
+    ) -> Result<ImpersonationSession> {
+        let now = Utc::now();
+        let expires_at = now + duration;
+
+        let (plan, features, roles, permissions) = match scope.service {
+            Some(service) => {
+                let (plan, features) = get_service_plan(pool, &target.id, &service.id).await?;
+                let (roles, permissions) =
+                    ServiceRoleService::effective_roles(pool, &target.id, &service.id).await?;
+                (Some(plan), features, Some(roles), Some(permissions))
+            }
+            None => (None, None, None, None),
+        };
+
+        let claims = Claims {
+            sub: target.id.clone(),
+            email: target.email.clone(),
+            is_platform_owner: target.is_platform_owner,
+            org: scope.org_slug.map(|s| s.to_string()),
+            service: scope.service.map(|s| s.slug.clone()),
+            plan,
+            features,
+            roles,
+            permissions,
+            act: Some(ActorClaim {
+                sub: actor.id.clone(),
+                email: actor.email.clone(),
+            }),
+            profile: ProfileClaims::from(target),
+            exp: expires_at.timestamp(),
+            iat: now.timestamp(),
+        };
+        let access_token = jwt_service.sign_claims(&claims)?;
+
+        let session_id = Uuid::new_v4().to_string();
+        sqlx::query(
+            r#"
+            INSERT INTO sessions
+            (id, user_id, token_hash, expires_at, org_slug, service_id, user_agent, ip_address,
+             created_at, last_used_at, impersonator_id, impersonation_reason)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&session_id)
+        .bind(&target.id)
+        .bind(JwtService::hash_token(&access_token))
+        .bind(expires_at)
+        .bind(scope.org_slug)
+        .bind(scope.service.map(|s| s.id.as_str()))
+        .bind(&client.user_agent)
+        .bind(&client.ip_address)
+        .bind(now)
+        .bind(now)
+        .bind(&actor.id)
+        .bind(reason)
+        .execute(pool)
+        .await?;
+
+        create_audit_log(
+            &mut *pool.acquire().await?,
+            &actor.id,
+            "impersonation_started",
+            "user",
+            &target.id,
+            Some(json!({
+                "session_id": session_id,
+                "via": scope.via,
+                "org_slug": scope.org_slug,
+                "service_slug": scope.service.map(|s| s.slug.as_str()),
+                "reason": reason,
+                "expires_at": expires_at,
+                "ip_address": client.ip_address,
+            })),
+        )
+        .await?;
+
+        Ok(ImpersonationSession {
+            session_id,
+            access_token,
+            expires_at,
+        })
+    }
+
+    /// Record the end of an impersonation session that has just been revoked,
+    /// in the platform log and, for organization sessions, in that org's log
+    pub async fn log_stopped(
+        pool: &SqlitePool,
+        session: &Session,
+        stopped_by: &str,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        let Some(ref impersonator_id) = session.impersonator_id else {
+            return Ok(());
+        };
+
+        let details = json!({
+            "session_id": session.id,
+            "stopped_by": stopped_by,
+        });
+
+        create_audit_log(
+            &mut *pool.acquire().await?,
+            impersonator_id,
+            "impersonation_stopped",
+            "user",
+            &session.user_id,
+            Some(details.clone()),
+        )
+        .await?;
+
+        if let Some(ref org_slug) = session.org_slug {
+            let org_id: Option<String> =
+                sqlx::query_scalar("SELECT id FROM organizations WHERE slug = ?")
+                    .bind(org_slug)
+                    .fetch_optional(pool)
+                    .await?;
+            if let Some(org_id) = org_id {
+                create_org_audit_log(
+                    &mut *pool.acquire().await?,
+                    audit,
+                    &org_id,
+                    "impersonation_stopped",
+                    "user",
+                    &session.user_id,
+                    diff(&serde_json::Value::Null, &details),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration_bounds() {
+        assert_eq!(
+            ImpersonationService::duration(None).unwrap(),
+            Duration::minutes(IMPERSONATION_DEFAULT_MINUTES)
+        );
+        assert!(ImpersonationService::duration(Some(0)).is_err());
+        assert!(ImpersonationService::duration(Some(MAX_IMPERSONATION_MINUTES + 1)).is_err());
+        assert!(ImpersonationService::validate_reason("   ").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::auth::email::{EmailService, TEMPLATE_INVITATION_REMINDER};
+use crate::db::models::{Organization, OrganizationInvitation, User};
+use crate::encryption::EncryptionService;
+use crate::error::{AppError, Result};
+use crate::handlers::invitations::hash_invitation_token;
+use chrono::{DateTime, Utc};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+/// Path of the link in invitation emails; it redirects to the accept page
+pub const INVITATION_ACCEPT_PATH: &str = "/invitations/accept";
+
+pub struct InvitationService;
+
+impl InvitationService {
+    /// Link in invitation emails for `token`
+    pub fn accept_url(base_url: &str, token: &str) -> String {
+        format!("{}{}?token={}", base_url, INVITATION_ACCEPT_PATH, token)
+    }
+
+    /// Queue an invitation email carrying `token` in its accept link
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_email(
+        conn: &mut SqliteConnection,
+        base_url: &str,
+        template: &str,
+        organization: &Organization,
+        inviter: &User,
+        invitation: &OrganizationInvitation,
+        token: &str,
+    ) -> Result<()> {
+        let accept_url = Self::accept_url(base_url, token);
+        EmailService::enqueue(
+            conn,
+            Some(&organization.id),
+            template,
+            &invitation.email,
+            &[
+                ("org_name", &organization.name),
+                (
+                    "inviter_name",
+                    inviter.name.as_deref().unwrap_or(&inviter.email),
+                ),
+                ("role", &invitation.role),
+                ("accept_url", &accept_url),
+                (
+                    "expires_at",
+                    &invitation
+                        .expires_at
+                        .format("%Y-%m-%d %H:%M UTC")
+                        .to_string(),
+                ),
+            ],
+        )
+        .await
+    }
+
+    /// Keep an encrypted copy of the invitation's token so reminders can
+    /// carry the same link. Without encryption nothing is kept and no
+    /// reminder is sent.
+    pub async fn keep_token(
+        conn: &mut SqliteConnection,
+        encryption: Option<&EncryptionService>,
+        invitation_id: &str,
+        token: &str,
+    ) -> Result<()> {
+        let token_encrypted = encryption
+            .map(|enc| enc.encrypt(token))
+            .transpose()
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Failed to encrypt invitation token: {}", e))
+            })?;
+
+        sqlx::query("UPDATE organization_invitations SET token_encrypted = ? WHERE id = ?")
+            .bind(token_encrypted)
+            .bind(invitation_id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Email the reminder for a pending invitation with its current link and
+    /// record that it went out. Returns false when the token was not kept.
+    pub async fn remind(
+        conn: &mut SqliteConnection,
+        base_url: &str,
+        encryption: &EncryptionService,
+        invitation: &OrganizationInvitation,
+    ) -> Result<bool> {
+        let token_encrypted: Option<Vec<u8>> = sqlx::query_scalar(
+            "UPDATE organization_invitations SET reminder_sent_at = ?
+             WHERE id = ? AND token_encrypted IS NOT NULL
+             RETURNING token_encrypted",
+        )
+        .bind(Utc::now())
+        .bind(&invitation.id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(token_encrypted) = token_encrypted else {
+            return Ok(false);
+        };
+        let token = encryption.decrypt(&token_encrypted).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to decrypt invitation token: {}", e))
+        })?;
+
+        let (organization, inviter) = Self::parties(conn, invitation).await?;
+        Self::send_email(
+            conn,
+            base_url,
+            TEMPLATE_INVITATION_REMINDER,
+            &organization,
+            &inviter,
+            invitation,
+            &token,
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Organization and inviter named in the invitation email
+    async fn parties(
+        conn: &mut SqliteConnection,
+        invitation: &OrganizationInvitation,
+    ) -> Result<(Organization, User)> {
+        let organization =
+            sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE id = ?")
+                .bind(&invitation.org_id)
+                .fetch_one(&mut *conn)
+                .await?;
+        let inviter = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(&invitation.invited_by)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok((organization, inviter))
+    }
+
+    /// Replace the invitation's token, set its expiry and make it pending
+    /// again, then email the new link with `template`. Only the hash (and,
+    /// with encryption, an encrypted copy) is stored, so links sent earlier
+    /// stop working. Returns the plaintext token.
+    pub async fn reissue(
+        conn: &mut SqliteConnection,
+        base_url: &str,
+        encryption: Option<&EncryptionService>,
+        template: &str,
+        invitation_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(OrganizationInvitation, String)> {
+        let token = Uuid::new_v4().to_string();
+        let invitation = sqlx::query_as::<_, OrganizationInvitation>(
+            "UPDATE organization_invitations
+             SET token = ?, expires_at = ?, status = 'pending', last_sent_at = ?, reminder_sent_at = NULL
+             WHERE id = ?
+             RETURNING *",
+        )
+        .bind(hash_invitation_token(&token))
+        .bind(expires_at)
+        .bind(Utc::now())
+        .bind(invitation_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        Self::keep_token(conn, encryption, invitation_id, &token).await?;
+
+        let (organization, inviter) = Self::parties(conn, &invitation).await?;
+        Self::send_email(
+            conn,
+            base_url,
+            template,
+            &organization,
+            &inviter,
+            &invitation,
+            &token,
+        )
+        .await?;
+
+        Ok((invitation, token))
+    }
+}
@@ -0,0 +1,456 @@
+use crate::auth::security_events::{SecurityEventService, SECURITY_EVENT_LOGIN_LOCKOUT};
+use crate::config::Config;
+use crate::constants::LOGIN_THROTTLE_MAX_SECONDS;
+use crate::db::models::LoginLockout;
+use crate::error::{AppError, Result};
+use crate::middleware::ClientInfo;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+// Sign-in flows a failure can come from
+pub const FLOW_OAUTH: &str = "oauth";
+pub const FLOW_DEVICE: &str = "device";
+pub const FLOW_CIBA: &str = "ciba";
+pub const FLOW_REFRESH: &str = "refresh";
+pub const FLOW_LOGIN_CHALLENGE: &str = "login_challenge";
+pub const FLOW_SSO_SESSION: &str = "sso_session";
+
+// Failure reasons
+pub const FAILURE_INVALID_STATE: &str = "invalid_state";
+pub const FAILURE_BROWSER_BINDING_MISMATCH: &str = "browser_binding_mismatch";
+pub const FAILURE_PROVIDER_DENIED: &str = "provider_denied";
+pub const FAILURE_PROVIDER_ERROR: &str = "provider_error";
+pub const FAILURE_REDIRECT_URI_MISMATCH: &str = "redirect_uri_mismatch";
+pub const FAILURE_ORG_NOT_ACTIVE: &str = "org_not_active";
+pub const FAILURE_EMAIL_NOT_VERIFIED: &str = "email_not_verified";
+pub const FAILURE_IDENTITY_CONFLICT: &str = "identity_conflict";
+pub const FAILURE_SIGN_IN_RESTRICTED: &str = "sign_in_restricted";
+pub const FAILURE_INVALID_USER_CODE: &str = "invalid_user_code";
+pub const FAILURE_DEVICE_CODE_EXPIRED: &str = "device_code_expired";
+pub const FAILURE_INVALID_REFRESH_TOKEN: &str = "invalid_refresh_token";
+pub const FAILURE_REFRESH_TOKEN_EXPIRED: &str = "refresh_token_expired";
+pub const FAILURE_REFRESH_TOKEN_REUSE: &str = "refresh_token_reuse";
+pub const FAILURE_INVALID_LOGIN_CODE: &str = "invalid_login_code";
+pub const FAILURE_LOCKED_OUT: &str = "locked_out";
+pub const FAILURE_INTERNAL_ERROR: &str = "internal_error";
+pub const FAILURE_OTHER: &str = "other";
+
+// Lockout scopes
+pub const LOCKOUT_SCOPE_USER: &str = "user";
+pub const LOCKOUT_SCOPE_IDENTITY: &str = "identity";
+pub const LOCKOUT_SCOPE_IP: &str = "ip";
+pub const LOCKOUT_SCOPES: &[&str] = &[LOCKOUT_SCOPE_USER, LOCKOUT_SCOPE_IDENTITY, LOCKOUT_SCOPE_IP];
+
+/// Thresholds for throttling and locking out, from the environment
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    pub window_minutes: i64,
+    pub throttle_after: i64,
+    pub lockout_after: i64,
+    pub ip_throttle_after: i64,
+    pub ip_lockout_after: i64,
+    pub lockout_minutes: i64,
+}
+
+impl LockoutPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            window_minutes: config.login_failure_window_minutes,
+            throttle_after: config.login_throttle_after_failures,
+            lockout_after: config.login_lockout_after_failures,
+            ip_throttle_after: config.login_ip_throttle_after_failures,
+            ip_lockout_after: config.login_ip_lockout_after_failures,
+            lockout_minutes: config.login_lockout_minutes,
+        }
+    }
+
+    /// Failures before throttling and before locking out. A shared address
+    /// (office NAT, mobile carrier) sees many users, so IPs get more room.
+    fn thresholds(&self, scope: &str) -> (i64, i64) {
+        if scope == LOCKOUT_SCOPE_IP {
+            (self.ip_throttle_after, self.ip_lockout_after)
+        } else {
+            (self.throttle_after, self.lockout_after)
+        }
+    }
+
+    /// Seconds to wait after the last failure; doubles with every failure
+    /// past the throttle threshold
+    fn throttle_seconds(&self, scope: &str, failures: i64) -> i64 {
+        let (throttle_after, _) = self.thresholds(scope);
+        if failures < throttle_after {
+            return 0;
+        }
+        let doublings = (failures - throttle_after).min(16) as u32;
+        2i64.pow(doublings).min(LOGIN_THROTTLE_MAX_SECONDS)
+    }
+}
+
+/// What is known about a sign-in attempt, filled in as the flow goes. When
+/// the attempt fails, this is what gets recorded.
+#[derive(Debug, Clone, Default)]
+pub struct LoginAttempt {
+    pub flow: &'static str,
+    pub reason: Option<&'static str>,
+    pub user_id: Option<String>,
+    pub provider: Option<String>,
+    pub provider_user_id: Option<String>,
+    pub org_id: Option<String>,
+    pub service_id: Option<String>,
+}
+
+impl LoginAttempt {
+    pub fn new(flow: &'static str) -> Self {
+        Self {
+            flow,
+            ..Default::default()
+        }
+    }
+
+    /// Tag the error of a failing step with a reason, for `map_err`
+    pub fn fail(&mut self, reason: &'static str) -> impl FnOnce(AppError) -> AppError + '_ {
+        move |e| {
+            self.reason = Some(reason);
+            e
+        }
+    }
+
+    /// The reason this attempt failed with `error`
+    fn reason_for(&self, error: &AppError) -> &'static str {
+        if let AppError::TooManyAttempts { .. } = error {
+            return FAILURE_LOCKED_OUT;
+        }
+        if let Some(reason) = self.reason {
+            return reason;
+        }
+        match error {
+            AppError::OAuth(_) => FAILURE_PROVIDER_ERROR,
+            AppError::OrganizationNotActive => FAILURE_ORG_NOT_ACTIVE,
+            AppError::Database(_) | AppError::InternalServerError(_) => FAILURE_INTERNAL_ERROR,
+            _ => FAILURE_OTHER,
+        }
+    }
+
+    fn identity_subject(&self) -> Option<String> {
+        Some(format!(
+            "{}:{}",
+            self.provider.as_deref()?,
+            self.provider_user_id.as_deref()?
+        ))
+    }
+
+    /// Every (scope, subject) the attempt can be throttled on
+    fn subjects(&self, client: &ClientInfo) -> Vec<(&'static str, String)> {
+        let mut subjects = Vec::new();
+        if let Some(ref ip) = client.ip_address {
+            subjects.push((LOCKOUT_SCOPE_IP, ip.clone()));
+        }
+        if let Some(identity) = self.identity_subject() {
+            subjects.push((LOCKOUT_SCOPE_IDENTITY, identity));
+        }
+        if let Some(ref user_id) = self.user_id {
+            subjects.push((LOCKOUT_SCOPE_USER, user_id.clone()));
+        }
+        subjects
+    }
+}
+
+/// A user cancelling at the provider, an app retrying a refresh token that
+/// ran out, a lockout that is already running and our own errors say nothing
+/// about who is trying to sign in
+fn counts_toward_lockout(reason: &str) -> bool {
+    !matches!(
+        reason,
+        FAILURE_PROVIDER_DENIED
+            | FAILURE_REFRESH_TOKEN_EXPIRED
+            | FAILURE_LOCKED_OUT
+            | FAILURE_INTERNAL_ERROR
+    )
+}
+
+pub struct LoginFailureService;
+
+impl LoginFailureService {
+    /// Refuse the attempt while any of its subjects is locked out or still
+    /// inside its throttle delay
+    pub async fn check(
+        pool: &SqlitePool,
+        policy: &LockoutPolicy,
+        attempt: &LoginAttempt,
+        client: &ClientInfo,
+    ) -> Result<()> {
+        let now = Utc::now();
+        for (scope, subject) in attempt.subjects(client) {
+            let Some(lockout) = Self::find(pool, scope, &subject).await? else {
+                continue;
+            };
+
+            if let Some(locked_until) = lockout.locked_until.filter(|until| *until > now) {
+                return Err(AppError::TooManyAttempts {
+                    message: format!(
+                        "Too many failed sign-in attempts, locked until {}",
+                        locked_until.format("%Y-%m-%d %H:%M UTC")
+                    ),
+                    retry_after: (locked_until - now).num_seconds().max(1),
+                });
+            }
+
+            if Self::window_expired(policy, &lockout, now) {
+                continue;
+            }
+            let delay = policy.throttle_seconds(scope, lockout.failures);
+            let retry_at = lockout.last_failure_at + Duration::seconds(delay);
+            if retry_at > now {
+                let retry_after = (retry_at - now).num_seconds().max(1);
+                return Err(AppError::TooManyAttempts {
+                    message: format!(
+                        "Too many failed sign-in attempts, try again in {} seconds",
+                        retry_after
+                    ),
+                    retry_after,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a failed attempt and count it against its user, identity and
+    /// IP. A subject that reaches the lockout threshold is locked and a
+    /// security event is recorded. Returns the reason code.
+    pub async fn record(
+        pool: &SqlitePool,
+        policy: &LockoutPolicy,
+        attempt: &LoginAttempt,
+        client: &ClientInfo,
+        error: &AppError,
+    ) -> Result<&'static str> {
+        let reason = attempt.reason_for(error);
+        let now = Utc::now();
+
+        sqlx::query(
+            r#"
+            INSERT INTO login_failures
+                (id, flow, reason, user_id, provider, provider_user_id, org_id, service_id,
+                 ip_address, user_agent, detail, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(attempt.flow)
+        .bind(reason)
+        .bind(&attempt.user_id)
+        .bind(&attempt.provider)
+        .bind(&attempt.provider_user_id)
+        .bind(&attempt.org_id)
+        .bind(&attempt.service_id)
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .bind(error.to_string())
+        .bind(now)
+        .execute(pool)
+        .await?;
+
+        if !counts_toward_lockout(reason) {
+            return Ok(reason);
+        }
+
+        for (scope, subject) in attempt.subjects(client) {
+            let lockout = Self::find(pool, scope, &subject).await?;
+            // A new window starts once the old one has passed or the last
+            // lockout has run out
+            let failures = match lockout {
+                Some(ref lockout)
+                    if !Self::window_expired(policy, lockout, now)
+                        && lockout.locked_until.is_none_or(|until| until > now) =>
+                {
+                    lockout.failures + 1
+                }
+                _ => 1,
+            };
+            let window_started_at = match lockout {
+                Some(ref lockout) if failures > 1 => lockout.window_started_at,
+                _ => now,
+            };
+
+            let (_, lockout_after) = policy.thresholds(scope);
+            let newly_locked = failures == lockout_after;
+            let locked_until = match lockout {
+                Some(ref lockout) if failures > 1 && lockout.locked_until.is_some() => {
+                    lockout.locked_until
+                }
+                _ if newly_locked => Some(now + Duration::minutes(policy.lockout_minutes)),
+                _ => None,
+            };
+            let owner = match scope {
+                LOCKOUT_SCOPE_IP => None,
+                _ => attempt.user_id.as_deref(),
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO login_lockouts
+                    (scope, subject, user_id, failures, window_started_at, last_failure_at, locked_until)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (scope, subject) DO UPDATE SET
+                    user_id = COALESCE(excluded.user_id, login_lockouts.user_id),
+                    failures = excluded.failures,
+                    window_started_at = excluded.window_started_at,
+                    last_failure_at = excluded.last_failure_at,
+                    locked_until = excluded.locked_until
+                "#,
+            )
+            .bind(scope)
+            .bind(&subject)
+            .bind(owner)
+            .bind(failures)
+            .bind(window_started_at)
+            .bind(now)
+            .bind(locked_until)
+            .execute(pool)
+            .await?;
+
+            if newly_locked {
+                tracing::warn!(
+                    "Locked out {} {} after {} failed sign-ins",
+                    scope,
+                    subject,
+                    failures
+                );
+                SecurityEventService::record(
+                    pool,
+                    SECURITY_EVENT_LOGIN_LOCKOUT,
+                    "medium",
+                    attempt.user_id.as_deref(),
+                    attempt.org_id.as_deref(),
+                    client,
+                    serde_json::json!({
+                        "scope": scope,
+                        "subject": subject,
+                        "failures": failures,
+                        "locked_until": locked_until,
+                        "reason": reason,
+                    }),
+                )
+                .await?;
+            }
+        }
+
+        Ok(reason)
+    }
+
+    /// A successful sign-in clears the user's and identity's counters. The
+    /// IP keeps its count: one working account must not reset it.
+    pub async fn clear(pool: &SqlitePool, attempt: &LoginAttempt) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM login_lockouts
+             WHERE (scope = 'user' AND subject = ?) OR (scope = 'identity' AND subject = ?)",
+        )
+        .bind(&attempt.user_id)
+        .bind(attempt.identity_subject())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Counters on a user and on their identities
+    pub async fn list_for_user(pool: &SqlitePool, user_id: &str) -> Result<Vec<LoginLockout>> {
+        let lockouts = sqlx::query_as::<_, LoginLockout>(
+            r#"
+            SELECT * FROM login_lockouts
+            WHERE (scope = 'user' AND subject = ?)
+               OR (scope = 'identity' AND (user_id = ? OR subject IN (
+                      SELECT provider || ':' || provider_user_id FROM identities WHERE user_id = ?)))
+            ORDER BY scope, subject
+            "#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(lockouts)
+    }
+
+    /// Clear a user's and their identities' counters (admin unlock).
+    /// Returns how many were cleared.
+    pub async fn unlock_user(pool: &SqlitePool, user_id: &str) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM login_lockouts
+            WHERE (scope = 'user' AND subject = ?)
+               OR (scope = 'identity' AND (user_id = ? OR subject IN (
+                      SELECT provider || ':' || provider_user_id FROM identities WHERE user_id = ?)))
+            "#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Clear one counter (platform owner unlock). Returns false if there was none.
+    pub async fn unlock(pool: &SqlitePool, scope: &str, subject: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM login_lockouts WHERE scope = ? AND subject = ?")
+            .bind(scope)
+            .bind(subject)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find(
+        pool: &SqlitePool,
+        scope: &str,
+        subject: &str,
+    ) -> Result<Option<LoginLockout>> {
+        let lockout = sqlx::query_as::<_, LoginLockout>(
+            "SELECT * FROM login_lockouts WHERE scope = ? AND subject = ?",
+        )
+        .bind(scope)
+        .bind(subject)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    fn window_expired(policy: &LockoutPolicy, lockout: &LoginLockout, now: DateTime<Utc>) -> bool {
+        lockout.locked_until.is_none()
+            && lockout.window_started_at + Duration::minutes(policy.window_minutes) <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_seconds() {
+        let policy = LockoutPolicy {
+            window_minutes: 15,
+            throttle_after: 3,
+            lockout_after: 10,
+            ip_throttle_after: 20,
+            ip_lockout_after: 100,
+            lockout_minutes: 15,
+        };
+
+        assert_eq!(policy.throttle_seconds(LOCKOUT_SCOPE_USER, 2), 0);
+        assert_eq!(policy.throttle_seconds(LOCKOUT_SCOPE_USER, 3), 1);
+        assert_eq!(policy.throttle_seconds(LOCKOUT_SCOPE_USER, 5), 4);
+        assert_eq!(
+            policy.throttle_seconds(LOCKOUT_SCOPE_USER, 40),
+            LOGIN_THROTTLE_MAX_SECONDS
+        );
+        assert_eq!(policy.throttle_seconds(LOCKOUT_SCOPE_IP, 5), 0);
+        assert_eq!(policy.throttle_seconds(LOCKOUT_SCOPE_IP, 21), 2);
+    }
+}
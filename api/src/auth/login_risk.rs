@@ -0,0 +1,448 @@
+use crate::auth::email::{EmailService, TEMPLATE_LOGIN_CODE};
+use crate::auth::jwt::JwtService;
+use crate::constants::{
+    IMPOSSIBLE_TRAVEL_MIN_DISTANCE_KM, IMPOSSIBLE_TRAVEL_SPEED_KMH, LOGIN_CHALLENGE_EXPIRE_MINUTES,
+    LOGIN_CHALLENGE_MAX_ATTEMPTS, LOGIN_RISK_HIGH_SCORE, LOGIN_RISK_IMPOSSIBLE_TRAVEL_SCORE,
+    LOGIN_RISK_MEDIUM_SCORE, LOGIN_RISK_NEW_COUNTRY_SCORE, LOGIN_RISK_NEW_DEVICE_SCORE,
+};
+use crate::db::models::LoginChallenge;
+use crate::error::{AppError, Result};
+use crate::geoip::{distance_km, GeoIpLookup, GeoLocation};
+use crate::middleware::ClientInfo;
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::net::IpAddr;
+use uuid::Uuid;
+
+// Risk reasons
+pub const RISK_NEW_DEVICE: &str = "new_device";
+pub const RISK_NEW_COUNTRY: &str = "new_country";
+pub const RISK_IMPOSSIBLE_TRAVEL: &str = "impossible_travel";
+
+/// What an organization's policy does with a risky login
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskAction {
+    Allow,
+    Notify,
+    /// Hold the login until the user enters a code sent to their email.
+    /// This is not a registered second factor: mailbox access is enough.
+    RequireEmailCode,
+    Block,
+}
+
+impl RiskAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskAction::Allow => "allow",
+            RiskAction::Notify => "notify",
+            RiskAction::RequireEmailCode => "require_email_code",
+            RiskAction::Block => "block",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(RiskAction::Allow),
+            "notify" => Some(RiskAction::Notify),
+            "require_email_code" => Some(RiskAction::RequireEmailCode),
+            "block" => Some(RiskAction::Block),
+            _ => None,
+        }
+    }
+}
+
+/// A login compared with the user's earlier successful logins
+#[derive(Debug, Clone)]
+pub struct LoginRiskAssessment {
+    pub device_fingerprint: Option<String>,
+    pub location: Option<GeoLocation>,
+    pub score: i64,
+    pub reasons: Vec<&'static str>,
+    pub action: RiskAction,
+}
+
+impl LoginRiskAssessment {
+    pub fn country(&self) -> Option<&str> {
+        self.location.as_ref()?.country.as_deref()
+    }
+
+    pub fn reasons_json(&self) -> Option<String> {
+        (!self.reasons.is_empty()).then(|| serde_json::json!(self.reasons).to_string())
+    }
+
+    /// Reasons for an email, e.g. "new device, new country"
+    pub fn reasons_text(&self) -> String {
+        self.reasons
+            .iter()
+            .map(|reason| reason.replace('_', " "))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Country and coordinates for an email
+    pub fn location_text(&self) -> String {
+        match self.location {
+            Some(ref location) => match (location.country.as_deref(), location.coordinates()) {
+                (Some(country), Some((lat, lon))) => {
+                    format!("{} ({:.1}, {:.1})", country, lat, lon)
+                }
+                (Some(country), None) => country.to_string(),
+                (None, Some((lat, lon))) => format!("{:.1}, {:.1}", lat, lon),
+                (None, None) => "unknown".to_string(),
+            },
+            None => "unknown".to_string(),
+        }
+    }
+}
+
+/// What the user's earlier successful logins looked like
+#[derive(Debug, Default)]
+struct LoginHistory {
+    device_fingerprints: Vec<String>,
+    countries: Vec<String>,
+    last_position: Option<((f64, f64), DateTime<Utc>)>,
+}
+
+pub struct LoginRiskService;
+
+impl LoginRiskService {
+    /// Hash of the user agent with version numbers removed, so a browser
+    /// update does not look like a new device
+    pub fn device_fingerprint(user_agent: &str) -> String {
+        let mut normalized = String::with_capacity(user_agent.len());
+        for c in user_agent.trim().chars() {
+            if c.is_ascii_digit() || c == '.' || c == '_' {
+                if !normalized.ends_with('#') {
+                    normalized.push('#');
+                }
+            } else {
+                normalized.push(c.to_ascii_lowercase());
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(normalized.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// Score a login against the user's history and look up what the
+    /// organization's policy does at that score. A user's first login has
+    /// no history and is never flagged.
+    pub async fn assess(
+        pool: &SqlitePool,
+        geoip: &dyn GeoIpLookup,
+        user_id: &str,
+        org_id: &str,
+        client: &ClientInfo,
+    ) -> Result<LoginRiskAssessment> {
+        let device_fingerprint = client.user_agent.as_deref().map(Self::device_fingerprint);
+        let location = client
+            .ip_address
+            .as_deref()
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .and_then(|ip| geoip.lookup(ip));
+
+        let history = Self::history(pool, user_id).await?;
+        let reasons = Self::signals(
+            &history,
+            device_fingerprint.as_deref(),
+            location.as_ref(),
+            Utc::now(),
+        );
+        let score = Self::score(&reasons);
+
+        let (medium_action, high_action): (String, String) = sqlx::query_as(
+            "SELECT login_risk_medium_action, login_risk_high_action FROM organizations WHERE id = ?",
+        )
+        .bind(org_id)
+        .fetch_one(pool)
+        .await?;
+        let action = if score >= LOGIN_RISK_HIGH_SCORE {
+            RiskAction::from_name(&high_action).unwrap_or(RiskAction::Notify)
+        } else if score >= LOGIN_RISK_MEDIUM_SCORE {
+            RiskAction::from_name(&medium_action).unwrap_or(RiskAction::Notify)
+        } else {
+            RiskAction::Allow
+        };
+
+        Ok(LoginRiskAssessment {
+            device_fingerprint,
+            location,
+            score,
+            reasons,
+            action,
+        })
+    }
+
+    async fn history(pool: &SqlitePool, user_id: &str) -> Result<LoginHistory> {
+        // Fingerprints are recomputed so logins from before fingerprinting count
+        let user_agents = sqlx::query_scalar::<_, String>(
+            "SELECT DISTINCT user_agent FROM login_events
+             WHERE user_id = ? AND outcome = 'success' AND user_agent IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let countries = sqlx::query_scalar::<_, String>(
+            "SELECT DISTINCT country FROM login_events
+             WHERE user_id = ? AND outcome = 'success' AND country IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let last_position = sqlx::query_as::<_, (f64, f64, DateTime<Utc>)>(
+            "SELECT latitude, longitude, created_at FROM login_events
+             WHERE user_id = ? AND outcome = 'success'
+               AND latitude IS NOT NULL AND longitude IS NOT NULL
+             ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let mut device_fingerprints: Vec<String> = user_agents
+            .iter()
+            .map(|user_agent| Self::device_fingerprint(user_agent))
+            .collect();
+        device_fingerprints.sort_unstable();
+        device_fingerprints.dedup();
+
+        Ok(LoginHistory {
+            device_fingerprints,
+            countries,
+            last_position: last_position.map(|(lat, lon, at)| ((lat, lon), at)),
+        })
+    }
+
+    fn signals(
+        history: &LoginHistory,
+        device_fingerprint: Option<&str>,
+        location: Option<&GeoLocation>,
+        now: DateTime<Utc>,
+    ) -> Vec<&'static str> {
+        let mut reasons = Vec::new();
+
+        if let Some(fingerprint) = device_fingerprint {
+            if !history.device_fingerprints.is_empty()
+                && !history
+                    .device_fingerprints
+                    .iter()
+                    .any(|known| known == fingerprint)
+            {
+                reasons.push(RISK_NEW_DEVICE);
+            }
+        }
+
+        if let Some(country) = location.and_then(|l| l.country.as_deref()) {
+            if !history.countries.is_empty() && !history.countries.iter().any(|c| c == country) {
+                reasons.push(RISK_NEW_COUNTRY);
+            }
+        }
+
+        if let (Some(here), Some((there, at))) = (
+            location.and_then(GeoLocation::coordinates),
+            history.last_position,
+        ) {
+            let distance = distance_km(there, here);
+            let hours = (now - at).num_seconds().max(1) as f64 / 3600.0;
+            if distance >= IMPOSSIBLE_TRAVEL_MIN_DISTANCE_KM
+                && distance / hours > IMPOSSIBLE_TRAVEL_SPEED_KMH
+            {
+                reasons.push(RISK_IMPOSSIBLE_TRAVEL);
+            }
+        }
+
+        reasons
+    }
+
+    fn score(reasons: &[&str]) -> i64 {
+        let total: i64 = reasons
+            .iter()
+            .map(|reason| match *reason {
+                RISK_NEW_DEVICE => LOGIN_RISK_NEW_DEVICE_SCORE,
+                RISK_NEW_COUNTRY => LOGIN_RISK_NEW_COUNTRY_SCORE,
+                RISK_IMPOSSIBLE_TRAVEL => LOGIN_RISK_IMPOSSIBLE_TRAVEL_SCORE,
+                _ => 0,
+            })
+            .sum();
+        total.min(100)
+    }
+
+    /// Hold a login back until the user enters the code emailed to them.
+    /// Returns the challenge id the service passes back with the code.
+    pub async fn create_challenge(
+        conn: &mut SqliteConnection,
+        login_event_id: &str,
+        user_id: &str,
+        service_id: &str,
+        org_id: &str,
+        risk: &LoginRiskAssessment,
+        client: &ClientInfo,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(LOGIN_CHALLENGE_EXPIRE_MINUTES);
+
+        sqlx::query(
+            r#"
+            INSERT INTO login_challenges
+                (id, login_event_id, user_id, service_id, code_hash, expires_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&id)
+        .bind(login_event_id)
+        .bind(user_id)
+        .bind(service_id)
+        .bind(JwtService::hash_token(&code))
+        .bind(expires_at)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        let (email, service_name): (String, String) = sqlx::query_as(
+            "SELECT u.email, s.name FROM users u, services s WHERE u.id = ? AND s.id = ?",
+        )
+        .bind(user_id)
+        .bind(service_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        EmailService::enqueue(
+            conn,
+            Some(org_id),
+            TEMPLATE_LOGIN_CODE,
+            &email,
+            &[
+                ("service_name", &service_name),
+                ("code", &code),
+                (
+                    "expires_at",
+                    &expires_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+                ),
+                (
+                    "ip_address",
+                    client.ip_address.as_deref().unwrap_or("unknown"),
+                ),
+                ("location", &risk.location_text()),
+            ],
+        )
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Check a code against an open challenge and mark it completed. Wrong
+    /// codes count against the challenge; once the attempts are used up the
+    /// user has to sign in again.
+    pub async fn verify_challenge(
+        pool: &SqlitePool,
+        challenge_id: &str,
+        code: &str,
+    ) -> Result<LoginChallenge> {
+        let challenge = sqlx::query_as::<_, LoginChallenge>(
+            "SELECT * FROM login_challenges WHERE id = ? AND completed_at IS NULL",
+        )
+        .bind(challenge_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Login challenge not found".to_string()))?;
+
+        if challenge.expires_at < Utc::now() || challenge.attempts >= LOGIN_CHALLENGE_MAX_ATTEMPTS {
+            return Err(AppError::Unauthorized(
+                "Login challenge has expired, sign in again".to_string(),
+            ));
+        }
+
+        if JwtService::hash_token(code.trim()) != challenge.code_hash {
+            sqlx::query("UPDATE login_challenges SET attempts = attempts + 1 WHERE id = ?")
+                .bind(&challenge.id)
+                .execute(pool)
+                .await?;
+            return Err(AppError::Unauthorized("Invalid code".to_string()));
+        }
+
+        // Only one request may complete the challenge
+        let completed = sqlx::query(
+            "UPDATE login_challenges SET completed_at = ? WHERE id = ? AND completed_at IS NULL",
+        )
+        .bind(Utc::now())
+        .bind(&challenge.id)
+        .execute(pool)
+        .await?;
+        if completed.rows_affected() == 0 {
+            return Err(AppError::NotFound("Login challenge not found".to_string()));
+        }
+
+        Ok(challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(location: (f64, f64), country: &str) -> GeoLocation {
+        GeoLocation {
+            country: Some(country.to_string()),
+            latitude: Some(location.0),
+            longitude: Some(location.1),
+        }
+    }
+
+    #[test]
+    fn test_login_signals() {
+        let chrome_119 = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/119.0.6045.105";
+        let chrome_120 = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/120.0.6099.71";
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+        assert_eq!(
+            LoginRiskService::device_fingerprint(chrome_119),
+            LoginRiskService::device_fingerprint(chrome_120)
+        );
+
+        let now = Utc::now();
+        let london = (51.5074, -0.1278);
+        let paris = (48.8566, 2.3522);
+        let new_york = (40.7128, -74.0060);
+        let history = LoginHistory {
+            device_fingerprints: vec![LoginRiskService::device_fingerprint(chrome_119)],
+            countries: vec!["GB".to_string()],
+            last_position: Some((london, now - Duration::hours(2))),
+        };
+
+        let same = LoginRiskService::signals(
+            &history,
+            Some(&LoginRiskService::device_fingerprint(chrome_120)),
+            Some(&at(london, "GB")),
+            now,
+        );
+        assert!(same.is_empty());
+
+        // 340 km in two hours is a short flight, not impossible travel
+        let nearby = LoginRiskService::signals(
+            &history,
+            Some(&LoginRiskService::device_fingerprint(firefox)),
+            Some(&at(paris, "FR")),
+            now,
+        );
+        assert_eq!(nearby, vec![RISK_NEW_DEVICE, RISK_NEW_COUNTRY]);
+        assert_eq!(LoginRiskService::score(&nearby), 70);
+
+        let far = LoginRiskService::signals(&history, None, Some(&at(new_york, "US")), now);
+        assert_eq!(far, vec![RISK_NEW_COUNTRY, RISK_IMPOSSIBLE_TRAVEL]);
+        assert_eq!(LoginRiskService::score(&far), 100);
+
+        let first_login = LoginRiskService::signals(
+            &LoginHistory::default(),
+            Some(&LoginRiskService::device_fingerprint(firefox)),
+            Some(&at(new_york, "US")),
+            now,
+        );
+        assert!(first_login.is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+use crate::auth::jwt::{Claims, JwtService};
+use crate::auth::sso_session::SsoSessionService;
+use crate::auth::webhooks::WebhookService;
+use crate::constants::WEBHOOK_EVENT_SESSION_REVOKED;
+use crate::db::models::{Organization, Service, Session};
+use crate::error::{AppError, Result};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+pub struct LogoutService;
+
+impl LogoutService {
+    /// Queue a signed logout_token for every revoked session whose service
+    /// registered a back-channel logout URI, and a `session.revoked` webhook
+    /// event for its organization. Delivery (with retries) is done by the
+    /// BackchannelLogoutJob and the WebhookDeliveryJob.
+    pub async fn notify_services(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        issuer: &str,
+        sessions: &[Session],
+    ) -> Result<()> {
+        for session in sessions {
+            let Some(ref service_id) = session.service_id else {
+                continue;
+            };
+
+            let service = sqlx::query_as::<_, Service>("SELECT * FROM services WHERE id = ?")
+                .bind(service_id)
+                .fetch_optional(pool)
+                .await?;
+
+            let Some(service) = service else {
+                continue;
+            };
+
+            WebhookService::emit(
+                &mut *pool.acquire().await?,
+                &service.org_id,
+                WEBHOOK_EVENT_SESSION_REVOKED,
+                json!({
+                    "user_id": session.user_id,
+                    "session_id": session.id,
+                    "service_id": service.id,
+                }),
+            )
+            .await?;
+
+            let Some(logout_uri) = service.backchannel_logout_uri else {
+                continue;
+            };
+
+            let logout_token = jwt_service.create_logout_token(
+                issuer,
+                &service.client_id,
+                &session.user_id,
+                &session.id,
+            )?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO backchannel_logout_deliveries
+                (id, service_id, session_id, logout_uri, logout_token, status, attempts, next_attempt_at, created_at)
+                VALUES (?, ?, ?, ?, ?, 'pending', 0, ?, ?)
+                "#,
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&service.id)
+            .bind(&session.id)
+            .bind(&logout_uri)
+            .bind(&logout_token)
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete the user's sessions in one organization and propagate the
+    /// logout to its services; sessions elsewhere are kept
+    pub async fn revoke_org_sessions(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        issuer: &str,
+        user_id: &str,
+        org: &Organization,
+    ) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            "DELETE FROM sessions
+             WHERE user_id = ?
+               AND (org_slug = ? OR service_id IN (SELECT id FROM services WHERE org_id = ?))
+             RETURNING *",
+        )
+        .bind(user_id)
+        .bind(&org.slug)
+        .bind(&org.id)
+        .fetch_all(pool)
+        .await?;
+
+        Self::notify_services(pool, jwt_service, issuer, &sessions).await?;
+        SsoSessionService::revoke_for_org_user(pool, &org.id, user_id).await?;
+
+        Ok(sessions)
+    }
+
+    /// The service an id_token_hint was issued for, which must be the client
+    /// asking for the logout
+    pub async fn hinted_service(
+        pool: &SqlitePool,
+        claims: &Claims,
+        client_id: &str,
+    ) -> Result<Service> {
+        let (Some(org_slug), Some(service_slug)) = (&claims.org, &claims.service) else {
+            return Err(AppError::BadRequest(
+                "id_token_hint must be a service token".to_string(),
+            ));
+        };
+
+        sqlx::query_as::<_, Service>(
+            "SELECT s.* FROM services s JOIN organizations o ON s.org_id = o.id
+             WHERE o.slug = ? AND s.slug = ?",
+        )
+        .bind(org_slug)
+        .bind(service_slug)
+        .fetch_optional(pool)
+        .await?
+        .filter(|service| service.client_id == client_id)
+        .ok_or_else(|| {
+            AppError::Unauthorized("id_token_hint was not issued to this client".to_string())
+        })
+    }
+
+    /// Revoke only the session an id_token_hint was issued for; the user's
+    /// other devices stay signed in
+    pub async fn revoke_hinted_session(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        issuer: &str,
+        id_token_hint: &str,
+        user_id: &str,
+    ) -> Result<Option<Session>> {
+        let session_id = sqlx::query_scalar::<_, String>(
+            "SELECT id FROM sessions WHERE token_hash = ? AND user_id = ?",
+        )
+        .bind(JwtService::hash_token(id_token_hint))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        match session_id {
+            Some(session_id) => Self::revoke_session(pool, jwt_service, issuer, &session_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Delete a single session and propagate the logout to its service
+    pub async fn revoke_session(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        issuer: &str,
+        session_id: &str,
+    ) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>("DELETE FROM sessions WHERE id = ? RETURNING *")
+            .bind(session_id)
+            .fetch_optional(pool)
+            .await?;
+
+        if let Some(ref session) = session {
+            Self::notify_services(pool, jwt_service, issuer, std::slice::from_ref(session)).await?;
+        }
+
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::tests::test_jwt_service;
+    use crate::db::models::User;
+    use crate::db::test_support::{insert_org, insert_service, insert_user, test_pool};
+
+    async fn insert_session(pool: &SqlitePool, user: &User, service: &Service, token: &str) {
+        sqlx::query(
+            "INSERT INTO sessions (id, user_id, token_hash, expires_at, service_id)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&user.id)
+        .bind(JwtService::hash_token(token))
+        .bind(Utc::now() + chrono::Duration::hours(1))
+        .bind(&service.id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hint_must_be_issued_to_the_client() {
+        let pool = test_pool().await;
+        let jwt_service = test_jwt_service();
+        let user = insert_user(&pool, "ada@acme.com").await;
+        let org = insert_org(&pool, "acme", &user).await;
+        let service = insert_service(&pool, &org, "billing").await;
+        let claims_for = |org_slug: Option<&str>, service_slug: Option<&str>| {
+            let token = jwt_service
+                .create_token(&user, org_slug, service_slug, None, None, None, None)
+                .unwrap();
+            jwt_service.decode_token_hint(&token).unwrap()
+        };
+        let claims = claims_for(Some("acme"), Some("billing"));
+
+        let hinted = LogoutService::hinted_service(&pool, &claims, &service.client_id)
+            .await
+            .unwrap();
+        assert_eq!(hinted.id, service.id);
+        assert!(matches!(
+            LogoutService::hinted_service(&pool, &claims, "another-client").await,
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            LogoutService::hinted_service(&pool, &claims_for(None, None), &service.client_id).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_end_only_the_hinted_session() {
+        let pool = test_pool().await;
+        let jwt_service = test_jwt_service();
+        let user = insert_user(&pool, "ada@acme.com").await;
+        let org = insert_org(&pool, "acme", &user).await;
+        let service = insert_service(&pool, &org, "billing").await;
+        sqlx::query("UPDATE services SET backchannel_logout_uri = ? WHERE id = ?")
+            .bind("https://billing.acme.com/logout")
+            .bind(&service.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let hint = jwt_service
+            .create_token(&user, Some("acme"), Some("billing"), None, None, None, None)
+            .unwrap();
+        insert_session(&pool, &user, &service, &hint).await;
+        insert_session(&pool, &user, &service, "laptop-token").await;
+
+        // The hint only counts for the user it was issued to
+        let other = insert_user(&pool, "eve@acme.com").await;
+        assert!(LogoutService::revoke_hinted_session(
+            &pool,
+            &jwt_service,
+            "https://sso.example.com",
+            &hint,
+            &other.id
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+        let ended = LogoutService::revoke_hinted_session(
+            &pool,
+            &jwt_service,
+            "https://sso.example.com",
+            &hint,
+            &user.id,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let remaining: Vec<String> =
+            sqlx::query_scalar("SELECT token_hash FROM sessions WHERE user_id = ?")
+                .bind(&user.id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(remaining, vec![JwtService::hash_token("laptop-token")]);
+
+        let deliveries: Vec<String> =
+            sqlx::query_scalar("SELECT session_id FROM backchannel_logout_deliveries")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(deliveries, vec![ended.id]);
+    }
+}
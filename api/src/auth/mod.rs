@@ -1,4 +1,24 @@
+pub mod account_merge;
+pub mod audit_chain;
+pub mod api_tokens;
+pub mod browser_binding;
 pub mod device_flow;
+pub mod domains;
+pub mod email;
+pub mod id_token;
+pub mod impersonation;
+pub mod invitations;
 pub mod jwt;
+pub mod login_failures;
+pub mod login_risk;
+pub mod logout;
+pub mod org_roles;
+pub mod privacy;
+pub mod profiles;
+pub mod scim;
+pub mod security_events;
+pub mod service_roles;
 pub mod sso;
+pub mod sso_session;
 pub mod token_refresher;
+pub mod webhooks;
@@ -0,0 +1,116 @@
+use crate::constants::{ADMIN_ORG_PERMISSIONS, MEMBER_ORG_PERMISSIONS, ORG_PERMISSIONS};
+use crate::error::{AppError, Result};
+use sqlx::SqlitePool;
+
+pub struct OrgRoleService;
+
+impl OrgRoleService {
+    /// Permissions granted by an organization role. Built-in roles map to
+    /// fixed sets; any other name is looked up in the org's custom roles.
+    /// Unknown roles grant nothing.
+    pub async fn permissions_for_role(
+        executor: impl sqlx::SqliteExecutor<'_>,
+        org_id: &str,
+        role: &str,
+    ) -> Result<Vec<String>> {
+        let builtin = match role {
+            "owner" => Some(ORG_PERMISSIONS),
+            "admin" => Some(ADMIN_ORG_PERMISSIONS),
+            "member" => Some(MEMBER_ORG_PERMISSIONS),
+            _ => None,
+        };
+        if let Some(permissions) = builtin {
+            return Ok(permissions.iter().map(|p| p.to_string()).collect());
+        }
+
+        let permissions: Option<String> =
+            sqlx::query_scalar("SELECT permissions FROM org_roles WHERE org_id = ? AND name = ?")
+                .bind(org_id)
+                .bind(role)
+                .fetch_optional(executor)
+                .await?;
+
+        Ok(permissions
+            .map(|p| Self::parse_permissions(&p))
+            .unwrap_or_default())
+    }
+
+    /// Whether a role name can be given to a member (built-in or custom)
+    pub async fn role_exists(
+        executor: impl sqlx::SqliteExecutor<'_>,
+        org_id: &str,
+        role: &str,
+    ) -> Result<bool> {
+        if crate::constants::VALID_ORG_ROLES.contains(&role) {
+            return Ok(true);
+        }
+
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM org_roles WHERE org_id = ? AND name = ?")
+                .bind(org_id)
+                .bind(role)
+                .fetch_one(executor)
+                .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Permissions stored on a custom role (JSON array)
+    pub fn parse_permissions(permissions: &str) -> Vec<String> {
+        serde_json::from_str(permissions).unwrap_or_default()
+    }
+
+    /// True when every permission in `granted` is also in `held`
+    pub fn is_within(granted: &[String], held: &[String]) -> bool {
+        granted.iter().all(|p| held.contains(p))
+    }
+
+    /// A non-owner may only grant permissions they hold themselves
+    pub async fn ensure_can_grant(
+        pool: &SqlitePool,
+        org_id: &str,
+        caller_role: &str,
+        granted: &[String],
+    ) -> Result<()> {
+        if caller_role == "owner" {
+            return Ok(());
+        }
+
+        let held = Self::permissions_for_role(pool, org_id, caller_role).await?;
+        if !Self::is_within(granted, &held) {
+            return Err(AppError::Forbidden(
+                "Cannot grant permissions you do not hold".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// A non-owner may only act on members whose role grants strictly less
+    /// than their own (so an admin cannot remove or re-role another admin)
+    pub async fn ensure_can_manage(
+        pool: &SqlitePool,
+        org_id: &str,
+        caller_role: &str,
+        target_role: &str,
+    ) -> Result<()> {
+        if caller_role == "owner" {
+            return Ok(());
+        }
+        if target_role == "owner" {
+            return Err(AppError::Forbidden(
+                "Only the owner can manage the owner".to_string(),
+            ));
+        }
+
+        let held = Self::permissions_for_role(pool, org_id, caller_role).await?;
+        let target = Self::permissions_for_role(pool, org_id, target_role).await?;
+        if !Self::is_within(&target, &held) || target.len() >= held.len() {
+            return Err(AppError::Forbidden(
+                "Cannot manage members whose role is not below your own".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
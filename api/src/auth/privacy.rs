@@ -0,0 +1,545 @@
+use crate::auth::jwt::JwtService;
+use crate::auth::logout::LogoutService;
+use crate::billing::stripe::StripeService;
+use crate::db::models::{Organization, Session, User};
+use crate::error::{AppError, Result};
+use crate::handlers::platform::create_audit_log;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+
+/// Everything the platform holds about a user that can be deleted outright.
+/// Each statement binds the user id once.
+const USER_DATA: &[&str] = &[
+    "DELETE FROM identities WHERE user_id = ?",
+    "DELETE FROM sso_sessions WHERE user_id = ?",
+    "DELETE FROM memberships WHERE user_id = ?",
+    "DELETE FROM subscriptions WHERE user_id = ?",
+    "DELETE FROM login_events WHERE user_id = ?",
+    "DELETE FROM user_group_members WHERE user_id = ?",
+    "DELETE FROM scim_users WHERE user_id = ?",
+    "DELETE FROM organization_invite_link_redemptions WHERE user_id = ?",
+    "DELETE FROM service_role_assignments WHERE user_id = ?",
+    "DELETE FROM api_tokens WHERE user_id = ?",
+    "DELETE FROM device_codes WHERE user_id = ?",
+    "DELETE FROM device_codes WHERE login_hint_user_id = ?",
+    "DELETE FROM oauth_states WHERE user_id_for_linking = ?",
+];
+
+/// The part of a user's data held on behalf of one organization.
+/// Each statement binds the user id, then the org id.
+const ORG_USER_DATA: &[&str] = &[
+    "DELETE FROM identities WHERE user_id = ? AND issuing_org_id = ?",
+    "DELETE FROM sso_sessions WHERE user_id = ? AND org_id = ?",
+    "DELETE FROM subscriptions
+     WHERE user_id = ? AND service_id IN (SELECT id FROM services WHERE org_id = ?)",
+    "DELETE FROM login_events
+     WHERE user_id = ? AND service_id IN (SELECT id FROM services WHERE org_id = ?)",
+    "DELETE FROM user_group_members
+     WHERE user_id = ? AND group_id IN (SELECT id FROM user_groups WHERE org_id = ?)",
+    "DELETE FROM scim_users WHERE user_id = ? AND org_id = ?",
+    "DELETE FROM service_role_assignments
+     WHERE user_id = ? AND role_id IN (
+         SELECT r.id FROM service_roles r JOIN services s ON r.service_id = s.id WHERE s.org_id = ?
+     )",
+];
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportedIdentity {
+    pub provider: String,
+    pub provider_user_id: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub issuing_org_id: Option<String>,
+    pub issuing_service_id: Option<String>,
+    pub scopes: Option<String>,
+    pub last_refreshed_at: Option<DateTime<Utc>>,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportedMembership {
+    pub org_slug: String,
+    pub org_name: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportedSession {
+    pub id: String,
+    pub org_slug: Option<String>,
+    pub service_slug: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportedLoginEvent {
+    pub service_slug: String,
+    pub provider: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportedSubscription {
+    pub service_slug: String,
+    pub plan_name: String,
+    pub status: String,
+    pub current_period_end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportedInvitation {
+    pub org_slug: String,
+    pub email: String,
+    pub role: String,
+    pub status: String,
+    pub sent_by_user: bool,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Machine-readable copy of the data held about a user (secrets excluded)
+#[derive(Debug, Serialize)]
+pub struct DataExport {
+    pub exported_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>, // org slug when scoped to one organization
+    pub user: User,
+    pub identities: Vec<ExportedIdentity>,
+    pub memberships: Vec<ExportedMembership>,
+    pub sessions: Vec<ExportedSession>,
+    pub login_events: Vec<ExportedLoginEvent>,
+    pub subscriptions: Vec<ExportedSubscription>,
+    pub invitations: Vec<ExportedInvitation>,
+}
+
+/// What an erasure request removed
+#[derive(Debug, Default, Serialize)]
+pub struct ErasureSummary {
+    pub sessions_revoked: usize,
+    pub stripe_subscriptions_cancelled: usize,
+    /// Subscriptions Stripe refused to cancel after the data was erased; they
+    /// need to be cancelled by hand
+    pub stripe_subscriptions_failed: Vec<String>,
+    pub rows_deleted: u64,
+    pub organizations_deleted: Vec<String>,
+    pub anonymized: bool,
+}
+
+pub struct PrivacyService;
+
+impl PrivacyService {
+    /// Address given to an erased user; the `.invalid` TLD can never log in
+    pub fn anonymized_email(user_id: &str) -> String {
+        format!("erased-{}@erased.invalid", user_id)
+    }
+
+    /// Collect the user's data, optionally limited to what one organization holds
+    pub async fn export(
+        pool: &SqlitePool,
+        user: &User,
+        org: Option<&Organization>,
+    ) -> Result<DataExport> {
+        let org_id = org.map(|o| o.id.as_str());
+        let org_slug = org.map(|o| o.slug.as_str());
+
+        let identities = sqlx::query_as::<_, ExportedIdentity>(
+            "SELECT provider, provider_user_id, email, email_verified, issuing_org_id, issuing_service_id, scopes,
+                    last_refreshed_at, username, name, given_name, family_name, avatar_url,
+                    locale
+             FROM identities
+             WHERE user_id = ? AND (? IS NULL OR issuing_org_id = ?)",
+        )
+        .bind(&user.id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_all(pool)
+        .await?;
+
+        let memberships = sqlx::query_as::<_, ExportedMembership>(
+            "SELECT o.slug as org_slug, o.name as org_name, m.role, m.created_at
+             FROM memberships m
+             JOIN organizations o ON m.org_id = o.id
+             WHERE m.user_id = ? AND (? IS NULL OR o.id = ?)",
+        )
+        .bind(&user.id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_all(pool)
+        .await?;
+
+        let sessions = sqlx::query_as::<_, ExportedSession>(
+            "SELECT ses.id, ses.org_slug, s.slug as service_slug, ses.ip_address, ses.user_agent,
+                    ses.created_at, ses.last_used_at, ses.expires_at
+             FROM sessions ses
+             LEFT JOIN services s ON ses.service_id = s.id
+             WHERE ses.user_id = ? AND (? IS NULL OR ses.org_slug = ?)
+             ORDER BY ses.created_at DESC",
+        )
+        .bind(&user.id)
+        .bind(org_slug)
+        .bind(org_slug)
+        .fetch_all(pool)
+        .await?;
+
+        let login_events = sqlx::query_as::<_, ExportedLoginEvent>(
+            "SELECT s.slug as service_slug, le.provider, le.ip_address, le.user_agent, le.created_at
+             FROM login_events le
+             JOIN services s ON le.service_id = s.id
+             WHERE le.user_id = ? AND (? IS NULL OR s.org_id = ?)
+             ORDER BY le.created_at DESC",
+        )
+        .bind(&user.id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_all(pool)
+        .await?;
+
+        let subscriptions = sqlx::query_as::<_, ExportedSubscription>(
+            "SELECT s.slug as service_slug, p.name as plan_name, sub.status, sub.current_period_end
+             FROM subscriptions sub
+             JOIN services s ON sub.service_id = s.id
+             JOIN plans p ON sub.plan_id = p.id
+             WHERE sub.user_id = ? AND (? IS NULL OR s.org_id = ?)",
+        )
+        .bind(&user.id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_all(pool)
+        .await?;
+
+        let invitations = sqlx::query_as::<_, ExportedInvitation>(
+            "SELECT o.slug as org_slug, i.email, i.role, i.status, i.invited_by = ? as sent_by_user,
+                    i.created_at, i.expires_at
+             FROM organization_invitations i
+             JOIN organizations o ON i.org_id = o.id
+             WHERE (i.email = ? OR i.invited_by = ?) AND (? IS NULL OR o.id = ?)
+             ORDER BY i.created_at DESC",
+        )
+        .bind(&user.id)
+        .bind(&user.email)
+        .bind(&user.id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(DataExport {
+            exported_at: Utc::now(),
+            organization: org_slug.map(|s| s.to_string()),
+            user: user.clone(),
+            identities,
+            memberships,
+            sessions,
+            login_events,
+            subscriptions,
+            invitations,
+        })
+    }
+
+    /// Erase the user's data, optionally only what one organization holds.
+    ///
+    /// Rows are removed in one transaction. Logout propagation and Stripe
+    /// cancellations run after it commits. A full erasure keeps the user row as
+    /// an anonymized tombstone so audit records that reference it stay valid;
+    /// an organization erasure leaves the account and other organizations' data alone.
+    pub async fn erase(
+        pool: &SqlitePool,
+        stripe: &StripeService,
+        jwt_service: &JwtService,
+        issuer: &str,
+        user: &User,
+        org: Option<&Organization>,
+        actor_id: &str,
+    ) -> Result<ErasureSummary> {
+        if user.is_platform_owner {
+            return Err(AppError::BadRequest(
+                "Platform owners must be demoted before their data can be erased".to_string(),
+            ));
+        }
+
+        let mut summary = ErasureSummary::default();
+        let org_id = org.map(|o| o.id.as_str());
+
+        // Owned organizations go with the account only when nobody else depends on them
+        let mut owned_orgs = Vec::new();
+        if org.is_none() {
+            owned_orgs = sqlx::query_as::<_, Organization>(
+                "SELECT * FROM organizations WHERE owner_user_id = ?",
+            )
+            .bind(&user.id)
+            .fetch_all(pool)
+            .await?;
+
+            for owned in &owned_orgs {
+                let dependents: i64 = sqlx::query_scalar(
+                    "SELECT (SELECT COUNT(*) FROM memberships WHERE org_id = ? AND user_id != ?)
+                          + (SELECT COUNT(*) FROM subscriptions sub
+                             JOIN services s ON sub.service_id = s.id
+                             WHERE s.org_id = ? AND sub.user_id != ?)",
+                )
+                .bind(&owned.id)
+                .bind(&user.id)
+                .bind(&owned.id)
+                .bind(&user.id)
+                .fetch_one(pool)
+                .await?;
+
+                if dependents > 0 {
+                    return Err(AppError::BadRequest(format!(
+                        "Organization '{}' still has other members or end-users. Transfer ownership or remove them first",
+                        owned.slug
+                    )));
+                }
+            }
+        }
+
+        // Read now, cancel after commit: Stripe calls cannot be rolled back
+        let stripe_subscriptions: Vec<String> = sqlx::query_scalar(
+            "SELECT sub.stripe_subscription_id
+             FROM subscriptions sub
+             JOIN services s ON sub.service_id = s.id
+             WHERE sub.user_id = ? AND sub.stripe_subscription_id IS NOT NULL
+               AND sub.status IN ('active', 'trialing', 'past_due')
+               AND (? IS NULL OR s.org_id = ?)",
+        )
+        .bind(&user.id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut tx = pool.begin().await?;
+
+        let revoked = sqlx::query_as::<_, Session>(
+            "DELETE FROM sessions WHERE user_id = ? AND (? IS NULL OR org_slug = ?) RETURNING *",
+        )
+        .bind(&user.id)
+        .bind(org.map(|o| o.slug.as_str()))
+        .bind(org.map(|o| o.slug.as_str()))
+        .fetch_all(&mut *tx)
+        .await?;
+        summary.sessions_revoked = revoked.len();
+
+        if let Some(org) = org {
+            // A primary profile copied from one of the organization's identities goes too
+            sqlx::query(
+                "UPDATE users
+                 SET name = NULL, given_name = NULL, family_name = NULL, avatar_url = NULL,
+                     locale = NULL, primary_identity_id = NULL
+                 WHERE id = ? AND primary_identity_id IN
+                     (SELECT id FROM identities WHERE issuing_org_id = ?)",
+            )
+            .bind(&user.id)
+            .bind(&org.id)
+            .execute(&mut *tx)
+            .await?;
+
+            for statement in ORG_USER_DATA {
+                summary.rows_deleted += sqlx::query(statement)
+                    .bind(&user.id)
+                    .bind(&org.id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            }
+            summary.rows_deleted +=
+                sqlx::query("DELETE FROM organization_invitations WHERE email = ? AND org_id = ?")
+                    .bind(&user.email)
+                    .bind(&org.id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+        } else {
+            // The account itself belongs to the user, not to any one organization
+            summary.anonymized = true;
+        }
+
+        if summary.anonymized {
+            for statement in USER_DATA {
+                summary.rows_deleted += sqlx::query(statement)
+                    .bind(&user.id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            }
+            summary.rows_deleted +=
+                sqlx::query("DELETE FROM organization_invitations WHERE email = ?")
+                    .bind(&user.email)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            summary.rows_deleted +=
+                sqlx::query("DELETE FROM email_outbox WHERE to_address = ? COLLATE NOCASE")
+                    .bind(&user.email)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            sqlx::query("UPDATE api_tokens SET created_by = NULL WHERE created_by = ?")
+                .bind(&user.id)
+                .execute(&mut *tx)
+                .await?;
+
+            for owned in &owned_orgs {
+                sqlx::query("DELETE FROM organizations WHERE id = ?")
+                    .bind(&owned.id)
+                    .execute(&mut *tx)
+                    .await?;
+                summary.organizations_deleted.push(owned.slug.clone());
+            }
+
+            sqlx::query(
+                "UPDATE users
+                 SET email = ?, name = NULL, given_name = NULL, family_name = NULL,
+                     avatar_url = NULL, locale = NULL, primary_identity_id = NULL
+                 WHERE id = ?",
+            )
+            .bind(Self::anonymized_email(&user.id))
+            .bind(&user.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        create_audit_log(
+            &mut tx,
+            actor_id,
+            "user_erased",
+            "user",
+            &user.id,
+            Some(json!({
+                "via": if org.is_some() { "organization" } else { "self" },
+                "org_slug": org.map(|o| o.slug.as_str()),
+                "summary": summary,
+                "stripe_subscriptions": stripe_subscriptions,
+            })),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        // Side effects outside the database happen only once the erasure is final
+        LogoutService::notify_services(pool, jwt_service, issuer, &revoked).await?;
+        for subscription_id in &stripe_subscriptions {
+            match stripe.cancel_subscription(subscription_id).await {
+                Ok(()) => summary.stripe_subscriptions_cancelled += 1,
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to cancel Stripe subscription {} for erased user {}: {}",
+                        subscription_id,
+                        user.id,
+                        e
+                    );
+                    summary
+                        .stripe_subscriptions_failed
+                        .push(subscription_id.clone());
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::tests::test_jwt_service;
+    use crate::db::test_support;
+
+    async fn count(pool: &SqlitePool, table: &str, user_id: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE user_id = ?", table))
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_erasure_removes_the_account_data_and_keeps_a_tombstone() {
+        let pool = test_support::test_pool().await;
+        let owner = test_support::insert_user(&pool, "owner@example.com").await;
+        let team = test_support::insert_org(&pool, "team", &owner).await;
+        let ada = test_support::insert_user(&pool, "ada@example.com").await;
+        test_support::insert_membership(&pool, &team, &ada, "member").await;
+        let solo = test_support::insert_org(&pool, "ada-solo", &ada).await;
+
+        sqlx::query("UPDATE users SET name = 'Ada Lovelace', locale = 'en-GB' WHERE id = ?")
+            .bind(&ada.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO identities (id, user_id, provider, provider_user_id, email, email_verified)
+             VALUES ('identity-1', ?, 'github', 'gh-ada', 'ada@example.com', 1)",
+        )
+        .bind(&ada.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO sessions (id, user_id, token_hash, expires_at, created_at)
+             VALUES ('session-1', ?, 'hash-1', ?, ?)",
+        )
+        .bind(&ada.id)
+        .bind(Utc::now() + chrono::Duration::hours(1))
+        .bind(Utc::now())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let stripe = StripeService::new("sk_test".to_string(), "whsec_test".to_string());
+        let summary = PrivacyService::erase(
+            &pool,
+            &stripe,
+            &test_jwt_service(),
+            "https://sso.example.com",
+            &ada,
+            None,
+            &ada.id,
+        )
+        .await
+        .unwrap();
+
+        assert!(summary.anonymized);
+        assert_eq!(summary.sessions_revoked, 1);
+        assert_eq!(summary.organizations_deleted, vec![solo.slug.clone()]);
+        for table in ["identities", "sessions", "memberships"] {
+            assert_eq!(
+                count(&pool, table, &ada.id).await,
+                0,
+                "{} left behind",
+                table
+            );
+        }
+
+        let erased = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(&ada.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(erased.email, PrivacyService::anonymized_email(&ada.id));
+        assert!(erased.name.is_none() && erased.locale.is_none());
+
+        // The organization with other members survives; the owner keeps their seat
+        assert_eq!(count(&pool, "memberships", &owner.id).await, 1);
+        let solo_left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM organizations WHERE id = ?")
+            .bind(&solo.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(solo_left, 0);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::auth::sso::UserInfo;
+use crate::db::models::{Identity, User};
+use crate::error::{AppError, Result};
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+/// Profile fields a user can edit directly. Absent fields are left as they
+/// are; an empty string clears the field.
+#[derive(Debug, Default)]
+pub struct ProfileUpdate {
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+}
+
+impl ProfileUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.given_name.is_none()
+            && self.family_name.is_none()
+            && self.avatar_url.is_none()
+            && self.locale.is_none()
+    }
+}
+
+pub struct ProfileService;
+
+impl ProfileService {
+    /// The primary profile follows an identity when it was chosen as primary,
+    /// or when the user has no profile yet and has not picked one
+    pub fn follows_identity(user: &User, identity_id: &str) -> bool {
+        match user.primary_identity_id {
+            Some(ref primary) => primary == identity_id,
+            None => {
+                user.name.is_none()
+                    && user.given_name.is_none()
+                    && user.family_name.is_none()
+                    && user.avatar_url.is_none()
+                    && user.locale.is_none()
+            }
+        }
+    }
+
+    /// Store the profile and email the provider reported for this identity and
+    /// refresh the user's primary profile if it follows the identity
+    pub async fn sync_from_identity(
+        pool: &SqlitePool,
+        identity: &Identity,
+        info: &UserInfo,
+    ) -> Result<User> {
+        sqlx::query(
+            r#"
+            UPDATE identities
+            SET name = ?, given_name = ?, family_name = ?, avatar_url = ?, locale = ?,
+                username = ?, email = ?, email_verified = ?, profile_updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&info.name)
+        .bind(&info.given_name)
+        .bind(&info.family_name)
+        .bind(&info.avatar_url)
+        .bind(&info.locale)
+        .bind(&info.username)
+        .bind(&info.email)
+        .bind(info.email_verified)
+        .bind(Utc::now())
+        .bind(&identity.id)
+        .execute(pool)
+        .await?;
+
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(&identity.user_id)
+            .fetch_one(pool)
+            .await?;
+
+        if !Self::follows_identity(&user, &identity.id) {
+            return Ok(user);
+        }
+
+        Self::copy_from_identity(pool, &user.id, &identity.id).await
+    }
+
+    /// Make one of the user's identities the source of their primary profile
+    pub async fn set_primary(pool: &SqlitePool, user_id: &str, identity_id: &str) -> Result<User> {
+        let exists: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM identities WHERE id = ? AND user_id = ?")
+                .bind(identity_id)
+                .bind(user_id)
+                .fetch_one(pool)
+                .await?;
+        if exists == 0 {
+            return Err(AppError::NotFound("Identity not found".to_string()));
+        }
+
+        Self::copy_from_identity(pool, user_id, identity_id).await
+    }
+
+    /// Edit the primary profile by hand. The profile stops following any
+    /// identity so the next login does not overwrite the edit.
+    pub async fn update(pool: &SqlitePool, user: &User, update: ProfileUpdate) -> Result<User> {
+        fn merged(new: Option<String>, current: &Option<String>) -> Option<String> {
+            match new {
+                Some(value) if value.trim().is_empty() => None,
+                Some(value) => Some(value.trim().to_string()),
+                None => current.clone(),
+            }
+        }
+
+        if let Some(ref locale) = update.locale {
+            // BCP 47 tag, e.g. "en" or "pt-BR"
+            if locale.len() > 35
+                || !locale
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                return Err(AppError::BadRequest("Invalid locale".to_string()));
+            }
+        }
+        if let Some(ref avatar_url) = update.avatar_url {
+            if !avatar_url.is_empty() && !avatar_url.starts_with("https://") {
+                return Err(AppError::BadRequest(
+                    "avatar_url must be an https URL".to_string(),
+                ));
+            }
+        }
+
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET name = ?, given_name = ?, family_name = ?, avatar_url = ?, locale = ?,
+                primary_identity_id = NULL
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(merged(update.name, &user.name))
+        .bind(merged(update.given_name, &user.given_name))
+        .bind(merged(update.family_name, &user.family_name))
+        .bind(merged(update.avatar_url, &user.avatar_url))
+        .bind(merged(update.locale, &user.locale))
+        .bind(&user.id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// The user one of whose identities carries this email, verified by its
+    /// provider. Only such a user may be signed into by another provider's
+    /// verified email; `users.email` alone proves nothing.
+    pub async fn verified_email_owner(pool: &SqlitePool, email: &str) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>(
+            "SELECT u.* FROM users u JOIN identities i ON i.user_id = u.id
+             WHERE i.email = ? COLLATE NOCASE AND i.email_verified = 1
+             ORDER BY u.created_at
+             LIMIT 1",
+        )
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// A user's email can only become an address a linked provider has verified
+    /// for them, since login-risk codes and domain policies trust it
+    pub async fn ensure_verified_email(
+        pool: &SqlitePool,
+        user_id: &str,
+        email: &str,
+    ) -> Result<()> {
+        let verified: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM identities
+             WHERE user_id = ? AND email = ? COLLATE NOCASE AND email_verified = 1",
+        )
+        .bind(user_id)
+        .bind(email)
+        .fetch_one(pool)
+        .await?;
+        if verified == 0 {
+            return Err(AppError::BadRequest(
+                "Email must be verified by one of your linked accounts".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn copy_from_identity(
+        pool: &SqlitePool,
+        user_id: &str,
+        identity_id: &str,
+    ) -> Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET (name, given_name, family_name, avatar_url, locale) =
+                (SELECT name, given_name, family_name, avatar_url, locale
+                 FROM identities WHERE id = ?),
+                primary_identity_id = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(identity_id)
+        .bind(identity_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    #[test]
+    fn test_follows_identity() {
+        let mut user = User {
+            id: "u".to_string(),
+            email: "u@example.com".to_string(),
+            is_platform_owner: false,
+            created_at: Utc::now(),
+            name: None,
+            given_name: None,
+            family_name: None,
+            avatar_url: None,
+            locale: None,
+            primary_identity_id: None,
+        };
+        assert!(ProfileService::follows_identity(&user, "github"));
+
+        user.name = Some("Edited by hand".to_string());
+        assert!(!ProfileService::follows_identity(&user, "github"));
+
+        user.primary_identity_id = Some("google".to_string());
+        assert!(ProfileService::follows_identity(&user, "google"));
+        assert!(!ProfileService::follows_identity(&user, "github"));
+    }
+
+    async fn insert_identity(
+        pool: &SqlitePool,
+        user: &User,
+        provider: &str,
+        email: &str,
+        verified: bool,
+    ) {
+        sqlx::query(
+            "INSERT INTO identities (id, user_id, provider, provider_user_id, email, email_verified)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&user.id)
+        .bind(provider)
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(email)
+        .bind(verified)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_only_provider_verified_emails_match_an_account() {
+        let pool = test_support::test_pool().await;
+        // The address on the user row was never verified by any provider
+        let squatter = test_support::insert_user(&pool, "victim@example.com").await;
+        insert_identity(&pool, &squatter, "github", "squatter@example.com", true).await;
+        assert!(
+            ProfileService::verified_email_owner(&pool, "victim@example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let user = test_support::insert_user(&pool, "ada@example.com").await;
+        insert_identity(&pool, &user, "github", "ada@example.com", false).await;
+        assert!(
+            ProfileService::verified_email_owner(&pool, "ada@example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        insert_identity(&pool, &user, "google", "Ada@Example.com", true).await;
+        let owner = ProfileService::verified_email_owner(&pool, "ada@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(owner.id, user.id);
+    }
+
+    #[tokio::test]
+    async fn test_email_changes_need_a_verified_identity_email() {
+        let pool = test_support::test_pool().await;
+        let user = test_support::insert_user(&pool, "ada@example.com").await;
+        let other = test_support::insert_user(&pool, "grace@example.com").await;
+        insert_identity(&pool, &user, "github", "ada@work.example", false).await;
+        insert_identity(&pool, &other, "github", "grace@work.example", true).await;
+
+        for email in [
+            "victim@example.com",
+            "ada@work.example",
+            "grace@work.example",
+        ] {
+            assert!(matches!(
+                ProfileService::ensure_verified_email(&pool, &user.id, email).await,
+                Err(AppError::BadRequest(_))
+            ));
+        }
+
+        insert_identity(&pool, &user, "google", "ada@personal.example", true).await;
+        ProfileService::ensure_verified_email(&pool, &user.id, "ada@personal.example")
+            .await
+            .unwrap();
+    }
+}
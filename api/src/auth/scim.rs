@@ -0,0 +1,297 @@
+use crate::auth::jwt::JwtService;
+use crate::auth::logout::LogoutService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::billing::tiers::TierService;
+use crate::db::models::{Organization, ScimUser};
+use crate::error::{AppError, Result};
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+/// Extension carrying the organization role of provisioned members
+pub const USER_EXTENSION_SCHEMA: &str = "urn:ietf:params:scim:schemas:extension:sso:2.0:User";
+pub const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+pub const SERVICE_PROVIDER_CONFIG_SCHEMA: &str =
+    "urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig";
+
+pub const USER_TYPE_MEMBER: &str = "member";
+pub const USER_TYPE_END_USER: &str = "end_user";
+
+/// A filter of the form `attribute eq "value"`, the only operator identity
+/// providers rely on
+#[derive(Debug, PartialEq)]
+pub struct ScimFilter {
+    pub attribute: String,
+    pub value: String,
+}
+
+pub struct ScimService;
+
+impl ScimService {
+    pub fn parse_filter(filter: &str) -> Result<ScimFilter> {
+        let mut parts = filter.trim().splitn(3, ' ');
+        let (Some(attribute), Some(operator), Some(value)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AppError::BadRequest(format!("Invalid filter: {}", filter)));
+        };
+        if !operator.eq_ignore_ascii_case("eq") {
+            return Err(AppError::BadRequest(
+                "Only 'eq' filters are supported".to_string(),
+            ));
+        }
+
+        let value = value.trim();
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => quoted.replace("\\\"", "\""),
+            None if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") => {
+                value.to_ascii_lowercase()
+            }
+            None => return Err(AppError::BadRequest(format!("Invalid filter: {}", filter))),
+        };
+
+        Ok(ScimFilter {
+            attribute: attribute.to_string(),
+            value,
+        })
+    }
+
+    /// Booleans arrive as JSON booleans or, from some providers, as strings
+    pub fn parse_bool(value: &Value) -> Option<bool> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) if s.eq_ignore_ascii_case("true") => Some(true),
+            Value::String(s) if s.eq_ignore_ascii_case("false") => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Whether the identity provider has deactivated the user in this organization
+    pub async fn is_deactivated(
+        executor: impl sqlx::SqliteExecutor<'_>,
+        org_id: &str,
+        user_id: &str,
+    ) -> Result<bool> {
+        let deactivated: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM scim_users WHERE org_id = ? AND user_id = ? AND active = 0",
+        )
+        .bind(org_id)
+        .bind(user_id)
+        .fetch_one(executor)
+        .await?;
+
+        Ok(deactivated > 0)
+    }
+
+    /// Refuse a sign-in to an organization's services by a user its identity
+    /// provider has deactivated
+    pub async fn check_sign_in(pool: &SqlitePool, org_id: &str, user_id: &str) -> Result<()> {
+        if Self::is_deactivated(pool, org_id, user_id).await? {
+            return Err(AppError::Forbidden(
+                "Your account has been deactivated by your organization".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether a SCIM client may bind an existing account: one that is a
+    /// member or end user of the organization, or that its SCIM client created.
+    /// Anyone else's account is not the organization's to provision.
+    pub async fn may_bind(pool: &SqlitePool, org_id: &str, user_id: &str) -> Result<bool> {
+        let bindable: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users u WHERE u.id = ? AND (
+                 u.scim_org_id = ?
+                 OR EXISTS (SELECT 1 FROM memberships m WHERE m.user_id = u.id AND m.org_id = ?)
+                 OR EXISTS (SELECT 1 FROM subscriptions sub JOIN services s ON sub.service_id = s.id
+                            WHERE sub.user_id = u.id AND s.org_id = ?)
+                 OR EXISTS (SELECT 1 FROM identities i WHERE i.user_id = u.id AND i.issuing_org_id = ?)
+             )",
+        )
+        .bind(user_id)
+        .bind(org_id)
+        .bind(org_id)
+        .bind(org_id)
+        .bind(org_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(bindable > 0)
+    }
+
+    /// Whether the organization's SCIM client created the account, which makes
+    /// the identity provider the source of its profile
+    pub async fn created_account(pool: &SqlitePool, org_id: &str, user_id: &str) -> Result<bool> {
+        let created: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = ? AND scim_org_id = ?")
+                .bind(user_id)
+                .bind(org_id)
+                .fetch_one(pool)
+                .await?;
+
+        Ok(created > 0)
+    }
+
+    /// Give a provisioned member their organization membership. An existing
+    /// membership is kept as is.
+    pub async fn grant_membership(
+        pool: &SqlitePool,
+        org: &Organization,
+        user_id: &str,
+        role: &str,
+    ) -> Result<()> {
+        let already_member: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ? AND user_id = ?")
+                .bind(&org.id)
+                .bind(user_id)
+                .fetch_one(pool)
+                .await?;
+        if already_member > 0 {
+            return Ok(());
+        }
+
+        let member_count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ?")
+                .bind(&org.id)
+                .fetch_one(pool)
+                .await?;
+        if member_count >= TierService::member_limit(pool, org).await? {
+            return Err(AppError::TeamLimitExceeded(
+                "Team limit reached".to_string(),
+            ));
+        }
+
+        // The role may have been deleted while the member was deactivated
+        let role = if OrgRoleService::role_exists(pool, &org.id, role).await? {
+            role
+        } else {
+            "member"
+        };
+
+        sqlx::query(
+            "INSERT INTO memberships (id, org_id, user_id, role, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&org.id)
+        .bind(user_id)
+        .bind(role)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deprovision a user: members lose their membership, end users can no
+    /// longer sign in to the organization's services, and the user's sessions
+    /// in the organization are revoked
+    pub async fn deactivate(
+        pool: &SqlitePool,
+        jwt_service: &JwtService,
+        issuer: &str,
+        org: &Organization,
+        scim_user: &ScimUser,
+    ) -> Result<()> {
+        if scim_user.user_type == USER_TYPE_MEMBER {
+            let role: Option<String> =
+                sqlx::query_scalar("SELECT role FROM memberships WHERE org_id = ? AND user_id = ?")
+                    .bind(&scim_user.org_id)
+                    .bind(&scim_user.user_id)
+                    .fetch_optional(pool)
+                    .await?;
+            if role.as_deref() == Some("owner") {
+                return Err(AppError::BadRequest(
+                    "The organization owner cannot be deprovisioned".to_string(),
+                ));
+            }
+
+            sqlx::query("DELETE FROM memberships WHERE org_id = ? AND user_id = ?")
+                .bind(&scim_user.org_id)
+                .bind(&scim_user.user_id)
+                .execute(pool)
+                .await?;
+        }
+
+        sqlx::query(
+            "UPDATE scim_users SET active = 0, updated_at = ? WHERE org_id = ? AND user_id = ?",
+        )
+        .bind(Utc::now())
+        .bind(&scim_user.org_id)
+        .bind(&scim_user.user_id)
+        .execute(pool)
+        .await?;
+
+        let revoked =
+            LogoutService::revoke_org_sessions(pool, jwt_service, issuer, &scim_user.user_id, org)
+                .await?;
+        tracing::info!(
+            "SCIM deprovisioned {} from {} ({} sessions revoked)",
+            scim_user.user_id,
+            scim_user.org_id,
+            revoked.len()
+        );
+
+        Ok(())
+    }
+
+    /// Undo a deactivation; members get their recorded role back
+    pub async fn reactivate(
+        pool: &SqlitePool,
+        org: &Organization,
+        scim_user: &ScimUser,
+    ) -> Result<()> {
+        if scim_user.user_type == USER_TYPE_MEMBER {
+            let role = scim_user.role.as_deref().unwrap_or("member");
+            Self::grant_membership(pool, org, &scim_user.user_id, role).await?;
+        }
+
+        sqlx::query(
+            "UPDATE scim_users SET active = 1, updated_at = ? WHERE org_id = ? AND user_id = ?",
+        )
+        .bind(Utc::now())
+        .bind(&scim_user.org_id)
+        .bind(&scim_user.user_id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(
+            ScimService::parse_filter(r#"userName eq "Ada@Customer.com""#).unwrap(),
+            ScimFilter {
+                attribute: "userName".to_string(),
+                value: "Ada@Customer.com".to_string(),
+            }
+        );
+        assert_eq!(
+            ScimService::parse_filter(r#"displayName EQ "Sales \"EMEA\"""#)
+                .unwrap()
+                .value,
+            r#"Sales "EMEA""#
+        );
+        assert_eq!(
+            ScimService::parse_filter("active eq True").unwrap().value,
+            "true"
+        );
+        assert!(ScimService::parse_filter(r#"userName co "ada""#).is_err());
+        assert!(ScimService::parse_filter("userName eq ada").is_err());
+        assert!(ScimService::parse_filter("userName").is_err());
+
+        assert_eq!(ScimService::parse_bool(&json!("False")), Some(false));
+        assert_eq!(ScimService::parse_bool(&json!(true)), Some(true));
+        assert_eq!(ScimService::parse_bool(&json!(1)), None);
+    }
+}
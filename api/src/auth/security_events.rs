@@ -0,0 +1,57 @@
+use crate::error::Result;
+use crate::middleware::ClientInfo;
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::Sqlite;
+use uuid::Uuid;
+
+// Security event types
+pub const SECURITY_EVENT_REFRESH_TOKEN_REUSE: &str = "refresh_token_reuse";
+pub const SECURITY_EVENT_BROWSER_BINDING_MISMATCH: &str = "oauth_browser_binding_mismatch";
+pub const SECURITY_EVENT_LOGIN_BLOCKED: &str = "risky_login_blocked";
+pub const SECURITY_EVENT_LOGIN_LOCKOUT: &str = "login_lockout";
+
+pub const SECURITY_SEVERITIES: &[&str] = &["low", "medium", "high"];
+
+pub struct SecurityEventService;
+
+impl SecurityEventService {
+    /// Record a security event for the SIEM export. `details` should hold
+    /// identifiers, never secrets or token values.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record<'a, E>(
+        executor: E,
+        event_type: &str,
+        severity: &str,
+        user_id: Option<&str>,
+        org_id: Option<&str>,
+        client: &ClientInfo,
+        details: Value,
+    ) -> Result<()>
+    where
+        E: sqlx::Executor<'a, Database = Sqlite>,
+    {
+        debug_assert!(SECURITY_SEVERITIES.contains(&severity));
+
+        sqlx::query(
+            r#"
+            INSERT INTO security_events
+                (id, event_type, severity, user_id, org_id, ip_address, user_agent, details, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(event_type)
+        .bind(severity)
+        .bind(user_id)
+        .bind(org_id)
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .bind(details.to_string())
+        .bind(Utc::now())
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
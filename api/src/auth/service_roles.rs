@@ -0,0 +1,46 @@
+use crate::db::models::ServiceRole;
+use crate::error::Result;
+use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+
+pub struct ServiceRoleService;
+
+impl ServiceRoleService {
+    /// Roles a user holds on a service, directly or through a group, and the
+    /// union of their permissions. Both lists are sorted and de-duplicated.
+    pub async fn effective_roles(
+        pool: &SqlitePool,
+        user_id: &str,
+        service_id: &str,
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        let roles = sqlx::query_as::<_, ServiceRole>(
+            r#"
+            SELECT DISTINCT r.* FROM service_roles r
+            JOIN service_role_assignments a ON a.role_id = r.id
+            LEFT JOIN user_group_members gm ON gm.group_id = a.group_id
+            WHERE r.service_id = ? AND (a.user_id = ? OR gm.user_id = ?)
+            ORDER BY r.name
+            "#,
+        )
+        .bind(service_id)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let permissions: BTreeSet<String> = roles
+            .iter()
+            .flat_map(|role| Self::parse_permissions(&role.permissions))
+            .collect();
+
+        Ok((
+            roles.into_iter().map(|role| role.name).collect(),
+            permissions.into_iter().collect(),
+        ))
+    }
+
+    /// Permissions stored on a role (JSON array)
+    pub fn parse_permissions(permissions: &str) -> Vec<String> {
+        serde_json::from_str(permissions).unwrap_or_default()
+    }
+}
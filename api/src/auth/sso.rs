@@ -1,12 +1,37 @@
 use crate::config::Config;
 use crate::error::{AppError, Result};
 use chrono::{DateTime, Utc};
+use crate::auth::id_token::{IdTokenClaims, IdTokenVerifier};
 use oauth2::{
-    basic::BasicClient, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken,
-    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, TokenResponse, TokenUrl,
+    basic::{
+        BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+        BasicTokenType,
+    },
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, ExtraTokenFields,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardRevocableToken,
+    StandardTokenResponse, TokenResponse, TokenUrl,
 };
 use serde::{Deserialize, Serialize};
 
+/// Keeps the `id_token` that Google and Microsoft return next to the access token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+/// ProviderClient with a token response that keeps the ID token
+pub type ProviderClient = oauth2::Client<
+    BasicErrorResponse,
+    StandardTokenResponse<IdTokenFields, BasicTokenType>,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Provider {
     Github,
@@ -37,7 +62,13 @@ impl Provider {
 pub struct UserInfo {
     pub provider_user_id: String,
     pub email: String,
+    pub email_verified: bool, // the provider vouches for the address
     pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub username: Option<String>,
 }
 
 #[derive(Debug)]
@@ -46,17 +77,19 @@ pub struct TokenDetails {
     pub refresh_token: Option<String>,
     pub expires_at: Option<DateTime<Utc>>,
     pub scopes: Vec<String>,
+    /// Verified ID token claims (only when a nonce was sent)
+    pub id_token: Option<IdTokenClaims>,
 }
 
 pub struct OAuthClient {
-    github_client: BasicClient,
-    google_client: BasicClient,
-    microsoft_client: BasicClient,
+    github_client: ProviderClient,
+    google_client: ProviderClient,
+    microsoft_client: ProviderClient,
 }
 
 impl OAuthClient {
     pub fn new(config: &Config) -> Result<Self> {
-        let github_client = BasicClient::new(
+        let github_client = ProviderClient::new(
             ClientId::new(config.github_client_id.clone()),
             Some(ClientSecret::new(config.github_client_secret.clone())),
             AuthUrl::new("https://github.com/login/oauth/authorize".to_string())
@@ -71,7 +104,7 @@ impl OAuthClient {
                 .map_err(|e| AppError::OAuth(e.to_string()))?,
         );
 
-        let google_client = BasicClient::new(
+        let google_client = ProviderClient::new(
             ClientId::new(config.google_client_id.clone()),
             Some(ClientSecret::new(config.google_client_secret.clone())),
             AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string())
@@ -86,7 +119,7 @@ impl OAuthClient {
                 .map_err(|e| AppError::OAuth(e.to_string()))?,
         );
 
-        let microsoft_client = BasicClient::new(
+        let microsoft_client = ProviderClient::new(
             ClientId::new(config.microsoft_client_id.clone()),
             Some(ClientSecret::new(config.microsoft_client_secret.clone())),
             AuthUrl::new(
@@ -112,7 +145,7 @@ impl OAuthClient {
         })
     }
 
-    pub fn get_client(&self, provider: Provider) -> &BasicClient {
+    pub fn get_client(&self, provider: Provider) -> &ProviderClient {
         match provider {
             Provider::Github => &self.github_client,
             Provider::Google => &self.google_client,
@@ -149,6 +182,7 @@ impl OAuthClient {
         &self,
         provider: Provider,
         scopes: Vec<String>,
+        nonce: Option<&str>,
     ) -> (String, CsrfToken, String) {
         let client = self.get_client(provider);
 
@@ -170,6 +204,10 @@ impl OAuthClient {
             auth_request = auth_request.set_pkce_challenge(challenge);
         }
 
+        if let Some(nonce) = nonce {
+            auth_request = auth_request.add_extra_param("nonce", nonce);
+        }
+
         let (auth_url, csrf_token) = auth_request.url();
 
         let verifier_secret = pkce_verifier
@@ -196,6 +234,7 @@ impl OAuthClient {
         provider: Provider,
         code: &str,
         pkce_verifier: Option<&str>,
+        nonce: Option<&str>,
     ) -> Result<TokenDetails> {
         let client = self.get_client(provider);
 
@@ -211,6 +250,19 @@ impl OAuthClient {
             .await
             .map_err(|e| AppError::OAuth(format!("Token exchange failed: {}", e)))?;
 
+        let id_token = match nonce {
+            Some(nonce) => Some(
+                IdTokenVerifier::verify(
+                    provider,
+                    token.extra_fields().id_token.as_deref(),
+                    client.client_id(),
+                    nonce,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
         let expires_at = token
             .expires_in()
             .map(|duration| Utc::now() + chrono::Duration::seconds(duration.as_secs() as i64));
@@ -225,6 +277,7 @@ impl OAuthClient {
             refresh_token: token.refresh_token().map(|rt| rt.secret().clone()),
             expires_at,
             scopes,
+            id_token,
         })
     }
 
@@ -0,0 +1,179 @@
+use crate::auth::jwt::JwtService;
+use crate::constants::{SSO_SESSION_COOKIE_NAME, SSO_SESSION_DEFAULT_LIFETIME_MINUTES};
+use crate::db::models::{Organization, SsoSession};
+use crate::error::Result;
+use crate::middleware::{read_cookie, ClientInfo};
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+pub struct SsoSessionService;
+
+impl SsoSessionService {
+    /// Generate an opaque cookie value (only its hash is stored)
+    pub fn generate_token() -> String {
+        let bytes: [u8; 32] = rand::random();
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Read the SSO session cookie from a request
+    pub fn read_cookie(headers: &HeaderMap) -> Option<String> {
+        read_cookie(headers, SSO_SESSION_COOKIE_NAME)
+    }
+
+    /// Lifetime in minutes for an organization (0 disables SSO sessions)
+    pub fn lifetime_minutes(org: &Organization) -> i64 {
+        org.sso_session_lifetime_minutes
+            .unwrap_or(SSO_SESSION_DEFAULT_LIFETIME_MINUTES)
+    }
+
+    /// Find a live SSO session for this browser in the given organization
+    pub async fn find_active(
+        pool: &SqlitePool,
+        token: &str,
+        org_id: &str,
+    ) -> Result<Option<SsoSession>> {
+        let now = Utc::now();
+        let session = sqlx::query_as::<_, SsoSession>(
+            r#"
+            UPDATE sso_sessions SET last_used_at = ?
+            WHERE token_hash = ? AND org_id = ? AND expires_at > ?
+            RETURNING *
+            "#,
+        )
+        .bind(now)
+        .bind(JwtService::hash_token(token))
+        .bind(org_id)
+        .bind(now)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Record a successful upstream login for an organization. Reuses the
+    /// browser's existing cookie when it belongs to the same user. Returns the
+    /// cookie value and its max-age in seconds, or None if the org disabled SSO.
+    pub async fn start(
+        pool: &SqlitePool,
+        existing_token: Option<&str>,
+        user_id: &str,
+        org: &Organization,
+        provider: &str,
+        client: &ClientInfo,
+    ) -> Result<Option<(String, i64)>> {
+        let lifetime = Self::lifetime_minutes(org);
+        if lifetime <= 0 {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+
+        // Only keep the cookie if every live grant on it belongs to this user
+        let token = match existing_token {
+            Some(token) => {
+                let other_users: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM sso_sessions WHERE token_hash = ? AND user_id != ? AND expires_at > ?",
+                )
+                .bind(JwtService::hash_token(token))
+                .bind(user_id)
+                .bind(now)
+                .fetch_one(pool)
+                .await?;
+
+                if other_users == 0 {
+                    token.to_string()
+                } else {
+                    Self::generate_token()
+                }
+            }
+            None => Self::generate_token(),
+        };
+        let token_hash = JwtService::hash_token(&token);
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO sso_sessions
+            (token_hash, org_id, user_id, provider, ip_address, user_agent, created_at, last_used_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&token_hash)
+        .bind(&org.id)
+        .bind(user_id)
+        .bind(provider)
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .bind(now)
+        .bind(now)
+        .bind(now + Duration::minutes(lifetime))
+        .execute(pool)
+        .await?;
+
+        // The cookie must outlive the longest grant it carries
+        let latest_expiry = sqlx::query_scalar::<_, chrono::DateTime<Utc>>(
+            "SELECT MAX(expires_at) FROM sso_sessions WHERE token_hash = ?",
+        )
+        .bind(&token_hash)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(Some((token, (latest_expiry - now).num_seconds().max(0))))
+    }
+
+    /// Revoke the user's SSO sessions for one organization
+    pub async fn revoke_for_org_user(
+        pool: &SqlitePool,
+        org_id: &str,
+        user_id: &str,
+    ) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sso_sessions WHERE org_id = ? AND user_id = ?")
+            .bind(org_id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// End this browser's SSO session for one organization. Returns whether the
+    /// cookie still carries grants for other organizations.
+    pub async fn end(pool: &SqlitePool, token: &str, org_id: &str, user_id: &str) -> Result<bool> {
+        let token_hash = JwtService::hash_token(token);
+        sqlx::query("DELETE FROM sso_sessions WHERE token_hash = ? AND org_id = ? AND user_id = ?")
+            .bind(&token_hash)
+            .bind(org_id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+
+        let remaining: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sso_sessions WHERE token_hash = ? AND expires_at > ?",
+        )
+        .bind(&token_hash)
+        .bind(Utc::now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(remaining > 0)
+    }
+
+    /// Build the Set-Cookie header value for the SSO session
+    pub fn cookie(token: &str, max_age_seconds: i64, base_url: &str) -> String {
+        let secure = if base_url.starts_with("https://") {
+            "; Secure"
+        } else {
+            ""
+        };
+        format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            SSO_SESSION_COOKIE_NAME, token, max_age_seconds, secure
+        )
+    }
+
+    /// Build a Set-Cookie header value that removes the SSO session cookie
+    pub fn clear_cookie(base_url: &str) -> String {
+        Self::cookie("", 0, base_url)
+    }
+}
@@ -0,0 +1,359 @@
+use crate::constants::WEBHOOK_EVENT_TYPES;
+use crate::db::models::WebhookEndpoint;
+use crate::error::{AppError, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use oauth2::url::{Host, Url};
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::SqliteConnection;
+use std::net::{IpAddr, SocketAddr};
+use uuid::Uuid;
+
+/// Header carrying `t=<unix timestamp>,v1=<hex signature>[,v1=...]`
+pub const SIGNATURE_HEADER: &str = "X-SSO-Signature";
+
+pub struct WebhookService;
+
+impl WebhookService {
+    pub fn generate_secret() -> String {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        format!("whsec_{}", hex::encode(bytes))
+    }
+
+    /// Hex HMAC-SHA256 of `<timestamp>.<body>`
+    pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Signature header value. While a rotated-out secret is in its grace
+    /// period the body is signed with both, so receivers can switch over at
+    /// their own pace.
+    pub fn signature_header(endpoint: &WebhookEndpoint, timestamp: i64, body: &str) -> String {
+        let mut header = format!(
+            "t={},v1={}",
+            timestamp,
+            Self::sign(&endpoint.secret, timestamp, body)
+        );
+        if let (Some(previous), Some(expires_at)) = (
+            &endpoint.previous_secret,
+            endpoint.previous_secret_expires_at,
+        ) {
+            if expires_at > Utc::now() {
+                header.push_str(",v1=");
+                header.push_str(&Self::sign(previous, timestamp, body));
+            }
+        }
+        header
+    }
+
+    /// Webhooks go to HTTPS URLs. A host given as an IP address must be a
+    /// public one; names are checked when they are resolved for delivery.
+    pub fn validate_url(url: &str) -> Result<Url> {
+        let parsed = Url::parse(url)
+            .map_err(|_| AppError::BadRequest(format!("Invalid webhook URL: {}", url)))?;
+        if parsed.scheme() != "https" {
+            return Err(AppError::BadRequest(
+                "Webhook URLs must be HTTPS URLs".to_string(),
+            ));
+        }
+
+        let public = match parsed.host() {
+            Some(Host::Ipv4(ip)) => Self::is_public_address(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => Self::is_public_address(IpAddr::V6(ip)),
+            Some(Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+                domain != "localhost" && !domain.ends_with(".localhost")
+            }
+            None => false,
+        };
+        if !public {
+            return Err(AppError::BadRequest(
+                "Webhook URLs must point to a public host".to_string(),
+            ));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Resolve the host of a webhook URL for delivery. Fails when any address
+    /// is not public, so a name cannot be pointed at internal services.
+    pub async fn resolve_public(url: &Url) -> Result<Vec<SocketAddr>> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| AppError::BadRequest("Webhook URL has no host".to_string()))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Could not resolve {}: {}", host, e)))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(AppError::BadRequest(format!("Could not resolve {}", host)));
+        }
+        if let Some(addr) = addrs
+            .iter()
+            .find(|addr| !Self::is_public_address(addr.ip()))
+        {
+            return Err(AppError::BadRequest(format!(
+                "{} resolves to non-public address {}",
+                host,
+                addr.ip()
+            )));
+        }
+
+        Ok(addrs)
+    }
+
+    /// Client that connects only to the public addresses the URL's host
+    /// resolved to just now, so the name cannot be re-pointed mid-request
+    pub async fn pinned_client(url: &str) -> Result<reqwest::Client> {
+        let url = Self::validate_url(url)?;
+        let addrs = Self::resolve_public(&url).await?;
+
+        let mut builder = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(Host::Domain(domain)) = url.host() {
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+
+        builder
+            .build()
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Whether an address is on the public internet: loopback, private,
+    /// link-local, shared, multicast and reserved ranges are not
+    pub fn is_public_address(ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_private()
+                    || ip.is_link_local()
+                    || ip.is_broadcast()
+                    || ip.is_documentation()
+                    || ip.is_multicast()
+                    || a == 0
+                    || a >= 240
+                    || (a == 100 && (64..128).contains(&b))
+                    || (a == 198 && (b == 18 || b == 19)))
+            }
+            IpAddr::V6(ip) => {
+                if let Some(mapped) = ip.to_ipv4_mapped() {
+                    return Self::is_public_address(IpAddr::V4(mapped));
+                }
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+                    || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+            }
+        }
+    }
+
+    /// Check a list of event types; `*` subscribes to everything
+    pub fn validate_event_types(event_types: &[String]) -> Result<()> {
+        if event_types.is_empty() {
+            return Err(AppError::BadRequest(
+                "Subscribe to at least one event type".to_string(),
+            ));
+        }
+        if let Some(unknown) = event_types
+            .iter()
+            .find(|t| t.as_str() != "*" && !WEBHOOK_EVENT_TYPES.contains(&t.as_str()))
+        {
+            return Err(AppError::BadRequest(format!(
+                "Unknown event type: {}. Must be one of: *, {}",
+                unknown,
+                WEBHOOK_EVENT_TYPES.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn parse_event_types(event_types: &str) -> Vec<String> {
+        serde_json::from_str(event_types).unwrap_or_default()
+    }
+
+    /// Record an event for an organization and queue a delivery to each of
+    /// its enabled endpoints subscribed to it. Nothing is stored when no
+    /// endpoint listens. Call with the transaction of the change the event
+    /// describes, where there is one.
+    pub async fn emit(
+        conn: &mut SqliteConnection,
+        org_id: &str,
+        event_type: &str,
+        data: Value,
+    ) -> Result<()> {
+        let endpoints = sqlx::query_as::<_, WebhookEndpoint>(
+            "SELECT * FROM webhook_endpoints WHERE org_id = ? AND enabled = 1",
+        )
+        .bind(org_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let endpoints: Vec<_> = endpoints
+            .into_iter()
+            .filter(|endpoint| {
+                Self::parse_event_types(&endpoint.event_types)
+                    .iter()
+                    .any(|t| t == "*" || t == event_type)
+            })
+            .collect();
+        if endpoints.is_empty() {
+            return Ok(());
+        }
+
+        let event_id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let payload = json!({
+            "id": event_id,
+            "type": event_type,
+            "org_id": org_id,
+            "created_at": now,
+            "data": data,
+        });
+
+        sqlx::query(
+            "INSERT INTO webhook_events (id, org_id, event_type, payload, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&event_id)
+        .bind(org_id)
+        .bind(event_type)
+        .bind(payload.to_string())
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        for endpoint in endpoints {
+            Self::queue_delivery(conn, &endpoint.id, &event_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Emit an event for the organization that owns a service
+    pub async fn emit_for_service(
+        conn: &mut SqliteConnection,
+        service_id: &str,
+        event_type: &str,
+        data: Value,
+    ) -> Result<()> {
+        let org_id: Option<String> = sqlx::query_scalar("SELECT org_id FROM services WHERE id = ?")
+            .bind(service_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        match org_id {
+            Some(org_id) => Self::emit(conn, &org_id, event_type, data).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn queue_delivery(
+        conn: &mut SqliteConnection,
+        endpoint_id: &str,
+        event_id: &str,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO webhook_deliveries
+                (id, endpoint_id, event_id, status, attempts, next_attempt_at, created_at, updated_at)
+             VALUES (?, ?, ?, 'pending', 0, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(endpoint_id)
+        .bind(event_id)
+        .bind(now)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn endpoint() -> WebhookEndpoint {
+        WebhookEndpoint {
+            id: "ep".to_string(),
+            org_id: "org".to_string(),
+            url: "https://example.com/hook".to_string(),
+            description: None,
+            event_types: r#"["*"]"#.to_string(),
+            secret: "new".to_string(),
+            previous_secret: Some("old".to_string()),
+            previous_secret_expires_at: Some(Utc::now() + Duration::hours(1)),
+            enabled: true,
+            created_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_signature_header() {
+        // What a receiver computes over `<timestamp>.<body>`
+        assert_eq!(
+            WebhookService::sign("whsec_test", 1700000000, r#"{"type":"login.succeeded"}"#),
+            "19c6195a6e1e48cbfe1d62d932bb0cb4e4235e728bef837be58ca00f7731deb0"
+        );
+
+        let mut endpoint = endpoint();
+        let header = WebhookService::signature_header(&endpoint, 1700000000, "{}");
+        assert_eq!(
+            header,
+            format!(
+                "t=1700000000,v1={},v1={}",
+                WebhookService::sign("new", 1700000000, "{}"),
+                WebhookService::sign("old", 1700000000, "{}")
+            )
+        );
+
+        // The old secret stops signing once its grace period is over
+        endpoint.previous_secret_expires_at = Some(Utc::now() - Duration::hours(1));
+        let header = WebhookService::signature_header(&endpoint, 1700000000, "{}");
+        assert_eq!(header.matches("v1=").count(), 1);
+
+        assert!(WebhookService::validate_event_types(&["login.succeeded".to_string()]).is_ok());
+        assert!(WebhookService::validate_event_types(&["*".to_string()]).is_ok());
+        assert!(WebhookService::validate_event_types(&["login.failed".to_string()]).is_err());
+        assert!(WebhookService::validate_event_types(&[]).is_err());
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(WebhookService::validate_url("https://hooks.example.com/sso").is_ok());
+        assert!(WebhookService::validate_url("https://93.184.216.34/sso").is_ok());
+        assert!(WebhookService::validate_url("http://hooks.example.com/sso").is_err());
+        assert!(WebhookService::validate_url("https://localhost:8080/sso").is_err());
+        assert!(WebhookService::validate_url("https://127.0.0.1/sso").is_err());
+        assert!(WebhookService::validate_url("https://10.1.2.3/sso").is_err());
+        assert!(WebhookService::validate_url("https://169.254.169.254/latest").is_err());
+        assert!(WebhookService::validate_url("https://100.64.0.1/sso").is_err());
+        assert!(WebhookService::validate_url("https://[::1]/sso").is_err());
+        assert!(WebhookService::validate_url("https://[fd00::1]/sso").is_err());
+        assert!(WebhookService::validate_url("https://[fe80::1]/sso").is_err());
+        assert!(WebhookService::validate_url("https://[::ffff:192.168.0.1]/sso").is_err());
+        assert!(WebhookService::validate_url("https://[2606:4700::1111]/sso").is_ok());
+    }
+}
@@ -1 +1,2 @@
 pub mod stripe;
+pub mod tiers;
@@ -1,16 +1,18 @@
+use crate::auth::webhooks::WebhookService;
+use crate::constants::WEBHOOK_EVENT_SUBSCRIPTION_CHANGED;
 use crate::db::models::{Organization, StripeCustomer};
 use crate::error::{AppError, Result};
 use chrono::Utc;
+use serde_json::json;
 use sqlx::SqlitePool;
 use stripe::{
-    CheckoutSession, CheckoutSessionMode, Client, CreateCheckoutSession,
+    CancelSubscription, CheckoutSession, CheckoutSessionMode, Client, CreateCheckoutSession,
     CreateCheckoutSessionLineItems, CreateCustomer, Customer, Event, EventObject, EventType,
-    Webhook,
+    SubscriptionId, Webhook,
 };
 use uuid::Uuid;
 
 pub struct StripeService {
-    #[allow(dead_code)]
     client: Client,
     webhook_secret: String,
 }
@@ -117,6 +119,19 @@ impl StripeService {
         Ok(session)
     }
 
+    /// Cancel a subscription immediately, e.g. when its user asks to be deleted
+    pub async fn cancel_subscription(&self, subscription_id: &str) -> Result<()> {
+        let id: SubscriptionId = subscription_id
+            .parse()
+            .map_err(|_| AppError::Stripe("Invalid subscription ID".to_string()))?;
+
+        stripe::Subscription::cancel(&self.client, &id, CancelSubscription::new())
+            .await
+            .map_err(|e| AppError::Stripe(e.to_string()))?;
+
+        Ok(())
+    }
+
     /// Verify webhook signature and parse event
     pub fn verify_webhook(&self, payload: &str, signature: &str) -> Result<Event> {
         Webhook::construct_event(payload, signature, &self.webhook_secret)
@@ -152,10 +167,11 @@ impl StripeService {
         let id = Uuid::new_v4().to_string();
         sqlx::query(
             r#"
-            INSERT INTO subscriptions (id, user_id, service_id, plan_id, status, current_period_end)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO subscriptions (id, user_id, service_id, plan_id, status, current_period_end,
+                                       stripe_subscription_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(user_id, service_id)
-            DO UPDATE SET plan_id = ?, status = ?, current_period_end = ?
+            DO UPDATE SET plan_id = ?, status = ?, current_period_end = ?, stripe_subscription_id = ?
             "#,
         )
         .bind(&id)
@@ -164,12 +180,28 @@ impl StripeService {
         .bind(plan_id)
         .bind(&status)
         .bind(current_period_end)
+        .bind(subscription.id.to_string())
         .bind(plan_id)
         .bind(&status)
         .bind(current_period_end)
+        .bind(subscription.id.to_string())
         .execute(pool)
         .await?;
 
+        WebhookService::emit_for_service(
+            &mut *pool.acquire().await?,
+            service_id,
+            WEBHOOK_EVENT_SUBSCRIPTION_CHANGED,
+            json!({
+                "user_id": user_id,
+                "service_id": service_id,
+                "plan_id": plan_id,
+                "status": status,
+                "current_period_end": current_period_end,
+            }),
+        )
+        .await?;
+
         Ok(())
     }
 
@@ -218,6 +250,19 @@ impl StripeService {
                         .bind(service_id)
                         .execute(pool)
                         .await?;
+
+                        WebhookService::emit_for_service(
+                            &mut *pool.acquire().await?,
+                            service_id,
+                            WEBHOOK_EVENT_SUBSCRIPTION_CHANGED,
+                            json!({
+                                "user_id": user_id,
+                                "service_id": service_id,
+                                "plan_id": metadata.get("plan_id"),
+                                "status": "cancelled",
+                            }),
+                        )
+                        .await?;
                     }
                 }
             }
@@ -0,0 +1,25 @@
+use crate::constants::DEFAULT_MAX_USERS;
+use crate::db::models::Organization;
+use crate::error::Result;
+
+pub struct TierService;
+
+impl TierService {
+    /// Most members the organization may have: its own override, else its tier's
+    pub async fn member_limit(
+        executor: impl sqlx::SqliteExecutor<'_>,
+        org: &Organization,
+    ) -> Result<i64> {
+        if let Some(max_users) = org.max_users {
+            return Ok(max_users);
+        }
+
+        let tier_limit: Option<i64> =
+            sqlx::query_scalar("SELECT default_max_users FROM organization_tiers WHERE id = ?")
+                .bind(&org.tier_id)
+                .fetch_optional(executor)
+                .await?;
+
+        Ok(tier_limit.unwrap_or(DEFAULT_MAX_USERS))
+    }
+}
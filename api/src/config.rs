@@ -1,3 +1,8 @@
+use crate::constants::{
+    LOGIN_FAILURE_WINDOW_MINUTES, LOGIN_IP_LOCKOUT_AFTER_FAILURES, LOGIN_IP_THROTTLE_AFTER_FAILURES,
+    LOGIN_LOCKOUT_AFTER_FAILURES, LOGIN_LOCKOUT_MINUTES, LOGIN_THROTTLE_AFTER_FAILURES,
+};
+use crate::middleware::TrustedProxies;
 use std::env;
 
 #[derive(Debug, Clone)]
@@ -36,6 +41,45 @@ pub struct Config {
     pub base_url: String,
     pub platform_admin_redirect_uri: String,
     pub platform_device_activation_uri: String,
+    // Frontend page that accepts invitations; invitation email links redirect there
+    pub invitation_accept_uri: Option<String>,
+
+    // DNS nameserver (host:port) for domain verification; system resolver when unset
+    pub dns_resolver: Option<String>,
+
+    // Outgoing email: 'smtp', 'file' (write .eml files) or 'log'
+    pub email_transport: String,
+    pub email_from: String,
+    pub email_outbox_dir: String,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_tls: String,
+
+    // Hours before an invitation expires to remind the invitee; no reminder when unset
+    pub invitation_reminder_hours: Option<i64>,
+
+    // Directory file SIEM sinks write into
+    pub siem_file_dir: String,
+
+    // MaxMind DB file for login locations; location checks are skipped when unset
+    pub geoip_database_path: Option<String>,
+
+    // Reverse proxies allowed to set X-Forwarded-For / X-Real-IP
+    pub trusted_proxies: TrustedProxies,
+
+    // Browser origins allowed to make credentialed cross-origin requests; any origin
+    // may make uncredentialed ones when empty
+    pub cors_allowed_origins: Vec<String>,
+
+    // Failed-login throttling and lockout thresholds
+    pub login_failure_window_minutes: i64,
+    pub login_throttle_after_failures: i64,
+    pub login_lockout_after_failures: i64,
+    pub login_ip_throttle_after_failures: i64,
+    pub login_ip_lockout_after_failures: i64,
+    pub login_lockout_minutes: i64,
 }
 
 impl Config {
@@ -97,6 +141,79 @@ impl Config {
                 .map_err(|_| "PLATFORM_ADMIN_REDIRECT_URI must be set")?,
             platform_device_activation_uri: env::var("PLATFORM_DEVICE_ACTIVATION_URI")
                 .map_err(|_| "PLATFORM_DEVICE_ACTIVATION_URI must be set")?,
+            invitation_accept_uri: env::var("INVITATION_ACCEPT_URI").ok(),
+
+            dns_resolver: env::var("DNS_RESOLVER").ok(),
+
+            email_transport: env::var("EMAIL_TRANSPORT").unwrap_or_else(|_| "log".to_string()),
+            email_from: env::var("EMAIL_FROM")
+                .unwrap_or_else(|_| "SSO <no-reply@localhost>".to_string()),
+            email_outbox_dir: env::var("EMAIL_OUTBOX_DIR")
+                .unwrap_or_else(|_| "./mail".to_string()),
+            smtp_host: env::var("SMTP_HOST").ok(),
+            smtp_port: env::var("SMTP_PORT")
+                .ok()
+                .map(|port| port.parse())
+                .transpose()
+                .map_err(|_| "SMTP_PORT must be a valid number")?,
+            smtp_username: env::var("SMTP_USERNAME").ok(),
+            smtp_password: env::var("SMTP_PASSWORD").ok(),
+            smtp_tls: env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string()),
+
+            invitation_reminder_hours: env::var("INVITATION_REMINDER_HOURS")
+                .ok()
+                .map(|hours| hours.parse())
+                .transpose()
+                .map_err(|_| "INVITATION_REMINDER_HOURS must be a valid number")?,
+
+            siem_file_dir: env::var("SIEM_FILE_DIR").unwrap_or_else(|_| "./siem".to_string()),
+
+            geoip_database_path: env::var("GEOIP_DATABASE_PATH").ok(),
+
+            trusted_proxies: TrustedProxies::parse(
+                &env::var("TRUSTED_PROXIES").unwrap_or_default(),
+            )?,
+
+            cors_allowed_origins: env::var("CORS_ALLOWED_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+
+            login_failure_window_minutes: env_number(
+                "LOGIN_FAILURE_WINDOW_MINUTES",
+                LOGIN_FAILURE_WINDOW_MINUTES,
+            )?,
+            login_throttle_after_failures: env_number(
+                "LOGIN_THROTTLE_AFTER_FAILURES",
+                LOGIN_THROTTLE_AFTER_FAILURES,
+            )?,
+            login_lockout_after_failures: env_number(
+                "LOGIN_LOCKOUT_AFTER_FAILURES",
+                LOGIN_LOCKOUT_AFTER_FAILURES,
+            )?,
+            login_ip_throttle_after_failures: env_number(
+                "LOGIN_IP_THROTTLE_AFTER_FAILURES",
+                LOGIN_IP_THROTTLE_AFTER_FAILURES,
+            )?,
+            login_ip_lockout_after_failures: env_number(
+                "LOGIN_IP_LOCKOUT_AFTER_FAILURES",
+                LOGIN_IP_LOCKOUT_AFTER_FAILURES,
+            )?,
+            login_lockout_minutes: env_number("LOGIN_LOCKOUT_MINUTES", LOGIN_LOCKOUT_MINUTES)?,
         })
     }
 }
+
+/// A positive number from the environment, or the default when unset
+fn env_number(name: &str, default: i64) -> Result<i64, String> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|number| *number > 0)
+            .ok_or_else(|| format!("{} must be a positive number", name)),
+        Err(_) => Ok(default),
+    }
+}
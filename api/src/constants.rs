@@ -2,10 +2,61 @@ pub const DEFAULT_TIER_NAME: &str = "free";
 pub const DEFAULT_MAX_SERVICES: i64 = 2;
 pub const DEFAULT_MAX_USERS: i64 = 3;
 pub const INVITATION_EXPIRY_DAYS: i64 = 7;
+pub const MAX_INVITATION_EXPIRY_DAYS: i64 = 30;
+pub const BULK_INVITATION_MAX_ROWS: usize = 500;
+pub const INVITE_LINK_DEFAULT_EXPIRY_DAYS: i64 = 7;
+pub const MAX_INVITE_LINK_EXPIRY_DAYS: i64 = 30;
+pub const MAX_INVITE_LINK_USES: i64 = 1000;
 pub const DEVICE_CODE_EXPIRE_MINUTES: i64 = 15;
+pub const CIBA_REQUEST_EXPIRE_MINUTES: i64 = 5;
+pub const CIBA_BINDING_MESSAGE_MAX_LENGTH: usize = 100;
 pub const JWT_EXPIRE_HOURS: i64 = 24;
+pub const ID_TOKEN_HINT_MAX_EXPIRED_MINUTES: u64 = 60;
 pub const OAUTH_STATE_EXPIRE_MINUTES: i64 = 10;
+pub const OAUTH_BINDING_COOKIE_NAME: &str = "oauth_binding";
+pub const SESSION_LAST_USED_RESOLUTION_MINUTES: i64 = 5;
+pub const SSO_SESSION_DEFAULT_LIFETIME_MINUTES: i64 = 8 * 60;
+pub const MAX_SSO_SESSION_LIFETIME_MINUTES: i64 = 30 * 24 * 60;
+pub const SSO_SESSION_COOKIE_NAME: &str = "sso_session";
 pub const TOKEN_REFRESH_LOCK_TIMEOUT_SECONDS: i64 = 30;
+pub const BACKCHANNEL_LOGOUT_MAX_ATTEMPTS: i64 = 6;
+pub const BACKCHANNEL_LOGOUT_BACKOFF_SECONDS: i64 = 30;
+pub const EMAIL_DELIVERY_MAX_ATTEMPTS: i64 = 8;
+pub const EMAIL_DELIVERY_BACKOFF_SECONDS: i64 = 60;
+pub const WEBHOOK_DELIVERY_MAX_ATTEMPTS: i64 = 8;
+pub const WEBHOOK_DELIVERY_BACKOFF_SECONDS: i64 = 30;
+pub const WEBHOOK_SECRET_ROTATION_GRACE_HOURS: i64 = 24;
+pub const MAX_WEBHOOK_ENDPOINTS_PER_ORG: i64 = 20;
+pub const AUDIT_CHECKPOINT_INTERVAL_MINUTES: i64 = 60;
+pub const SIEM_EXPORT_INTERVAL_SECONDS: u64 = 10;
+pub const SIEM_EXPORT_BATCH_SIZE: i64 = 500;
+pub const SIEM_EXPORT_MAX_BATCHES: usize = 20;
+pub const LOGIN_RISK_ACTIONS: &[&str] = &["allow", "notify", "require_email_code", "block"];
+pub const LOGIN_RISK_NEW_DEVICE_SCORE: i64 = 30;
+pub const LOGIN_RISK_NEW_COUNTRY_SCORE: i64 = 40;
+pub const LOGIN_RISK_IMPOSSIBLE_TRAVEL_SCORE: i64 = 70;
+pub const LOGIN_RISK_MEDIUM_SCORE: i64 = 30;
+pub const LOGIN_RISK_HIGH_SCORE: i64 = 70;
+pub const IMPOSSIBLE_TRAVEL_SPEED_KMH: f64 = 1000.0;
+pub const IMPOSSIBLE_TRAVEL_MIN_DISTANCE_KM: f64 = 500.0; // GeoIP is rarely closer than this
+pub const LOGIN_CHALLENGE_EXPIRE_MINUTES: i64 = 10;
+pub const LOGIN_CHALLENGE_MAX_ATTEMPTS: i64 = 5;
+// Failed-login defaults, overridable with LOGIN_* environment variables
+pub const LOGIN_FAILURE_WINDOW_MINUTES: i64 = 15;
+pub const LOGIN_THROTTLE_AFTER_FAILURES: i64 = 3;
+pub const LOGIN_LOCKOUT_AFTER_FAILURES: i64 = 10;
+pub const LOGIN_IP_THROTTLE_AFTER_FAILURES: i64 = 20;
+pub const LOGIN_IP_LOCKOUT_AFTER_FAILURES: i64 = 100;
+pub const LOGIN_LOCKOUT_MINUTES: i64 = 15;
+pub const LOGIN_THROTTLE_MAX_SECONDS: i64 = 60;
+pub const IMPERSONATION_DEFAULT_MINUTES: i64 = 30;
+pub const MAX_IMPERSONATION_MINUTES: i64 = 60;
+pub const ACCOUNT_MERGE_MAX_AUTH_AGE_MINUTES: i64 = 10;
+pub const ACCOUNT_MERGE_PROOF_MINUTES: i64 = 5;
+pub const API_TOKEN_DEFAULT_LIFETIME_DAYS: i64 = 90;
+pub const MAX_API_TOKEN_LIFETIME_DAYS: i64 = 365;
+pub const PERSONAL_ACCESS_TOKEN_PREFIX: &str = "sso_pat_";
+pub const ORG_API_KEY_PREFIX: &str = "sso_key_";
 
 pub const RESERVED_SLUGS: &[&str] = &[
     "api", "www", "mail", "ftp", "admin", "root", "support", "help", "docs", "blog", "news",
@@ -14,6 +65,62 @@ pub const RESERVED_SLUGS: &[&str] = &[
 
 pub const VALID_ORG_ROLES: &[&str] = &["owner", "admin", "member"];
 pub const VALID_INVITATION_ROLES: &[&str] = &["admin", "member"];
+pub const INVITATION_STATUSES: &[&str] = &["pending", "expired", "accepted", "declined", "cancelled"];
+
+// Organization permission catalogue. Custom org roles are built from these;
+// the owner implicitly holds all of them.
+pub const ORG_PERMISSIONS: &[&str] = &[
+    "manage_organization",
+    "manage_members",
+    "manage_roles",
+    "manage_invitations",
+    "manage_api_keys",
+    "manage_services",
+    "delete_services",
+    "manage_oauth_credentials",
+    "manage_domains",
+    "manage_end_users",
+    "view_end_users",
+    "revoke_sessions",
+    "impersonate_users",
+    "view_analytics",
+    "manage_billing",
+    "manage_webhooks",
+    "view_audit_log",
+];
+pub const ADMIN_ORG_PERMISSIONS: &[&str] = &[
+    "manage_organization",
+    "manage_members",
+    "manage_invitations",
+    "manage_api_keys",
+    "manage_services",
+    "manage_oauth_credentials",
+    "manage_end_users",
+    "view_end_users",
+    "revoke_sessions",
+    "view_analytics",
+    "manage_billing",
+    "manage_webhooks",
+    "view_audit_log",
+];
+pub const MEMBER_ORG_PERMISSIONS: &[&str] = &["view_end_users", "view_analytics"];
+pub const VALID_API_TOKEN_SCOPES: &[&str] = &["read", "write", "platform", "scim", "ciba"];
+pub const WEBHOOK_EVENT_USER_CREATED: &str = "user.created";
+pub const WEBHOOK_EVENT_LOGIN_SUCCEEDED: &str = "login.succeeded";
+pub const WEBHOOK_EVENT_IDENTITY_LINKED: &str = "identity.linked";
+pub const WEBHOOK_EVENT_IDENTITY_UNLINKED: &str = "identity.unlinked";
+pub const WEBHOOK_EVENT_SESSION_REVOKED: &str = "session.revoked";
+pub const WEBHOOK_EVENT_MEMBER_ROLE_CHANGED: &str = "member.role_changed";
+pub const WEBHOOK_EVENT_SUBSCRIPTION_CHANGED: &str = "subscription.changed";
+pub const WEBHOOK_EVENT_TYPES: &[&str] = &[
+    WEBHOOK_EVENT_USER_CREATED,
+    WEBHOOK_EVENT_LOGIN_SUCCEEDED,
+    WEBHOOK_EVENT_IDENTITY_LINKED,
+    WEBHOOK_EVENT_IDENTITY_UNLINKED,
+    WEBHOOK_EVENT_SESSION_REVOKED,
+    WEBHOOK_EVENT_MEMBER_ROLE_CHANGED,
+    WEBHOOK_EVENT_SUBSCRIPTION_CHANGED,
+];
 pub const VALID_SERVICE_TYPES: &[&str] = &["web", "mobile", "desktop", "api"];
 
 pub const MIN_SLUG_LENGTH: usize = 3;
@@ -67,3 +67,83 @@ pub async fn init_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
 
     Ok(pool)
 }
+
+/// Throwaway migrated databases and rows for tests that run SQL
+#[cfg(test)]
+pub mod test_support {
+    use super::models::{Organization, Service, User};
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use sqlx::SqlitePool;
+    use uuid::Uuid;
+
+    /// A fresh database file with every migration applied. One connection, so
+    /// every query sees the writes before it.
+    pub async fn test_pool() -> SqlitePool {
+        let path = std::env::temp_dir().join(format!("sso-test-{}.db", Uuid::new_v4()));
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_secs(5))
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        pool
+    }
+
+    pub async fn insert_user(pool: &SqlitePool, email: &str) -> User {
+        sqlx::query_as::<_, User>(
+            "INSERT INTO users (id, email, is_platform_owner) VALUES (?, ?, 0) RETURNING *",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(email)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    /// An active organization with its owner as a member
+    pub async fn insert_org(pool: &SqlitePool, slug: &str, owner: &User) -> Organization {
+        let org = sqlx::query_as::<_, Organization>(
+            "INSERT INTO organizations (id, slug, name, owner_user_id, status)
+             VALUES (?, ?, ?, ?, 'active') RETURNING *",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(slug)
+        .bind(slug)
+        .bind(&owner.id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        insert_membership(pool, &org, owner, "owner").await;
+        org
+    }
+
+    pub async fn insert_membership(pool: &SqlitePool, org: &Organization, user: &User, role: &str) {
+        sqlx::query("INSERT INTO memberships (id, org_id, user_id, role) VALUES (?, ?, ?, ?)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(&org.id)
+            .bind(&user.id)
+            .bind(role)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    pub async fn insert_service(pool: &SqlitePool, org: &Organization, slug: &str) -> Service {
+        sqlx::query_as::<_, Service>(
+            "INSERT INTO services (id, org_id, slug, name, service_type, client_id)
+             VALUES (?, ?, ?, ?, 'web', ?) RETURNING *",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&org.id)
+        .bind(slug)
+        .bind(slug)
+        .bind(format!("client-{}", Uuid::new_v4()))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+}
@@ -8,6 +8,12 @@ pub struct User {
     pub email: String,
     pub is_platform_owner: bool,
     pub created_at: DateTime<Utc>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub primary_identity_id: Option<String>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -31,6 +37,15 @@ pub struct Identity {
     pub issuing_org_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub issuing_service_id: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub username: Option<String>,
+    pub profile_updated_at: Option<DateTime<Utc>>,
+    pub email: Option<String>,
+    pub email_verified: bool,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -48,6 +63,9 @@ pub struct Organization {
     pub rejected_by: Option<String>,
     pub rejected_at: Option<DateTime<Utc>>,
     pub rejection_reason: Option<String>,
+    pub sso_session_lifetime_minutes: Option<i64>,
+    pub login_risk_medium_action: String, // 'allow', 'notify', 'require_email_code' or 'block'
+    pub login_risk_high_action: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -74,7 +92,12 @@ pub struct Service {
     pub google_scopes: Option<String>,
     pub redirect_uris: Option<String>,
     pub device_activation_uri: Option<String>,
+    pub ciba_notification_endpoint: Option<String>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    pub post_logout_redirect_uris: Option<String>, // JSON array
     pub created_at: DateTime<Utc>,
+    pub require_verified_email: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,7 +113,12 @@ pub struct ServiceResponse {
     pub google_scopes: Option<Vec<String>>,
     pub redirect_uris: Option<Vec<String>>,
     pub device_activation_uri: Option<String>,
+    pub ciba_notification_endpoint: Option<String>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    pub post_logout_redirect_uris: Option<Vec<String>>,
     pub created_at: DateTime<Utc>,
+    pub require_verified_email: bool,
 }
 
 impl From<Service> for ServiceResponse {
@@ -107,7 +135,14 @@ impl From<Service> for ServiceResponse {
             google_scopes: service.google_scopes.and_then(|s| serde_json::from_str(&s).ok()),
             redirect_uris: service.redirect_uris.and_then(|s| serde_json::from_str(&s).ok()),
             device_activation_uri: service.device_activation_uri,
+            ciba_notification_endpoint: service.ciba_notification_endpoint,
+            backchannel_logout_uri: service.backchannel_logout_uri,
+            frontchannel_logout_uri: service.frontchannel_logout_uri,
+            post_logout_redirect_uris: service
+                .post_logout_redirect_uris
+                .and_then(|s| serde_json::from_str(&s).ok()),
             created_at: service.created_at,
+            require_verified_email: service.require_verified_email,
         }
     }
 }
@@ -123,6 +158,7 @@ pub struct Plan {
     pub created_at: DateTime<Utc>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Subscription {
     pub id: String,
@@ -144,6 +180,13 @@ pub struct DeviceCode {
     pub expires_at: DateTime<Utc>,
     pub user_id: Option<String>,
     pub status: String,
+    pub flow_type: String,
+    pub login_hint_user_id: Option<String>,
+    pub binding_message: Option<String>,
+    pub client_notification_token: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub provider: Option<String>, // Provider the user signed in with to authorize it
+    pub authenticated_at: Option<DateTime<Utc>>, // When that sign-in happened
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -159,6 +202,11 @@ pub struct Session {
     pub user_agent: Option<String>,
     pub ip_address: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub impersonator_id: Option<String>,
+    pub impersonation_reason: Option<String>,
+    pub provider: Option<String>, // Provider of the sign-in; NULL for older and impersonation sessions
+    pub authenticated_at: Option<DateTime<Utc>>, // Kept across refreshes; NULL for impersonation sessions
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -169,11 +217,13 @@ pub struct StripeCustomer {
 }
 
 // Helper structs for queries
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlanFeatures {
     pub features: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserWithContext {
     pub user: User,
@@ -194,8 +244,11 @@ pub struct OAuthState {
     pub device_user_code: Option<String>,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+    pub browser_binding: Option<String>,
+    pub nonce: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct TokenRefreshLock {
     pub user_id: String,
@@ -226,6 +279,8 @@ pub struct OrganizationInvitation {
     pub status: String,
     pub token: String,
     pub expires_at: DateTime<Utc>,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub reminder_sent_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -238,8 +293,79 @@ pub struct PlatformAuditLog {
     pub target_id: String,
     pub metadata: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub seq: Option<i64>, // Position in the audit hash chain
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
 }
 
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct OrganizationAuditLog {
+    pub id: String,
+    pub org_id: String,
+    pub actor_id: String,
+    pub impersonator_id: Option<String>,
+    pub api_token_id: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub changes: Option<String>, // JSON object of field -> {before, after}
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub seq: Option<i64>, // Position in the organization's audit hash chain
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub id: String,
+    pub event_type: String,
+    pub severity: String, // 'low', 'medium', 'high'
+    pub user_id: Option<String>,
+    pub org_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Option<String>, // JSON object
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SiemSink {
+    pub id: String,
+    pub name: String,
+    pub kind: String,    // 'file', 'syslog', 'http'
+    pub config: String,  // JSON, shape depends on kind
+    pub sources: String, // JSON array
+    pub enabled: bool,
+    pub last_error: Option<String>,
+    pub last_exported_at: Option<DateTime<Utc>>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SiemSinkCursor {
+    pub sink_id: String,
+    pub source: String,
+    pub position: i64, // rowid of the last exported row
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+    pub id: String,
+    pub org_id: Option<String>, // None for the platform audit log
+    pub seq: i64,
+    pub entry_hash: String,
+    pub signature: String,
+    pub key_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct LoginEvent {
     pub id: String,
@@ -249,6 +375,55 @@ pub struct LoginEvent {
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub outcome: String, // 'success', 'challenged' or 'blocked'
+    pub device_fingerprint: Option<String>,
+    pub country: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub risk_score: i64,
+    pub risk_reasons: Option<String>, // JSON array
+    pub risk_action: String,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LoginChallenge {
+    pub id: String,
+    pub login_event_id: String,
+    pub user_id: String,
+    pub service_id: String,
+    #[serde(skip_serializing)]
+    pub code_hash: String,
+    pub attempts: i64,
+    pub expires_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LoginFailure {
+    pub id: String,
+    pub flow: String,
+    pub reason: String,
+    pub user_id: Option<String>,
+    pub provider: Option<String>,
+    pub provider_user_id: Option<String>,
+    pub org_id: Option<String>,
+    pub service_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LoginLockout {
+    pub scope: String,
+    pub subject: String,
+    pub user_id: Option<String>,
+    pub failures: i64,
+    pub window_started_at: DateTime<Utc>,
+    pub last_failure_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -264,3 +439,211 @@ pub struct OrganizationOAuthCredential {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct BackchannelLogoutDelivery {
+    pub id: String,
+    pub service_id: String,
+    pub session_id: String,
+    pub logout_uri: String,
+    pub logout_token: String,
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SsoSession {
+    pub token_hash: String,
+    pub org_id: String,
+    pub user_id: String,
+    pub provider: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserGroup {
+    pub id: String,
+    pub org_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub external_id: Option<String>, // set for groups provisioned through SCIM
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ServiceRole {
+    pub id: String,
+    pub service_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: String, // JSON array
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ServiceRoleAssignment {
+    pub id: String,
+    pub role_id: String,
+    pub user_id: Option<String>,
+    pub group_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct OrgRole {
+    pub id: String,
+    pub org_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: String, // JSON array
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub kind: String, // 'personal' or 'org'
+    pub name: String,
+    pub token_prefix: String, // token_hash is never loaded
+    pub user_id: String,
+    pub org_id: Option<String>,
+    pub role: Option<String>,
+    pub created_by: Option<String>,
+    pub scopes: String, // JSON array
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct OrganizationDomain {
+    pub id: String,
+    pub org_id: String,
+    pub domain: String,
+    pub verification_token: String,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub auto_join: bool,
+    pub default_role: String,
+    pub enforce_sso: bool,
+    pub required_provider: Option<String>, // 'github', 'google' or 'microsoft'
+    pub require_enterprise_connection: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ScimUser {
+    pub org_id: String,
+    pub user_id: String,
+    pub external_id: Option<String>,
+    pub user_type: String,    // 'member' or 'end_user'
+    pub role: Option<String>, // organization role for members
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // Name sent by the identity provider, as the organization sees the user
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct EmailOutboxMessage {
+    pub id: String,
+    pub org_id: Option<String>,
+    pub template: String,
+    pub to_address: String,
+    pub subject: String,
+    pub body_text: String,
+    pub status: String, // 'pending', 'sent', 'failed', 'bounced' or 'suppressed'
+    pub attempts: i64,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct EmailTemplateOverride {
+    pub org_id: String,
+    pub template: String,
+    pub subject: String,
+    pub body_text: String,
+    pub updated_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct EmailSuppression {
+    pub email: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct OrganizationInviteLink {
+    pub id: String,
+    pub org_id: String,
+    pub token_hash: String,
+    pub role: String,
+    pub max_uses: i64,
+    pub use_count: i64,
+    pub allowed_domain: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub org_id: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub event_types: String, // JSON array
+    pub secret: String,
+    pub previous_secret: Option<String>,
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    pub enabled: bool,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    pub org_id: String,
+    pub event_type: String,
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub endpoint_id: String,
+    pub event_id: String,
+    pub status: String, // 'pending', 'delivered' or 'failed'
+    pub attempts: i64,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
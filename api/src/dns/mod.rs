@@ -0,0 +1,128 @@
+use crate::error::{AppError, Result};
+use futures::future::BoxFuture;
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::TokioAsyncResolver;
+use std::net::SocketAddr;
+
+/// Source of DNS TXT records. Domain verification goes through this trait so
+/// it can run against a stub resolver.
+pub trait TxtResolver: Send + Sync {
+    /// All TXT strings published at `name`; empty when there are none
+    fn txt_records<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+}
+
+pub struct DnsResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsResolver {
+    /// Query `nameserver` (`host:port`) when given, otherwise the nameservers
+    /// from the system configuration
+    pub fn new(nameserver: Option<&str>) -> Result<Self> {
+        let resolver = match nameserver {
+            Some(nameserver) => {
+                let addr: SocketAddr = nameserver.parse().map_err(|_| {
+                    AppError::InternalServerError(format!("Invalid DNS resolver: {}", nameserver))
+                })?;
+                let group = NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true);
+                TokioAsyncResolver::tokio(
+                    ResolverConfig::from_parts(None, vec![], group),
+                    Self::options(),
+                )
+            }
+            None => {
+                let (config, _) =
+                    hickory_resolver::system_conf::read_system_conf().map_err(|e| {
+                        AppError::InternalServerError(format!("Failed to read DNS config: {}", e))
+                    })?;
+                TokioAsyncResolver::tokio(config, Self::options())
+            }
+        };
+
+        Ok(Self { resolver })
+    }
+
+    fn options() -> ResolverOpts {
+        let mut options = ResolverOpts::default();
+        // A record published a moment ago must be seen on the next attempt
+        options.cache_size = 0;
+        options
+    }
+}
+
+impl TxtResolver for DnsResolver {
+    fn txt_records<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(async move {
+            match self.resolver.txt_lookup(name).await {
+                Ok(lookup) => Ok(lookup
+                    .iter()
+                    .map(|txt| {
+                        txt.txt_data()
+                            .iter()
+                            .map(|part| String::from_utf8_lossy(part))
+                            .collect::<String>()
+                    })
+                    .collect()),
+                Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                    Ok(Vec::new())
+                }
+                Err(e) => Err(AppError::InternalServerError(format!(
+                    "DNS lookup for {} failed: {}",
+                    name, e
+                ))),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::op::{Message, MessageType};
+    use hickory_resolver::proto::rr::{rdata::TXT, RData, Record};
+    use tokio::net::UdpSocket;
+
+    /// Answer every query with one TXT record
+    async fn dns_stub(txt: &'static str) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let query = Message::from_vec(&buf[..len]).unwrap();
+                let mut response = Message::new();
+                response
+                    .set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(query.op_code())
+                    .set_recursion_desired(true)
+                    .set_recursion_available(true)
+                    .add_queries(query.queries().to_vec());
+                let name = query.queries()[0].name().clone();
+                response.add_answer(Record::from_rdata(
+                    name,
+                    60,
+                    RData::TXT(TXT::new(vec![txt.to_string()])),
+                ));
+                socket
+                    .send_to(&response.to_vec().unwrap(), peer)
+                    .await
+                    .unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_txt_lookup_against_stub() {
+        let addr = dns_stub("sso-domain-verification=abc").await;
+        let resolver = DnsResolver::new(Some(&addr.to_string())).unwrap();
+
+        let records = resolver
+            .txt_records("_sso-verification.example.com.")
+            .await
+            .unwrap();
+        assert_eq!(records, vec!["sso-domain-verification=abc".to_string()]);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use futures::future::BoxFuture;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Why a message could not be handed over. Permanent failures (a 5xx reply,
+/// an unparseable address) are treated as bounces and never retried.
+#[derive(Debug)]
+pub struct DeliveryError {
+    pub permanent: bool,
+    pub message: String,
+}
+
+impl DeliveryError {
+    fn permanent(message: impl Into<String>) -> Self {
+        Self {
+            permanent: true,
+            message: message.into(),
+        }
+    }
+
+    fn transient(message: impl Into<String>) -> Self {
+        Self {
+            permanent: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// Destination for outgoing mail. The delivery job goes through this trait so
+/// the SMTP relay can be swapped for a file or log sink.
+pub trait MailTransport: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        message: &'a Message,
+    ) -> BoxFuture<'a, std::result::Result<(), DeliveryError>>;
+}
+
+/// Build a plain-text message
+pub fn compose(
+    from: &Mailbox,
+    to: &str,
+    subject: &str,
+    body: &str,
+) -> std::result::Result<Message, DeliveryError> {
+    let to: Mailbox = to
+        .parse()
+        .map_err(|e| DeliveryError::permanent(format!("Invalid recipient {}: {}", to, e)))?;
+
+    Message::builder()
+        .message_id(None)
+        .from(from.clone())
+        .to(to)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body.to_string())
+        .map_err(|e| DeliveryError::permanent(e.to_string()))
+}
+
+/// Pick the transport named by `EMAIL_TRANSPORT`
+pub fn transport_from_config(config: &Config) -> Result<Arc<dyn MailTransport>> {
+    match config.email_transport.as_str() {
+        "smtp" => {
+            let host = config.smtp_host.as_deref().ok_or_else(|| {
+                AppError::InternalServerError(
+                    "SMTP_HOST must be set for the smtp transport".to_string(),
+                )
+            })?;
+            let credentials = match (&config.smtp_username, &config.smtp_password) {
+                (Some(username), Some(password)) => {
+                    Some(Credentials::new(username.clone(), password.clone()))
+                }
+                _ => None,
+            };
+            Ok(Arc::new(SmtpMailer::new(
+                host,
+                config.smtp_port,
+                &config.smtp_tls,
+                credentials,
+            )?))
+        }
+        "file" => Ok(Arc::new(FileMailer::new(&config.email_outbox_dir)?)),
+        "log" => Ok(Arc::new(LogMailer)),
+        other => Err(AppError::InternalServerError(format!(
+            "Unknown EMAIL_TRANSPORT: {} (expected smtp, file or log)",
+            other
+        ))),
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    /// `tls` is `starttls` (upgrade on the submission port), `tls` (implicit
+    /// TLS) or `none` for local catchers
+    pub fn new(
+        host: &str,
+        port: Option<u16>,
+        tls: &str,
+        credentials: Option<Credentials>,
+    ) -> Result<Self> {
+        let mut builder = match tls {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+            other => {
+                return Err(AppError::InternalServerError(format!(
+                    "Unknown SMTP_TLS mode: {} (expected starttls, tls or none)",
+                    other
+                )))
+            }
+        }
+        .map_err(|e| {
+            AppError::InternalServerError(format!("Invalid SMTP relay {}: {}", host, e))
+        })?;
+
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+        if let Some(credentials) = credentials {
+            builder = builder.credentials(credentials);
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+impl MailTransport for SmtpMailer {
+    fn send<'a>(
+        &'a self,
+        message: &'a Message,
+    ) -> BoxFuture<'a, std::result::Result<(), DeliveryError>> {
+        Box::pin(async move {
+            match self.transport.send(message.clone()).await {
+                Ok(_) => Ok(()),
+                Err(e) if e.is_permanent() => Err(DeliveryError::permanent(e.to_string())),
+                Err(e) => Err(DeliveryError::transient(e.to_string())),
+            }
+        })
+    }
+}
+
+/// Writes every message as an `.eml` file into a directory
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to create mail directory {}: {}", dir, e))
+        })?;
+
+        Ok(Self {
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+impl MailTransport for FileMailer {
+    fn send<'a>(
+        &'a self,
+        message: &'a Message,
+    ) -> BoxFuture<'a, std::result::Result<(), DeliveryError>> {
+        Box::pin(async move {
+            let name = format!(
+                "{}-{}.eml",
+                chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+                uuid::Uuid::new_v4()
+            );
+            tokio::fs::write(self.dir.join(name), message.formatted())
+                .await
+                .map_err(|e| DeliveryError::transient(e.to_string()))
+        })
+    }
+}
+
+/// Logs messages instead of sending them
+pub struct LogMailer;
+
+impl MailTransport for LogMailer {
+    fn send<'a>(
+        &'a self,
+        message: &'a Message,
+    ) -> BoxFuture<'a, std::result::Result<(), DeliveryError>> {
+        Box::pin(async move {
+            tracing::info!(
+                "Email (log transport):\n{}",
+                String::from_utf8_lossy(&message.formatted())
+            );
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Minimal SMTP server that accepts every message, or rejects every
+    /// recipient with `rcpt_reply`
+    async fn smtp_stub(rcpt_reply: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (read, mut write) = stream.into_split();
+                let mut lines = BufReader::new(read).lines();
+                write.write_all(b"220 stub ESMTP\r\n").await.unwrap();
+                let mut in_data = false;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let reply = if in_data {
+                        if line != "." {
+                            continue;
+                        }
+                        in_data = false;
+                        "250 queued"
+                    } else {
+                        match line.get(..4).unwrap_or("").to_ascii_uppercase().as_str() {
+                            "EHLO" | "HELO" => "250 stub",
+                            "RCPT" => rcpt_reply,
+                            "DATA" => {
+                                in_data = true;
+                                "354 go ahead"
+                            }
+                            "QUIT" => "221 bye",
+                            _ => "250 ok",
+                        }
+                    };
+                    write
+                        .write_all(format!("{}\r\n", reply).as_bytes())
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+        port
+    }
+
+    fn message() -> Message {
+        let from: Mailbox = "SSO <no-reply@example.com>".parse().unwrap();
+        compose(&from, "ada@example.com", "Hello", "Body").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_smtp_delivery_against_stub() {
+        let port = smtp_stub("250 ok").await;
+        let mailer = SmtpMailer::new("127.0.0.1", Some(port), "none", None).unwrap();
+        assert!(mailer.send(&message()).await.is_ok());
+
+        let port = smtp_stub("550 no such user").await;
+        let mailer = SmtpMailer::new("127.0.0.1", Some(port), "none", None).unwrap();
+        assert!(mailer.send(&message()).await.unwrap_err().permanent);
+
+        let port = smtp_stub("451 try again later").await;
+        let mailer = SmtpMailer::new("127.0.0.1", Some(port), "none", None).unwrap();
+        assert!(!mailer.send(&message()).await.unwrap_err().permanent);
+    }
+}
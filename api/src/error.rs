@@ -46,6 +46,9 @@ pub enum AppError {
     #[error("Device code pending")]
     DeviceCodePending,
 
+    #[error("Authorization denied")]
+    AuthorizationDenied,
+
     #[error("Service limit exceeded: {0}")]
     ServiceLimitExceeded(String),
 
@@ -57,6 +60,9 @@ pub enum AppError {
 
     #[error("Organization not active")]
     OrganizationNotActive,
+
+    #[error("Too many failed attempts: {message}")]
+    TooManyAttempts { message: String, retry_after: i64 },
 }
 
 impl IntoResponse for AppError {
@@ -89,12 +95,16 @@ impl IntoResponse for AppError {
             AppError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired"),
             AppError::DeviceCodeExpired => (StatusCode::BAD_REQUEST, "Device code expired"),
             AppError::DeviceCodePending => (StatusCode::BAD_REQUEST, "Authorization pending"),
+            AppError::AuthorizationDenied => (StatusCode::BAD_REQUEST, "Authorization denied"),
             AppError::ServiceLimitExceeded(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
             AppError::TeamLimitExceeded(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
             AppError::InvitationExpired => (StatusCode::BAD_REQUEST, "Invitation has expired"),
             AppError::OrganizationNotActive => {
                 (StatusCode::FORBIDDEN, "Organization is not active")
             }
+            AppError::TooManyAttempts { ref message, .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, message.as_str())
+            }
         };
 
         let body = Json(json!({
@@ -104,8 +114,10 @@ impl IntoResponse for AppError {
                 AppError::TeamLimitExceeded(_) => "TEAM_LIMIT_EXCEEDED",
                 AppError::InvitationExpired => "INVITATION_EXPIRED",
                 AppError::OrganizationNotActive => "ORGANIZATION_NOT_ACTIVE",
+                AppError::TooManyAttempts { .. } => "TOO_MANY_ATTEMPTS",
                 AppError::DeviceCodeExpired => "DEVICE_CODE_EXPIRED",
                 AppError::DeviceCodePending => "DEVICE_CODE_PENDING",
+                AppError::AuthorizationDenied => "ACCESS_DENIED",
                 AppError::NotFound(_) => "NOT_FOUND",
                 AppError::Unauthorized(_) => "UNAUTHORIZED",
                 AppError::Forbidden(_) => "FORBIDDEN",
@@ -120,7 +132,13 @@ impl IntoResponse for AppError {
             "timestamp": chrono::Utc::now().to_rfc3339(),
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let AppError::TooManyAttempts { retry_after, .. } = self {
+            response
+                .headers_mut()
+                .insert(axum::http::header::RETRY_AFTER, retry_after.into());
+        }
+        response
     }
 }
 
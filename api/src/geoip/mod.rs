@@ -0,0 +1,101 @@
+use crate::error::{AppError, Result};
+use maxminddb::{geoip2, Reader};
+use std::net::IpAddr;
+
+/// Where an IP address is, as far as the GeoIP database knows
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoLocation {
+    pub country: Option<String>, // ISO 3166-1 alpha-2
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl GeoLocation {
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        Some((self.latitude?, self.longitude?))
+    }
+}
+
+/// Source of IP locations. Login risk checks go through this trait so they
+/// can run against fixed locations.
+pub trait GeoIpLookup: Send + Sync {
+    /// None for private addresses and addresses the database does not cover
+    fn lookup(&self, ip: IpAddr) -> Option<GeoLocation>;
+}
+
+/// A MaxMind DB file (GeoLite2 or GeoIP2, City or Country edition), read
+/// into memory at startup. Country editions have no coordinates, so
+/// impossible-travel checks are skipped with them.
+pub struct MaxMindGeoIp {
+    reader: Reader<Vec<u8>>,
+}
+
+impl MaxMindGeoIp {
+    pub fn open(path: &str) -> Result<Self> {
+        let reader = Reader::open_readfile(path).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to open GeoIP database {}: {}", path, e))
+        })?;
+
+        Ok(Self { reader })
+    }
+}
+
+impl GeoIpLookup for MaxMindGeoIp {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let record: geoip2::City = self.reader.lookup(ip).ok()?;
+        let country = record
+            .country
+            .and_then(|country| country.iso_code)
+            .map(str::to_string);
+        let (latitude, longitude) = record
+            .location
+            .map(|location| (location.latitude, location.longitude))
+            .unwrap_or_default();
+
+        if country.is_none() && latitude.is_none() {
+            return None;
+        }
+        Some(GeoLocation {
+            country,
+            latitude,
+            longitude,
+        })
+    }
+}
+
+/// Used when `GEOIP_DATABASE_PATH` is unset: every lookup misses, so only
+/// device checks run
+pub struct NoGeoIp;
+
+impl GeoIpLookup for NoGeoIp {
+    fn lookup(&self, _ip: IpAddr) -> Option<GeoLocation> {
+        None
+    }
+}
+
+/// Great-circle distance in kilometres
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_km() {
+        let london = (51.5074, -0.1278);
+        let new_york = (40.7128, -74.0060);
+
+        let distance = distance_km(london, new_york);
+        assert!((distance - 5570.0).abs() < 10.0, "got {}", distance);
+        assert_eq!(distance_km(london, london), 0.0);
+    }
+}
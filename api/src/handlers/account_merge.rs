@@ -0,0 +1,159 @@
+use crate::auth::account_merge::{AccountMergeService, MergeSummary};
+use crate::constants::ACCOUNT_MERGE_MAX_AUTH_AGE_MINUTES;
+use crate::db::models::User;
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::middleware::AuthUser;
+use axum::{extract::State, Json};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Deserialize)]
+pub struct MergeAccountRequest {
+    /// Merge token minted by signing in to the account being merged away
+    pub merge_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeTokenResponse {
+    pub merge_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeAccountResponse {
+    pub user: User,
+    pub merged_user_id: String,
+    pub summary: MergeSummary,
+}
+
+/// Both sides of a merge must come from a recent interactive login. The
+/// session's authentication time counts, not the token's: refreshing issues
+/// new tokens without the user proving anything again.
+async fn ensure_recent_login(pool: &SqlitePool, session_id: &str, user_id: &str) -> Result<()> {
+    let authenticated_at: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT authenticated_at FROM sessions WHERE id = ? AND user_id = ?")
+            .bind(session_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+
+    let oldest = Utc::now() - Duration::minutes(ACCOUNT_MERGE_MAX_AUTH_AGE_MINUTES);
+    if authenticated_at.is_none_or(|at| at < oldest) {
+        return Err(AppError::Forbidden(format!(
+            "Sign in again to both accounts; logins older than {} minutes cannot be used to merge",
+            ACCOUNT_MERGE_MAX_AUTH_AGE_MINUTES
+        )));
+    }
+    Ok(())
+}
+
+/// POST /api/user/merge/token
+/// Mint a single-use token proving control of the caller's account, so it can
+/// be merged into another account the caller controls
+pub async fn create_merge_token(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<MergeTokenResponse>> {
+    ensure_recent_login(&state.pool, &auth_user.session_id, &auth_user.user.id).await?;
+
+    let (merge_token, expires_at) = AccountMergeService::issue_proof(
+        &state.pool,
+        &state.jwt_service,
+        &auth_user.user,
+        &auth_user.session_id,
+    )
+    .await?;
+
+    Ok(Json(MergeTokenResponse {
+        merge_token,
+        expires_at,
+    }))
+}
+
+/// POST /api/user/merge
+/// Merge another account the caller controls into the caller's account. The
+/// caller proves control of the other account with a merge token minted by it.
+pub async fn merge_account(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(req): Json<MergeAccountRequest>,
+) -> Result<Json<MergeAccountResponse>> {
+    ensure_recent_login(&state.pool, &auth_user.session_id, &auth_user.user.id).await?;
+
+    let proof = state.jwt_service.validate_merge_proof(&req.merge_token)?;
+
+    // The login that minted the token must still be live
+    let session_alive: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sessions WHERE id = ? AND user_id = ? AND expires_at > ?",
+    )
+    .bind(&proof.sid)
+    .bind(&proof.sub)
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+    if session_alive == 0 {
+        return Err(AppError::Unauthorized(
+            "merge_token session revoked or expired".to_string(),
+        ));
+    }
+
+    let source = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&proof.sub)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let summary =
+        AccountMergeService::merge(&state.pool, &auth_user.user, &source, &proof.jti).await?;
+
+    Ok(Json(MergeAccountResponse {
+        user: auth_user.user,
+        merged_user_id: source.id,
+        summary,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    #[tokio::test]
+    async fn test_refreshed_sessions_do_not_count_as_recent_logins() {
+        let pool = test_support::test_pool().await;
+        let user = test_support::insert_user(&pool, "ada@example.com").await;
+        let now = Utc::now();
+        // A session signed in to long ago whose tokens were just refreshed,
+        // one signed in to just now, and an impersonation session
+        for (id, authenticated_at) in [
+            ("refreshed", Some(now - Duration::days(3))),
+            ("fresh", Some(now)),
+            ("impersonation", None),
+        ] {
+            sqlx::query(
+                "INSERT INTO sessions (id, user_id, token_hash, expires_at, created_at, authenticated_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(&user.id)
+            .bind(format!("hash-{}", id))
+            .bind(now + Duration::hours(1))
+            .bind(now)
+            .bind(authenticated_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        ensure_recent_login(&pool, "fresh", &user.id).await.unwrap();
+        for session_id in ["refreshed", "impersonation", "api-token-id"] {
+            assert!(matches!(
+                ensure_recent_login(&pool, session_id, &user.id).await,
+                Err(AppError::Forbidden(_))
+            ));
+        }
+    }
+}
@@ -39,6 +39,46 @@ pub struct LoginsByProvider {
     pub count: i64,
 }
 
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FailedLoginsByReason {
+    pub service_id: Option<String>,
+    pub service_name: Option<String>,
+    pub reason: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RiskyLoginQuery {
+    pub min_score: Option<i64>,
+    pub outcome: Option<String>, // 'success', 'challenged' or 'blocked'
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RiskyLogin {
+    pub id: String,
+    pub user_id: String,
+    pub user_email: String,
+    pub service_id: String,
+    pub service_name: String,
+    pub provider: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub country: Option<String>,
+    pub risk_score: i64,
+    pub risk_reasons: Option<String>, // JSON array
+    pub risk_action: String,
+    pub outcome: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskyLoginResponse {
+    pub logins: Vec<RiskyLogin>,
+    pub total: i64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct RecentLogin {
     pub id: String,
@@ -55,8 +95,8 @@ pub async fn get_login_trends(
     Query(query): Query<AnalyticsQuery>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<LoginTrendPoint>>> {
-    // Verify user is a member of this organization
-    verify_org_membership(&state.pool, &auth_user.claims.sub, &org_slug).await?;
+    // Verify user may view this organization's analytics
+    verify_analytics_access(&state.pool, &auth_user.claims.sub, &org_slug).await?;
 
     // Parse date range or use defaults (last 30 days)
     let end_date = query
@@ -83,6 +123,7 @@ pub async fn get_login_trends(
         FROM login_events le
         JOIN services s ON le.service_id = s.id
         WHERE s.org_id = ?
+          AND le.outcome = 'success'
           AND DATE(le.created_at) >= DATE(?)
           AND DATE(le.created_at) <= DATE(?)
         GROUP BY DATE(le.created_at)
@@ -110,8 +151,8 @@ pub async fn get_logins_by_service(
     Query(query): Query<AnalyticsQuery>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<LoginsByService>>> {
-    // Verify user is a member of this organization
-    verify_org_membership(&state.pool, &auth_user.claims.sub, &org_slug).await?;
+    // Verify user may view this organization's analytics
+    verify_analytics_access(&state.pool, &auth_user.claims.sub, &org_slug).await?;
 
     // Parse date range or use defaults (last 30 days)
     let end_date = query
@@ -139,6 +180,7 @@ pub async fn get_logins_by_service(
         FROM login_events le
         JOIN services s ON le.service_id = s.id
         WHERE s.org_id = ?
+          AND le.outcome = 'success'
           AND DATE(le.created_at) >= DATE(?)
           AND DATE(le.created_at) <= DATE(?)
         GROUP BY s.id, s.name
@@ -170,8 +212,8 @@ pub async fn get_logins_by_provider(
     Query(query): Query<AnalyticsQuery>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<LoginsByProvider>>> {
-    // Verify user is a member of this organization
-    verify_org_membership(&state.pool, &auth_user.claims.sub, &org_slug).await?;
+    // Verify user may view this organization's analytics
+    verify_analytics_access(&state.pool, &auth_user.claims.sub, &org_slug).await?;
 
     // Parse date range or use defaults (last 30 days)
     let end_date = query
@@ -198,6 +240,7 @@ pub async fn get_logins_by_provider(
         FROM login_events le
         JOIN services s ON le.service_id = s.id
         WHERE s.org_id = ?
+          AND le.outcome = 'success'
           AND DATE(le.created_at) >= DATE(?)
           AND DATE(le.created_at) <= DATE(?)
         GROUP BY le.provider
@@ -225,8 +268,8 @@ pub async fn get_recent_logins(
     Query(query): Query<AnalyticsQuery>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<RecentLogin>>> {
-    // Verify user is a member of this organization
-    verify_org_membership(&state.pool, &auth_user.claims.sub, &org_slug).await?;
+    // Verify user may view this organization's analytics
+    verify_analytics_access(&state.pool, &auth_user.claims.sub, &org_slug).await?;
 
     let limit = query.limit.unwrap_or(10);
 
@@ -248,6 +291,7 @@ pub async fn get_recent_logins(
         FROM login_events le
         JOIN services s ON le.service_id = s.id
         WHERE s.org_id = ?
+          AND le.outcome = 'success'
         ORDER BY le.created_at DESC
         LIMIT ?
         "#,
@@ -273,28 +317,151 @@ pub async fn get_recent_logins(
     Ok(Json(result))
 }
 
-// Helper function to verify organization membership
-async fn verify_org_membership(pool: &SqlitePool, user_id: &str, org_slug: &str) -> Result<()> {
-    let membership_exists = sqlx::query_scalar::<_, bool>(
+/// GET /api/organizations/:org_slug/analytics/risky-logins
+/// Logins the risk engine flagged, including blocked and challenged attempts
+pub async fn get_risky_logins(
+    State(state): State<AnalyticsState>,
+    Path(org_slug): Path<String>,
+    Query(query): Query<RiskyLoginQuery>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<RiskyLoginResponse>> {
+    // Verify user may view this organization's analytics
+    verify_analytics_access(&state.pool, &auth_user.claims.sub, &org_slug).await?;
+
+    if let Some(ref outcome) = query.outcome {
+        if !matches!(outcome.as_str(), "success" | "challenged" | "blocked") {
+            return Err(AppError::BadRequest(format!(
+                "Invalid outcome: {} (expected success, challenged or blocked)",
+                outcome
+            )));
+        }
+    }
+    let min_score = query.min_score.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    // Get organization ID
+    let org_id = sqlx::query_scalar::<_, String>("SELECT id FROM organizations WHERE slug = ?")
+        .bind(&org_slug)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let total: i64 = sqlx::query_scalar(
         r#"
-        SELECT EXISTS(
-            SELECT 1
-            FROM memberships m
-            JOIN organizations o ON m.org_id = o.id
-            WHERE o.slug = ? AND m.user_id = ?
-        )
+        SELECT COUNT(*)
+        FROM login_events le
+        JOIN services s ON le.service_id = s.id
+        WHERE s.org_id = ? AND le.risk_score >= ? AND (? IS NULL OR le.outcome = ?)
         "#,
     )
-    .bind(org_slug)
-    .bind(user_id)
-    .fetch_one(pool)
+    .bind(&org_id)
+    .bind(min_score)
+    .bind(&query.outcome)
+    .bind(&query.outcome)
+    .fetch_one(&state.pool)
     .await?;
 
-    if !membership_exists {
-        return Err(AppError::Forbidden(
-            "You are not a member of this organization".to_string(),
-        ));
-    }
+    let logins = sqlx::query_as::<_, RiskyLogin>(
+        r#"
+        SELECT
+            le.id,
+            le.user_id,
+            u.email as user_email,
+            le.service_id,
+            s.name as service_name,
+            le.provider,
+            le.ip_address,
+            le.user_agent,
+            le.country,
+            le.risk_score,
+            le.risk_reasons,
+            le.risk_action,
+            le.outcome,
+            le.created_at
+        FROM login_events le
+        JOIN services s ON le.service_id = s.id
+        JOIN users u ON le.user_id = u.id
+        WHERE s.org_id = ? AND le.risk_score >= ? AND (? IS NULL OR le.outcome = ?)
+        ORDER BY le.created_at DESC
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(&org_id)
+    .bind(min_score)
+    .bind(&query.outcome)
+    .bind(&query.outcome)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(RiskyLoginResponse { logins, total }))
+}
+
+/// GET /api/organizations/:org_slug/analytics/failed-logins
+/// Failed sign-in attempts grouped by service and reason. Failures that
+/// happened before a service was known have no service.
+pub async fn get_failed_logins(
+    State(state): State<AnalyticsState>,
+    Path(org_slug): Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<FailedLoginsByReason>>> {
+    // Verify user may view this organization's analytics
+    verify_analytics_access(&state.pool, &auth_user.claims.sub, &org_slug).await?;
+
+    // Parse date range or use defaults (last 30 days)
+    let end_date = query
+        .end_date
+        .unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
+    let start_date = query.start_date.unwrap_or_else(|| {
+        (Utc::now() - chrono::Duration::days(30))
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+
+    // Get organization ID
+    let org_id = sqlx::query_scalar::<_, String>("SELECT id FROM organizations WHERE slug = ?")
+        .bind(&org_slug)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let failures = sqlx::query_as::<_, FailedLoginsByReason>(
+        r#"
+        SELECT
+            lf.service_id,
+            s.name as service_name,
+            lf.reason,
+            COUNT(*) as count
+        FROM login_failures lf
+        LEFT JOIN services s ON lf.service_id = s.id
+        WHERE lf.org_id = ?
+          AND DATE(lf.created_at) >= DATE(?)
+          AND DATE(lf.created_at) <= DATE(?)
+        GROUP BY lf.service_id, s.name, lf.reason
+        ORDER BY count DESC
+        "#,
+    )
+    .bind(&org_id)
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(failures))
+}
+
+// Helper function to verify the user's org role grants view_analytics
+async fn verify_analytics_access(pool: &SqlitePool, user_id: &str, org_slug: &str) -> Result<()> {
+    let org_id = sqlx::query_scalar::<_, String>("SELECT id FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::Forbidden("You are not a member of this organization".to_string())
+        })?;
+
+    crate::middleware::check_org_permission(pool, user_id, &org_id, "view_analytics").await?;
 
     Ok(())
 }
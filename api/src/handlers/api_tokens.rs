@@ -0,0 +1,342 @@
+use crate::auth::api_tokens::ApiTokenService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::constants::{
+    API_TOKEN_DEFAULT_LIFETIME_DAYS, MAX_API_TOKEN_LIFETIME_DAYS, MAX_NAME_LENGTH, MIN_NAME_LENGTH,
+};
+use crate::db::models::{ApiToken, Organization};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePersonalTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrgApiKeyRequest {
+    pub name: String,
+    pub role: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokenResponse {
+    pub id: String,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    pub created_by: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiToken> for ApiTokenResponse {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            scopes: ApiTokenService::parse_scopes(&token.scopes),
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            role: token.role,
+            created_by: token.created_by,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+/// Returned once at creation; the plaintext token cannot be retrieved again
+#[derive(Debug, Serialize)]
+pub struct CreatedApiTokenResponse {
+    #[serde(flatten)]
+    pub token: ApiTokenResponse,
+    pub secret: String,
+}
+
+async fn find_organization(pool: &SqlitePool, org_slug: &str) -> Result<Organization> {
+    sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
+
+fn validate_token_name(name: &str) -> Result<()> {
+    if name.len() < MIN_NAME_LENGTH || name.len() > MAX_NAME_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "Token name must be between {} and {} characters",
+            MIN_NAME_LENGTH, MAX_NAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+fn expiry_from_days(expires_in_days: Option<i64>) -> Result<DateTime<Utc>> {
+    let days = expires_in_days.unwrap_or(API_TOKEN_DEFAULT_LIFETIME_DAYS);
+    if !(1..=MAX_API_TOKEN_LIFETIME_DAYS).contains(&days) {
+        return Err(AppError::BadRequest(format!(
+            "expires_in_days must be between 1 and {}",
+            MAX_API_TOKEN_LIFETIME_DAYS
+        )));
+    }
+    Ok(Utc::now() + Duration::days(days))
+}
+
+/// Mark a live token revoked; it stops authenticating immediately
+async fn revoke(pool: &SqlitePool, token_id: &str) -> Result<()> {
+    sqlx::query("UPDATE api_tokens SET revoked_at = ? WHERE id = ?")
+        .bind(Utc::now())
+        .bind(token_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// GET /api/user/tokens
+/// List the caller's live personal access tokens
+pub async fn list_personal_tokens(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<ApiTokenResponse>>> {
+    let tokens = sqlx::query_as::<_, ApiToken>(
+        "SELECT * FROM api_tokens
+         WHERE kind = 'personal' AND user_id = ? AND revoked_at IS NULL AND expires_at > ?
+         ORDER BY created_at DESC",
+    )
+    .bind(&auth_user.user.id)
+    .bind(Utc::now())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(tokens.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/user/tokens
+/// Mint a personal access token that acts as the caller
+pub async fn create_personal_token(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(req): Json<CreatePersonalTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>)> {
+    let name = req.name.trim();
+    validate_token_name(name)?;
+    let scopes = ApiTokenService::normalize_scopes(req.scopes)?;
+    if scopes.iter().any(|s| s == "platform") && !auth_user.user.is_platform_owner {
+        return Err(AppError::Forbidden(
+            "Only platform owners can create tokens with the platform scope".to_string(),
+        ));
+    }
+    if let Some(scope) = scopes.iter().find(|s| *s == "scim" || *s == "ciba") {
+        return Err(AppError::BadRequest(format!(
+            "The {} scope is only available on organization API keys",
+            scope
+        )));
+    }
+    let expires_at = expiry_from_days(req.expires_in_days)?;
+
+    let (token, secret) = ApiTokenService::create(
+        &state.pool,
+        "personal",
+        name,
+        &auth_user.user.id,
+        None,
+        None,
+        &auth_user.user.id,
+        &scopes,
+        expires_at,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse {
+            token: token.into(),
+            secret,
+        }),
+    ))
+}
+
+/// DELETE /api/user/tokens/:token_id
+pub async fn revoke_personal_token(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(token_id): Path<String>,
+) -> Result<StatusCode> {
+    let exists: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM api_tokens
+         WHERE id = ? AND kind = 'personal' AND user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(&token_id)
+    .bind(&auth_user.user.id)
+    .fetch_one(&state.pool)
+    .await?;
+    if exists == 0 {
+        return Err(AppError::NotFound("Token not found".to_string()));
+    }
+
+    revoke(&state.pool, &token_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/organizations/:org_slug/api-keys
+pub async fn list_org_api_keys(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<Vec<ApiTokenResponse>>> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_api_keys",
+    )
+    .await?;
+
+    let keys = sqlx::query_as::<_, ApiToken>(
+        "SELECT * FROM api_tokens
+         WHERE kind = 'org' AND org_id = ? AND revoked_at IS NULL AND expires_at > ?
+         ORDER BY created_at DESC",
+    )
+    .bind(&org.id)
+    .bind(Utc::now())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(keys.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/organizations/:org_slug/api-keys
+/// Mint an API key that acts with an org role rather than as a person
+pub async fn create_org_api_key(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Json(req): Json<CreateOrgApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>)> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    let membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_api_keys",
+    )
+    .await?;
+
+    let name = req.name.trim();
+    validate_token_name(name)?;
+    let scopes = ApiTokenService::normalize_scopes(req.scopes)?;
+    if scopes.iter().any(|s| s == "platform") {
+        return Err(AppError::BadRequest(
+            "Organization API keys cannot have the platform scope".to_string(),
+        ));
+    }
+    let expires_at = expiry_from_days(req.expires_in_days)?;
+
+    if req.role == "owner" || !OrgRoleService::role_exists(&state.pool, &org.id, &req.role).await? {
+        return Err(AppError::BadRequest(
+            "Invalid role. Must be admin, member, or a custom role".to_string(),
+        ));
+    }
+    let granted = OrgRoleService::permissions_for_role(&state.pool, &org.id, &req.role).await?;
+    OrgRoleService::ensure_can_grant(&state.pool, &org.id, &membership.role, &granted).await?;
+
+    let service_account = ApiTokenService::create_service_account(&state.pool, &org.slug).await?;
+    let (key, secret) = ApiTokenService::create(
+        &state.pool,
+        "org",
+        name,
+        &service_account.id,
+        Some(&org.id),
+        Some(&req.role),
+        &auth_user.user.id,
+        &scopes,
+        expires_at,
+    )
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "api_key_created",
+        "api_key",
+        &key.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!(ApiTokenResponse::from(key.clone())),
+        ),
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse {
+            token: key.into(),
+            secret,
+        }),
+    ))
+}
+
+/// DELETE /api/organizations/:org_slug/api-keys/:key_id
+pub async fn revoke_org_api_key(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, key_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_api_keys",
+    )
+    .await?;
+
+    let exists: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM api_tokens
+         WHERE id = ? AND kind = 'org' AND org_id = ? AND revoked_at IS NULL",
+    )
+    .bind(&key_id)
+    .bind(&org.id)
+    .fetch_one(&state.pool)
+    .await?;
+    if exists == 0 {
+        return Err(AppError::NotFound("API key not found".to_string()));
+    }
+
+    revoke(&state.pool, &key_id).await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "api_key_revoked",
+        "api_key",
+        &key_id,
+        None,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,15 +1,43 @@
+use crate::auth::browser_binding::BrowserBindingService;
+use crate::auth::security_events::{
+    SecurityEventService, SECURITY_EVENT_BROWSER_BINDING_MISMATCH, SECURITY_EVENT_LOGIN_BLOCKED,
+    SECURITY_EVENT_REFRESH_TOKEN_REUSE,
+};
 use crate::auth::device_flow::DeviceFlowService;
+use crate::auth::domains::DomainService;
+use crate::auth::email::{EmailService, TEMPLATE_NEW_DEVICE};
+use crate::auth::id_token::IdTokenVerifier;
 use crate::auth::jwt::JwtService;
-use crate::auth::sso::{OAuthClient, Provider};
-use crate::constants::{DEVICE_CODE_EXPIRE_MINUTES, JWT_EXPIRE_HOURS, OAUTH_STATE_EXPIRE_MINUTES};
+use crate::auth::login_failures::{
+    LoginAttempt, LoginFailureService, FAILURE_BROWSER_BINDING_MISMATCH,
+    FAILURE_DEVICE_CODE_EXPIRED, FAILURE_EMAIL_NOT_VERIFIED, FAILURE_IDENTITY_CONFLICT,
+    FAILURE_INVALID_LOGIN_CODE, FAILURE_INVALID_REFRESH_TOKEN, FAILURE_INVALID_STATE,
+    FAILURE_INVALID_USER_CODE, FAILURE_PROVIDER_DENIED, FAILURE_PROVIDER_ERROR,
+    FAILURE_REDIRECT_URI_MISMATCH, FAILURE_REFRESH_TOKEN_EXPIRED, FAILURE_REFRESH_TOKEN_REUSE,
+    FAILURE_SIGN_IN_RESTRICTED, FLOW_CIBA, FLOW_DEVICE, FLOW_LOGIN_CHALLENGE, FLOW_OAUTH,
+    FLOW_REFRESH, FLOW_SSO_SESSION,
+};
+use crate::auth::login_risk::{LoginRiskAssessment, LoginRiskService, RiskAction};
+use crate::auth::logout::LogoutService;
+use crate::auth::profiles::ProfileService;
+use crate::auth::scim::ScimService;
+use crate::auth::service_roles::ServiceRoleService;
+use crate::auth::sso_session::SsoSessionService;
+use crate::auth::sso::{OAuthClient, Provider, ProviderClient};
+use crate::auth::webhooks::WebhookService;
+use crate::constants::{
+    DEVICE_CODE_EXPIRE_MINUTES, JWT_EXPIRE_HOURS, OAUTH_STATE_EXPIRE_MINUTES,
+    WEBHOOK_EVENT_IDENTITY_LINKED, WEBHOOK_EVENT_LOGIN_SUCCEEDED, WEBHOOK_EVENT_USER_CREATED,
+};
 use crate::db::models::{DeviceCode, Identity, User};
 use crate::error::{AppError, Result};
+use crate::middleware::{AuditContext, ClientInfo, RequestId};
 use axum::{
     extract::{Path, Query, State},
     response::{Html, IntoResponse, Redirect, Response},
-    Json,
+    Extension, Json,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use oauth2::{CsrfToken, PkceCodeChallenge, PkceCodeVerifier};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
@@ -88,6 +116,11 @@ pub struct AppState {
     pub base_url: String,
     pub db_tx: mpsc::Sender<DbRequest>, // Sender for the DB writer task
     pub encryption: Option<Arc<crate::encryption::EncryptionService>>,
+    pub stripe_service: Arc<crate::billing::stripe::StripeService>,
+    pub dns_resolver: Arc<dyn crate::dns::TxtResolver>,
+    pub geoip: Arc<dyn crate::geoip::GeoIpLookup>,
+    pub lockout_policy: crate::auth::login_failures::LockoutPolicy,
+    pub invitation_accept_uri: Option<String>,
 }
 // --- End DB Task Definitions ---
 
@@ -98,6 +131,7 @@ pub struct AuthRequest {
     pub service: String,
     pub redirect_uri: Option<String>,
     pub user_code: Option<String>,
+    pub prompt: Option<String>, // 'login' forces the upstream provider, 'none' is silent auth
 }
 
 // Admin Auth Request
@@ -110,8 +144,10 @@ pub struct AdminAuthRequest {
 // SSO Callback Query Parameters
 #[derive(Debug, Deserialize)]
 pub struct CallbackQuery {
+    #[serde(default)]
     pub code: String,
     pub state: Option<String>,
+    pub error: Option<String>, // set by the provider instead of a code, e.g. access_denied
 }
 
 // Device Code Request
@@ -150,7 +186,8 @@ pub struct DeviceVerifyResponse {
 #[derive(Debug, Deserialize)]
 pub struct TokenRequest {
     pub client_id: String,
-    pub device_code: String,
+    pub device_code: Option<String>,
+    pub auth_req_id: Option<String>,
     pub grant_type: String,
 }
 
@@ -167,9 +204,16 @@ pub async fn auth_provider(
     State(state): State<AppState>,
     Path(provider_str): Path<String>,
     Query(params): Query<AuthRequest>,
+    client: ClientInfo,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response> {
     let provider = Provider::from_str(&provider_str)?;
 
+    let prompt = params.prompt.as_deref();
+    if !matches!(prompt, None | Some("login") | Some("none")) {
+        return Err(AppError::BadRequest("prompt must be 'login' or 'none'".to_string()));
+    }
+
     // Get service to fetch configured scopes and validate redirect_uri
     let service = sqlx::query_as::<_, crate::db::models::Service>(
         "SELECT s.* FROM services s JOIN organizations o ON s.org_id = o.id
@@ -182,12 +226,55 @@ pub async fn auth_provider(
 
     let service = service.ok_or_else(|| AppError::NotFound("Service not found".to_string()))?;
 
-    // Validate redirect_uri against allowed URIs
-    if let Some(redirect_uri) = &params.redirect_uri {
-        validate_redirect_uri(redirect_uri, &service)?;
+    let mut attempt = LoginAttempt {
+        provider: Some(provider.as_str().to_string()),
+        org_id: Some(service.org_id.clone()),
+        service_id: Some(service.id.clone()),
+        ..LoginAttempt::new(FLOW_OAUTH)
+    };
+    let checked = async {
+        LoginFailureService::check(&state.pool, &state.lockout_policy, &attempt, &client).await?;
+        ensure_org_active(&state.pool, &service.org_id).await?;
+        // Validate redirect_uri against allowed URIs
+        if let Some(redirect_uri) = &params.redirect_uri {
+            validate_redirect_uri(redirect_uri, &service)
+                .map_err(attempt.fail(FAILURE_REDIRECT_URI_MISMATCH))?;
+        }
+        Ok::<_, AppError>(())
+    }
+    .await;
+    if let Err(e) = checked {
+        record_login_failure(&state, &attempt, &client, &e).await;
+        return Err(e);
+    }
+
+    // Reuse a central SSO session from a sibling service instead of the upstream provider
+    if prompt != Some("login") && params.user_code.is_none() {
+        if let (Some(redirect_uri), Some(token)) =
+            (&params.redirect_uri, SsoSessionService::read_cookie(&headers))
+        {
+            if let Some(sso) =
+                SsoSessionService::find_active(&state.pool, &token, &service.org_id).await?
+            {
+                return sso_login(&state, &sso, &params.org, &service, redirect_uri, &client).await;
+            }
+        }
+    }
+
+    // Silent auth cannot show a login page
+    if prompt == Some("none") {
+        let redirect_uri = params
+            .redirect_uri
+            .as_deref()
+            .ok_or_else(|| AppError::BadRequest("login_required".to_string()))?;
+        let mut error_url = url::Url::parse(redirect_uri)
+            .map_err(|_| AppError::BadRequest("Invalid redirect_uri".to_string()))?;
+        error_url.query_pairs_mut().append_pair("error", "login_required");
+        return Ok(Redirect::to(error_url.as_str()).into_response());
     }
 
     let scopes = get_provider_scopes(&service, provider);
+    let nonce = IdTokenVerifier::generate_nonce(provider, &scopes);
 
     // Check if organization has custom OAuth credentials for this provider
     let org_id = sqlx::query_scalar::<_, String>("SELECT id FROM organizations WHERE slug = ?")
@@ -222,14 +309,18 @@ pub async fn auth_provider(
 
         let custom_client =
             create_custom_oauth_client(&config, provider, &creds.client_id, &client_secret)?;
-        get_authorization_url_for_client(&custom_client, provider, scopes)
+        get_authorization_url_for_client(&custom_client, provider, scopes, nonce.as_deref())
     } else {
         // Fall back to platform's default OAuth credentials
         state
             .oauth_client
-            .get_authorization_url_with_pkce(provider, scopes)
+            .get_authorization_url_with_pkce(provider, scopes, nonce.as_deref())
     };
 
+    // Tie the state to this browser so a callback URL cannot be replayed in another one
+    let (browser_binding, binding_cookie) =
+        BrowserBindingService::bind(&state.jwt_service, &headers, &state.base_url)?;
+
     // Store OAuth state
     let expires_at = Utc::now() + chrono::Duration::minutes(OAUTH_STATE_EXPIRE_MINUTES);
     let pkce_value = if provider == Provider::Microsoft && !pkce_verifier.is_empty() {
@@ -239,8 +330,8 @@ pub async fn auth_provider(
     };
 
     sqlx::query(
-        "INSERT INTO oauth_states (state, pkce_verifier, service_id, redirect_uri, org_slug, service_slug, is_admin_flow, user_id_for_linking, device_user_code, created_at, expires_at)
-         VALUES (?, ?, ?, ?, ?, ?, 0, NULL, ?, datetime('now'), ?)",
+        "INSERT INTO oauth_states (state, pkce_verifier, service_id, redirect_uri, org_slug, service_slug, is_admin_flow, user_id_for_linking, device_user_code, created_at, expires_at, browser_binding, nonce)
+         VALUES (?, ?, ?, ?, ?, ?, 0, NULL, ?, datetime('now'), ?, ?, ?)",
     )
     .bind(csrf_token.secret())
     .bind(pkce_value)
@@ -250,10 +341,12 @@ pub async fn auth_provider(
     .bind(&params.service)
     .bind(&params.user_code)
     .bind(expires_at)
+    .bind(&browser_binding)
+    .bind(&nonce)
     .execute(&state.pool)
     .await?;
 
-    Ok(Redirect::to(&auth_url).into_response())
+    Ok(([(axum::http::header::SET_COOKIE, binding_cookie)], Redirect::to(&auth_url)).into_response())
 }
 
 pub fn get_provider_scopes(service: &crate::db::models::Service, provider: Provider) -> Vec<String> {
@@ -282,19 +375,38 @@ pub async fn auth_callback(
     State(state): State<AppState>,
     Path(provider_str): Path<String>,
     Query(callback): Query<CallbackQuery>,
+    client: ClientInfo,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response> {
     // Wrap the main logic to catch errors and handle them appropriately
-    match auth_callback_impl(state, provider_str, callback).await {
-        Ok(response) => Ok(response),
+    let mut attempt = LoginAttempt::new(FLOW_OAUTH);
+    match auth_callback_impl(
+        state.clone(),
+        provider_str,
+        callback,
+        client.clone(),
+        headers,
+        &mut attempt,
+    )
+    .await
+    {
+        Ok(response) => {
+            if attempt.user_id.is_some() {
+                LoginFailureService::clear(&state.pool, &attempt).await?;
+            }
+            Ok(response)
+        }
         Err(e) => {
             // Log the error
             tracing::error!("OAuth callback error: {}", e);
+            record_login_failure(&state, &attempt, &client, &e).await;
 
             // Return a simple HTML error page
             let error_message = match &e {
                 AppError::OAuth(msg) => msg.clone(),
                 AppError::BadRequest(msg) => msg.clone(),
                 AppError::Unauthorized(msg) => msg.clone(),
+                AppError::TooManyAttempts { message, .. } => message.clone(),
                 _ => "Authentication failed".to_string(),
             };
 
@@ -331,8 +443,14 @@ async fn auth_callback_impl(
     state: AppState,
     provider_str: String,
     callback: CallbackQuery,
+    client: ClientInfo,
+    headers: axum::http::HeaderMap,
+    attempt: &mut LoginAttempt,
 ) -> Result<Response> {
     let provider = Provider::from_str(&provider_str)?;
+    attempt.provider = Some(provider.as_str().to_string());
+    LoginFailureService::check(&state.pool, &state.lockout_policy, attempt, &client).await?;
+    let sso_cookie = SsoSessionService::read_cookie(&headers);
 
     // Get OAuth state (includes PKCE verifier, redirect_uri, org/service context)
     let oauth_state = if let Some(ref state_param) = callback.state {
@@ -355,184 +473,280 @@ async fn auth_callback_impl(
             .await;
     }
 
+    // A callback without a live state we issued cannot be tied to any login attempt
+    let oauth_ctx = oauth_state
+        .filter(|s| s.expires_at > Utc::now())
+        .ok_or_else(|| AppError::BadRequest("Invalid state parameter".to_string()))
+        .map_err(attempt.fail(FAILURE_INVALID_STATE))?;
+    attempt.user_id = oauth_ctx.user_id_for_linking.clone();
+    attempt.service_id = oauth_ctx.service_id.clone();
+
+    // The user cancelled at the provider, or the provider refused
+    if let Some(ref error) = callback.error {
+        attempt.reason = Some(FAILURE_PROVIDER_DENIED);
+        return Err(AppError::BadRequest(format!("Sign-in was not completed: {}", error)));
+    }
+
+    // Login CSRF: the callback must land in the browser that started the flow
+    // Linking states are always bound, so a missing binding there means the state was forged
+    let binding_check = match oauth_ctx.browser_binding {
+        Some(ref binding) => BrowserBindingService::verify(&state.jwt_service, &headers, binding),
+        None if oauth_ctx.user_id_for_linking.is_some() => Err(AppError::BadRequest(
+            "This sign-in was started in a different browser. Please try again.".to_string(),
+        )),
+        None => Ok(()),
+    };
+    if let Err(e) = binding_check {
+        attempt.reason = Some(FAILURE_BROWSER_BINDING_MISMATCH);
+        SecurityEventService::record(
+            &state.pool,
+            SECURITY_EVENT_BROWSER_BINDING_MISMATCH,
+            "medium",
+            oauth_ctx.user_id_for_linking.as_deref(),
+            None,
+            &client,
+            serde_json::json!({
+                "provider": provider_str,
+                "org_slug": oauth_ctx.org_slug,
+                "service_slug": oauth_ctx.service_slug,
+            }),
+        )
+        .await?;
+        return Err(e);
+    }
+
     // Exchange code with PKCE verifier to get full token details
     // Check if we should use organization's BYOO credentials
-    let pkce_verifier = oauth_state
-        .as_ref()
-        .and_then(|s| s.pkce_verifier.as_deref());
+    let pkce_verifier = oauth_ctx.pkce_verifier.as_deref();
+    let nonce = oauth_ctx.nonce.as_deref();
 
     // Determine issuing context (org_id and service_id) for proper identity isolation
-    let (token_details, issuing_org_id, issuing_service_id) = if let Some(ref oauth_ctx) = oauth_state {
-        // Check if this is a service flow (has service_id)
-        if let Some(ref service_id) = oauth_ctx.service_id {
-            // Service flow - get org_id from service and use service credentials
-            let service = sqlx::query_as::<_, crate::db::models::Service>(
-                "SELECT * FROM services WHERE id = ?"
-            )
-            .bind(service_id)
-            .fetch_one(&state.pool)
-            .await?;
-
-            let org_id = service.org_id.clone();
-
-            // Check for BYOO credentials for this organization
-            let provider_str = provider.as_str();
-            let org_credentials = sqlx::query!(
-                "SELECT client_id, client_secret_encrypted, encryption_key_id
-                 FROM organization_oauth_credentials
-                 WHERE org_id = ? AND provider = ?",
-                org_id,
-                provider_str
-            )
-            .fetch_optional(&state.pool)
-            .await?;
+    // Check if this is a service flow (has service_id)
+    let (token_details, issuing_org_id, issuing_service_id) = if let Some(ref service_id) = oauth_ctx.service_id {
+        // Service flow - get org_id from service and use service credentials
+        let service = sqlx::query_as::<_, crate::db::models::Service>(
+            "SELECT * FROM services WHERE id = ?"
+        )
+        .bind(service_id)
+        .fetch_one(&state.pool)
+        .await?;
 
-            let details = if let Some(creds) = org_credentials {
-                // Use organization's custom OAuth credentials for token exchange
-                let encryption = crate::encryption::EncryptionService::new()
-                    .map_err(|e| AppError::InternalServerError(format!("Encryption unavailable: {}", e)))?;
+        let org_id = service.org_id.clone();
+        attempt.org_id = Some(org_id.clone());
+        ensure_org_active(&state.pool, &org_id).await?;
 
-                let client_secret = encryption
-                    .decrypt(&creds.client_secret_encrypted)
-                    .map_err(|e| {
-                        AppError::InternalServerError(format!("Failed to decrypt secret: {}", e))
-                    })?;
+        // Check for BYOO credentials for this organization
+        let org_credentials = sqlx::query_as::<_, (String, Vec<u8>)>(
+            "SELECT client_id, client_secret_encrypted
+             FROM organization_oauth_credentials
+             WHERE org_id = ? AND provider = ?",
+        )
+        .bind(&org_id)
+        .bind(provider.as_str())
+        .fetch_optional(&state.pool)
+        .await?;
 
-                let config = crate::config::Config::from_env()
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let details = if let Some((client_id, secret)) = org_credentials {
+            // Use organization's custom OAuth credentials for token exchange
+            let encryption = crate::encryption::EncryptionService::new()
+                .map_err(|e| AppError::InternalServerError(format!("Encryption unavailable: {}", e)))?;
 
-                let custom_client =
-                    create_custom_oauth_client(&config, provider, &creds.client_id, &client_secret)?;
+            let client_secret = encryption
+                .decrypt(&secret)
+                .map_err(|e| {
+                    AppError::InternalServerError(format!("Failed to decrypt secret: {}", e))
+                })?;
 
-                exchange_custom_code(&custom_client, provider, &callback.code, pkce_verifier).await?
-            } else {
-                // Fall back to platform credentials for this service
-                state
-                    .oauth_client
-                    .exchange_code_with_details(provider, &callback.code, pkce_verifier)
-                    .await?
-            };
+            let config = crate::config::Config::from_env()
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
-            (details, Some(org_id), Some(service_id.clone()))
-        } else if let Some(ref org_slug) = oauth_ctx.org_slug {
-            // Legacy org-based flow (no service_id) - use org credentials but no service isolation
-            let org_id = sqlx::query_scalar::<_, String>("SELECT id FROM organizations WHERE slug = ?")
-                .bind(org_slug)
-                .fetch_one(&state.pool)
-                .await?;
+            let custom_client =
+                create_custom_oauth_client(&config, provider, &client_id, &client_secret)?;
 
-            let provider_str = provider.as_str();
-            let org_credentials = sqlx::query!(
-                "SELECT client_id, client_secret_encrypted, encryption_key_id
-                 FROM organization_oauth_credentials
-                 WHERE org_id = ? AND provider = ?",
-                org_id,
-                provider_str
-            )
-            .fetch_optional(&state.pool)
+            exchange_custom_code(&custom_client, provider, &callback.code, pkce_verifier, nonce)
+                .await
+                .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?
+        } else {
+            // Fall back to platform credentials for this service
+            state
+                .oauth_client
+                .exchange_code_with_details(provider, &callback.code, pkce_verifier, nonce)
+                .await
+                .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?
+        };
+
+        (details, Some(org_id), Some(service_id.clone()))
+    } else if let Some(ref org_slug) = oauth_ctx.org_slug {
+        // Legacy org-based flow (no service_id) - use org credentials but no service isolation
+        let org_id = sqlx::query_scalar::<_, String>("SELECT id FROM organizations WHERE slug = ?")
+            .bind(org_slug)
+            .fetch_one(&state.pool)
             .await?;
+        attempt.org_id = Some(org_id.clone());
+        ensure_org_active(&state.pool, &org_id).await?;
 
-            let details = if let Some(creds) = org_credentials {
-                // Use organization's custom OAuth credentials for token exchange
-                let encryption = crate::encryption::EncryptionService::new()
-                    .map_err(|e| AppError::InternalServerError(format!("Encryption unavailable: {}", e)))?;
+        let org_credentials = sqlx::query_as::<_, (String, Vec<u8>)>(
+            "SELECT client_id, client_secret_encrypted
+             FROM organization_oauth_credentials
+             WHERE org_id = ? AND provider = ?",
+        )
+        .bind(&org_id)
+        .bind(provider.as_str())
+        .fetch_optional(&state.pool)
+        .await?;
 
-                let client_secret = encryption
-                    .decrypt(&creds.client_secret_encrypted)
-                    .map_err(|e| {
-                        AppError::InternalServerError(format!("Failed to decrypt secret: {}", e))
-                    })?;
+        let details = if let Some((client_id, secret)) = org_credentials {
+            // Use organization's custom OAuth credentials for token exchange
+            let encryption = crate::encryption::EncryptionService::new()
+                .map_err(|e| AppError::InternalServerError(format!("Encryption unavailable: {}", e)))?;
 
-                let config = crate::config::Config::from_env()
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            let client_secret = encryption
+                .decrypt(&secret)
+                .map_err(|e| {
+                    AppError::InternalServerError(format!("Failed to decrypt secret: {}", e))
+                })?;
 
-                let custom_client =
-                    create_custom_oauth_client(&config, provider, &creds.client_id, &client_secret)?;
+            let config = crate::config::Config::from_env()
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
-                exchange_custom_code(&custom_client, provider, &callback.code, pkce_verifier).await?
-            } else {
-                // Fall back to platform credentials
-                state
-                    .oauth_client
-                    .exchange_code_with_details(provider, &callback.code, pkce_verifier)
-                    .await?
-            };
+            let custom_client =
+                create_custom_oauth_client(&config, provider, &client_id, &client_secret)?;
 
-            (details, Some(org_id), None)
+            exchange_custom_code(&custom_client, provider, &callback.code, pkce_verifier, nonce)
+                .await
+                .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?
         } else {
-            // No service or org context - platform credentials
-            let details = state
+            // Fall back to platform credentials
+            state
                 .oauth_client
-                .exchange_code_with_details(provider, &callback.code, pkce_verifier)
-                .await?;
-            (details, None, None)
-        }
+                .exchange_code_with_details(provider, &callback.code, pkce_verifier, nonce)
+                .await
+                .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?
+        };
+
+        (details, Some(org_id), None)
     } else {
-        // No oauth state - platform credentials
+        // No service or org context - platform credentials
         let details = state
             .oauth_client
-            .exchange_code_with_details(provider, &callback.code, pkce_verifier)
-            .await?;
+            .exchange_code_with_details(provider, &callback.code, pkce_verifier, nonce)
+            .await
+            .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?;
         (details, None, None)
     };
 
     // Get user info from provider (standalone, not using OAuth client)
-    let user_info = get_provider_user_info(provider, &token_details.access_token).await?;
+    let mut user_info = get_provider_user_info(provider, &token_details.access_token)
+        .await
+        .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?;
+    if let Some(ref id_token) = token_details.id_token {
+        IdTokenVerifier::check_subject(provider, id_token, &user_info)
+            .map_err(attempt.fail(FAILURE_PROVIDER_ERROR))?;
+        user_info.email_verified |= IdTokenVerifier::email_verified(id_token, &user_info);
+    }
+    attempt.provider_user_id = Some(user_info.provider_user_id.clone());
+    LoginFailureService::check(&state.pool, &state.lockout_policy, attempt, &client).await?;
+
+    if let Some(ref service_id) = issuing_service_id {
+        let require_verified_email: bool =
+            sqlx::query_scalar("SELECT require_verified_email FROM services WHERE id = ?")
+                .bind(service_id)
+                .fetch_one(&state.pool)
+                .await?;
+        if require_verified_email && !user_info.email_verified {
+            attempt.reason = Some(FAILURE_EMAIL_NOT_VERIFIED);
+            return Err(AppError::Forbidden(
+                "This service requires an email address verified by the provider".to_string(),
+            ));
+        }
+    }
 
     // Check if this is a linking flow (user_id_for_linking is set)
-    if let Some(ref oauth_ctx) = oauth_state {
-        if let Some(ref linking_user_id) = oauth_ctx.user_id_for_linking {
-            // This is a linking flow - link the new provider to the existing user
+    if let Some(ref linking_user_id) = oauth_ctx.user_id_for_linking {
+        // This is a linking flow - link the new provider to the existing user
 
-            // Security check: Ensure the provider account is not already linked to a different user
-            let existing_identity = sqlx::query_as::<_, crate::db::models::Identity>(
-                "SELECT * FROM identities WHERE provider = ? AND provider_user_id = ?",
-            )
-            .bind(provider.as_str())
-            .bind(&user_info.provider_user_id)
-            .fetch_optional(&state.pool)
-            .await?;
+        // Security check: Ensure the provider account is not already linked to a different user
+        let existing_identity = sqlx::query_as::<_, crate::db::models::Identity>(
+            "SELECT * FROM identities WHERE provider = ? AND provider_user_id = ?",
+        )
+        .bind(provider.as_str())
+        .bind(&user_info.provider_user_id)
+        .fetch_optional(&state.pool)
+        .await?;
 
-            if let Some(existing) = existing_identity {
-                if existing.user_id != *linking_user_id {
-                    return Err(AppError::BadRequest(
-                        "This social account is already linked to a different user".to_string(),
-                    ));
-                }
-                // Already linked to the same user, just update tokens
+        if let Some(existing) = existing_identity {
+            if existing.user_id != *linking_user_id {
+                attempt.reason = Some(FAILURE_IDENTITY_CONFLICT);
+                return Err(AppError::BadRequest(
+                    "This social account is already linked to a different user".to_string(),
+                ));
             }
+            // Already linked to the same user, just update tokens
+        }
 
-            // Create or update identity for the linking user
-            upsert_identity_with_details(
-                &state.pool,
-                state.encryption.as_ref(),
-                linking_user_id,
-                provider,
-                &user_info.provider_user_id,
-                &token_details.access_token,
-                token_details.refresh_token.as_deref(),
-                token_details.expires_at,
-                &token_details.scopes,
-                issuing_org_id.as_deref(),
-                issuing_service_id.as_deref(),
-            )
-            .await?;
+        let known_providers =
+            org_identity_providers(&state.pool, linking_user_id, issuing_org_id.as_deref()).await?;
+
+        // Create or update identity for the linking user
+        let identity = upsert_identity_with_details(
+            &state.pool,
+            state.encryption.as_ref(),
+            linking_user_id,
+            provider,
+            &user_info.provider_user_id,
+            &token_details.access_token,
+            token_details.refresh_token.as_deref(),
+            token_details.expires_at,
+            &token_details.scopes,
+            issuing_org_id.as_deref(),
+            issuing_service_id.as_deref(),
+        )
+        .await?;
+        ProfileService::sync_from_identity(&state.pool, &identity, &user_info).await?;
 
-            // Redirect to frontend callback URL
-            // redirect_uri already contains query params: ?status=success&provider=X&action=link
-            let redirect_url = oauth_ctx.redirect_uri.as_ref()
-                .ok_or_else(|| AppError::InternalServerError(
-                    "No redirect_uri in oauth state for linking flow".to_string()
-                ))?;
-            return Ok(Redirect::to(redirect_url).into_response());
+        if !known_providers.iter().any(|p| p == provider.as_str()) {
+            for org_id in
+                identity_event_orgs(&state.pool, linking_user_id, issuing_org_id.as_deref()).await?
+            {
+                emit_after_login(
+                    &state.pool,
+                    &org_id,
+                    WEBHOOK_EVENT_IDENTITY_LINKED,
+                    serde_json::json!({
+                        "user_id": linking_user_id,
+                        "provider": provider.as_str(),
+                        "service_id": issuing_service_id,
+                    }),
+                )
+                .await;
+            }
         }
+
+        // Redirect to frontend callback URL
+        // redirect_uri already contains query params: ?status=success&provider=X&action=link
+        let redirect_url = oauth_ctx.redirect_uri.as_ref()
+            .ok_or_else(|| AppError::InternalServerError(
+                "No redirect_uri in oauth state for linking flow".to_string()
+            ))?;
+        return Ok(Redirect::to(redirect_url).into_response());
     }
 
     // Normal login flow - find or create user
-    let user = find_or_create_user(&state.pool, &user_info.email).await?;
+    let user = find_or_create_user(&state.pool, provider, &user_info).await?;
+    check_authenticated_user(
+        &state,
+        attempt,
+        &user,
+        Some(provider),
+        issuing_org_id.as_deref(),
+        &client,
+    )
+    .await?;
+    let known_providers =
+        org_identity_providers(&state.pool, &user.id, issuing_org_id.as_deref()).await?;
 
     // Update identity with full token details
-    upsert_identity_with_details(
+    let identity = upsert_identity_with_details(
         &state.pool,
         state.encryption.as_ref(),
         &user.id,
@@ -546,178 +760,216 @@ async fn auth_callback_impl(
         issuing_service_id.as_deref(),
     )
     .await?;
+    let user = ProfileService::sync_from_identity(&state.pool, &identity, &user_info).await?;
+
+    // Tell the organization about end users it sees for the first time and
+    // about accounts they add. Organizations that know the user from a
+    // platform-level sign-in only hear about the new account.
+    let event = if known_providers.iter().any(|p| p == provider.as_str()) {
+        None
+    } else if known_providers.is_empty() && issuing_org_id.is_some() {
+        Some(WEBHOOK_EVENT_USER_CREATED)
+    } else {
+        Some(WEBHOOK_EVENT_IDENTITY_LINKED)
+    };
+    if let Some(event) = event {
+        for org_id in identity_event_orgs(&state.pool, &user.id, issuing_org_id.as_deref()).await? {
+            emit_after_login(
+                &state.pool,
+                &org_id,
+                event,
+                serde_json::json!({
+                    "user_id": user.id,
+                    "email": user.email,
+                    "provider": provider.as_str(),
+                    "service_id": issuing_service_id,
+                }),
+            )
+            .await;
+        }
+    }
 
     // Handle device flow completion
-    if let Some(ref oauth_ctx) = oauth_state {
-        if oauth_ctx.redirect_uri.is_none()
-            && (oauth_ctx.org_slug.is_some() || oauth_ctx.service_slug.is_some())
+    if oauth_ctx.redirect_uri.is_none()
+        && (oauth_ctx.org_slug.is_some() || oauth_ctx.service_slug.is_some())
+    {
+        // This is a device flow callback - find and update the device code
+        if let (Some(org_slug), Some(service_slug)) =
+            (&oauth_ctx.org_slug, &oauth_ctx.service_slug)
         {
-            // This is a device flow callback - find and update the device code
-            if let (Some(org_slug), Some(service_slug)) =
-                (&oauth_ctx.org_slug, &oauth_ctx.service_slug)
-            {
-                // Find device code using the user_code if provided, otherwise fall back to most recent
-                let device_code = if let Some(ref user_code) = oauth_ctx.device_user_code {
-                    sqlx::query_as::<_, DeviceCode>(
-                        "SELECT * FROM device_codes WHERE user_code = ? AND status = 'pending'"
-                    )
-                    .bind(user_code)
-                    .fetch_optional(&state.pool)
-                    .await?
-                } else {
-                    sqlx::query_as::<_, DeviceCode>(
-                        "SELECT * FROM device_codes
-                         WHERE org_slug = ? AND service_slug = ? AND status = 'pending'
-                         ORDER BY created_at DESC LIMIT 1",
-                    )
-                    .bind(org_slug)
-                    .bind(service_slug)
-                    .fetch_optional(&state.pool)
-                    .await?
-                };
-
-                if let Some(dc) = device_code {
-                    // Authorize the device code
-                    sqlx::query!(
-                        "UPDATE device_codes SET user_id = ?, status = 'authorized' WHERE id = ?",
-                        user.id,
-                        dc.id
-                    )
-                    .execute(&state.pool)
-                    .await?;
-                }
-
-                // This is a device flow completion - redirect to service's success page
-                // Get service to find device activation URI
-                let service = sqlx::query_as::<_, crate::db::models::Service>(
-                    "SELECT s.* FROM services s JOIN organizations o ON s.org_id = o.id
-                     WHERE o.slug = ? AND s.slug = ?",
+            // Find device code using the user_code if provided, otherwise fall back to most recent
+            let device_code = if let Some(ref user_code) = oauth_ctx.device_user_code {
+                sqlx::query_as::<_, DeviceCode>(
+                    "SELECT * FROM device_codes WHERE user_code = ? AND status = 'pending' AND flow_type = 'device'"
+                )
+                .bind(user_code)
+                .fetch_optional(&state.pool)
+                .await?
+            } else {
+                sqlx::query_as::<_, DeviceCode>(
+                    "SELECT * FROM device_codes
+                     WHERE org_slug = ? AND service_slug = ? AND status = 'pending' AND flow_type = 'device'
+                     ORDER BY created_at DESC LIMIT 1",
                 )
                 .bind(org_slug)
                 .bind(service_slug)
                 .fetch_optional(&state.pool)
+                .await?
+            };
+
+            if let Some(dc) = device_code {
+                // Authorize the device code
+                sqlx::query(
+                    "UPDATE device_codes
+                     SET user_id = ?, status = 'authorized', provider = ?, authenticated_at = ?
+                     WHERE id = ?",
+                )
+                .bind(&user.id)
+                .bind(provider.as_str())
+                .bind(Utc::now())
+                .bind(&dc.id)
+                .execute(&state.pool)
                 .await?;
+            }
 
-                // Use the service's configured device activation URI
-                let base_activation_uri = service
-                    .and_then(|s| s.device_activation_uri)
-                    .ok_or_else(|| AppError::InternalServerError("Device activation URI not configured for this service".to_string()))?;
+            // This is a device flow completion - redirect to service's success page
+            // Get service to find device activation URI
+            let service = sqlx::query_as::<_, crate::db::models::Service>(
+                "SELECT s.* FROM services s JOIN organizations o ON s.org_id = o.id
+                 WHERE o.slug = ? AND s.slug = ?",
+            )
+            .bind(org_slug)
+            .bind(service_slug)
+            .fetch_optional(&state.pool)
+            .await?;
 
-                // Create success redirect URL with token
-                let mut success_url = url::Url::parse(&base_activation_uri)
-                    .map_err(|_| AppError::InternalServerError("Invalid device activation URI configured".to_string()))?;
+            // Use the service's configured device activation URI
+            let base_activation_uri = service
+                .and_then(|s| s.device_activation_uri)
+                .ok_or_else(|| AppError::InternalServerError("Device activation URI not configured for this service".to_string()))?;
 
-                // Set path to success page and include status and token
-                success_url.set_path("/activate/success");
-                success_url.query_pairs_mut()
-                    .append_pair("status", "success")
-                    .append_pair("device_flow", "true");
+            // Create success redirect URL with token
+            let mut success_url = url::Url::parse(&base_activation_uri)
+                .map_err(|_| AppError::InternalServerError("Invalid device activation URI configured".to_string()))?;
 
-                return Ok(Redirect::to(success_url.as_str()).into_response());
-            }
+            // Set path to success page and include status and token
+            success_url.set_path("/activate/success");
+            success_url.query_pairs_mut()
+                .append_pair("status", "success")
+                .append_pair("device_flow", "true");
+
+            return Ok(Redirect::to(success_url.as_str()).into_response());
         }
     }
 
     // If redirect_uri provided, issue JWT and redirect
-    if let Some(ref oauth_ctx) = oauth_state {
-        if let Some(ref redirect_uri) = oauth_ctx.redirect_uri {
-            // Get service and subscription info for JWT
-            let (service_slug, plan_name, features) =
-                if let (Some(org), Some(svc)) = (&oauth_ctx.org_slug, &oauth_ctx.service_slug) {
-                    // Get service
-                    let service = sqlx::query_as::<_, crate::db::models::Service>(
-                        "SELECT s.* FROM services s JOIN organizations o ON s.org_id = o.id
-                     WHERE o.slug = ? AND s.slug = ?",
-                    )
-                    .bind(org)
-                    .bind(svc)
-                    .fetch_optional(&state.pool)
-                    .await?;
-
-                    if let Some(service) = service {
-                        // Validate redirect_uri again before redirecting
-                        validate_redirect_uri(redirect_uri, &service)?;
-                        // Get subscription if exists
-                        let subscription = sqlx::query!(
-                            r#"
-                        SELECT p.name as plan_name, p.features
-                        FROM subscriptions sub
-                        JOIN plans p ON sub.plan_id = p.id
-                        WHERE sub.user_id = ? AND sub.service_id = ? AND sub.status = 'active'
-                        "#,
-                            user.id,
-                            service.id
+    if let Some(ref redirect_uri) = oauth_ctx.redirect_uri {
+        // Risky logins may be blocked or need a code before any token is issued
+        let risk = match oauth_ctx.service_id {
+            Some(ref service_id) => {
+                match screen_login(&state, &user.id, service_id, provider, true, &client).await? {
+                    LoginDecision::Proceed(risk) => Some(risk),
+                    LoginDecision::Blocked => {
+                        return login_error_redirect(redirect_uri, "access_denied", None)
+                    }
+                    LoginDecision::Challenged(challenge_id) => {
+                        return login_error_redirect(
+                            redirect_uri,
+                            "email_code_required",
+                            Some(&challenge_id),
                         )
-                        .fetch_optional(&state.pool)
-                        .await?;
-
-                        let plan = subscription
-                            .as_ref()
-                            .map(|s| s.plan_name.clone())
-                            .unwrap_or_else(|| "free".to_string());
-                        let feats = subscription
-                            .as_ref()
-                            .and_then(|s| s.features.as_ref())
-                            .and_then(|f| serde_json::from_str::<Vec<String>>(f).ok());
-
-                        (Some(svc.clone()), Some(plan), feats)
-                    } else {
-                        (None, None, None)
                     }
+                }
+            }
+            None => None,
+        };
+
+        // Get service, subscription and role info for JWT
+        let (service_slug, plan_name, features, roles, permissions) =
+            if let (Some(org), Some(svc)) = (&oauth_ctx.org_slug, &oauth_ctx.service_slug) {
+                // Get service
+                let service = sqlx::query_as::<_, crate::db::models::Service>(
+                    "SELECT s.* FROM services s JOIN organizations o ON s.org_id = o.id
+                 WHERE o.slug = ? AND s.slug = ?",
+                )
+                .bind(org)
+                .bind(svc)
+                .fetch_optional(&state.pool)
+                .await?;
+
+                if let Some(service) = service {
+                    // Validate redirect_uri again before redirecting
+                    validate_redirect_uri(redirect_uri, &service)
+                        .map_err(attempt.fail(FAILURE_REDIRECT_URI_MISMATCH))?;
+                    // Get subscription if exists
+                    let (plan, feats) =
+                        get_service_plan(&state.pool, &user.id, &service.id).await?;
+                    let (roles, permissions) =
+                        ServiceRoleService::effective_roles(&state.pool, &user.id, &service.id)
+                            .await?;
+
+                    (Some(svc.clone()), Some(plan), feats, Some(roles), Some(permissions))
                 } else {
-                    (None, None, None)
-                };
+                    (None, None, None, None, None)
+                }
+            } else {
+                (None, None, None, None, None)
+            };
+
+        // Create JWT
+        let jwt = state.jwt_service.create_token(
+            &user,
+            oauth_ctx.org_slug.as_deref(),
+            service_slug.as_deref(),
+            plan_name.as_deref(),
+            features,
+            roles,
+            permissions,
+        )?;
+
+        // Generate refresh token
+        let refresh_token = uuid::Uuid::new_v4().to_string();
+
+        // Store session with refresh token
+        create_session(
+            &state.pool,
+            &user.id,
+            &jwt,
+            &refresh_token,
+            oauth_ctx.org_slug.as_deref(),
+            oauth_ctx.service_id.as_deref(),
+            Some(provider),
+            Some(Utc::now()),
+            &client,
+        )
+        .await?;
 
-            // Create JWT
-            let jwt = state.jwt_service.create_token(
+        // Record login event if service_id is available
+        if let (Some(service_id), Some(risk)) = (&oauth_ctx.service_id, &risk) {
+            let _ =
+                record_login_event(&state.pool, &user.id, service_id, provider, &client, risk)
+                    .await;
+        }
+
+        // Redirect with both tokens as query parameters
+        let redirect_url = format!("{}?access_token={}&refresh_token={}", redirect_uri, jwt, refresh_token);
+        let mut response = Redirect::to(&redirect_url).into_response();
+
+        // Remember the upstream login so sibling services can skip the provider
+        if let Some(ref org_slug) = oauth_ctx.org_slug {
+            attach_sso_cookie(
+                &state,
+                &mut response,
+                sso_cookie.as_deref(),
                 &user.id,
-                &user.email,
-                user.is_platform_owner,
-                oauth_ctx.org_slug.as_deref(),
-                service_slug.as_deref(),
-                plan_name.as_deref(),
-                features,
-            )?;
-
-            // Generate refresh token
-            let refresh_token = uuid::Uuid::new_v4().to_string();
-
-            // Store session with refresh token
-            let session_id = uuid::Uuid::new_v4().to_string();
-            let token_hash = JwtService::hash_token(&jwt);
-            let now = Utc::now();
-            let expires_at = now + chrono::Duration::hours(JWT_EXPIRE_HOURS);
-            let refresh_expires_at = now + chrono::Duration::days(30);
-            let created_at = now;
-
-            sqlx::query!(
-                r#"
-                INSERT INTO sessions
-                (id, user_id, token_hash, expires_at, refresh_token, refresh_token_expires_at, org_slug, service_id, created_at)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-                "#,
-                session_id,
-                user.id,
-                token_hash,
-                expires_at,
-                refresh_token,
-                refresh_expires_at,
-                oauth_ctx.org_slug,
-                oauth_ctx.service_id,
-                created_at
+                org_slug,
+                provider,
+                &client,
             )
-            .execute(&state.pool)
             .await?;
-
-            // Record login event if service_id is available
-            if let Some(ref service_id) = oauth_ctx.service_id {
-                let _ = record_login_event(&state.pool, &user.id, service_id, provider).await;
-            }
-
-            // Redirect with both tokens as query parameters
-            let redirect_url = format!("{}?access_token={}&refresh_token={}", redirect_uri, jwt, refresh_token);
-            return Ok(Redirect::to(&redirect_url).into_response());
         }
+
+        return Ok(response);
     }
 
     // No redirect_uri - show HTML success page
@@ -815,15 +1067,35 @@ pub async fn device_code(
 /// Device Flow: Verify user code and return context for frontend
 pub async fn device_verify(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(req): Json<DeviceVerifyRequest>,
 ) -> Result<Json<DeviceVerifyResponse>> {
+    // User codes are short, so guessing them is throttled like any sign-in
+    let mut attempt = LoginAttempt::new(FLOW_DEVICE);
+    let result = device_verify_impl(&state, req, &client, &mut attempt).await;
+    if let Err(ref e) = result {
+        record_login_failure(&state, &attempt, &client, e).await;
+    }
+    result
+}
+
+async fn device_verify_impl(
+    state: &AppState,
+    req: DeviceVerifyRequest,
+    client: &ClientInfo,
+    attempt: &mut LoginAttempt,
+) -> Result<Json<DeviceVerifyResponse>> {
+    LoginFailureService::check(&state.pool, &state.lockout_policy, attempt, client).await?;
+
     // Find device code
     let device_code = DeviceFlowService::find_by_user_code(&state.pool, &req.user_code)
         .await?
-        .ok_or_else(|| AppError::BadRequest("Invalid user code".to_string()))?;
+        .ok_or_else(|| AppError::BadRequest("Invalid user code".to_string()))
+        .map_err(attempt.fail(FAILURE_INVALID_USER_CODE))?;
 
     // Check if expired
     if DeviceFlowService::is_expired(&device_code) {
+        attempt.reason = Some(FAILURE_DEVICE_CODE_EXPIRED);
         return Err(AppError::DeviceCodeExpired);
     }
 
@@ -883,23 +1155,31 @@ pub async fn device_verify(
     }))
 }
 
-/// Device Flow: Exchange device code for token
+/// Device Flow / CIBA: Exchange device code or auth_req_id for token
 pub async fn token_exchange(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(req): Json<TokenRequest>,
 ) -> Result<Json<TokenResponse>> {
-    // Validate grant type
-    if req.grant_type != "urn:ietf:params:oauth:grant-type:device_code" {
-        return Err(AppError::BadRequest("Invalid grant type".to_string()));
-    }
+    // Validate grant type - CIBA requests live in device_codes with their own flow_type
+    let (code, flow_type) = match req.grant_type.as_str() {
+        "urn:ietf:params:oauth:grant-type:device_code" => (req.device_code, "device"),
+        "urn:openid:params:grant-type:ciba" => (req.auth_req_id, "ciba"),
+        _ => return Err(AppError::BadRequest("Invalid grant type".to_string())),
+    };
+    let code = code.ok_or_else(|| {
+        AppError::BadRequest("Missing device_code or auth_req_id".to_string())
+    })?;
 
     // Validate and get device code
     let device_code = DeviceFlowService::validate_for_token_exchange(
         &state.pool,
-        &req.device_code,
+        &code,
         &req.client_id,
+        flow_type,
     )
     .await?;
+    DeviceFlowService::consume(&state.pool, &device_code.id).await?;
 
     let user_id = device_code
         .user_id
@@ -907,19 +1187,59 @@ pub async fn token_exchange(
 
     // Get user info
     let user = sqlx::query_as::<_, User>(
-        "SELECT id, email, is_platform_owner, created_at FROM users WHERE id = ?",
+        "SELECT * FROM users WHERE id = ?",
     )
     .bind(&user_id)
     .fetch_one(&state.pool)
     .await?;
 
+    // The sign-in that authorized the request is checked against the user's
+    // lockout, the domain's policy and the organization's SCIM client again
+    let platform = device_code.org_slug == "platform" && device_code.service_slug == "admin-cli";
+    let authorized_with = device_code
+        .provider
+        .as_deref()
+        .and_then(|provider| Provider::from_str(provider).ok());
+    let org_id: Option<String> = if platform {
+        None
+    } else {
+        sqlx::query_scalar("SELECT id FROM organizations WHERE slug = ?")
+            .bind(&device_code.org_slug)
+            .fetch_optional(&state.pool)
+            .await?
+    };
+    let flow = if flow_type == "ciba" {
+        FLOW_CIBA
+    } else {
+        FLOW_DEVICE
+    };
+    let mut attempt = LoginAttempt {
+        user_id: Some(user.id.clone()),
+        provider: authorized_with.map(|p| p.as_str().to_string()),
+        org_id: org_id.clone(),
+        ..LoginAttempt::new(flow)
+    };
+    if let Err(e) = check_authenticated_user(
+        &state,
+        &mut attempt,
+        &user,
+        authorized_with,
+        org_id.as_deref(),
+        &client,
+    )
+    .await
+    {
+        record_login_failure(&state, &attempt, &client, &e).await;
+        return Err(e);
+    }
+
     // Check if this is a platform-level device flow
-    if device_code.org_slug == "platform" && device_code.service_slug == "admin-cli" {
+    if platform {
         // Generate platform JWT for admin CLI
         let token = state.jwt_service.create_token(
-            &user.id,
-            &user.email,
-            user.is_platform_owner,
+            &user,
+            None,
+            None,
             None,
             None,
             None,
@@ -930,30 +1250,17 @@ pub async fn token_exchange(
         let refresh_token = Uuid::new_v4().to_string();
 
         // Store session with refresh token
-        let session_id = Uuid::new_v4().to_string();
-        let token_hash = JwtService::hash_token(&token);
-        let now = Utc::now();
-        let expires_at = now + chrono::Duration::hours(JWT_EXPIRE_HOURS);
-        let refresh_expires_at = now + chrono::Duration::days(30);
-        let created_at = now;
-
-        sqlx::query!(
-            r#"
-            INSERT INTO sessions
-            (id, user_id, token_hash, expires_at, refresh_token, refresh_token_expires_at, org_slug, service_id, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-            session_id,
-            user_id,
-            token_hash,
-            expires_at,
-            refresh_token,
-            refresh_expires_at,
-            None::<String>,
-            None::<String>,
-            created_at
+        create_session(
+            &state.pool,
+            &user_id,
+            &token,
+            &refresh_token,
+            None,
+            None,
+            authorized_with,
+            device_code.authenticated_at,
+            &client,
         )
-        .execute(&state.pool)
         .await?;
 
         return Ok(Json(TokenResponse {
@@ -992,81 +1299,477 @@ pub async fn token_exchange(
         .and_then(|f| serde_json::from_str(f).ok())
         .unwrap_or_default();
 
+    let (roles, permissions) = match result.service_id {
+        Some(ref service_id) => {
+            let (roles, permissions) =
+                ServiceRoleService::effective_roles(&state.pool, &user.id, service_id).await?;
+            (Some(roles), Some(permissions))
+        }
+        None => (None, None),
+    };
+
+    // Provider of the user's most recent identity, for the login event, when
+    // the request does not record one
+    let provider = match authorized_with {
+        Some(provider) => Some(provider),
+        None => sqlx::query_scalar::<_, String>(
+            "SELECT provider FROM identities WHERE user_id = ? ORDER BY last_refreshed_at DESC LIMIT 1",
+        )
+        .bind(&user_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .and_then(|provider| Provider::from_str(&provider).ok()),
+    };
+
+    // There is no browser to enter a code in, so a login the policy would
+    // challenge is blocked like one it blocks outright
+    let risk = match (result.service_id.as_ref(), provider) {
+        (Some(service_id), Some(provider)) => {
+            match screen_login(&state, &user_id, service_id, provider, false, &client).await? {
+                LoginDecision::Proceed(risk) => Some(risk),
+                _ => {
+                    sqlx::query("UPDATE device_codes SET status = 'denied' WHERE id = ?")
+                        .bind(&device_code.id)
+                        .execute(&state.pool)
+                        .await?;
+                    return Err(AppError::AuthorizationDenied);
+                }
+            }
+        }
+        _ => None,
+    };
+
     // Generate JWT
     let token = state.jwt_service.create_token(
-        &user.id,
-        &user.email,
-        user.is_platform_owner,
+        &user,
         Some(&result.org_slug),
         Some(&result.service_slug),
         Some(&plan_name),
         Some(features),
+        roles,
+        permissions,
     )?;
 
     // Generate refresh token
     let refresh_token = Uuid::new_v4().to_string();
 
     // Store session with refresh token
+    create_session(
+        &state.pool,
+        &user_id,
+        &token,
+        &refresh_token,
+        Some(&result.org_slug),
+        result.service_id.as_deref(),
+        authorized_with,
+        device_code.authenticated_at,
+        &client,
+    )
+    .await?;
+
+    // Record login event
+    if let (Some(service_id), Some(provider), Some(risk)) =
+        (result.service_id.as_ref(), provider, risk.as_ref())
+    {
+        let _ = record_login_event(&state.pool, &user_id, service_id, provider, &client, risk).await;
+    }
+
+    Ok(Json(TokenResponse {
+        access_token: token,
+        token_type: "Bearer".to_string(),
+        expires_in: JWT_EXPIRE_HOURS * 3600, // Convert hours to seconds
+    }))
+}
+
+// Helper functions
+
+/// Look up the user's active plan name and features for a service
+pub async fn get_service_plan(
+    pool: &SqlitePool,
+    user_id: &str,
+    service_id: &str,
+) -> Result<(String, Option<Vec<String>>)> {
+    let subscription = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        SELECT p.name, p.features
+        FROM subscriptions sub
+        JOIN plans p ON sub.plan_id = p.id
+        WHERE sub.user_id = ? AND sub.service_id = ? AND sub.status = 'active'
+        "#,
+    )
+    .bind(user_id)
+    .bind(service_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let plan = subscription
+        .as_ref()
+        .map(|(plan_name, _)| plan_name.clone())
+        .unwrap_or_else(|| "free".to_string());
+    let features = subscription
+        .as_ref()
+        .and_then(|(_, features)| features.as_ref())
+        .and_then(|f| serde_json::from_str::<Vec<String>>(f).ok());
+
+    Ok((plan, features))
+}
+
+/// SSO: issue service tokens from an existing SSO session without contacting the provider
+async fn sso_login(
+    state: &AppState,
+    sso: &crate::db::models::SsoSession,
+    org_slug: &str,
+    service: &crate::db::models::Service,
+    redirect_uri: &str,
+    client: &ClientInfo,
+) -> Result<Response> {
+    let provider = Provider::from_str(&sso.provider)?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&sso.user_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    // The same checks as a login through the provider: the session may
+    // predate a lockout, a domain policy or a SCIM deprovisioning
+    let mut attempt = LoginAttempt {
+        provider: Some(provider.as_str().to_string()),
+        provider_user_id: sqlx::query_scalar(
+            "SELECT provider_user_id FROM identities WHERE user_id = ? AND provider = ?
+             ORDER BY last_refreshed_at DESC LIMIT 1",
+        )
+        .bind(&user.id)
+        .bind(provider.as_str())
+        .fetch_optional(&state.pool)
+        .await?,
+        org_id: Some(service.org_id.clone()),
+        service_id: Some(service.id.clone()),
+        ..LoginAttempt::new(FLOW_SSO_SESSION)
+    };
+    if let Err(e) = check_authenticated_user(
+        state,
+        &mut attempt,
+        &user,
+        Some(provider),
+        Some(&service.org_id),
+        client,
+    )
+    .await
+    {
+        record_login_failure(state, &attempt, client, &e).await;
+        return login_error_redirect(redirect_uri, "access_denied", None);
+    }
+
+    // A new device or location is checked even when the provider is skipped
+    let risk = match screen_login(state, &sso.user_id, &service.id, provider, true, client).await? {
+        LoginDecision::Proceed(risk) => risk,
+        LoginDecision::Blocked => return login_error_redirect(redirect_uri, "access_denied", None),
+        LoginDecision::Challenged(challenge_id) => {
+            return login_error_redirect(redirect_uri, "email_code_required", Some(&challenge_id))
+        }
+    };
+
+    // The user authenticated when the SSO session was started upstream
+    let (jwt, refresh_token) = issue_service_tokens(
+        state,
+        &sso.user_id,
+        org_slug,
+        service,
+        Some(provider),
+        sso.created_at,
+        client,
+    )
+    .await?;
+
+    let _ = record_login_event(&state.pool, &sso.user_id, &service.id, provider, client, &risk).await;
+    LoginFailureService::clear(&state.pool, &attempt).await?;
+
+    let redirect_url = format!(
+        "{}?access_token={}&refresh_token={}",
+        redirect_uri, jwt, refresh_token
+    );
+    Ok(Redirect::to(&redirect_url).into_response())
+}
+
+/// Checks every sign-in of a known user must pass, whether the provider was
+/// contacted, an SSO session was reused or tokens are issued from an earlier
+/// sign-in: lockout, the email domain's sign-in policy and deprovisioning by
+/// the organization's SCIM client
+async fn check_authenticated_user(
+    state: &AppState,
+    attempt: &mut LoginAttempt,
+    user: &User,
+    provider: Option<Provider>,
+    org_id: Option<&str>,
+    client: &ClientInfo,
+) -> Result<()> {
+    attempt.user_id = Some(user.id.clone());
+    LoginFailureService::check(&state.pool, &state.lockout_policy, attempt, client).await?;
+    check_sign_in_policy(state, attempt, user, provider, org_id).await
+}
+
+/// The email domain's sign-in policy and deprovisioning by the organization's
+/// SCIM client, checked again whenever tokens are issued from an earlier
+/// sign-in: refresh, device and CIBA grants
+async fn check_sign_in_policy(
+    state: &AppState,
+    attempt: &mut LoginAttempt,
+    user: &User,
+    provider: Option<Provider>,
+    org_id: Option<&str>,
+) -> Result<()> {
+    DomainService::check_sign_in(&state.pool, user, provider, org_id)
+        .await
+        .map_err(attempt.fail(FAILURE_SIGN_IN_RESTRICTED))?;
+    if let Some(org_id) = org_id {
+        ScimService::check_sign_in(&state.pool, org_id, &user.id)
+            .await
+            .map_err(attempt.fail(FAILURE_SIGN_IN_RESTRICTED))?;
+    }
+
+    Ok(())
+}
+
+/// Create a session for a user signing in to a service. Returns the access
+/// and refresh tokens.
+async fn issue_service_tokens(
+    state: &AppState,
+    user_id: &str,
+    org_slug: &str,
+    service: &crate::db::models::Service,
+    provider: Option<Provider>,
+    authenticated_at: DateTime<Utc>,
+    client: &ClientInfo,
+) -> Result<(String, String)> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let (plan_name, features) = get_service_plan(&state.pool, &user.id, &service.id).await?;
+    let (roles, permissions) =
+        ServiceRoleService::effective_roles(&state.pool, &user.id, &service.id).await?;
+
+    let jwt = state.jwt_service.create_token(
+        &user,
+        Some(org_slug),
+        Some(&service.slug),
+        Some(&plan_name),
+        features,
+        Some(roles),
+        Some(permissions),
+    )?;
+
+    let refresh_token = Uuid::new_v4().to_string();
+    create_session(
+        &state.pool,
+        &user.id,
+        &jwt,
+        &refresh_token,
+        Some(org_slug),
+        Some(&service.id),
+        provider,
+        Some(authenticated_at),
+        client,
+    )
+    .await?;
+
+    Ok((jwt, refresh_token))
+}
+
+/// SSO: start (or extend) the browser's SSO session and set the cookie on the response
+async fn attach_sso_cookie(
+    state: &AppState,
+    response: &mut Response,
+    existing_token: Option<&str>,
+    user_id: &str,
+    org_slug: &str,
+    provider: Provider,
+    client: &ClientInfo,
+) -> Result<()> {
+    let org = sqlx::query_as::<_, crate::db::models::Organization>(
+        "SELECT * FROM organizations WHERE slug = ?",
+    )
+    .bind(org_slug)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    let Some(org) = org else {
+        return Ok(());
+    };
+
+    if let Some((token, max_age)) = SsoSessionService::start(
+        &state.pool,
+        existing_token,
+        user_id,
+        &org,
+        provider.as_str(),
+        client,
+    )
+    .await?
+    {
+        let cookie = SsoSessionService::cookie(&token, max_age, &state.base_url);
+        if let Ok(value) = axum::http::HeaderValue::from_str(&cookie) {
+            response
+                .headers_mut()
+                .append(axum::http::header::SET_COOKIE, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Queue a webhook about a sign-in or link that has already gone through. A
+/// failure is logged; the user is not turned away over a notification.
+async fn emit_after_login(
+    pool: &SqlitePool,
+    org_id: &str,
+    event_type: &str,
+    data: serde_json::Value,
+) {
+    let result = match pool.acquire().await {
+        Ok(mut conn) => WebhookService::emit(&mut conn, org_id, event_type, data).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        tracing::error!(
+            org_id = %org_id,
+            event_type = %event_type,
+            "Failed to queue webhook event: {}",
+            e
+        );
+    }
+}
+
+/// Persist the session backing a freshly issued access token and refresh token
+#[allow(clippy::too_many_arguments)]
+async fn create_session(
+    pool: &SqlitePool,
+    user_id: &str,
+    access_token: &str,
+    refresh_token: &str,
+    org_slug: Option<&str>,
+    service_id: Option<&str>,
+    provider: Option<Provider>,
+    authenticated_at: Option<DateTime<Utc>>,
+    client: &ClientInfo,
+) -> Result<String> {
     let session_id = Uuid::new_v4().to_string();
-    let token_hash = JwtService::hash_token(&token);
     let now = Utc::now();
     let expires_at = now + chrono::Duration::hours(JWT_EXPIRE_HOURS);
     let refresh_expires_at = now + chrono::Duration::days(30);
-    let created_at = now;
 
-    sqlx::query!(
+    sqlx::query(
         r#"
         INSERT INTO sessions
-        (id, user_id, token_hash, expires_at, refresh_token, refresh_token_expires_at, org_slug, service_id, created_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        (id, user_id, token_hash, expires_at, refresh_token, refresh_token_expires_at, org_slug, service_id,
+         user_agent, ip_address, created_at, last_used_at, provider, authenticated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
-        session_id,
-        user_id,
-        token_hash,
-        expires_at,
-        refresh_token,
-        refresh_expires_at,
-        result.org_slug,
-        result.service_id,
-        created_at
     )
-    .execute(&state.pool)
+    .bind(&session_id)
+    .bind(user_id)
+    .bind(JwtService::hash_token(access_token))
+    .bind(expires_at)
+    .bind(refresh_token)
+    .bind(refresh_expires_at)
+    .bind(org_slug)
+    .bind(service_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .bind(now)
+    .bind(now)
+    .bind(provider.map(|p| p.as_str()))
+    .bind(authenticated_at)
+    .execute(pool)
     .await?;
 
-    // Record login event - get provider from most recent identity
-    if let Some(service_id) = result.service_id.as_ref() {
-        if let Ok(provider_str) = sqlx::query_scalar::<_, String>(
-            "SELECT provider FROM identities WHERE user_id = ? ORDER BY last_refreshed_at DESC LIMIT 1"
-        )
-        .bind(&user_id)
-        .fetch_one(&state.pool)
-        .await
-        {
-            if let Ok(provider) = Provider::from_str(&provider_str) {
-                let _ = record_login_event(&state.pool, &user_id, service_id, provider).await;
-            }
-        }
-    }
+    Ok(session_id)
+}
 
-    Ok(Json(TokenResponse {
-        access_token: token,
-        token_type: "Bearer".to_string(),
-        expires_in: JWT_EXPIRE_HOURS * 3600, // Convert hours to seconds
-    }))
+/// Providers the user has identities for in an organization's services, or
+/// at platform level without one; empty for someone not seen there before
+async fn org_identity_providers(
+    pool: &SqlitePool,
+    user_id: &str,
+    org_id: Option<&str>,
+) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        "SELECT DISTINCT provider FROM identities
+         WHERE user_id = ? AND issuing_org_id IS ? AND (? IS NOT NULL OR issuing_service_id IS NULL)",
+    )
+    .bind(user_id)
+    .bind(org_id)
+    .bind(org_id)
+    .fetch_all(pool)
+    .await?)
 }
 
-// Helper functions
+/// Organizations told about a user's sign-ins and linked accounts: the one
+/// signed in to, or for a platform-level sign-in every organization the user
+/// is a member or subscriber of
+pub async fn identity_event_orgs(
+    pool: &SqlitePool,
+    user_id: &str,
+    org_id: Option<&str>,
+) -> Result<Vec<String>> {
+    if let Some(org_id) = org_id {
+        return Ok(vec![org_id.to_string()]);
+    }
 
-async fn find_or_create_user(pool: &SqlitePool, email: &str) -> Result<User> {
-    // Try to find existing user
-    if let Some(user) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
-        .bind(email)
-        .fetch_optional(pool)
-        .await?
+    Ok(sqlx::query_scalar(
+        "SELECT org_id FROM memberships WHERE user_id = ?
+         UNION
+         SELECT s.org_id FROM subscriptions sub JOIN services s ON sub.service_id = s.id
+         WHERE sub.user_id = ?",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+async fn find_or_create_user(
+    pool: &SqlitePool,
+    provider: Provider,
+    user_info: &crate::auth::sso::UserInfo,
+) -> Result<User> {
+    let email = user_info.email.as_str();
+
+    // A provider account that is already linked always signs in as its user
+    if let Some(user) = sqlx::query_as::<_, User>(
+        "SELECT u.* FROM users u JOIN identities i ON i.user_id = u.id
+         WHERE i.provider = ? AND i.provider_user_id = ?
+         LIMIT 1",
+    )
+    .bind(provider.as_str())
+    .bind(&user_info.provider_user_id)
+    .fetch_optional(pool)
+    .await?
     {
         return Ok(user);
     }
 
+    // Matching an existing account by email needs a verified email on both
+    // sides: this login's, and one of the account's own identities. The address
+    // on the user row alone could have been typed in by anyone.
+    if user_info.email_verified {
+        if let Some(user) = ProfileService::verified_email_owner(pool, email).await? {
+            return Ok(user);
+        }
+    }
+    let taken: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE email = ? COLLATE NOCASE")
+            .bind(email)
+            .fetch_one(pool)
+            .await?;
+    if taken > 0 {
+        return Err(AppError::Forbidden(
+            "An account with this email already exists. Sign in to it with the provider you \
+             used before, then link this account from your settings"
+                .to_string(),
+        ));
+    }
+
     // Create new user
     let id = Uuid::new_v4().to_string();
     let user = sqlx::query_as::<_, User>(
@@ -1265,16 +1968,75 @@ pub struct RefreshTokenResponse {
 
 pub async fn refresh_token(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(req): Json<RefreshTokenRequest>,
 ) -> Result<Json<RefreshTokenResponse>> {
+    // Refresh tokens are only counted against the IP: a failing app must not
+    // lock its user out
+    let mut attempt = LoginAttempt::new(FLOW_REFRESH);
+    let result = refresh_token_impl(&state, req, &client, &mut attempt).await;
+    if let Err(ref e) = result {
+        record_login_failure(&state, &attempt, &client, e).await;
+    }
+    result
+}
+
+async fn refresh_token_impl(
+    state: &AppState,
+    req: RefreshTokenRequest,
+    client: &ClientInfo,
+    attempt: &mut LoginAttempt,
+) -> Result<Json<RefreshTokenResponse>> {
+    // Only the IP is known yet; the user is checked once the token resolves
+    LoginFailureService::check(&state.pool, &state.lockout_policy, attempt, client).await?;
+
     // Find the session by refresh token
     let session = sqlx::query_as::<_, crate::db::models::Session>(
         "SELECT * FROM sessions WHERE refresh_token = ?",
     )
     .bind(&req.refresh_token)
     .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".to_string()))?;
+    .await?;
+
+    let Some(session) = session else {
+        // A rotated-out refresh token being replayed means the token family leaked:
+        // revoke the whole session and tell the service
+        let reused = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT r.session_id, s.user_id
+             FROM rotated_refresh_tokens r
+             LEFT JOIN sessions s ON s.id = r.session_id
+             WHERE r.token_hash = ?",
+        )
+        .bind(JwtService::hash_token(&req.refresh_token))
+        .fetch_optional(&state.pool)
+        .await?;
+
+        if let Some((session_id, user_id)) = reused {
+            tracing::warn!("Refresh token reuse detected, revoking session {}", session_id);
+            attempt.reason = Some(FAILURE_REFRESH_TOKEN_REUSE);
+            SecurityEventService::record(
+                &state.pool,
+                SECURITY_EVENT_REFRESH_TOKEN_REUSE,
+                "high",
+                user_id.as_deref(),
+                None,
+                client,
+                serde_json::json!({ "session_id": session_id }),
+            )
+            .await?;
+            LogoutService::revoke_session(
+                &state.pool,
+                &state.jwt_service,
+                &state.base_url,
+                &session_id,
+            )
+            .await?;
+        } else {
+            attempt.reason = Some(FAILURE_INVALID_REFRESH_TOKEN);
+        }
+
+        return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+    };
 
     // Check if refresh token has expired
     if let Some(refresh_expires_at) = session.refresh_token_expires_at {
@@ -1283,10 +2045,12 @@ pub async fn refresh_token(
             sqlx::query!("DELETE FROM sessions WHERE id = ?", session.id)
                 .execute(&state.pool)
                 .await?;
+            attempt.reason = Some(FAILURE_REFRESH_TOKEN_EXPIRED);
             return Err(AppError::Unauthorized("Refresh token expired".to_string()));
         }
     } else {
         // No expiration set - invalid session
+        attempt.reason = Some(FAILURE_INVALID_REFRESH_TOKEN);
         return Err(AppError::Unauthorized("Invalid session".to_string()));
     }
 
@@ -1295,58 +2059,53 @@ pub async fn refresh_token(
         .bind(&session.user_id)
         .fetch_one(&state.pool)
         .await?;
+    attempt.user_id = Some(user.id.clone());
 
-    // Reconstruct JWT with original session context
-    // If service_id is present, get full service and subscription details
-    let (service_slug, plan_name, features) = if let Some(ref svc_id) = session.service_id {
-        let service = sqlx::query_as::<_, crate::db::models::Service>(
-            "SELECT * FROM services WHERE id = ?",
-        )
-        .bind(svc_id)
-        .fetch_optional(&state.pool)
-        .await?;
-
-        if let Some(svc) = service {
-            // Get subscription if exists
-            let subscription = sqlx::query!(
-                r#"
-                SELECT p.name as plan_name, p.features
-                FROM subscriptions sub
-                JOIN plans p ON sub.plan_id = p.id
-                WHERE sub.user_id = ? AND sub.service_id = ? AND sub.status = 'active'
-                "#,
-                user.id,
-                svc.id
-            )
-            .fetch_optional(&state.pool)
-            .await?;
+    let service = match session.service_id {
+        Some(ref svc_id) => {
+            sqlx::query_as::<_, crate::db::models::Service>("SELECT * FROM services WHERE id = ?")
+                .bind(svc_id)
+                .fetch_optional(&state.pool)
+                .await?
+        }
+        None => None,
+    };
 
-            let plan = subscription
-                .as_ref()
-                .map(|s| s.plan_name.clone())
-                .unwrap_or_else(|| "free".to_string());
-            let feats = subscription
-                .as_ref()
-                .and_then(|s| s.features.as_ref())
-                .and_then(|f| serde_json::from_str::<Vec<String>>(f).ok());
+    // The user may have been locked out, the domain may have started enforcing
+    // a provider or the organization deprovisioned the user since the sign-in
+    let provider = session
+        .provider
+        .as_deref()
+        .and_then(|provider| Provider::from_str(provider).ok());
+    attempt.provider = provider.map(|p| p.as_str().to_string());
+    let org_id = service.as_ref().map(|svc| svc.org_id.clone());
+    attempt.org_id = org_id.clone();
+    attempt.service_id = session.service_id.clone();
+    check_authenticated_user(state, attempt, &user, provider, org_id.as_deref(), client).await?;
 
-            (Some(svc.slug), Some(plan), feats)
-        } else {
-            (None, None, None)
-        }
+    // Reconstruct JWT with original session context
+    // If service_id is present, get full service, subscription and role details
+    let (service_slug, plan_name, features, roles, permissions) = if let Some(svc) = service {
+        // Get subscription if exists
+        let (plan, feats) = get_service_plan(&state.pool, &user.id, &svc.id).await?;
+        // Recomputed on every refresh so role changes reach the service
+        let (roles, permissions) =
+            ServiceRoleService::effective_roles(&state.pool, &user.id, &svc.id).await?;
+
+        (Some(svc.slug), Some(plan), feats, Some(roles), Some(permissions))
     } else {
-        (None, None, None)
+        (None, None, None, None, None)
     };
 
     // Create new access token with preserved context
     let new_access_token = state.jwt_service.create_token(
-        &user.id,
-        &user.email,
-        user.is_platform_owner,
+        &user,
         session.org_slug.as_deref(),
         service_slug.as_deref(),
         plan_name.as_deref(),
         features,
+        roles,
+        permissions,
     )?;
 
     // Implement token rotation: generate new refresh token
@@ -1355,6 +2114,16 @@ pub async fn refresh_token(
     let new_access_expires_at = Utc::now() + chrono::Duration::hours(JWT_EXPIRE_HOURS);
     let new_refresh_expires_at = Utc::now() + chrono::Duration::days(30);
 
+    // Remember the old refresh token so a replay can be detected
+    sqlx::query(
+        "INSERT OR IGNORE INTO rotated_refresh_tokens (token_hash, session_id, rotated_at) VALUES (?, ?, ?)",
+    )
+    .bind(JwtService::hash_token(&req.refresh_token))
+    .bind(&session.id)
+    .bind(Utc::now())
+    .execute(&state.pool)
+    .await?;
+
     // Update session with new tokens (token rotation)
     sqlx::query!(
         r#"
@@ -1381,6 +2150,103 @@ pub async fn refresh_token(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyLoginChallengeRequest {
+    pub code: String,
+}
+
+/// Login challenge: finish a login the organization's risk policy held back.
+/// The service got the challenge id in the `email_code_required` redirect; the user
+/// got the code by email.
+pub async fn verify_login_challenge(
+    State(state): State<AppState>,
+    Path(challenge_id): Path<String>,
+    client: ClientInfo,
+    Json(req): Json<VerifyLoginChallengeRequest>,
+) -> Result<Json<RefreshTokenResponse>> {
+    // Wrong codes count against the user as well as the challenge
+    let mut attempt = LoginAttempt::new(FLOW_LOGIN_CHALLENGE);
+    let pending = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT c.user_id, c.service_id, s.org_id FROM login_challenges c
+         JOIN services s ON s.id = c.service_id WHERE c.id = ?",
+    )
+    .bind(&challenge_id)
+    .fetch_optional(&state.pool)
+    .await?;
+    if let Some((user_id, service_id, org_id)) = pending {
+        attempt.user_id = Some(user_id);
+        attempt.service_id = Some(service_id);
+        attempt.org_id = Some(org_id);
+    }
+
+    let verified = async {
+        LoginFailureService::check(&state.pool, &state.lockout_policy, &attempt, &client).await?;
+        LoginRiskService::verify_challenge(&state.pool, &challenge_id, &req.code)
+            .await
+            .map_err(attempt.fail(FAILURE_INVALID_LOGIN_CODE))
+    }
+    .await;
+    let challenge = match verified {
+        Ok(challenge) => challenge,
+        Err(e) => {
+            record_login_failure(&state, &attempt, &client, &e).await;
+            return Err(e);
+        }
+    };
+    LoginFailureService::clear(&state.pool, &attempt).await?;
+
+    let service = sqlx::query_as::<_, crate::db::models::Service>(
+        "SELECT * FROM services WHERE id = ?",
+    )
+    .bind(&challenge.service_id)
+    .fetch_one(&state.pool)
+    .await?;
+    let org_slug: String = sqlx::query_scalar("SELECT slug FROM organizations WHERE id = ?")
+        .bind(&service.org_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let provider =
+        sqlx::query_scalar::<_, String>("SELECT provider FROM login_events WHERE id = ?")
+            .bind(&challenge.login_event_id)
+            .fetch_optional(&state.pool)
+            .await?
+            .and_then(|provider| Provider::from_str(&provider).ok());
+    let (access_token, refresh_token) = issue_service_tokens(
+        &state,
+        &challenge.user_id,
+        &org_slug,
+        &service,
+        provider,
+        Utc::now(),
+        &client,
+    )
+    .await?;
+
+    // The challenged attempt stays as it was; the completed login is a new event
+    let login_event_id = Uuid::new_v4().to_string();
+    let mut tx = state.pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO login_events (id, user_id, service_id, provider, ip_address, user_agent, created_at,
+             outcome, device_fingerprint, country, latitude, longitude, risk_score, risk_reasons, risk_action)
+         SELECT ?, user_id, service_id, provider, ip_address, user_agent, datetime('now'),
+             'success', device_fingerprint, country, latitude, longitude, risk_score, risk_reasons, risk_action
+         FROM login_events WHERE id = ?",
+    )
+    .bind(&login_event_id)
+    .bind(&challenge.login_event_id)
+    .execute(&mut *tx)
+    .await?;
+    complete_login_event(&mut tx, &login_event_id, None).await?;
+    tx.commit().await?;
+
+    Ok(Json(RefreshTokenResponse {
+        access_token,
+        refresh_token,
+        expires_in: JWT_EXPIRE_HOURS * 3600,
+    }))
+}
+
 /// Logout: Invalidate JWT session
 pub async fn logout(
     State(state): State<AppState>,
@@ -1398,20 +2264,143 @@ pub async fn logout(
     // Hash token
     let token_hash = JwtService::hash_token(token);
 
-    // Delete session (also removes refresh token)
-    sqlx::query!("DELETE FROM sessions WHERE token_hash = ?", token_hash)
-        .execute(&state.pool)
-        .await
-        .map_err(AppError::Database)?;
+    // Delete session (also removes refresh token) and notify the service
+    let session_id =
+        sqlx::query_scalar::<_, String>("SELECT id FROM sessions WHERE token_hash = ?")
+            .bind(&token_hash)
+            .fetch_optional(&state.pool)
+            .await?;
+
+    if let Some(session_id) = session_id {
+        LogoutService::revoke_session(
+            &state.pool,
+            &state.jwt_service,
+            &state.base_url,
+            &session_id,
+        )
+        .await?;
+    }
 
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
+// RP-Initiated Logout Request
+#[derive(Debug, Deserialize)]
+pub struct EndSessionRequest {
+    pub id_token_hint: String,
+    pub client_id: String,
+    pub post_logout_redirect_uri: Option<String>,
+    pub state: Option<String>,
+}
+
+/// RP-Initiated Logout: GET /auth/end-session
+/// Ends the session the hint was issued for and this browser's SSO session
+/// with its organization, renders the front-channel logout page and then
+/// returns to the service's registered post_logout_redirect_uri
+pub async fn end_session(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Query(req): Query<EndSessionRequest>,
+) -> Result<Response> {
+    let claims = state.jwt_service.decode_token_hint(&req.id_token_hint)?;
+    let service = LogoutService::hinted_service(&state.pool, &claims, &req.client_id).await?;
+
+    let redirect_uri = match req.post_logout_redirect_uri {
+        Some(ref uri) => {
+            validate_post_logout_redirect_uri(uri, &service)?;
+
+            let mut url = url::Url::parse(uri)
+                .map_err(|_| AppError::BadRequest("Invalid post_logout_redirect_uri".to_string()))?;
+            if let Some(ref logout_state) = req.state {
+                url.query_pairs_mut().append_pair("state", logout_state);
+            }
+            Some(url.to_string())
+        }
+        None => None,
+    };
+
+    let session = LogoutService::revoke_hinted_session(
+        &state.pool,
+        &state.jwt_service,
+        &state.base_url,
+        &req.id_token_hint,
+        &claims.sub,
+    )
+    .await?;
+
+    // End this browser's SSO session with the organization, keeping the
+    // cookie while it still carries other organizations
+    let keep_cookie = match SsoSessionService::read_cookie(&headers) {
+        Some(token) => {
+            SsoSessionService::end(&state.pool, &token, &service.org_id, &claims.sub).await?
+        }
+        None => false,
+    };
+
+    // Front-channel logout: a hidden iframe for the service, if it is browser-based
+    let mut iframes = String::new();
+    if let (Some(session), Some(uri)) = (session, service.frontchannel_logout_uri.as_ref()) {
+        if let Ok(mut url) = url::Url::parse(uri) {
+            url.query_pairs_mut()
+                .append_pair("iss", &state.base_url)
+                .append_pair("sid", &session.id);
+            iframes.push_str(&format!(
+                r#"<iframe src="{}" style="display:none"></iframe>"#,
+                html_escape(url.as_str())
+            ));
+        }
+    }
+
+    let redirect_script = redirect_uri
+        .map(|uri| {
+            format!(
+                r#"<script>window.addEventListener("load", function () {{ window.location.replace({}); }});</script>"#,
+                serde_json::to_string(&uri).unwrap_or_default()
+            )
+        })
+        .unwrap_or_default();
+
+    let html = format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head><title>Signed Out</title></head>
+        <body>
+            <h1>You have been signed out</h1>
+            {}
+            {}
+        </body>
+        </html>
+        "#,
+        iframes, redirect_script
+    );
+
+    let mut response = Html(html).into_response();
+    if !keep_cookie {
+        if let Ok(cookie) = SsoSessionService::clear_cookie(&state.base_url).parse() {
+            response
+                .headers_mut()
+                .insert(axum::http::header::SET_COOKIE, cookie);
+        }
+    }
+
+    Ok(response)
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 /// Admin Auth: Initiate OAuth flow for platform/org admin login
 pub async fn auth_admin_provider(
     State(state): State<AppState>,
     Path(provider_str): Path<String>,
     Query(params): Query<AdminAuthRequest>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response> {
     let provider = Provider::from_str(&provider_str)?;
 
@@ -1423,10 +2412,14 @@ pub async fn auth_admin_provider(
 
     // Use default admin scopes based on provider
     let scopes = default_scopes_for_provider(provider);
+    let nonce = IdTokenVerifier::generate_nonce(provider, &scopes);
 
     // Generate authorization URL with PKCE (for Microsoft)
     let (auth_url, csrf_token, pkce_verifier) =
-        get_admin_authorization_url(&admin_oauth_client, provider, scopes);
+        get_admin_authorization_url(&admin_oauth_client, provider, scopes, nonce.as_deref());
+
+    let (browser_binding, binding_cookie) =
+        BrowserBindingService::bind(&state.jwt_service, &headers, &state.base_url)?;
 
     // Store OAuth state with is_admin_flow = true
     let expires_at = Utc::now() + chrono::Duration::minutes(OAUTH_STATE_EXPIRE_MINUTES);
@@ -1438,8 +2431,8 @@ pub async fn auth_admin_provider(
 
     let is_admin_flow = true;
     sqlx::query(
-        "INSERT INTO oauth_states (state, pkce_verifier, service_id, redirect_uri, org_slug, service_slug, is_admin_flow, user_id_for_linking, device_user_code, created_at, expires_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, NULL, ?, datetime('now'), ?)",
+        "INSERT INTO oauth_states (state, pkce_verifier, service_id, redirect_uri, org_slug, service_slug, is_admin_flow, user_id_for_linking, device_user_code, created_at, expires_at, browser_binding, nonce)
+         VALUES (?, ?, ?, ?, ?, ?, ?, NULL, ?, datetime('now'), ?, ?, ?)",
     )
     .bind(csrf_token.secret())
     .bind(pkce_value)
@@ -1450,10 +2443,12 @@ pub async fn auth_admin_provider(
     .bind(is_admin_flow)
     .bind(&params.user_code)
     .bind(expires_at)
+    .bind(&browser_binding)
+    .bind(&nonce)
     .execute(&state.pool)
     .await?;
 
-    Ok(Redirect::to(&auth_url).into_response())
+    Ok(([(axum::http::header::SET_COOKIE, binding_cookie)], Redirect::to(&auth_url)).into_response())
 }
 
 /// Admin Auth: Handle OAuth callback for admin login
@@ -1461,13 +2456,17 @@ pub async fn auth_admin_callback(
     State(state): State<AppState>,
     Path(provider_str): Path<String>,
     Query(callback): Query<CallbackQuery>,
+    client: ClientInfo,
+    request_id: Option<Extension<RequestId>>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response> {
     // Load config early so we can use it for error redirects
     let config = crate::config::Config::from_env()
         .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
     // Wrap the main logic to catch errors and redirect to frontend with error info
-    match auth_admin_callback_impl(state, provider_str, callback).await {
+    match auth_admin_callback_impl(state, provider_str, callback, client, request_id, headers).await
+    {
         Ok(response) => Ok(response),
         Err(e) => {
             // Log the error
@@ -1498,6 +2497,9 @@ async fn auth_admin_callback_impl(
     state: AppState,
     provider_str: String,
     callback: CallbackQuery,
+    client: ClientInfo,
+    request_id: Option<Extension<RequestId>>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response> {
     let provider = Provider::from_str(&provider_str)?;
 
@@ -1513,13 +2515,31 @@ async fn auth_admin_callback_impl(
         None
     };
 
-    let oauth_state =
-        oauth_state.ok_or_else(|| AppError::BadRequest("Invalid state parameter".to_string()))?;
+    let oauth_state = oauth_state
+        .filter(|s| s.expires_at > Utc::now())
+        .ok_or_else(|| AppError::BadRequest("Invalid state parameter".to_string()))?;
 
     if !oauth_state.is_admin_flow {
         return Err(AppError::BadRequest("Not an admin flow".to_string()));
     }
 
+    // Login CSRF: the callback must land in the browser that started the flow
+    if let Some(ref binding) = oauth_state.browser_binding {
+        if let Err(e) = BrowserBindingService::verify(&state.jwt_service, &headers, binding) {
+            SecurityEventService::record(
+                &state.pool,
+                SECURITY_EVENT_BROWSER_BINDING_MISMATCH,
+                "medium",
+                None,
+                None,
+                &client,
+                serde_json::json!({ "provider": provider_str, "admin_flow": true }),
+            )
+            .await?;
+            return Err(e);
+        }
+    }
+
     // Clean up OAuth state immediately to prevent replay attacks
     // Do this before token exchange so even if exchange fails, state cannot be reused
     if let Some(ref state_param) = callback.state {
@@ -1537,17 +2557,37 @@ async fn auth_admin_callback_impl(
 
     // Exchange code with PKCE verifier
     let pkce_verifier = oauth_state.pkce_verifier.as_deref();
-    let token_details =
-        exchange_admin_code(&admin_oauth_client, provider, &callback.code, pkce_verifier).await?;
+    let token_details = exchange_admin_code(
+        &admin_oauth_client,
+        provider,
+        &callback.code,
+        pkce_verifier,
+        oauth_state.nonce.as_deref(),
+    )
+    .await?;
 
     // Get user info from provider (standalone, not using OAuth client)
-    let user_info = get_provider_user_info(provider, &token_details.access_token).await?;
+    let mut user_info = get_provider_user_info(provider, &token_details.access_token).await?;
+    if let Some(ref id_token) = token_details.id_token {
+        IdTokenVerifier::check_subject(provider, id_token, &user_info)?;
+        user_info.email_verified |= IdTokenVerifier::email_verified(id_token, &user_info);
+    }
 
     // Find or create user
-    let user = find_or_create_user(&state.pool, &user_info.email).await?;
+    let user = find_or_create_user(&state.pool, provider, &user_info).await?;
+    DomainService::check_sign_in(&state.pool, &user, Some(provider), None).await?;
+    let audit = AuditContext {
+        actor_id: user.id.clone(),
+        impersonator_id: None,
+        api_token_id: None,
+        ip_address: client.ip_address.clone(),
+        user_agent: client.user_agent.clone(),
+        request_id: request_id.map(|Extension(RequestId(id))| id),
+    };
+    DomainService::auto_join(&state.pool, &audit, &user, &user_info).await?;
 
     // Update identity (admin flow always uses platform credentials, so issuing_org_id and issuing_service_id are None)
-    upsert_identity_with_details(
+    let identity = upsert_identity_with_details(
         &state.pool,
         state.encryption.as_ref(),
         &user.id,
@@ -1561,12 +2601,13 @@ async fn auth_admin_callback_impl(
         None,
     )
     .await?;
+    let user = ProfileService::sync_from_identity(&state.pool, &identity, &user_info).await?;
 
     // Check if this is a device flow completion - prioritize this over normal web login
     if let Some(ref user_code) = oauth_state.device_user_code {
         // Find the specific device code by user_code
         let device_code = sqlx::query_as::<_, DeviceCode>(
-            "SELECT * FROM device_codes WHERE user_code = ? AND status = 'pending'",
+            "SELECT * FROM device_codes WHERE user_code = ? AND status = 'pending' AND flow_type = 'device'",
         )
         .bind(user_code)
         .fetch_optional(&state.pool)
@@ -1574,11 +2615,15 @@ async fn auth_admin_callback_impl(
 
         if let Some(dc) = device_code {
             // Authorize the device code
-            sqlx::query!(
-                "UPDATE device_codes SET user_id = ?, status = 'authorized' WHERE id = ?",
-                user.id,
-                dc.id
+            sqlx::query(
+                "UPDATE device_codes
+                 SET user_id = ?, status = 'authorized', provider = ?, authenticated_at = ?
+                 WHERE id = ?",
             )
+            .bind(&user.id)
+            .bind(provider.as_str())
+            .bind(Utc::now())
+            .bind(&dc.id)
             .execute(&state.pool)
             .await?;
 
@@ -1621,7 +2666,7 @@ async fn auth_admin_callback_impl(
         // Create Platform JWT (no org or service claims)
         state
             .jwt_service
-            .create_token(&user.id, &user.email, true, None, None, None, None)?
+            .create_token(&user, None, None, None, None, None, None)?
     } else if let Some(org_slug) = &oauth_state.org_slug {
         // Check if user is a member of the requested organization
         let membership = sqlx::query_as::<_, crate::db::models::Membership>(
@@ -1637,20 +2682,20 @@ async fn auth_admin_callback_impl(
         if let Some(_membership) = membership {
             // Create Org Management JWT (org claim present, service claim null)
             state.jwt_service.create_token(
-                &user.id,
-                &user.email,
-                false,
+                &user,
                 Some(org_slug),
                 None,
                 None,
                 None,
+                None,
+                None,
             )?
         } else {
             // User is not a member - issue basic JWT so they can access signup page
             state.jwt_service.create_token(
-                &user.id,
-                &user.email,
-                false,
+                &user,
+                None,
+                None,
                 None,
                 None,
                 None,
@@ -1676,20 +2721,20 @@ async fn auth_admin_callback_impl(
         if let Some(first_membership) = memberships.first() {
             // User is a member of at least one org. Issue a token for the first one.
             state.jwt_service.create_token(
-                &user.id,
-                &user.email,
-                false,
+                &user,
                 Some(&first_membership.slug),
                 None,
                 None,
                 None,
+                None,
+                None,
             )?
         } else {
             // User is not a member of any org: Issue a basic JWT to prompt for creation.
             state.jwt_service.create_token(
-                &user.id,
-                &user.email,
-                false,
+                &user,
+                None,
+                None,
                 None,
                 None,
                 None,
@@ -1702,30 +2747,17 @@ async fn auth_admin_callback_impl(
     let refresh_token = Uuid::new_v4().to_string();
 
     // Store session with refresh token
-    let session_id = Uuid::new_v4().to_string();
-    let token_hash = JwtService::hash_token(&jwt);
-    let now = Utc::now();
-    let expires_at = now + chrono::Duration::hours(JWT_EXPIRE_HOURS);
-    let refresh_expires_at = now + chrono::Duration::days(30);
-    let created_at = now;
-
-    sqlx::query!(
-        r#"
-        INSERT INTO sessions
-        (id, user_id, token_hash, expires_at, refresh_token, refresh_token_expires_at, org_slug, service_id, created_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#,
-        session_id,
-        user.id,
-        token_hash,
-        expires_at,
-        refresh_token,
-        refresh_expires_at,
-        oauth_state.org_slug,
-        None::<String>,
-        created_at
+    create_session(
+        &state.pool,
+        &user.id,
+        &jwt,
+        &refresh_token,
+        oauth_state.org_slug.as_deref(),
+        None,
+        Some(provider),
+        Some(Utc::now()),
+        &client,
     )
-    .execute(&state.pool)
     .await?;
 
     // Load config for redirect URL
@@ -1740,14 +2772,14 @@ async fn auth_admin_callback_impl(
 // Helper functions for admin OAuth
 
 /// Unified OAuth client builder to reduce code duplication.
-/// Creates an OAuth2 BasicClient for any provider with the given credentials and callback URI.
+/// Creates an OAuth2 client for any provider with the given credentials and callback URI.
 fn build_oauth_client(
     provider: Provider,
     client_id: String,
     client_secret: String,
     callback_uri: String,
-) -> Result<oauth2::basic::BasicClient> {
-    use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+) -> Result<ProviderClient> {
+    use oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
 
     let (auth_url, token_url) = match provider {
         Provider::Github => (
@@ -1764,7 +2796,7 @@ fn build_oauth_client(
         ),
     };
 
-    Ok(BasicClient::new(
+    Ok(ProviderClient::new(
         ClientId::new(client_id),
         Some(ClientSecret::new(client_secret)),
         AuthUrl::new(auth_url.to_string()).map_err(|e| AppError::OAuth(e.to_string()))?,
@@ -1776,7 +2808,7 @@ fn build_oauth_client(
 fn create_admin_oauth_client(
     config: &crate::config::Config,
     provider: Provider,
-) -> Result<oauth2::basic::BasicClient> {
+) -> Result<ProviderClient> {
     let (client_id, client_secret) = match provider {
         Provider::Github => (
             config.platform_github_client_id.clone(),
@@ -1798,9 +2830,10 @@ fn create_admin_oauth_client(
 }
 
 fn get_admin_authorization_url(
-    client: &oauth2::basic::BasicClient,
+    client: &ProviderClient,
     provider: Provider,
     scopes: Vec<String>,
+    nonce: Option<&str>,
 ) -> (String, CsrfToken, String) {
     use oauth2::Scope;
 
@@ -1822,6 +2855,10 @@ fn get_admin_authorization_url(
         auth_request = auth_request.set_pkce_challenge(challenge);
     }
 
+    if let Some(nonce) = nonce {
+        auth_request = auth_request.add_extra_param("nonce", nonce);
+    }
+
     let (auth_url, csrf_token) = auth_request.url();
 
     let verifier_secret = pkce_verifier
@@ -1832,10 +2869,11 @@ fn get_admin_authorization_url(
 }
 
 async fn exchange_admin_code(
-    client: &oauth2::basic::BasicClient,
-    _provider: Provider,
+    client: &ProviderClient,
+    provider: Provider,
     code: &str,
     pkce_verifier: Option<&str>,
+    nonce: Option<&str>,
 ) -> Result<crate::auth::sso::TokenDetails> {
     use oauth2::{AuthorizationCode, TokenResponse};
 
@@ -1851,6 +2889,19 @@ async fn exchange_admin_code(
         .await
         .map_err(|e| AppError::OAuth(format!("Token exchange failed: {}", e)))?;
 
+    let id_token = match nonce {
+        Some(nonce) => Some(
+            IdTokenVerifier::verify(
+                provider,
+                token.extra_fields().id_token.as_deref(),
+                client.client_id(),
+                nonce,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
     let expires_at = token
         .expires_in()
         .map(|duration| Utc::now() + chrono::Duration::seconds(duration.as_secs() as i64));
@@ -1865,14 +2916,16 @@ async fn exchange_admin_code(
         refresh_token: token.refresh_token().map(|rt| rt.secret().clone()),
         expires_at,
         scopes,
+        id_token,
     })
 }
 
 async fn exchange_custom_code(
-    client: &oauth2::basic::BasicClient,
-    _provider: Provider,
+    client: &ProviderClient,
+    provider: Provider,
     code: &str,
     pkce_verifier: Option<&str>,
+    nonce: Option<&str>,
 ) -> Result<crate::auth::sso::TokenDetails> {
     use oauth2::{AuthorizationCode, TokenResponse};
 
@@ -1888,6 +2941,19 @@ async fn exchange_custom_code(
         .await
         .map_err(|e| AppError::OAuth(format!("Token exchange failed: {}", e)))?;
 
+    let id_token = match nonce {
+        Some(nonce) => Some(
+            IdTokenVerifier::verify(
+                provider,
+                token.extra_fields().id_token.as_deref(),
+                client.client_id(),
+                nonce,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
     let expires_at = token
         .expires_in()
         .map(|duration| Utc::now() + chrono::Duration::seconds(duration.as_secs() as i64));
@@ -1902,6 +2968,7 @@ async fn exchange_custom_code(
         refresh_token: token.refresh_token().map(|rt| rt.secret().clone()),
         expires_at,
         scopes,
+        id_token,
     })
 }
 
@@ -1924,12 +2991,31 @@ fn validate_redirect_uri(redirect_uri: &str, service: &crate::db::models::Servic
     Ok(())
 }
 
+fn validate_post_logout_redirect_uri(
+    redirect_uri: &str,
+    service: &crate::db::models::Service,
+) -> Result<()> {
+    // Unlike login redirects, logout redirects must always be registered explicitly
+    let allowed_uris: Vec<String> = service
+        .post_logout_redirect_uris
+        .as_deref()
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default();
+
+    if !allowed_uris.iter().any(|uri| uri == redirect_uri) {
+        return Err(AppError::BadRequest(
+            "post_logout_redirect_uri is not registered for this service".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 pub fn create_custom_oauth_client(
     config: &crate::config::Config,
     provider: Provider,
     client_id: &str,
     client_secret: &str,
-) -> Result<oauth2::basic::BasicClient> {
+) -> Result<ProviderClient> {
     let callback_uri = format!("{}/auth/{}/callback", config.base_url, provider.as_str());
     build_oauth_client(
         provider,
@@ -1940,9 +3026,10 @@ pub fn create_custom_oauth_client(
 }
 
 pub fn get_authorization_url_for_client(
-    client: &oauth2::basic::BasicClient,
+    client: &ProviderClient,
     provider: Provider,
     scopes: Vec<String>,
+    nonce: Option<&str>,
 ) -> (String, CsrfToken, String) {
     use oauth2::Scope;
 
@@ -1964,6 +3051,10 @@ pub fn get_authorization_url_for_client(
         auth_request = auth_request.set_pkce_challenge(challenge);
     }
 
+    if let Some(nonce) = nonce {
+        auth_request = auth_request.add_extra_param("nonce", nonce);
+    }
+
     let (auth_url, csrf_token) = auth_request.url();
 
     let verifier_secret = pkce_verifier
@@ -1982,8 +3073,10 @@ async fn get_provider_user_info(provider: Provider, access_token: &str) -> Resul
             #[derive(Deserialize)]
             struct GithubUser {
                 id: u64,
+                login: String,
                 email: Option<String>,
                 name: Option<String>,
+                avatar_url: Option<String>,
             }
 
             #[derive(Deserialize)]
@@ -2006,31 +3099,47 @@ async fn get_provider_user_info(provider: Provider, access_token: &str) -> Resul
                 .await
                 .map_err(|e| AppError::OAuth(format!("Failed to parse user: {}", e)))?;
 
-            let email = if let Some(email) = user.email {
-                email
-            } else {
-                let emails: Vec<GithubEmail> = client
+            // The public profile email carries no verification status, so
+            // check it against the email list (needs the user:email scope)
+            let emails: Result<Vec<GithubEmail>> = async {
+                client
                     .get("https://api.github.com/user/emails")
                     .header("Authorization", format!("Bearer {}", access_token))
                     .header("User-Agent", "SSO-Service")
                     .send()
                     .await
                     .map_err(|e| AppError::OAuth(format!("Failed to fetch emails: {}", e)))?
+                    .error_for_status()
+                    .map_err(|e| AppError::OAuth(format!("Failed to fetch emails: {}", e)))?
                     .json()
                     .await
-                    .map_err(|e| AppError::OAuth(format!("Failed to parse emails: {}", e)))?;
+                    .map_err(|e| AppError::OAuth(format!("Failed to parse emails: {}", e)))
+            }
+            .await;
 
-                emails
+            let (email, email_verified) = match (user.email, emails) {
+                (Some(email), Ok(emails)) => {
+                    let verified = emails.iter().any(|e| e.verified && e.email == email);
+                    (email, verified)
+                }
+                (Some(email), Err(_)) => (email, false),
+                (None, emails) => emails?
                     .into_iter()
                     .find(|e| e.primary && e.verified)
-                    .map(|e| e.email)
-                    .ok_or_else(|| AppError::OAuth("No verified email found".to_string()))?
+                    .map(|e| (e.email, true))
+                    .ok_or_else(|| AppError::OAuth("No verified email found".to_string()))?,
             };
 
             Ok(crate::auth::sso::UserInfo {
                 provider_user_id: user.id.to_string(),
                 email,
+                email_verified,
                 name: user.name,
+                given_name: None,
+                family_name: None,
+                avatar_url: user.avatar_url,
+                locale: None,
+                username: Some(user.login),
             })
         }
         Provider::Google => {
@@ -2038,7 +3147,13 @@ async fn get_provider_user_info(provider: Provider, access_token: &str) -> Resul
             struct GoogleUser {
                 id: String,
                 email: String,
+                #[serde(default)]
+                verified_email: bool,
                 name: Option<String>,
+                given_name: Option<String>,
+                family_name: Option<String>,
+                picture: Option<String>,
+                locale: Option<String>,
             }
 
             let client = reqwest::Client::new();
@@ -2055,7 +3170,13 @@ async fn get_provider_user_info(provider: Provider, access_token: &str) -> Resul
             Ok(crate::auth::sso::UserInfo {
                 provider_user_id: user.id,
                 email: user.email,
+                email_verified: user.verified_email,
                 name: user.name,
+                given_name: user.given_name,
+                family_name: user.family_name,
+                avatar_url: user.picture,
+                locale: user.locale,
+                username: None,
             })
         }
         Provider::Microsoft => {
@@ -2066,6 +3187,11 @@ async fn get_provider_user_info(provider: Provider, access_token: &str) -> Resul
                 email: String,
                 #[serde(rename = "displayName")]
                 name: Option<String>,
+                #[serde(rename = "givenName")]
+                given_name: Option<String>,
+                surname: Option<String>,
+                #[serde(rename = "preferredLanguage")]
+                locale: Option<String>,
             }
 
             let client = reqwest::Client::new();
@@ -2081,34 +3207,251 @@ async fn get_provider_user_info(provider: Provider, access_token: &str) -> Resul
 
             Ok(crate::auth::sso::UserInfo {
                 provider_user_id: user.id,
+                username: Some(user.email.clone()),
                 email: user.email,
+                // Graph does not say whether anyone verified the UPN; an ID
+                // token claim can still vouch for it (see IdTokenVerifier)
+                email_verified: false,
                 name: user.name,
+                given_name: user.given_name,
+                family_name: user.surname,
+                avatar_url: None, // Graph serves photos as binary only
+                locale: user.locale,
             })
         }
     }
 }
 
-/// Record login event for analytics
-async fn record_login_event(
-    pool: &SqlitePool,
+/// What happens to a login once the organization's risk policy has looked at it
+enum LoginDecision {
+    /// Issue tokens, then record the login with this assessment
+    Proceed(LoginRiskAssessment),
+    Blocked,
+    /// Tokens are issued once the user enters the emailed code
+    Challenged(String),
+}
+
+/// Assess a login before any token is issued. Blocked and challenged
+/// attempts are recorded here; logins that proceed are recorded by the
+/// caller once their session exists. Flows with no browser to enter a code
+/// in block logins the policy would challenge.
+async fn screen_login(
+    state: &AppState,
     user_id: &str,
     service_id: &str,
     provider: Provider,
-) -> Result<()> {
+    can_challenge: bool,
+    client: &ClientInfo,
+) -> Result<LoginDecision> {
+    let org_id: String = sqlx::query_scalar("SELECT org_id FROM services WHERE id = ?")
+        .bind(service_id)
+        .fetch_one(&state.pool)
+        .await?;
+    let risk =
+        LoginRiskService::assess(&state.pool, state.geoip.as_ref(), user_id, &org_id, client)
+            .await?;
+
+    match risk.action {
+        RiskAction::Allow | RiskAction::Notify => Ok(LoginDecision::Proceed(risk)),
+        RiskAction::RequireEmailCode if can_challenge => {
+            let mut tx = state.pool.begin().await?;
+            let event_id =
+                insert_login_event(&mut tx, user_id, service_id, provider, client, &risk, "challenged")
+                    .await?;
+            let challenge_id = LoginRiskService::create_challenge(
+                &mut tx, &event_id, user_id, service_id, &org_id, &risk, client,
+            )
+            .await?;
+            tx.commit().await?;
+
+            tracing::info!(user_id, score = risk.score, "Risky login needs a code");
+            Ok(LoginDecision::Challenged(challenge_id))
+        }
+        RiskAction::RequireEmailCode | RiskAction::Block => {
+            let mut tx = state.pool.begin().await?;
+            insert_login_event(&mut tx, user_id, service_id, provider, client, &risk, "blocked")
+                .await?;
+            SecurityEventService::record(
+                &mut *tx,
+                SECURITY_EVENT_LOGIN_BLOCKED,
+                "high",
+                Some(user_id),
+                Some(&org_id),
+                client,
+                serde_json::json!({
+                    "service_id": service_id,
+                    "risk_score": risk.score,
+                    "risk_reasons": risk.reasons,
+                    "country": risk.country(),
+                }),
+            )
+            .await?;
+            tx.commit().await?;
+
+            tracing::warn!(user_id, score = risk.score, "Blocked risky login");
+            Ok(LoginDecision::Blocked)
+        }
+    }
+}
+
+/// Send the browser back to the service with an OAuth-style error instead of tokens
+fn login_error_redirect(
+    redirect_uri: &str,
+    error: &str,
+    challenge_id: Option<&str>,
+) -> Result<Response> {
+    let mut error_url = url::Url::parse(redirect_uri)
+        .map_err(|_| AppError::BadRequest("Invalid redirect_uri".to_string()))?;
+    error_url.query_pairs_mut().append_pair("error", error);
+    if let Some(challenge_id) = challenge_id {
+        error_url
+            .query_pairs_mut()
+            .append_pair("challenge_id", challenge_id);
+    }
+    Ok(Redirect::to(error_url.as_str()).into_response())
+}
+
+/// End users cannot sign in to a suspended organization's services
+async fn ensure_org_active(pool: &SqlitePool, org_id: &str) -> Result<()> {
+    let status: String = sqlx::query_scalar("SELECT status FROM organizations WHERE id = ?")
+        .bind(org_id)
+        .fetch_one(pool)
+        .await?;
+    if status != "active" {
+        return Err(AppError::OrganizationNotActive);
+    }
+    Ok(())
+}
+
+/// Record a failed sign-in. The caller's error is what the client sees, so a
+/// failure to record is only logged.
+async fn record_login_failure(
+    state: &AppState,
+    attempt: &LoginAttempt,
+    client: &ClientInfo,
+    error: &AppError,
+) {
+    match LoginFailureService::record(&state.pool, &state.lockout_policy, attempt, client, error)
+        .await
+    {
+        Ok(reason) => tracing::info!("Sign-in failed ({}): {}", reason, error),
+        Err(e) => tracing::error!("Failed to record sign-in failure: {}", e),
+    }
+}
+
+async fn insert_login_event(
+    conn: &mut sqlx::SqliteConnection,
+    user_id: &str,
+    service_id: &str,
+    provider: Provider,
+    client: &ClientInfo,
+    risk: &LoginRiskAssessment,
+    outcome: &str,
+) -> Result<String> {
     let id = Uuid::new_v4().to_string();
-    let provider_str = provider.as_str();
+    let coordinates = risk.location.as_ref().and_then(|l| l.coordinates());
 
-    // Record login event (fire and forget - don't fail the login if this fails)
-    let _ = sqlx::query(
-        "INSERT INTO login_events (id, user_id, service_id, provider, created_at)
-         VALUES (?, ?, ?, ?, datetime('now'))"
+    sqlx::query(
+        "INSERT INTO login_events (id, user_id, service_id, provider, ip_address, user_agent, created_at,
+             outcome, device_fingerprint, country, latitude, longitude, risk_score, risk_reasons, risk_action)
+         VALUES (?, ?, ?, ?, ?, ?, datetime('now'), ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&id)
     .bind(user_id)
     .bind(service_id)
-    .bind(provider_str)
-    .execute(pool)
-    .await;
+    .bind(provider.as_str())
+    .bind(&client.ip_address)
+    .bind(&client.user_agent)
+    .bind(outcome)
+    .bind(&risk.device_fingerprint)
+    .bind(risk.country())
+    .bind(coordinates.map(|(lat, _)| lat))
+    .bind(coordinates.map(|(_, lon)| lon))
+    .bind(risk.score)
+    .bind(risk.reasons_json())
+    .bind(risk.action.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+/// Record a successful login for analytics, and email the user when the
+/// organization's policy asks for it
+async fn record_login_event(
+    pool: &SqlitePool,
+    user_id: &str,
+    service_id: &str,
+    provider: Provider,
+    client: &ClientInfo,
+    risk: &LoginRiskAssessment,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let id = insert_login_event(&mut tx, user_id, service_id, provider, client, risk, "success")
+        .await?;
+    let notify = (risk.action == RiskAction::Notify).then_some(risk);
+    complete_login_event(&mut tx, &id, notify).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Send what a successful login triggers: the new sign-in email for a
+/// login the policy says to notify about, and the `login.succeeded` webhook
+async fn complete_login_event(
+    conn: &mut sqlx::SqliteConnection,
+    login_event_id: &str,
+    notify: Option<&LoginRiskAssessment>,
+) -> Result<()> {
+    let event = sqlx::query_as::<_, crate::db::models::LoginEvent>(
+        "SELECT * FROM login_events WHERE id = ?",
+    )
+    .bind(login_event_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    let (org_id, service_name, email): (String, String, String) = sqlx::query_as(
+        "SELECT s.org_id, s.name, u.email FROM services s, users u WHERE s.id = ? AND u.id = ?",
+    )
+    .bind(&event.service_id)
+    .bind(&event.user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if let Some(risk) = notify {
+        EmailService::enqueue(
+            conn,
+            Some(&org_id),
+            TEMPLATE_NEW_DEVICE,
+            &email,
+            &[
+                ("service_name", &service_name),
+                ("signed_in_at", &Utc::now().format("%Y-%m-%d %H:%M UTC").to_string()),
+                ("user_agent", event.user_agent.as_deref().unwrap_or("unknown")),
+                ("ip_address", event.ip_address.as_deref().unwrap_or("unknown")),
+                ("location", &risk.location_text()),
+                ("reasons", &risk.reasons_text()),
+            ],
+        )
+        .await?;
+    }
+
+    WebhookService::emit(
+        conn,
+        &org_id,
+        WEBHOOK_EVENT_LOGIN_SUCCEEDED,
+        serde_json::json!({
+            "user_id": event.user_id,
+            "service_id": event.service_id,
+            "provider": event.provider,
+            "ip_address": event.ip_address,
+            "user_agent": event.user_agent,
+            "risk_score": event.risk_score,
+        }),
+    )
+    .await?;
 
     Ok(())
 }
+
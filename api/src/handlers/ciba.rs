@@ -0,0 +1,363 @@
+use crate::auth::api_tokens::ApiTokenService;
+use crate::auth::device_flow::DeviceFlowService;
+use crate::auth::webhooks::WebhookService;
+use crate::constants::{
+    CIBA_BINDING_MESSAGE_MAX_LENGTH, CIBA_REQUEST_EXPIRE_MINUTES, ORG_API_KEY_PREFIX,
+};
+use crate::db::models::{DeviceCode, Service, User};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::middleware::AuthUser;
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+
+// CIBA Authentication Request
+#[derive(Debug, Deserialize)]
+pub struct BackchannelAuthRequest {
+    pub client_id: String,
+    pub org: String,
+    pub service: String,
+    /// User email or user id
+    pub login_hint: String,
+    pub binding_message: Option<String>,
+    /// Required when the service has a CIBA notification endpoint (ping mode)
+    pub client_notification_token: Option<String>,
+}
+
+// CIBA Authentication Response
+#[derive(Debug, Serialize)]
+pub struct BackchannelAuthResponse {
+    pub auth_req_id: String,
+    pub expires_in: i64,
+    pub interval: i64,
+}
+
+// Pending request as shown to the user (never exposes the auth_req_id)
+#[derive(Debug, Serialize)]
+pub struct PendingBackchannelRequest {
+    pub id: String,
+    pub org_slug: String,
+    pub service_slug: String,
+    pub binding_message: Option<String>,
+    pub user_code: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<DeviceCode> for PendingBackchannelRequest {
+    fn from(request: DeviceCode) -> Self {
+        Self {
+            id: request.id,
+            org_slug: request.org_slug,
+            service_slug: request.service_slug,
+            binding_message: request.binding_message,
+            user_code: request.user_code,
+            created_at: request.created_at,
+            expires_at: request.expires_at,
+        }
+    }
+}
+
+/// Services authenticate with an API key of the organization that owns them
+/// carrying the `ciba` scope. The client_id alone is public.
+async fn authenticate_client(
+    pool: &SqlitePool,
+    headers: &HeaderMap,
+    service: &Service,
+) -> Result<()> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .filter(|token| token.starts_with(ORG_API_KEY_PREFIX))
+        .ok_or_else(|| {
+            AppError::Unauthorized(
+                "Backchannel authentication requires an organization API key".to_string(),
+            )
+        })?;
+
+    let (api_token, _, _) = ApiTokenService::authenticate(pool, token).await?;
+    if api_token.org_id.as_deref() != Some(service.org_id.as_str())
+        || !ApiTokenService::parse_scopes(&api_token.scopes)
+            .iter()
+            .any(|s| s == "ciba")
+    {
+        return Err(AppError::Unauthorized(
+            "Invalid client credentials".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The user a login_hint (email or user id) names. Same error for any hint we
+/// cannot act on, so callers cannot probe for accounts.
+async fn find_hinted_user(pool: &SqlitePool, login_hint: &str) -> Result<User> {
+    let login_hint = login_hint.trim();
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ? OR email = ?")
+        .bind(login_hint)
+        .bind(login_hint)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Invalid login_hint".to_string()))
+}
+
+/// POST /auth/bc-authorize
+/// Service starts a backchannel login for a user who is not at the calling device
+pub async fn backchannel_authorize(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<BackchannelAuthRequest>,
+) -> Result<Json<BackchannelAuthResponse>> {
+    let service = sqlx::query_as::<_, Service>(
+        r#"
+        SELECT s.* FROM services s
+        JOIN organizations o ON s.org_id = o.id
+        WHERE s.client_id = ? AND o.slug = ? AND s.slug = ?
+        "#,
+    )
+    .bind(&req.client_id)
+    .bind(&req.org)
+    .bind(&req.service)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid client credentials".to_string()))?;
+    authenticate_client(&state.pool, &headers, &service).await?;
+
+    if let Some(ref message) = req.binding_message {
+        if message.len() > CIBA_BINDING_MESSAGE_MAX_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "Binding message must be at most {} characters",
+                CIBA_BINDING_MESSAGE_MAX_LENGTH
+            )));
+        }
+    }
+
+    // Ping mode services must give us a token to present on the callback
+    let notification_token = if service.ciba_notification_endpoint.is_some() {
+        Some(req.client_notification_token.as_deref().ok_or_else(|| {
+            AppError::BadRequest("client_notification_token is required".to_string())
+        })?)
+    } else {
+        None
+    };
+
+    let user = find_hinted_user(&state.pool, &req.login_hint).await?;
+
+    let request = DeviceFlowService::create_backchannel_request(
+        &state.pool,
+        &req.client_id,
+        &req.org,
+        &req.service,
+        &user.id,
+        req.binding_message.as_deref(),
+        notification_token,
+    )
+    .await?;
+
+    Ok(Json(BackchannelAuthResponse {
+        auth_req_id: request.device_code,
+        expires_in: CIBA_REQUEST_EXPIRE_MINUTES * 60,
+        interval: 5,
+    }))
+}
+
+/// GET /api/user/backchannel-requests
+/// List login requests waiting for the current user's approval
+pub async fn list_backchannel_requests(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<PendingBackchannelRequest>>> {
+    let requests =
+        DeviceFlowService::list_pending_backchannel_requests(&state.pool, &auth_user.user.id)
+            .await?;
+
+    Ok(Json(requests.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/user/backchannel-requests/:id/approve
+pub async fn approve_backchannel_request(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    resolve_backchannel_request(&state.pool, &id, &auth_user, true).await
+}
+
+/// POST /api/user/backchannel-requests/:id/deny
+pub async fn deny_backchannel_request(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    resolve_backchannel_request(&state.pool, &id, &auth_user, false).await
+}
+
+async fn resolve_backchannel_request(
+    pool: &SqlitePool,
+    id: &str,
+    auth_user: &AuthUser,
+    approve: bool,
+) -> Result<Json<serde_json::Value>> {
+    let request = DeviceFlowService::resolve_backchannel_request(
+        pool,
+        id,
+        &auth_user.user.id,
+        &auth_user.session_id,
+        approve,
+    )
+    .await?;
+
+    notify_client(pool, &request).await;
+
+    Ok(Json(json!({
+        "message": if approve { "Request approved" } else { "Request denied" },
+        "status": request.status,
+    })))
+}
+
+/// Ping the service's notification endpoint so it can collect the result from /auth/token
+async fn notify_client(pool: &SqlitePool, request: &DeviceCode) {
+    let Some(ref token) = request.client_notification_token else {
+        return;
+    };
+
+    let endpoint = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT s.ciba_notification_endpoint FROM services s
+         JOIN organizations o ON s.org_id = o.id
+         WHERE o.slug = ? AND s.slug = ?",
+    )
+    .bind(&request.org_slug)
+    .bind(&request.service_slug)
+    .fetch_optional(pool)
+    .await
+    .ok()
+    .flatten()
+    .flatten();
+
+    let Some(endpoint) = endpoint else {
+        return;
+    };
+
+    let token = token.clone();
+    let auth_req_id = request.device_code.clone();
+    tokio::spawn(async move {
+        // The host is resolved again for every ping and must still be public
+        let client = match WebhookService::pinned_client(&endpoint).await {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::warn!("CIBA ping to {} refused: {}", endpoint, e);
+                return;
+            }
+        };
+        let result = client
+            .post(&endpoint)
+            .bearer_auth(token)
+            .json(&json!({ "auth_req_id": auth_req_id }))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => tracing::warn!(
+                "CIBA ping to {} returned status {}",
+                endpoint,
+                response.status()
+            ),
+            Err(e) => tracing::warn!("CIBA ping to {} failed: {}", endpoint, e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_org, insert_service, insert_user, test_pool};
+    use axum::http::HeaderValue;
+    use chrono::Duration;
+
+    async fn create_key(pool: &SqlitePool, org_id: &str, kind: &str, scopes: &[&str]) -> String {
+        let account = ApiTokenService::create_service_account(pool, "acme")
+            .await
+            .unwrap();
+        let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        let (_, token) = ApiTokenService::create(
+            pool,
+            kind,
+            "backchannel",
+            &account.id,
+            (kind == "org").then_some(org_id),
+            (kind == "org").then_some("member"),
+            &account.id,
+            &scopes,
+            Utc::now() + Duration::days(1),
+        )
+        .await
+        .unwrap();
+        token
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_client_needs_org_key_with_ciba_scope() {
+        let pool = test_pool().await;
+        let owner = insert_user(&pool, "owner@acme.com").await;
+        let org = insert_org(&pool, "acme", &owner).await;
+        let other_org = insert_org(&pool, "globex", &owner).await;
+        let service = insert_service(&pool, &org, "billing").await;
+
+        let ciba_key = create_key(&pool, &org.id, "org", &["ciba"]).await;
+        let read_key = create_key(&pool, &org.id, "org", &["read"]).await;
+        let other_key = create_key(&pool, &other_org.id, "org", &["ciba"]).await;
+        let personal = create_key(&pool, &org.id, "personal", &["ciba"]).await;
+
+        assert!(authenticate_client(&pool, &bearer(&ciba_key), &service)
+            .await
+            .is_ok());
+        for headers in [
+            HeaderMap::new(),
+            bearer(&read_key),
+            bearer(&other_key),
+            bearer(&personal),
+        ] {
+            assert!(matches!(
+                authenticate_client(&pool, &headers, &service).await,
+                Err(AppError::Unauthorized(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_login_hints_get_the_same_error() {
+        let pool = test_pool().await;
+        let user = insert_user(&pool, "ada@acme.com").await;
+
+        assert_eq!(
+            find_hinted_user(&pool, " ada@acme.com ").await.unwrap().id,
+            user.id
+        );
+        assert_eq!(find_hinted_user(&pool, &user.id).await.unwrap().id, user.id);
+
+        let by_email = find_hinted_user(&pool, "nobody@acme.com")
+            .await
+            .unwrap_err();
+        let by_id = find_hinted_user(&pool, "no-such-id").await.unwrap_err();
+        assert_eq!(by_email.to_string(), by_id.to_string());
+        assert!(matches!(by_email, AppError::BadRequest(_)));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::auth::domains::DomainService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::auth::sso::Provider;
+use crate::db::models::{Membership, Organization, OrganizationDomain};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct AddDomainRequest {
+    pub domain: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDomainRequest {
+    pub auto_join: Option<bool>,
+    pub default_role: Option<String>,
+    pub enforce_sso: Option<bool>,
+    pub required_provider: Option<String>,
+    pub require_enterprise_connection: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationRecord {
+    pub record_type: &'static str,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DomainResponse {
+    pub id: String,
+    pub domain: String,
+    pub verified: bool,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub verification_record: VerificationRecord,
+    pub auto_join: bool,
+    pub default_role: String,
+    pub enforce_sso: bool,
+    pub required_provider: Option<String>,
+    pub require_enterprise_connection: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OrganizationDomain> for DomainResponse {
+    fn from(domain: OrganizationDomain) -> Self {
+        Self {
+            verification_record: VerificationRecord {
+                record_type: "TXT",
+                name: DomainService::record_name(&domain.domain),
+                value: DomainService::record_value(&domain.verification_token),
+            },
+            id: domain.id,
+            domain: domain.domain,
+            verified: domain.verified_at.is_some(),
+            verified_at: domain.verified_at,
+            last_checked_at: domain.last_checked_at,
+            auto_join: domain.auto_join,
+            default_role: domain.default_role,
+            enforce_sso: domain.enforce_sso,
+            required_provider: domain.required_provider,
+            require_enterprise_connection: domain.require_enterprise_connection,
+            created_at: domain.created_at,
+        }
+    }
+}
+
+async fn find_organization(
+    pool: &SqlitePool,
+    auth_user: &AuthUser,
+    org_slug: &str,
+) -> Result<(Organization, Membership)> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    let membership = crate::middleware::check_org_permission(
+        pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_domains",
+    )
+    .await?;
+
+    Ok((org, membership))
+}
+
+async fn find_domain(pool: &SqlitePool, org_id: &str, domain: &str) -> Result<OrganizationDomain> {
+    sqlx::query_as::<_, OrganizationDomain>(
+        "SELECT * FROM organization_domains WHERE org_id = ? AND domain = ?",
+    )
+    .bind(org_id)
+    .bind(domain.to_ascii_lowercase())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Domain not found".to_string()))
+}
+
+/// GET /api/organizations/:org_slug/domains
+pub async fn list_domains(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<Vec<DomainResponse>>> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+
+    let domains = sqlx::query_as::<_, OrganizationDomain>(
+        "SELECT * FROM organization_domains WHERE org_id = ? ORDER BY domain",
+    )
+    .bind(&org.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(domains.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/organizations/:org_slug/domains
+/// Claim a domain; the response names the TXT record that verifies it
+pub async fn add_domain(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Json(req): Json<AddDomainRequest>,
+) -> Result<(StatusCode, Json<DomainResponse>)> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let domain = DomainService::normalize(&req.domain)?;
+
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM organization_domains
+         WHERE domain = ? AND (org_id = ? OR verified_at IS NOT NULL)",
+    )
+    .bind(&domain)
+    .bind(&org.id)
+    .fetch_one(&state.pool)
+    .await?;
+    if existing > 0 {
+        return Err(AppError::BadRequest(
+            "This domain is already claimed".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    let created = sqlx::query_as::<_, OrganizationDomain>(
+        r#"
+        INSERT INTO organization_domains
+        (id, org_id, domain, verification_token, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&org.id)
+    .bind(&domain)
+    .bind(DomainService::generate_token())
+    .bind(now)
+    .bind(now)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "domain_added",
+        "domain",
+        &created.domain,
+        diff(&serde_json::Value::Null, &json!(created)),
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(created.into())))
+}
+
+/// POST /api/organizations/:org_slug/domains/:domain/verify
+/// Check DNS for the verification record
+pub async fn verify_domain(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, domain)): Path<(String, String)>,
+) -> Result<Json<DomainResponse>> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let domain = find_domain(&state.pool, &org.id, &domain).await?;
+
+    let verified = DomainService::verify(&state.pool, state.dns_resolver.as_ref(), &domain).await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "domain_verified",
+        "domain",
+        &verified.domain,
+        diff(&json!(domain), &json!(verified)),
+    )
+    .await?;
+
+    Ok(Json(verified.into()))
+}
+
+/// PATCH /api/organizations/:org_slug/domains/:domain
+/// Configure auto-join and enforced sign-in for a domain
+pub async fn update_domain(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, domain)): Path<(String, String)>,
+    Json(req): Json<UpdateDomainRequest>,
+) -> Result<Json<DomainResponse>> {
+    let (org, membership) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let domain = find_domain(&state.pool, &org.id, &domain).await?;
+    let before = json!(domain);
+
+    let auto_join = req.auto_join.unwrap_or(domain.auto_join);
+    let enforce_sso = req.enforce_sso.unwrap_or(domain.enforce_sso);
+    if (auto_join || enforce_sso) && domain.verified_at.is_none() {
+        return Err(AppError::BadRequest(
+            "Verify the domain before enabling auto-join or enforced sign-in".to_string(),
+        ));
+    }
+
+    let default_role = match req.default_role {
+        Some(role) => {
+            if role == "owner" || !OrgRoleService::role_exists(&state.pool, &org.id, &role).await? {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid default role '{}'",
+                    role
+                )));
+            }
+            // Auto-joined members cannot get more than the caller holds
+            let permissions =
+                OrgRoleService::permissions_for_role(&state.pool, &org.id, &role).await?;
+            OrgRoleService::ensure_can_grant(&state.pool, &org.id, &membership.role, &permissions)
+                .await?;
+            role
+        }
+        None => domain.default_role,
+    };
+
+    // An empty string clears the provider
+    let required_provider = match req.required_provider.as_deref() {
+        Some("") => None,
+        Some(provider) => Some(Provider::from_str(provider)?.as_str().to_string()),
+        None => domain.required_provider,
+    };
+    if enforce_sso && required_provider.is_none() {
+        return Err(AppError::BadRequest(
+            "Enforced sign-in needs a required_provider".to_string(),
+        ));
+    }
+
+    // The enterprise connection is the organization's own OAuth app for the
+    // required provider, so it has to exist before it can be required
+    let require_enterprise_connection = req
+        .require_enterprise_connection
+        .unwrap_or(domain.require_enterprise_connection);
+    if require_enterprise_connection {
+        let Some(provider) = required_provider.as_ref().filter(|_| enforce_sso) else {
+            return Err(AppError::BadRequest(
+                "Requiring the enterprise connection needs enforced sign-in".to_string(),
+            ));
+        };
+        let connected: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM organization_oauth_credentials WHERE org_id = ? AND provider = ?",
+        )
+        .bind(&org.id)
+        .bind(provider)
+        .fetch_one(&state.pool)
+        .await?;
+        if connected == 0 {
+            return Err(AppError::BadRequest(format!(
+                "Set the organization's {} OAuth credentials before requiring them",
+                provider
+            )));
+        }
+    }
+
+    let updated = sqlx::query_as::<_, OrganizationDomain>(
+        r#"
+        UPDATE organization_domains
+        SET auto_join = ?, default_role = ?, enforce_sso = ?, required_provider = ?,
+            require_enterprise_connection = ?, updated_at = ?
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(auto_join)
+    .bind(&default_role)
+    .bind(enforce_sso)
+    .bind(&required_provider)
+    .bind(require_enterprise_connection)
+    .bind(Utc::now())
+    .bind(&domain.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "domain_updated",
+        "domain",
+        &updated.domain,
+        diff(&before, &json!(updated)),
+    )
+    .await?;
+
+    Ok(Json(updated.into()))
+}
+
+/// DELETE /api/organizations/:org_slug/domains/:domain
+/// Release a domain claim; existing memberships are kept
+pub async fn delete_domain(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, domain)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let domain = find_domain(&state.pool, &org.id, &domain).await?;
+
+    sqlx::query("DELETE FROM organization_domains WHERE id = ?")
+        .bind(&domain.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "domain_removed",
+        "domain",
+        &domain.domain,
+        diff(&json!(domain), &serde_json::Value::Null),
+    )
+    .await?;
+
+    Ok(Json(json!({
+        "message": "Domain removed"
+    })))
+}
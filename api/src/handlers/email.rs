@@ -0,0 +1,399 @@
+use crate::auth::email::{EmailService, EmailTemplate, EMAIL_TEMPLATES};
+use crate::db::models::{
+    EmailOutboxMessage, EmailSuppression, EmailTemplateOverride, Organization,
+};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::handlers::platform::create_audit_log;
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateEmailTemplateRequest {
+    pub subject: String,
+    pub body_text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailTemplateResponse {
+    pub template: &'static str,
+    pub subject: String,
+    pub body_text: String,
+    pub variables: &'static [&'static str],
+    pub customized: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl EmailTemplateResponse {
+    fn new(template: &'static EmailTemplate, custom: Option<EmailTemplateOverride>) -> Self {
+        match custom {
+            Some(custom) => Self {
+                template: template.name,
+                subject: custom.subject,
+                body_text: custom.body_text,
+                variables: template.variables,
+                customized: true,
+                updated_at: Some(custom.updated_at),
+            },
+            None => Self {
+                template: template.name,
+                subject: template.subject.to_string(),
+                body_text: template.body_text.to_string(),
+                variables: template.variables,
+                customized: false,
+                updated_at: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxQuery {
+    pub status: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutboxResponse {
+    pub messages: Vec<EmailOutboxMessage>,
+    pub total: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSuppressionRequest {
+    pub email: String,
+    pub reason: Option<String>,
+}
+
+async fn find_organization(
+    pool: &SqlitePool,
+    auth_user: &AuthUser,
+    org_slug: &str,
+) -> Result<Organization> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(
+        pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_organization",
+    )
+    .await?;
+
+    Ok(org)
+}
+
+async fn find_override(
+    pool: &SqlitePool,
+    org_id: &str,
+    template: &str,
+) -> Result<Option<EmailTemplateOverride>> {
+    Ok(sqlx::query_as::<_, EmailTemplateOverride>(
+        "SELECT * FROM email_templates WHERE org_id = ? AND template = ?",
+    )
+    .bind(org_id)
+    .bind(template)
+    .fetch_optional(pool)
+    .await?)
+}
+
+fn require_platform_owner(auth_user: &AuthUser) -> Result<()> {
+    if !auth_user.user.is_platform_owner {
+        return Err(AppError::Forbidden(
+            "Platform owner access required".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/organizations/:org_slug/email-templates
+/// List the organization's effective templates
+pub async fn list_email_templates(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<Vec<EmailTemplateResponse>>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+
+    let mut overrides = sqlx::query_as::<_, EmailTemplateOverride>(
+        "SELECT * FROM email_templates WHERE org_id = ?",
+    )
+    .bind(&org.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let templates = EMAIL_TEMPLATES
+        .iter()
+        .map(|template| {
+            let custom = overrides
+                .iter()
+                .position(|custom| custom.template == template.name)
+                .map(|index| overrides.swap_remove(index));
+            EmailTemplateResponse::new(template, custom)
+        })
+        .collect();
+
+    Ok(Json(templates))
+}
+
+/// GET /api/organizations/:org_slug/email-templates/:template
+pub async fn get_email_template(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, template)): Path<(String, String)>,
+) -> Result<Json<EmailTemplateResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let template = EmailService::template(&template)?;
+    let custom = find_override(&state.pool, &org.id, template.name).await?;
+
+    Ok(Json(EmailTemplateResponse::new(template, custom)))
+}
+
+/// PUT /api/organizations/:org_slug/email-templates/:template
+/// Override a built-in template for this organization
+pub async fn update_email_template(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, template)): Path<(String, String)>,
+    Json(req): Json<UpdateEmailTemplateRequest>,
+) -> Result<Json<EmailTemplateResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let template = EmailService::template(&template)?;
+    EmailService::validate_override(template, &req.subject, &req.body_text)?;
+    let before = EmailTemplateResponse::new(
+        template,
+        find_override(&state.pool, &org.id, template.name).await?,
+    );
+
+    let custom = sqlx::query_as::<_, EmailTemplateOverride>(
+        r#"
+        INSERT INTO email_templates (org_id, template, subject, body_text, updated_by, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(org_id, template) DO UPDATE SET
+            subject = excluded.subject,
+            body_text = excluded.body_text,
+            updated_by = excluded.updated_by,
+            updated_at = excluded.updated_at
+        RETURNING *
+        "#,
+    )
+    .bind(&org.id)
+    .bind(template.name)
+    .bind(req.subject.trim())
+    .bind(&req.body_text)
+    .bind(&auth_user.user.id)
+    .bind(Utc::now())
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    let after = EmailTemplateResponse::new(template, Some(custom));
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "email_template_updated",
+        "email_template",
+        template.name,
+        diff(&json!(before), &json!(after)),
+    )
+    .await?;
+
+    Ok(Json(after))
+}
+
+/// DELETE /api/organizations/:org_slug/email-templates/:template
+/// Go back to the built-in template
+pub async fn reset_email_template(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, template)): Path<(String, String)>,
+) -> Result<Json<EmailTemplateResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let template = EmailService::template(&template)?;
+    let before = EmailTemplateResponse::new(
+        template,
+        find_override(&state.pool, &org.id, template.name).await?,
+    );
+
+    sqlx::query("DELETE FROM email_templates WHERE org_id = ? AND template = ?")
+        .bind(&org.id)
+        .bind(template.name)
+        .execute(&state.pool)
+        .await?;
+
+    let after = EmailTemplateResponse::new(template, None);
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "email_template_reset",
+        "email_template",
+        template.name,
+        diff(&json!(before), &json!(after)),
+    )
+    .await?;
+
+    Ok(Json(after))
+}
+
+/// GET /api/platform/email/outbox
+/// Inspect queued and delivered messages
+pub async fn list_outbox(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<OutboxQuery>,
+) -> Result<Json<OutboxResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    let limit = query.limit.unwrap_or(50).min(100);
+    let offset = query.offset.unwrap_or(0);
+
+    let condition = "(? IS NULL OR status = ?) AND (? IS NULL OR to_address = ? COLLATE NOCASE)";
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM email_outbox WHERE {}",
+        condition
+    ))
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(&query.to)
+    .bind(&query.to)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let messages = sqlx::query_as::<_, EmailOutboxMessage>(&format!(
+        "SELECT * FROM email_outbox WHERE {} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        condition
+    ))
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(&query.to)
+    .bind(&query.to)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(OutboxResponse { messages, total }))
+}
+
+/// POST /api/platform/email/outbox/:id/retry
+/// Queue a message that gave up after its retries for another round
+pub async fn retry_outbox_message(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(message_id): Path<String>,
+) -> Result<Json<EmailOutboxMessage>> {
+    require_platform_owner(&auth_user)?;
+
+    let message = sqlx::query_as::<_, EmailOutboxMessage>(
+        "UPDATE email_outbox
+         SET status = 'pending', attempts = 0, next_attempt_at = ?, updated_at = ?
+         WHERE id = ? AND status = 'failed'
+         RETURNING *",
+    )
+    .bind(Utc::now())
+    .bind(Utc::now())
+    .bind(&message_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("No failed message with this id".to_string()))?;
+
+    Ok(Json(message))
+}
+
+/// GET /api/platform/email/suppressions
+pub async fn list_suppressions(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<EmailSuppression>>> {
+    require_platform_owner(&auth_user)?;
+
+    let suppressions = sqlx::query_as::<_, EmailSuppression>(
+        "SELECT * FROM email_suppressions ORDER BY created_at DESC",
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(suppressions))
+}
+
+/// POST /api/platform/email/suppressions
+/// Record a bounce or complaint reported after delivery
+pub async fn create_suppression(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CreateSuppressionRequest>,
+) -> Result<Json<serde_json::Value>> {
+    require_platform_owner(&auth_user)?;
+
+    let email = req.email.trim();
+    if !email.contains('@') {
+        return Err(AppError::BadRequest("Invalid email address".to_string()));
+    }
+    let reason = req.reason.as_deref().unwrap_or("Added by platform owner");
+
+    EmailService::suppress(&state.pool, email, reason).await?;
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "suppress_email",
+        "email",
+        email,
+        Some(json!({ "reason": reason })),
+    )
+    .await?;
+
+    Ok(Json(json!({
+        "message": "Address suppressed"
+    })))
+}
+
+/// DELETE /api/platform/email/suppressions/:email
+/// Allow mail to a previously bounced address again
+pub async fn delete_suppression(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(email): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    require_platform_owner(&auth_user)?;
+
+    let deleted = sqlx::query("DELETE FROM email_suppressions WHERE email = ?")
+        .bind(&email)
+        .execute(&state.pool)
+        .await?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(AppError::NotFound("Address is not suppressed".to_string()));
+    }
+
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "unsuppress_email",
+        "email",
+        &email,
+        None,
+    )
+    .await?;
+
+    Ok(Json(json!({
+        "message": "Suppression removed"
+    })))
+}
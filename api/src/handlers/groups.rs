@@ -0,0 +1,345 @@
+use crate::constants::{MAX_NAME_LENGTH, MIN_NAME_LENGTH};
+use crate::db::models::{Organization, User, UserGroup};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddGroupMemberRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GroupResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub member_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GroupMemberResponse {
+    pub user_id: String,
+    pub email: String,
+    pub added_at: DateTime<Utc>,
+}
+
+async fn find_organization(pool: &SqlitePool, org_slug: &str) -> Result<Organization> {
+    sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
+
+/// Look up a group that belongs to the organization
+pub async fn find_group(pool: &SqlitePool, org_id: &str, group_id: &str) -> Result<UserGroup> {
+    sqlx::query_as::<_, UserGroup>("SELECT * FROM user_groups WHERE id = ? AND org_id = ?")
+        .bind(group_id)
+        .bind(org_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Group not found".to_string()))
+}
+
+/// Look up a user who belongs to the organization: a member, or an end-user
+/// with a subscription or identity there. Ids from other tenants are not found.
+pub async fn find_org_user(pool: &SqlitePool, org_id: &str, user_id: &str) -> Result<User> {
+    sqlx::query_as::<_, User>(
+        "SELECT * FROM users u WHERE u.id = ? AND (
+             EXISTS (SELECT 1 FROM memberships m WHERE m.user_id = u.id AND m.org_id = ?)
+             OR EXISTS (SELECT 1 FROM subscriptions sub JOIN services s ON sub.service_id = s.id
+                        WHERE sub.user_id = u.id AND s.org_id = ?)
+             OR EXISTS (SELECT 1 FROM identities i WHERE i.user_id = u.id AND i.issuing_org_id = ?)
+         )",
+    )
+    .bind(user_id)
+    .bind(org_id)
+    .bind(org_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+/// GET /api/organizations/:org_slug/groups
+pub async fn list_groups(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<Vec<GroupResponse>>> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[]).await?;
+
+    let groups = sqlx::query_as::<_, GroupResponse>(
+        r#"
+        SELECT g.id, g.name, g.description, g.created_at,
+               (SELECT COUNT(*) FROM user_group_members gm WHERE gm.group_id = g.id) as member_count
+        FROM user_groups g
+        WHERE g.org_id = ?
+        ORDER BY g.name
+        "#,
+    )
+    .bind(&org.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(groups))
+}
+
+/// POST /api/organizations/:org_slug/groups
+pub async fn create_group(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Json(req): Json<CreateGroupRequest>,
+) -> Result<(StatusCode, Json<GroupResponse>)> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let name = req.name.trim();
+    if name.len() < MIN_NAME_LENGTH || name.len() > MAX_NAME_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "Group name must be between {} and {} characters",
+            MIN_NAME_LENGTH, MAX_NAME_LENGTH
+        )));
+    }
+
+    let exists: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM user_groups WHERE org_id = ? AND name = ?")
+            .bind(&org.id)
+            .bind(name)
+            .fetch_one(&state.pool)
+            .await?;
+    if exists > 0 {
+        return Err(AppError::BadRequest(
+            "A group with this name already exists".to_string(),
+        ));
+    }
+
+    let group = sqlx::query_as::<_, UserGroup>(
+        r#"
+        INSERT INTO user_groups (id, org_id, name, description, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&org.id)
+    .bind(name)
+    .bind(&req.description)
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "group_created",
+        "group",
+        &group.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!({ "name": group.name, "description": group.description }),
+        ),
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(GroupResponse {
+            id: group.id,
+            name: group.name,
+            description: group.description,
+            member_count: 0,
+            created_at: group.created_at,
+        }),
+    ))
+}
+
+/// DELETE /api/organizations/:org_slug/groups/:group_id
+/// Role assignments made through the group are removed with it
+pub async fn delete_group(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, group_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let group = find_group(&state.pool, &org.id, &group_id).await?;
+
+    sqlx::query("DELETE FROM user_groups WHERE id = ?")
+        .bind(&group.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "group_deleted",
+        "group",
+        &group.id,
+        diff(
+            &serde_json::json!({ "name": group.name, "description": group.description }),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/organizations/:org_slug/groups/:group_id/members
+pub async fn list_group_members(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, group_id)): Path<(String, String)>,
+) -> Result<Json<Vec<GroupMemberResponse>>> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[]).await?;
+
+    let group = find_group(&state.pool, &org.id, &group_id).await?;
+
+    let members = sqlx::query_as::<_, GroupMemberResponse>(
+        r#"
+        SELECT u.id as user_id, u.email, gm.created_at as added_at
+        FROM user_group_members gm
+        JOIN users u ON gm.user_id = u.id
+        WHERE gm.group_id = ?
+        ORDER BY u.email
+        "#,
+    )
+    .bind(&group.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(members))
+}
+
+/// POST /api/organizations/:org_slug/groups/:group_id/members
+pub async fn add_group_member(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, group_id)): Path<(String, String)>,
+    Json(req): Json<AddGroupMemberRequest>,
+) -> Result<StatusCode> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let group = find_group(&state.pool, &org.id, &group_id).await?;
+
+    let user = find_org_user(&state.pool, &org.id, &req.user_id).await?;
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO user_group_members (group_id, user_id, created_at) VALUES (?, ?, ?)",
+    )
+    .bind(&group.id)
+    .bind(&user.id)
+    .bind(Utc::now())
+    .execute(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "group_member_added",
+        "group",
+        &group.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!({ "user_id": user.id }),
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/organizations/:org_slug/groups/:group_id/members/:user_id
+pub async fn remove_group_member(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, group_id, user_id)): Path<(String, String, String)>,
+) -> Result<StatusCode> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let group = find_group(&state.pool, &org.id, &group_id).await?;
+
+    let result = sqlx::query("DELETE FROM user_group_members WHERE group_id = ? AND user_id = ?")
+        .bind(&group.id)
+        .bind(&user_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(
+            "User is not a member of this group".to_string(),
+        ));
+    }
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "group_member_removed",
+        "group",
+        &group.id,
+        diff(
+            &serde_json::json!({ "user_id": user_id }),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,8 +1,13 @@
+use crate::auth::browser_binding::BrowserBindingService;
+use crate::auth::id_token::IdTokenVerifier;
 use crate::auth::sso::Provider;
+use crate::auth::webhooks::WebhookService;
+use crate::constants::WEBHOOK_EVENT_IDENTITY_UNLINKED;
 use crate::error::{AppError, Result};
-use crate::handlers::auth::{create_custom_oauth_client, get_authorization_url_for_client, get_provider_scopes, AppState};
+use crate::handlers::auth::{create_custom_oauth_client, get_authorization_url_for_client, get_provider_scopes, identity_event_orgs, AppState};
 use axum::{
     extract::{Path, State},
+    http::{header::SET_COOKIE, HeaderMap},
     Json,
 };
 use chrono::Utc;
@@ -11,7 +16,12 @@ use sqlx::SqlitePool;
 
 #[derive(Debug, Serialize)]
 pub struct IdentityResponse {
+    pub id: String,
     pub provider: String,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub is_primary: bool, // the user's profile follows this identity
 }
 
 #[derive(Debug, Serialize)]
@@ -92,7 +102,12 @@ pub async fn list_identities(
     let response: Vec<IdentityResponse> = identities
         .into_iter()
         .map(|identity| IdentityResponse {
+            is_primary: auth_user.user.primary_identity_id.as_deref() == Some(identity.id.as_str()),
+            id: identity.id,
             provider: identity.provider,
+            username: identity.username,
+            name: identity.name,
+            avatar_url: identity.avatar_url,
         })
         .collect();
 
@@ -105,11 +120,15 @@ pub async fn list_identities(
 /// After OAuth completes, the user will be redirected to the service's redirect_uri with:
 /// - ?status=success&provider={provider}&action=link (on success)
 /// - ?status=error&error={message}&action=link (on failure)
+///
+/// The response sets the OAuth browser binding cookie, so it must be called with
+/// credentials from the same browser that then opens `authorization_url`.
 pub async fn start_link(
     State(state): State<AppState>,
     Path(provider_str): Path<String>,
+    headers: HeaderMap,
     auth_user: Option<axum::extract::Extension<crate::middleware::AuthUser>>,
-) -> Result<Json<StartLinkResponse>> {
+) -> Result<([(axum::http::HeaderName, String); 1], Json<StartLinkResponse>)> {
     let auth_user = auth_user
         .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string()))?
         .0;
@@ -122,7 +141,7 @@ pub async fn start_link(
         (Some(org), Some(service)) if !(org == "platform" && service == "admin-cli")
     );
 
-    let (_scopes, is_admin_flow, org_slug, service_slug, service_id, redirect_uri, auth_url, csrf_token, pkce_verifier) = if is_service_level {
+    let (_scopes, is_admin_flow, org_slug, service_slug, service_id, redirect_uri, auth_url, csrf_token, pkce_verifier, nonce) = if is_service_level {
         // Service-level linking: Read scopes and redirect_uris from service configuration
         let org = auth_user.claims.org.as_ref().unwrap();
         let service_slug = auth_user.claims.service.as_ref().unwrap();
@@ -139,6 +158,7 @@ pub async fn start_link(
 
         let service_id = service.id.clone();
         let scopes = get_provider_scopes(&service, provider);
+        let nonce = IdTokenVerifier::generate_nonce(provider, &scopes);
 
         // Parse redirect_uris to get the primary redirect
         let redirect_uris: Vec<String> = service.redirect_uris
@@ -159,24 +179,23 @@ pub async fn start_link(
         );
 
         // Check if org has BYOO credentials for this provider
-        let provider_str = provider.as_str();
-        let org_credentials = sqlx::query!(
-            "SELECT client_id, client_secret_encrypted, encryption_key_id
+        let org_credentials = sqlx::query_as::<_, (String, Vec<u8>)>(
+            "SELECT client_id, client_secret_encrypted
              FROM organization_oauth_credentials
              WHERE org_id = ? AND provider = ?",
-            service.org_id,
-            provider_str
         )
+        .bind(&service.org_id)
+        .bind(provider.as_str())
         .fetch_optional(&state.pool)
         .await?;
 
-        let (auth_url, csrf_token, pkce_verifier) = if let Some(creds) = org_credentials {
+        let (auth_url, csrf_token, pkce_verifier) = if let Some((client_id, secret)) = org_credentials {
             // Use BYOO credentials
             let encryption = crate::encryption::EncryptionService::new()
                 .map_err(|e| AppError::InternalServerError(format!("Encryption unavailable: {}", e)))?;
 
             let client_secret = encryption
-                .decrypt(&creds.client_secret_encrypted)
+                .decrypt(&secret)
                 .map_err(|e| {
                     AppError::InternalServerError(format!("Failed to decrypt secret: {}", e))
                 })?;
@@ -187,17 +206,17 @@ pub async fn start_link(
             let custom_client = create_custom_oauth_client(
                 &config,
                 provider,
-                &creds.client_id,
+                &client_id,
                 &client_secret
             )?;
 
-            get_authorization_url_for_client(&custom_client, provider, scopes.clone())
+            get_authorization_url_for_client(&custom_client, provider, scopes.clone(), nonce.as_deref())
         } else {
             // Use platform credentials
-            state.oauth_client.get_authorization_url_with_pkce(provider, scopes.clone())
+            state.oauth_client.get_authorization_url_with_pkce(provider, scopes.clone(), nonce.as_deref())
         };
 
-        (scopes, false, Some(org.clone()), Some(service_slug.clone()), Some(service_id), redirect_uri, auth_url, csrf_token, pkce_verifier)
+        (scopes, false, Some(org.clone()), Some(service_slug.clone()), Some(service_id), redirect_uri, auth_url, csrf_token, pkce_verifier, nonce)
     } else {
         // Platform-level linking: Use provider default scopes
         let default_scopes = match provider {
@@ -206,10 +225,12 @@ pub async fn start_link(
             Provider::Google => vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
         };
 
+        let nonce = IdTokenVerifier::generate_nonce(provider, &default_scopes);
+
         // Generate OAuth authorization URL with platform credentials
         let (auth_url, csrf_token, pkce_verifier) = state
             .oauth_client
-            .get_authorization_url_with_pkce(provider, default_scopes.clone());
+            .get_authorization_url_with_pkce(provider, default_scopes.clone(), nonce.as_deref());
 
         // For platform-level linking, use base_url + settings page
         let redirect_uri = format!(
@@ -218,9 +239,14 @@ pub async fn start_link(
             provider.as_str()
         );
 
-        (default_scopes, true, None, None, None, redirect_uri, auth_url, csrf_token, pkce_verifier)
+        (default_scopes, true, None, None, None, redirect_uri, auth_url, csrf_token, pkce_verifier, nonce)
     };
 
+    // Tie the state to this browser so the callback cannot be completed in another
+    // one, which would attach someone else's provider account to this user
+    let (browser_binding, binding_cookie) =
+        BrowserBindingService::bind(&state.jwt_service, &headers, &state.base_url)?;
+
     // Store OAuth state with user_id_for_linking set
     let expires_at = Utc::now() + chrono::Duration::minutes(10);
     let pkce_value = if provider == Provider::Microsoft && !pkce_verifier.is_empty() {
@@ -230,8 +256,8 @@ pub async fn start_link(
     };
 
     sqlx::query(
-        "INSERT INTO oauth_states (state, pkce_verifier, service_id, redirect_uri, org_slug, service_slug, is_admin_flow, user_id_for_linking, created_at, expires_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), ?)",
+        "INSERT INTO oauth_states (state, pkce_verifier, service_id, redirect_uri, org_slug, service_slug, is_admin_flow, user_id_for_linking, created_at, expires_at, browser_binding, nonce)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), ?, ?, ?)",
     )
     .bind(csrf_token.secret())
     .bind(pkce_value)
@@ -242,12 +268,17 @@ pub async fn start_link(
     .bind(is_admin_flow)
     .bind(&auth_user.user.id) // Set user_id_for_linking
     .bind(expires_at)
+    .bind(&browser_binding)
+    .bind(&nonce)
     .execute(&state.pool)
     .await?;
 
-    Ok(Json(StartLinkResponse {
-        authorization_url: auth_url,
-    }))
+    Ok((
+        [(SET_COOKIE, binding_cookie)],
+        Json(StartLinkResponse {
+            authorization_url: auth_url,
+        }),
+    ))
 }
 
 /// DELETE /api/user/identities/:provider - Unlink a social account
@@ -343,5 +374,21 @@ pub async fn unlink_identity(
         )));
     }
 
+    for org_id in
+        identity_event_orgs(&state.pool, &auth_user.user.id, issuing_org_id.as_deref()).await?
+    {
+        WebhookService::emit(
+            &mut *state.pool.acquire().await?,
+            &org_id,
+            WEBHOOK_EVENT_IDENTITY_UNLINKED,
+            serde_json::json!({
+                "user_id": auth_user.user.id,
+                "provider": provider.as_str(),
+                "service_id": issuing_service_id,
+            }),
+        )
+        .await?;
+    }
+
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,311 @@
+use crate::auth::impersonation::{ImpersonationScope, ImpersonationService};
+use crate::auth::logout::LogoutService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::constants::MEMBER_ORG_PERMISSIONS;
+use crate::db::models::{Organization, Service, User};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser, ClientInfo};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Deserialize)]
+pub struct StartImpersonationRequest {
+    pub reason: String,
+    pub duration_minutes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartOrgImpersonationRequest {
+    pub service_slug: String,
+    pub reason: String,
+    pub duration_minutes: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub session_id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+async fn find_target(pool: &SqlitePool, actor: &User, user_id: &str) -> Result<User> {
+    if actor.id == user_id {
+        return Err(AppError::BadRequest(
+            "You cannot impersonate yourself".to_string(),
+        ));
+    }
+
+    let target = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if target.is_platform_owner {
+        return Err(AppError::Forbidden(
+            "Platform owners cannot be impersonated".to_string(),
+        ));
+    }
+
+    // An impersonation token would carry the target's organization rights, so
+    // only members whose role, built-in or custom, grants no more than a plain
+    // member's can be impersonated
+    let memberships: Vec<(String, String)> =
+        sqlx::query_as("SELECT org_id, role FROM memberships WHERE user_id = ?")
+            .bind(&target.id)
+            .fetch_all(pool)
+            .await?;
+    let member: Vec<String> = MEMBER_ORG_PERMISSIONS
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    for (org_id, role) in memberships {
+        let granted = OrgRoleService::permissions_for_role(pool, &org_id, &role).await?;
+        if !OrgRoleService::is_within(&granted, &member) {
+            return Err(AppError::Forbidden(
+                "Organization members with more than member permissions cannot be impersonated"
+                    .to_string(),
+            ));
+        }
+    }
+
+    Ok(target)
+}
+
+/// POST /api/platform/users/:user_id/impersonate
+/// Start a time-boxed session as any non-platform-owner user
+pub async fn start_platform_impersonation(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    client: ClientInfo,
+    Path(user_id): Path<String>,
+    Json(req): Json<StartImpersonationRequest>,
+) -> Result<(StatusCode, Json<ImpersonationResponse>)> {
+    crate::middleware::ensure_interactive_session(&auth_user)?;
+    let reason = ImpersonationService::validate_reason(&req.reason)?;
+    let duration = ImpersonationService::duration(req.duration_minutes)?;
+    let target = find_target(&state.pool, &auth_user.user, &user_id).await?;
+
+    let session = ImpersonationService::start(
+        &state.pool,
+        &state.jwt_service,
+        &auth_user.user,
+        &target,
+        ImpersonationScope {
+            org_slug: None,
+            service: None,
+            via: "platform",
+        },
+        &reason,
+        duration,
+        &client,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImpersonationResponse {
+            access_token: session.access_token,
+            token_type: "Bearer".to_string(),
+            session_id: session.session_id,
+            expires_at: session.expires_at,
+        }),
+    ))
+}
+
+/// POST /api/organizations/:org_slug/users/:user_id/impersonate
+/// Start a time-boxed session as one of the organization's end-users, scoped
+/// to one of its services
+pub async fn start_org_impersonation(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    audit: AuditContext,
+    Path((org_slug, user_id)): Path<(String, String)>,
+    Json(req): Json<StartOrgImpersonationRequest>,
+) -> Result<(StatusCode, Json<ImpersonationResponse>)> {
+    crate::middleware::ensure_interactive_session(&auth_user)?;
+
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(&org_slug)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "impersonate_users",
+    )
+    .await?;
+
+    let reason = ImpersonationService::validate_reason(&req.reason)?;
+    let duration = ImpersonationService::duration(req.duration_minutes)?;
+    let target = find_target(&state.pool, &auth_user.user, &user_id).await?;
+
+    // Organization staff carry their org permissions in any token issued for
+    // them, so only plain end-users can be impersonated from an organization
+    let memberships: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE user_id = ?")
+        .bind(&target.id)
+        .fetch_one(&state.pool)
+        .await?;
+    if memberships > 0 {
+        return Err(AppError::Forbidden(
+            "Organization members cannot be impersonated".to_string(),
+        ));
+    }
+
+    let service =
+        sqlx::query_as::<_, Service>("SELECT * FROM services WHERE org_id = ? AND slug = ?")
+            .bind(&org.id)
+            .bind(&req.service_slug)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Service not found".to_string()))?;
+
+    let subscribed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM subscriptions WHERE user_id = ? AND service_id = ?",
+    )
+    .bind(&target.id)
+    .bind(&service.id)
+    .fetch_one(&state.pool)
+    .await?;
+    if subscribed == 0 {
+        return Err(AppError::NotFound(
+            "User is not an end-user of this service".to_string(),
+        ));
+    }
+
+    let session = ImpersonationService::start(
+        &state.pool,
+        &state.jwt_service,
+        &auth_user.user,
+        &target,
+        ImpersonationScope {
+            org_slug: Some(&org.slug),
+            service: Some(&service),
+            via: "organization",
+        },
+        &reason,
+        duration,
+        &client,
+    )
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "impersonation_started",
+        "user",
+        &target.id,
+        diff(
+            &serde_json::Value::Null,
+            &json!({
+                "session_id": session.session_id,
+                "service_id": service.id,
+                "reason": reason,
+                "expires_at": session.expires_at,
+            }),
+        ),
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImpersonationResponse {
+            access_token: session.access_token,
+            token_type: "Bearer".to_string(),
+            session_id: session.session_id,
+            expires_at: session.expires_at,
+        }),
+    ))
+}
+
+/// POST /api/impersonation/stop
+/// End the impersonation session the request is authenticated with
+pub async fn stop_impersonation(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+) -> Result<Json<serde_json::Value>> {
+    let Some(ref actor) = auth_user.claims.act else {
+        return Err(AppError::BadRequest(
+            "This session is not an impersonation session".to_string(),
+        ));
+    };
+
+    if let Some(session) = LogoutService::revoke_session(
+        &state.pool,
+        &state.jwt_service,
+        &state.base_url,
+        &auth_user.session_id,
+    )
+    .await?
+    {
+        ImpersonationService::log_stopped(&state.pool, &session, &actor.sub, &audit).await?;
+    }
+
+    Ok(Json(json!({
+        "message": "Impersonation session ended"
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    #[tokio::test]
+    async fn test_members_with_elevated_roles_cannot_be_impersonated() {
+        let pool = test_support::test_pool().await;
+        let actor = test_support::insert_user(&pool, "support@example.com").await;
+        let owner = test_support::insert_user(&pool, "owner@example.com").await;
+        let org = test_support::insert_org(&pool, "acme", &owner).await;
+        for (name, permissions) in [
+            ("viewer", r#"["view_analytics"]"#),
+            ("billing", r#"["view_analytics", "manage_billing"]"#),
+        ] {
+            sqlx::query(
+                "INSERT INTO org_roles (id, org_id, name, permissions, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&org.id)
+            .bind(name)
+            .bind(permissions)
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let member = test_support::insert_user(&pool, "member@example.com").await;
+        test_support::insert_membership(&pool, &org, &member, "member").await;
+        let viewer = test_support::insert_user(&pool, "viewer@example.com").await;
+        test_support::insert_membership(&pool, &org, &viewer, "viewer").await;
+        let billing = test_support::insert_user(&pool, "billing@example.com").await;
+        test_support::insert_membership(&pool, &org, &billing, "billing").await;
+
+        find_target(&pool, &actor, &member.id).await.unwrap();
+        find_target(&pool, &actor, &viewer.id).await.unwrap();
+        for target in [&billing, &owner] {
+            assert!(matches!(
+                find_target(&pool, &actor, &target.id).await,
+                Err(AppError::Forbidden(_))
+            ));
+        }
+    }
+}
@@ -1,25 +1,149 @@
-use crate::constants::{DEFAULT_MAX_USERS, INVITATION_EXPIRY_DAYS, VALID_INVITATION_ROLES};
+use crate::auth::email::TEMPLATE_INVITATION;
+use crate::auth::invitations::InvitationService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::billing::tiers::TierService;
+use crate::constants::{
+    BULK_INVITATION_MAX_ROWS, INVITATION_EXPIRY_DAYS,
+    INVITATION_STATUSES, MAX_INVITATION_EXPIRY_DAYS, VALID_INVITATION_ROLES,
+};
 use crate::db::models::{Organization, OrganizationInvitation, User};
+use crate::encryption::EncryptionService;
 use crate::error::{AppError, Result};
 use crate::handlers::auth::AppState;
-use crate::middleware::AuthUser;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::handlers::organizations::validate_email;
+use crate::middleware::{AuditContext, AuthUser};
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
+    http::{header, HeaderMap},
     response::Redirect,
     Json,
 };
 use chrono::{Duration as ChronoDuration, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
 use uuid::Uuid;
 
 /// Hash an invitation token using SHA256
-fn hash_invitation_token(token: &str) -> String {
+pub fn hash_invitation_token(token: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(token.as_bytes());
     hex::encode(hasher.finalize())
 }
 
+/// A role may be offered when it is a built-in invitation role or a custom
+/// role (never owner) granting no more than the inviter holds
+pub async fn validate_invitation_role(
+    pool: &SqlitePool,
+    org_id: &str,
+    inviter_role: &str,
+    role: &str,
+) -> Result<()> {
+    if !VALID_INVITATION_ROLES.contains(&role)
+        && (role == "owner" || !OrgRoleService::role_exists(pool, org_id, role).await?)
+    {
+        return Err(AppError::BadRequest(
+            format!("Invalid role. Must be one of: {} or a custom role", VALID_INVITATION_ROLES.join(", ")),
+        ));
+    }
+
+    let granted = OrgRoleService::permissions_for_role(pool, org_id, role).await?;
+    OrgRoleService::ensure_can_grant(pool, org_id, inviter_role, &granted).await
+}
+
+/// The parts of an invitation recorded in the organization audit log
+fn audit_fields(invitation: &OrganizationInvitation) -> serde_json::Value {
+    serde_json::json!({
+        "email": invitation.email,
+        "role": invitation.role,
+        "status": invitation.status,
+        "expires_at": invitation.expires_at,
+    })
+}
+
+/// Seats left for new invitations and the member limit. Pending invitations
+/// hold a seat until they are answered or expire.
+async fn free_seats(
+    conn: &mut SqliteConnection,
+    organization: &Organization,
+) -> Result<(i64, i64)> {
+    let member_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ?")
+        .bind(&organization.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let reserved: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM organization_invitations
+         WHERE org_id = ? AND status = 'pending' AND expires_at > ?",
+    )
+    .bind(&organization.id)
+    .bind(Utc::now())
+    .fetch_one(&mut *conn)
+    .await?;
+    let limit = TierService::member_limit(&mut *conn, organization).await?;
+
+    Ok(((limit - member_count - reserved).max(0), limit))
+}
+
+/// Create a pending invitation and queue its email. Returns the plaintext
+/// token; only its hash and, with encryption, an encrypted copy are stored.
+async fn insert_invitation(
+    conn: &mut SqliteConnection,
+    base_url: &str,
+    encryption: Option<&EncryptionService>,
+    organization: &Organization,
+    inviter: &User,
+    email: &str,
+    role: &str,
+) -> Result<(OrganizationInvitation, String)> {
+    let token = Uuid::new_v4().to_string();
+    let expires_at = Utc::now() + ChronoDuration::days(INVITATION_EXPIRY_DAYS);
+
+    // A lapsed invitation the lifecycle job has not marked yet makes way for the new one
+    sqlx::query(
+        "UPDATE organization_invitations SET status = 'expired'
+         WHERE org_id = ? AND email = ? AND status = 'pending' AND expires_at <= ?",
+    )
+    .bind(&organization.id)
+    .bind(email)
+    .bind(Utc::now())
+    .execute(&mut *conn)
+    .await?;
+
+    let invitation = sqlx::query_as::<_, OrganizationInvitation>(
+        "INSERT INTO organization_invitations (id, org_id, email, role, invited_by, status, token, expires_at, created_at)
+         VALUES (?, ?, ?, ?, ?, 'pending', ?, ?, ?)
+         RETURNING *"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&organization.id)
+    .bind(email)
+    .bind(role)
+    .bind(&inviter.id)
+    .bind(hash_invitation_token(&token))
+    .bind(expires_at)
+    .bind(Utc::now())
+    .fetch_one(&mut *conn)
+    .await?;
+    InvitationService::keep_token(conn, encryption, &invitation.id, &token).await?;
+
+    // Queue the invitation email; it is only sent if the invitation commits
+    InvitationService::send_email(
+        conn,
+        base_url,
+        TEMPLATE_INVITATION,
+        organization,
+        inviter,
+        &invitation,
+        &token,
+    )
+    .await?;
+
+    Ok((invitation, token))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateInvitationRequest {
     pub email: String,
@@ -35,21 +159,70 @@ pub struct UpdateInvitationRequest {
 pub struct InvitationResponse {
     pub invitation: OrganizationInvitation,
     pub inviter: User,
-    pub token: String, // Plaintext token for email links (only returned once)
+    pub token: String, // Plaintext token (only returned once); also emailed to the invitee
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResendInvitationResponse {
+    pub invitation: OrganizationInvitation,
+    pub token: String, // New plaintext token; the previous one no longer works
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExtendInvitationRequest {
+    pub days: i64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ListInvitationsQuery {
     pub page: Option<i64>,
     pub limit: Option<i64>,
-    #[allow(dead_code)]
     pub status: Option<String>,
 }
 
-/// Create invitation (owner/admin only)
+#[derive(Debug, Deserialize)]
+pub struct BulkInvitationQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkInvitationRow {
+    pub email: String,
+    pub role: Option<String>, // defaults to member
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkInvitationRequest {
+    pub invitations: Vec<BulkInvitationRow>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkInvitationResult {
+    pub row: usize,
+    pub email: String,
+    pub role: String,
+    pub status: &'static str, // 'created', 'valid' (dry run) or 'failed'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invitation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkInvitationResponse {
+    pub dry_run: bool,
+    pub created: usize,
+    pub failed: usize,
+    pub results: Vec<BulkInvitationResult>,
+}
+
+/// Create invitation (requires manage_invitations)
 pub async fn create_invitation(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path(org_slug): Path<String>,
     Json(req): Json<CreateInvitationRequest>,
 ) -> Result<Json<InvitationResponse>> {
@@ -64,16 +237,16 @@ pub async fn create_invitation(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner or admin
-    let _membership =
-        crate::middleware::check_org_admin(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may invite members
+    let membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_invitations",
+    )
+    .await?;
 
-    // Validate role
-    if !VALID_INVITATION_ROLES.contains(&req.role.as_str()) {
-        return Err(AppError::BadRequest(
-            format!("Invalid role. Must be one of: {}", VALID_INVITATION_ROLES.join(", ")),
-        ));
-    }
+    validate_invitation_role(&state.pool, &organization.id, &membership.role, &req.role).await?;
 
     // Check if email is already a member
     let existing_member = sqlx::query!(
@@ -94,26 +267,22 @@ pub async fn create_invitation(
     }
 
     // Check for existing pending invitation
-    let existing_invitation = sqlx::query!(
-        "SELECT COUNT(*) as count FROM organization_invitations
-         WHERE org_id = ? AND email = ? AND status = 'pending'",
-        organization.id,
-        req.email
+    let existing_invitation: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM organization_invitations
+         WHERE org_id = ? AND email = ? AND status = 'pending' AND expires_at > ?",
     )
+    .bind(&organization.id)
+    .bind(&req.email)
+    .bind(Utc::now())
     .fetch_one(&state.pool)
     .await
     .map_err(AppError::Database)?;
 
-    if existing_invitation.count > 0 {
+    if existing_invitation > 0 {
         return Err(AppError::BadRequest("Invitation already sent".to_string()));
     }
 
     // Create invitation
-    let invitation_id = Uuid::new_v4().to_string();
-    let token = Uuid::new_v4().to_string(); // Generate plaintext token
-    let token_hash = hash_invitation_token(&token); // Hash for storage
-    let expires_at = Utc::now() + ChronoDuration::days(INVITATION_EXPIRY_DAYS);
-
     // Log invitation creation
     tracing::info!(
         org_slug = %org_slug,
@@ -123,34 +292,282 @@ pub async fn create_invitation(
         "Creating organization invitation"
     );
 
-    let invitation = sqlx::query_as::<_, OrganizationInvitation>(
-        "INSERT INTO organization_invitations (id, org_id, email, role, invited_by, status, token, expires_at, created_at)
-         VALUES (?, ?, ?, ?, ?, 'pending', ?, ?, ?)
-         RETURNING *"
-    )
-    .bind(&invitation_id)
-    .bind(&organization.id)
-    .bind(&req.email)
-    .bind(&req.role)
-    .bind(&user.id)
-    .bind(&token_hash) // Store hashed token
-    .bind(expires_at)
-    .bind(Utc::now())
-    .fetch_one(&state.pool)
-    .await
-    .map_err(AppError::Database)?;
+    let mut tx = state.pool.begin().await.map_err(AppError::Database)?;
 
     // Get inviter details
     let inviter = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
         .bind(&user.id)
-        .fetch_one(&state.pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(AppError::Database)?;
 
+    let (headroom, limit) = free_seats(&mut tx, &organization).await?;
+    if headroom < 1 {
+        return Err(AppError::TeamLimitExceeded(format!(
+            "Team limit reached: all {} seats are taken by members and pending invitations",
+            limit
+        )));
+    }
+
+    let (invitation, token) = insert_invitation(
+        &mut tx,
+        &state.base_url,
+        state.encryption.as_deref(),
+        &organization,
+        &inviter,
+        &req.email,
+        &req.role,
+    )
+    .await?;
+
+    create_org_audit_log(
+        &mut tx,
+        &audit,
+        &organization.id,
+        "invitation_created",
+        "invitation",
+        &invitation.id,
+        diff(&serde_json::Value::Null, &audit_fields(&invitation)),
+    )
+    .await?;
+
+    tx.commit().await.map_err(AppError::Database)?;
+
     Ok(Json(InvitationResponse {
         invitation,
         inviter,
-        token, // Return plaintext token for email links
+        token, // Returned for callers that deliver the invitation themselves
+    }))
+}
+
+/// Read invitation rows from CSV (`email,role` per line, header optional) or
+/// a JSON body of the form `{"invitations": [{"email", "role"}]}`
+fn parse_bulk_rows(headers: &HeaderMap, body: &[u8]) -> Result<Vec<BulkInvitationRow>> {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/csv"));
+
+    if !is_csv {
+        let request: BulkInvitationRequest = serde_json::from_slice(body)
+            .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))?;
+        return Ok(request.invitations);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| AppError::BadRequest(format!("Invalid CSV: {}", e)))?;
+        let email = record.get(0).unwrap_or_default();
+        if email.is_empty() || (index == 0 && email.eq_ignore_ascii_case("email")) {
+            continue;
+        }
+        rows.push(BulkInvitationRow {
+            email: email.to_string(),
+            role: record.get(1).map(str::to_string),
+        });
+    }
+    Ok(rows)
+}
+
+/// Validation failures are reported per row; anything else aborts the request
+fn row_error(e: AppError) -> Result<String> {
+    match e {
+        AppError::BadRequest(message) | AppError::Forbidden(message) => Ok(message),
+        e => Err(e),
+    }
+}
+
+/// Validate bulk invitation rows without writing anything: per-row problems
+/// are reported in the results, and the valid rows must fit in the free seats
+async fn check_bulk_rows(
+    pool: &SqlitePool,
+    organization: &Organization,
+    inviter_role: &str,
+    rows: Vec<BulkInvitationRow>,
+) -> Result<Vec<BulkInvitationResult>> {
+    let members: HashSet<String> = sqlx::query_scalar(
+        "SELECT LOWER(u.email) FROM memberships m JOIN users u ON m.user_id = u.id WHERE m.org_id = ?",
+    )
+    .bind(&organization.id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+    let pending: HashSet<String> = sqlx::query_scalar(
+        "SELECT LOWER(email) FROM organization_invitations
+         WHERE org_id = ? AND status = 'pending' AND expires_at > ?",
+    )
+    .bind(&organization.id)
+    .bind(Utc::now())
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    // Check every row, so one response lists all the problems
+    let mut role_checks: HashMap<String, Option<String>> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(rows.len());
+    for (index, row) in rows.into_iter().enumerate() {
+        let email = row.email.trim().to_string();
+        let role = row
+            .role
+            .map(|role| role.trim().to_string())
+            .filter(|role| !role.is_empty())
+            .unwrap_or_else(|| "member".to_string());
+        let key = email.to_lowercase();
+
+        let error = if let Err(e) = validate_email(&email) {
+            Some(row_error(e)?)
+        } else if !seen.insert(key.clone()) {
+            Some("Duplicate email in this request".to_string())
+        } else if members.contains(&key) {
+            Some("User is already a member of this organization".to_string())
+        } else if pending.contains(&key) {
+            Some("Invitation already sent".to_string())
+        } else {
+            if !role_checks.contains_key(&role) {
+                let check = match validate_invitation_role(
+                    pool,
+                    &organization.id,
+                    inviter_role,
+                    &role,
+                )
+                .await
+                {
+                    Ok(()) => None,
+                    Err(e) => Some(row_error(e)?),
+                };
+                role_checks.insert(role.clone(), check);
+            }
+            role_checks[&role].clone()
+        };
+
+        results.push(BulkInvitationResult {
+            row: index + 1,
+            email,
+            role,
+            status: if error.is_some() { "failed" } else { "valid" },
+            error,
+            invitation_id: None,
+            token: None,
+        });
+    }
+
+    let valid = results
+        .iter()
+        .filter(|result| result.error.is_none())
+        .count();
+    let (headroom, limit) = free_seats(&mut *pool.acquire().await?, organization).await?;
+    if valid as i64 > headroom {
+        return Err(AppError::TeamLimitExceeded(format!(
+            "Team limit reached: {} valid invitations but only {} of {} seats are free",
+            valid, headroom, limit
+        )));
+    }
+
+    Ok(results)
+}
+
+/// Bulk-create invitations (requires manage_invitations)
+pub async fn bulk_create_invitations(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Query(query): Query<BulkInvitationQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BulkInvitationResponse>> {
+    let user = &auth_user.user;
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let organization =
+        sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+            .bind(&org_slug)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    let membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_invitations",
+    )
+    .await?;
+
+    let rows = parse_bulk_rows(&headers, &body)?;
+    if rows.is_empty() {
+        return Err(AppError::BadRequest("No invitations given".to_string()));
+    }
+    if rows.len() > BULK_INVITATION_MAX_ROWS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} invitations can be sent at once",
+            BULK_INVITATION_MAX_ROWS
+        )));
+    }
+
+    let mut results =
+        check_bulk_rows(&state.pool, &organization, &membership.role, rows).await?;
+    let valid = results
+        .iter()
+        .filter(|result| result.error.is_none())
+        .count();
+
+    if !dry_run && valid > 0 {
+        tracing::info!(
+            org_slug = %org_slug,
+            count = valid,
+            inviter_id = %user.id,
+            "Creating organization invitations in bulk"
+        );
+
+        let mut tx = state.pool.begin().await?;
+        let inviter = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+            .bind(&user.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        for result in results.iter_mut().filter(|result| result.error.is_none()) {
+            let (invitation, token) = insert_invitation(
+                &mut tx,
+                &state.base_url,
+                state.encryption.as_deref(),
+                &organization,
+                &inviter,
+                &result.email,
+                &result.role,
+            )
+            .await?;
+            create_org_audit_log(
+                &mut tx,
+                &audit,
+                &organization.id,
+                "invitation_created",
+                "invitation",
+                &invitation.id,
+                diff(&serde_json::Value::Null, &audit_fields(&invitation)),
+            )
+            .await?;
+            result.status = "created";
+            result.invitation_id = Some(invitation.id);
+            result.token = Some(token);
+        }
+
+        tx.commit().await?;
+    }
+
+    Ok(Json(BulkInvitationResponse {
+        dry_run,
+        created: if dry_run { 0 } else { valid },
+        failed: results.len() - valid,
+        results,
     }))
 }
 
@@ -161,36 +578,23 @@ pub async fn list_user_invitations(
 ) -> Result<Json<Vec<serde_json::Value>>> {
     let user = &auth_user.user;
 
-    let rows = sqlx::query!(
+    let invitations = sqlx::query_as::<_, InvitationBasic>(
         r#"
         SELECT
             i.id, i.email, i.role, i.token, i.expires_at, i.created_at,
             o.slug as org_slug, o.name as org_name
         FROM organization_invitations i
         JOIN organizations o ON i.org_id = o.id
-        WHERE i.email = ? AND i.status = 'pending'
+        WHERE i.email = ? AND i.status = 'pending' AND i.expires_at > ?
         ORDER BY i.created_at DESC
         "#,
-        user.email
     )
+    .bind(&user.email)
+    .bind(Utc::now())
     .fetch_all(&state.pool)
     .await
     .map_err(AppError::Database)?;
 
-    let invitations: Vec<InvitationBasic> = rows
-        .into_iter()
-        .map(|row| InvitationBasic {
-            id: row.id.unwrap_or_default(),
-            email: row.email,
-            role: row.role,
-            token: row.token,
-            expires_at: row.expires_at,
-            created_at: row.created_at.unwrap_or_default(),
-            org_slug: row.org_slug,
-            org_name: row.org_name,
-        })
-        .collect();
-
     let responses: Vec<serde_json::Value> = invitations
         .into_iter()
         .map(|row| {
@@ -212,25 +616,28 @@ pub async fn list_user_invitations(
     Ok(Json(responses))
 }
 
-/// Accept invitation (public endpoint)
+/// Accept invitation as the signed-in user
 pub async fn accept_invitation(
     State(state): State<AppState>,
+    audit: AuditContext,
     Json(req): Json<UpdateInvitationRequest>,
 ) -> Result<Json<()>> {
-    accept_invitation_internal(State(state), req.token, "accepted").await
+    accept_invitation_internal(State(state), &audit, req.token, "accepted").await
 }
 
-/// Decline invitation (public endpoint)
+/// Decline invitation as the signed-in user
 pub async fn decline_invitation(
     State(state): State<AppState>,
+    audit: AuditContext,
     Json(req): Json<UpdateInvitationRequest>,
 ) -> Result<Json<()>> {
-    accept_invitation_internal(State(state), req.token, "rejected").await
+    accept_invitation_internal(State(state), &audit, req.token, "declined").await
 }
 
 /// Internal invitation acceptance/rejection logic
 async fn accept_invitation_internal(
     state: State<AppState>,
+    audit: &AuditContext,
     token: String,
     new_status: &str,
 ) -> Result<Json<()>> {
@@ -241,7 +648,7 @@ async fn accept_invitation_internal(
 
     // Find invitation
     let invitation = sqlx::query_as::<_, OrganizationInvitation>(
-        "SELECT * FROM organization_invitations WHERE token = ? AND status = 'pending'",
+        "SELECT * FROM organization_invitations WHERE token = ? AND status IN ('pending', 'expired')",
     )
     .bind(&token_hash)
     .fetch_optional(&mut *tx)
@@ -249,11 +656,15 @@ async fn accept_invitation_internal(
     .map_err(AppError::Database)?
     .ok_or_else(|| AppError::NotFound("Invitation not found or already processed".to_string()))?;
 
-    // Check if expired
-    if invitation.expires_at < Utc::now() {
+    // Check if expired (the expiry job may not have marked it yet)
+    if invitation.status == "expired" || invitation.expires_at < Utc::now() {
         return Err(AppError::BadRequest("Invitation has expired".to_string()));
     }
 
+    let before = audit_fields(&invitation);
+    let mut after = before.clone();
+    after["status"] = serde_json::json!(new_status);
+
     if new_status == "accepted" {
         // Find or create user
         let user = find_or_create_user_tx(&mut tx, &invitation.email).await?;
@@ -273,26 +684,7 @@ async fn accept_invitation_internal(
             .await
             .map_err(AppError::Database)?;
 
-        let tier_limit =
-            if let (Some(max_users), Some(_tier_id)) = (org.max_users, org.tier_id.as_ref()) {
-                // Use org-specific limit if set
-                max_users
-            } else if let Some(tier_id) = org.tier_id.as_ref() {
-                // Use tier default
-                let tier = sqlx::query!(
-                    "SELECT default_max_users FROM organization_tiers WHERE id = ?",
-                    tier_id
-                )
-                .fetch_one(&mut *tx)
-                .await
-                .map_err(AppError::Database)?;
-
-                tier.default_max_users
-            } else {
-                DEFAULT_MAX_USERS // Free tier default
-            };
-
-        if member_count >= tier_limit {
+        if member_count >= TierService::member_limit(&mut *tx, &org).await? {
             return Err(AppError::BadRequest("Team limit reached".to_string()));
         }
 
@@ -311,6 +703,8 @@ async fn accept_invitation_internal(
         .execute(&mut *tx)
         .await
         .map_err(AppError::Database)?;
+
+        after["user_id"] = serde_json::json!(user.id);
     }
 
     // Update invitation status
@@ -323,28 +717,52 @@ async fn accept_invitation_internal(
     .await
     .map_err(AppError::Database)?;
 
+    create_org_audit_log(
+        &mut tx,
+        audit,
+        &invitation.org_id,
+        &format!("invitation_{}", new_status),
+        "invitation",
+        &invitation.id,
+        diff(&before, &after),
+    )
+    .await?;
+
     tx.commit().await.map_err(AppError::Database)?;
 
     Ok(Json(()))
 }
 
-/// Accept invitation via email link (redirect)
+#[derive(Debug, Deserialize)]
+pub struct AcceptLinkQuery {
+    pub token: String,
+}
+
+/// Accept invitation via email link: send the browser to the configured
+/// accept page, which signs the user in and calls `POST /api/invitations/accept`
 pub async fn accept_invitation_redirect(
-    State(_state): State<AppState>,
-    Path(token): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<AcceptLinkQuery>,
 ) -> Result<Redirect> {
-    // For now, redirect to a simple success page
-    // In production, this would redirect to your web app
-    Ok(Redirect::permanent(&format!(
-        "/invitations/accept?token={}",
-        token
-    )))
+    accept_page_redirect(state.invitation_accept_uri.as_deref(), &query.token)
+}
+
+fn accept_page_redirect(accept_uri: Option<&str>, token: &str) -> Result<Redirect> {
+    let accept_uri = accept_uri
+        .ok_or_else(|| AppError::NotFound("No invitation accept page is configured".to_string()))?;
+    let mut location = oauth2::url::Url::parse(accept_uri).map_err(|_| {
+        AppError::InternalServerError("INVITATION_ACCEPT_URI is not a valid URL".to_string())
+    })?;
+    location.query_pairs_mut().append_pair("token", token);
+
+    Ok(Redirect::to(location.as_str()))
 }
 
-/// Cancel invitation (owner/admin only)
+/// Cancel invitation (requires manage_invitations)
 pub async fn cancel_invitation(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path((org_slug, invitation_id)): Path<(String, String)>,
 ) -> Result<Json<()>> {
     let user = &auth_user.user;
@@ -358,9 +776,14 @@ pub async fn cancel_invitation(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner or admin
-    let _membership =
-        crate::middleware::check_org_admin(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may manage invitations
+    let _membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_invitations",
+    )
+    .await?;
 
     // Cancel invitation
     let result = sqlx::query!(
@@ -379,10 +802,198 @@ pub async fn cancel_invitation(
         ));
     }
 
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &organization.id,
+        "invitation_cancelled",
+        "invitation",
+        &invitation_id,
+        diff(
+            &serde_json::json!({ "status": "pending" }),
+            &serde_json::json!({ "status": "cancelled" }),
+        ),
+    )
+    .await?;
+
     Ok(Json(()))
 }
 
-/// List organization invitations (owner/admin only)
+/// Load an invitation of an organization the user may manage invitations for.
+/// Only pending and expired invitations can still be resent or extended, and
+/// only by someone who could offer its role today: resending hands out a new
+/// token and both reopen an expired invitation.
+async fn find_open_invitation(
+    pool: &SqlitePool,
+    user: &User,
+    org_slug: &str,
+    invitation_id: &str,
+) -> Result<OrganizationInvitation> {
+    let organization =
+        sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+            .bind(org_slug)
+            .fetch_optional(pool)
+            .await
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    let membership = crate::middleware::check_org_permission(
+        pool,
+        &user.id,
+        &organization.id,
+        "manage_invitations",
+    )
+    .await?;
+
+    let invitation = sqlx::query_as::<_, OrganizationInvitation>(
+        "SELECT * FROM organization_invitations WHERE id = ? AND org_id = ?",
+    )
+    .bind(invitation_id)
+    .bind(&organization.id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)?
+    .ok_or_else(|| AppError::NotFound("Invitation not found".to_string()))?;
+
+    if invitation.status != "pending" && invitation.status != "expired" {
+        return Err(AppError::BadRequest(format!(
+            "Invitation is already {}",
+            invitation.status
+        )));
+    }
+
+    validate_invitation_role(pool, &organization.id, &membership.role, &invitation.role).await?;
+
+    // Reopening an expired invitation must not collide with a newer one
+    if invitation.status == "expired" {
+        let blocked: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM organization_invitations
+                     WHERE org_id = ? AND email = ? AND status = 'pending')
+                  + (SELECT COUNT(*) FROM memberships m JOIN users u ON m.user_id = u.id
+                     WHERE m.org_id = ? AND u.email = ?)",
+        )
+        .bind(&invitation.org_id)
+        .bind(&invitation.email)
+        .bind(&invitation.org_id)
+        .bind(&invitation.email)
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::Database)?;
+        if blocked > 0 {
+            return Err(AppError::BadRequest(
+                "A newer invitation or membership exists for this email".to_string(),
+            ));
+        }
+    }
+
+    Ok(invitation)
+}
+
+/// Resend an invitation with a new token and at least the default expiry
+/// (requires manage_invitations). The link in the earlier email stops working.
+pub async fn resend_invitation(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, invitation_id)): Path<(String, String)>,
+) -> Result<Json<ResendInvitationResponse>> {
+    let invitation =
+        find_open_invitation(&state.pool, &auth_user.user, &org_slug, &invitation_id).await?;
+
+    let before = audit_fields(&invitation);
+
+    let mut tx = state.pool.begin().await.map_err(AppError::Database)?;
+    let (invitation, token) = InvitationService::reissue(
+        &mut tx,
+        &state.base_url,
+        state.encryption.as_deref(),
+        TEMPLATE_INVITATION,
+        &invitation.id,
+        // Never shorten an expiry that was extended
+        invitation
+            .expires_at
+            .max(Utc::now() + ChronoDuration::days(INVITATION_EXPIRY_DAYS)),
+    )
+    .await?;
+    create_org_audit_log(
+        &mut tx,
+        &audit,
+        &invitation.org_id,
+        "invitation_resent",
+        "invitation",
+        &invitation.id,
+        diff(&before, &audit_fields(&invitation)),
+    )
+    .await?;
+    tx.commit().await.map_err(AppError::Database)?;
+
+    tracing::info!(
+        org_slug = %org_slug,
+        invitation_id = %invitation.id,
+        user_id = %auth_user.user.id,
+        "Resent organization invitation"
+    );
+
+    Ok(Json(ResendInvitationResponse { invitation, token }))
+}
+
+/// Push back an invitation's expiry without changing its link (requires
+/// manage_invitations)
+pub async fn extend_invitation(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, invitation_id)): Path<(String, String)>,
+    Json(req): Json<ExtendInvitationRequest>,
+) -> Result<Json<OrganizationInvitation>> {
+    if !(1..=MAX_INVITATION_EXPIRY_DAYS).contains(&req.days) {
+        return Err(AppError::BadRequest(format!(
+            "days must be between 1 and {}",
+            MAX_INVITATION_EXPIRY_DAYS
+        )));
+    }
+
+    let invitation =
+        find_open_invitation(&state.pool, &auth_user.user, &org_slug, &invitation_id).await?;
+
+    let now = Utc::now();
+    let expires_at = invitation.expires_at.max(now) + ChronoDuration::days(req.days);
+    if expires_at > now + ChronoDuration::days(MAX_INVITATION_EXPIRY_DAYS) {
+        return Err(AppError::BadRequest(format!(
+            "Invitations cannot expire more than {} days from now",
+            MAX_INVITATION_EXPIRY_DAYS
+        )));
+    }
+
+    let before = audit_fields(&invitation);
+
+    let invitation = sqlx::query_as::<_, OrganizationInvitation>(
+        "UPDATE organization_invitations
+         SET expires_at = ?, status = 'pending', reminder_sent_at = NULL
+         WHERE id = ?
+         RETURNING *",
+    )
+    .bind(expires_at)
+    .bind(&invitation.id)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(AppError::Database)?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &invitation.org_id,
+        "invitation_extended",
+        "invitation",
+        &invitation.id,
+        diff(&before, &audit_fields(&invitation)),
+    )
+    .await?;
+
+    Ok(Json(invitation))
+}
+
+/// List organization invitations (requires manage_invitations)
 pub async fn list_invitations(
     State(state): State<AppState>,
     auth_user: AuthUser,
@@ -400,51 +1011,59 @@ pub async fn list_invitations(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner or admin
-    let _membership =
-        crate::middleware::check_org_admin(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may manage invitations
+    let _membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_invitations",
+    )
+    .await?;
 
     // Extract pagination parameters with defaults
     let page = query.page.unwrap_or(1).max(1);
     let limit = query.limit.unwrap_or(50).clamp(1, 100);
     let offset = (page - 1) * limit;
 
-    // Get invitations for this organization with pagination
-    let rows = sqlx::query!(
+    if let Some(status) = query.status.as_deref() {
+        if !INVITATION_STATUSES.contains(&status) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid status. Must be one of: {}",
+                INVITATION_STATUSES.join(", ")
+            )));
+        }
+    }
+
+    // Get invitations for this organization with pagination. Pending
+    // invitations past their expiry count as expired even before the expiry
+    // job has marked them.
+    let invitations = sqlx::query_as::<_, InvitationWithInviter>(
         r#"
-        SELECT
-            i.id, i.email, i.role, i.status, i.token, i.expires_at, i.created_at,
-            u.email as inviter_email, u.id as inviter_id, u.created_at as inviter_created_at
-        FROM organization_invitations i
-        JOIN users u ON i.invited_by = u.id
-        WHERE i.org_id = ?
-        ORDER BY i.created_at DESC
+        SELECT * FROM (
+            SELECT
+                i.id, i.email, i.role,
+                CASE WHEN i.status = 'pending' AND i.expires_at <= ? THEN 'expired' ELSE i.status END as status,
+                i.token, i.expires_at, i.last_sent_at, i.reminder_sent_at, i.created_at,
+                u.email as inviter_email, u.id as inviter_id, u.created_at as inviter_created_at
+            FROM organization_invitations i
+            JOIN users u ON i.invited_by = u.id
+            WHERE i.org_id = ?
+        )
+        WHERE ? IS NULL OR status = ?
+        ORDER BY created_at DESC
         LIMIT ? OFFSET ?
         "#,
-        organization.id,
-        limit,
-        offset
     )
+    .bind(Utc::now())
+    .bind(&organization.id)
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(limit)
+    .bind(offset)
     .fetch_all(&state.pool)
     .await
     .map_err(AppError::Database)?;
 
-    let invitations: Vec<InvitationWithInviter> = rows
-        .into_iter()
-        .map(|row| InvitationWithInviter {
-            id: row.id.unwrap_or_default(),
-            email: row.email,
-            role: row.role,
-            status: row.status,
-            token: row.token,
-            expires_at: row.expires_at,
-            created_at: row.created_at.unwrap_or_default(),
-            inviter_email: row.inviter_email,
-            inviter_id: row.inviter_id.unwrap_or_default(),
-            inviter_created_at: row.inviter_created_at.unwrap_or_default(),
-        })
-        .collect();
-
     let responses: Vec<serde_json::Value> = invitations
         .into_iter()
         .map(|row| {
@@ -456,6 +1075,8 @@ pub async fn list_invitations(
                     "status": row.status,
                     "token": row.token,
                     "expires_at": row.expires_at,
+                    "last_sent_at": row.last_sent_at,
+                    "reminder_sent_at": row.reminder_sent_at,
                     "created_at": row.created_at
                 },
                 "inviter": {
@@ -503,7 +1124,7 @@ async fn find_or_create_user_tx(
 }
 
 // Simplified structs for query results
-#[derive(Debug)]
+#[derive(Debug, FromRow)]
 struct InvitationBasic {
     id: String,
     email: String,
@@ -515,7 +1136,7 @@ struct InvitationBasic {
     org_name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, FromRow)]
 struct InvitationWithInviter {
     id: String,
     email: String,
@@ -523,8 +1144,188 @@ struct InvitationWithInviter {
     status: String,
     token: String,
     expires_at: chrono::NaiveDateTime,
+    last_sent_at: Option<chrono::NaiveDateTime>,
+    reminder_sent_at: Option<chrono::NaiveDateTime>,
     created_at: chrono::NaiveDateTime,
     inviter_email: String,
     inviter_id: String,
     inviter_created_at: chrono::NaiveDateTime,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::invitations::INVITATION_ACCEPT_PATH;
+    use crate::db::test_support;
+    use axum::{body::Body, http::Request, http::StatusCode, routing::get, Router};
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_email_link_redirects_to_accept_page() {
+        let mut app = Router::new().route(
+            INVITATION_ACCEPT_PATH,
+            get(|Query(query): Query<AcceptLinkQuery>| async move {
+                accept_page_redirect(Some("https://app.example.com/join?lang=en"), &query.token)
+            }),
+        );
+        let link = InvitationService::accept_url("https://sso.example.com", "3f2b-token");
+        let path = link.strip_prefix("https://sso.example.com").unwrap();
+
+        let response = app
+            .call(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://app.example.com/join?lang=en&token=3f2b-token"
+        );
+        assert!(accept_page_redirect(None, "3f2b-token").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reopening_needs_the_right_to_offer_the_role() {
+        let pool = test_support::test_pool().await;
+        let owner = test_support::insert_user(&pool, "owner@example.com").await;
+        let org = test_support::insert_org(&pool, "acme", &owner).await;
+        sqlx::query(
+            "INSERT INTO org_roles (id, org_id, name, permissions, created_at, updated_at)
+             VALUES (?, ?, 'recruiter', '[\"manage_invitations\"]', ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&org.id)
+        .bind(Utc::now())
+        .bind(Utc::now())
+        .execute(&pool)
+        .await
+        .unwrap();
+        let recruiter = test_support::insert_user(&pool, "recruiter@example.com").await;
+        test_support::insert_membership(&pool, &org, &recruiter, "recruiter").await;
+
+        let invitation_id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO organization_invitations
+                 (id, org_id, email, role, invited_by, status, token, expires_at)
+             VALUES (?, ?, 'ada@example.com', 'admin', ?, 'expired', 'token-hash', ?)",
+        )
+        .bind(&invitation_id)
+        .bind(&org.id)
+        .bind(&owner.id)
+        .bind(Utc::now() - ChronoDuration::days(1))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            find_open_invitation(&pool, &recruiter, "acme", &invitation_id).await,
+            Err(AppError::Forbidden(_))
+        ));
+        let invitation = find_open_invitation(&pool, &owner, "acme", &invitation_id)
+            .await
+            .unwrap();
+        assert_eq!(invitation.status, "expired");
+    }
+
+    #[test]
+    fn test_parse_bulk_csv_rows() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            "text/csv; charset=utf-8".parse().unwrap(),
+        );
+        let body = concat!(
+            "Email,Role\n",
+            " ada@example.com , admin\n",
+            "\n",
+            "\"grace@example.com\",\"billing, eu\"\n",
+            "alan@example.com\n",
+            ",member\n",
+        );
+
+        let rows = parse_bulk_rows(&headers, body.as_bytes()).unwrap();
+        let rows: Vec<(&str, Option<&str>)> = rows
+            .iter()
+            .map(|row| (row.email.as_str(), row.role.as_deref()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("ada@example.com", Some("admin")),
+                ("grace@example.com", Some("billing, eu")),
+                ("alan@example.com", None),
+            ]
+        );
+
+        // Only the first line can be a header
+        let rows = parse_bulk_rows(&headers, b"ada@example.com\nemail\n").unwrap();
+        assert_eq!(rows.len(), 2);
+
+        assert!(matches!(
+            parse_bulk_rows(&headers, b"ada@example.com,\xff\xfe\n"),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_bulk_json_rows() {
+        let body = br#"{"invitations": [
+            {"email": "ada@example.com", "role": "admin"},
+            {"email": "grace@example.com"}
+        ]}"#;
+        let rows = parse_bulk_rows(&HeaderMap::new(), body).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].role, None);
+
+        assert!(matches!(
+            parse_bulk_rows(&HeaderMap::new(), b"email,role\nada@example.com,admin"),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_dry_run_reports_rows_and_writes_nothing() {
+        let pool = test_support::test_pool().await;
+        let owner = test_support::insert_user(&pool, "owner@example.com").await;
+        let org = test_support::insert_org(&pool, "acme", &owner).await;
+
+        let rows = [
+            ("ada@example.com", None),
+            ("not-an-email", None),
+            ("ADA@example.com", Some("admin")),
+            ("owner@example.com", None),
+            ("grace@example.com", Some("owner")),
+        ]
+        .into_iter()
+        .map(|(email, role)| BulkInvitationRow {
+            email: email.to_string(),
+            role: role.map(str::to_string),
+        })
+        .collect();
+
+        let results = check_bulk_rows(&pool, &org, "owner", rows).await.unwrap();
+        let statuses: Vec<(usize, &str)> = results
+            .iter()
+            .map(|result| (result.row, result.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![(1, "valid"), (2, "failed"), (3, "failed"), (4, "failed"), (5, "failed")]
+        );
+        assert_eq!(results[0].role, "member");
+        assert_eq!(
+            results[2].error.as_deref(),
+            Some("Duplicate email in this request")
+        );
+        assert!(results.iter().all(|result| result.invitation_id.is_none()));
+
+        let written: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM organization_invitations)
+                  + (SELECT COUNT(*) FROM organization_audit_log)",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(written, 0);
+    }
+}
@@ -0,0 +1,605 @@
+use crate::auth::domains::DomainService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::auth::scim::ScimService;
+use crate::billing::tiers::TierService;
+use crate::constants::{
+    INVITE_LINK_DEFAULT_EXPIRY_DAYS, MAX_INVITE_LINK_EXPIRY_DAYS, MAX_INVITE_LINK_USES,
+};
+use crate::db::models::{Membership, Organization, OrganizationInviteLink, User};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::invitations::{hash_invitation_token, validate_invitation_role};
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteLinkRequest {
+    pub role: Option<String>, // defaults to member
+    pub max_uses: i64,
+    pub expires_in_days: Option<i64>,
+    pub allowed_domain: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemInviteLinkRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InviteLinkResponse {
+    pub id: String,
+    pub role: String,
+    pub max_uses: i64,
+    pub use_count: i64,
+    pub allowed_domain: Option<String>,
+    pub status: &'static str, // 'active', 'exhausted', 'expired' or 'revoked'
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OrganizationInviteLink> for InviteLinkResponse {
+    fn from(link: OrganizationInviteLink) -> Self {
+        let status = if link.revoked_at.is_some() {
+            "revoked"
+        } else if link.expires_at <= Utc::now() {
+            "expired"
+        } else if link.use_count >= link.max_uses {
+            "exhausted"
+        } else {
+            "active"
+        };
+
+        Self {
+            id: link.id,
+            role: link.role,
+            max_uses: link.max_uses,
+            use_count: link.use_count,
+            allowed_domain: link.allowed_domain,
+            status,
+            expires_at: link.expires_at,
+            created_by: link.created_by,
+            revoked_at: link.revoked_at,
+            created_at: link.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateInviteLinkResponse {
+    pub link: InviteLinkResponse,
+    pub token: String, // Plaintext token (only returned once)
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct InviteLinkRedemption {
+    pub user_id: String,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedeemInviteLinkResponse {
+    pub organization: Organization,
+    pub role: String,
+}
+
+async fn find_organization(
+    pool: &SqlitePool,
+    auth_user: &AuthUser,
+    org_slug: &str,
+) -> Result<(Organization, Membership)> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    let membership = crate::middleware::check_org_permission(
+        pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_invitations",
+    )
+    .await?;
+
+    Ok((org, membership))
+}
+
+async fn find_link(
+    pool: &SqlitePool,
+    org_id: &str,
+    link_id: &str,
+) -> Result<OrganizationInviteLink> {
+    sqlx::query_as::<_, OrganizationInviteLink>(
+        "SELECT * FROM organization_invite_links WHERE id = ? AND org_id = ?",
+    )
+    .bind(link_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Invite link not found".to_string()))
+}
+
+/// POST /api/organizations/:org_slug/invite-links
+/// Create a shareable join link (requires manage_invitations)
+pub async fn create_invite_link(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Json(req): Json<CreateInviteLinkRequest>,
+) -> Result<(StatusCode, Json<CreateInviteLinkResponse>)> {
+    let (org, membership) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+
+    let role = req.role.unwrap_or_else(|| "member".to_string());
+    validate_invitation_role(&state.pool, &org.id, &membership.role, &role).await?;
+
+    if !(1..=MAX_INVITE_LINK_USES).contains(&req.max_uses) {
+        return Err(AppError::BadRequest(format!(
+            "max_uses must be between 1 and {}",
+            MAX_INVITE_LINK_USES
+        )));
+    }
+    let expires_in_days = req
+        .expires_in_days
+        .unwrap_or(INVITE_LINK_DEFAULT_EXPIRY_DAYS);
+    if !(1..=MAX_INVITE_LINK_EXPIRY_DAYS).contains(&expires_in_days) {
+        return Err(AppError::BadRequest(format!(
+            "expires_in_days must be between 1 and {}",
+            MAX_INVITE_LINK_EXPIRY_DAYS
+        )));
+    }
+    let allowed_domain = match req.allowed_domain.as_deref().map(str::trim) {
+        Some(domain) if !domain.is_empty() => Some(DomainService::normalize(domain)?),
+        _ => None,
+    };
+
+    let token = Uuid::new_v4().simple().to_string();
+    let link = sqlx::query_as::<_, OrganizationInviteLink>(
+        r#"
+        INSERT INTO organization_invite_links
+            (id, org_id, token_hash, role, max_uses, use_count, allowed_domain, expires_at, created_by, created_at)
+        VALUES (?, ?, ?, ?, ?, 0, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&org.id)
+    .bind(hash_invitation_token(&token))
+    .bind(&role)
+    .bind(req.max_uses)
+    .bind(&allowed_domain)
+    .bind(Utc::now() + Duration::days(expires_in_days))
+    .bind(&auth_user.user.id)
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "invite_link_created",
+        "invite_link",
+        &link.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!(InviteLinkResponse::from(link.clone())),
+        ),
+    )
+    .await?;
+
+    tracing::info!(
+        org_slug = %org_slug,
+        link_id = %link.id,
+        role = %role,
+        creator_id = %auth_user.user.id,
+        "Created organization invite link"
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateInviteLinkResponse {
+            link: link.into(),
+            token,
+        }),
+    ))
+}
+
+/// GET /api/organizations/:org_slug/invite-links
+pub async fn list_invite_links(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<Vec<InviteLinkResponse>>> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+
+    let links = sqlx::query_as::<_, OrganizationInviteLink>(
+        "SELECT * FROM organization_invite_links WHERE org_id = ? ORDER BY created_at DESC",
+    )
+    .bind(&org.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(links.into_iter().map(Into::into).collect()))
+}
+
+/// GET /api/organizations/:org_slug/invite-links/:link_id/redemptions
+pub async fn list_invite_link_redemptions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, link_id)): Path<(String, String)>,
+) -> Result<Json<Vec<InviteLinkRedemption>>> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let link = find_link(&state.pool, &org.id, &link_id).await?;
+
+    let redemptions = sqlx::query_as::<_, InviteLinkRedemption>(
+        r#"
+        SELECT r.user_id, u.email, r.created_at
+        FROM organization_invite_link_redemptions r
+        JOIN users u ON r.user_id = u.id
+        WHERE r.link_id = ?
+        ORDER BY r.created_at DESC
+        "#,
+    )
+    .bind(&link.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(redemptions))
+}
+
+/// DELETE /api/organizations/:org_slug/invite-links/:link_id
+/// Revoke a link; members who already joined through it stay
+pub async fn revoke_invite_link(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, link_id)): Path<(String, String)>,
+) -> Result<Json<InviteLinkResponse>> {
+    let (org, _) = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let link = find_link(&state.pool, &org.id, &link_id).await?;
+    let before = serde_json::json!(InviteLinkResponse::from(link.clone()));
+    if link.revoked_at.is_some() {
+        return Err(AppError::BadRequest(
+            "Invite link is already revoked".to_string(),
+        ));
+    }
+
+    let link = sqlx::query_as::<_, OrganizationInviteLink>(
+        "UPDATE organization_invite_links SET revoked_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(Utc::now())
+    .bind(&link.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "invite_link_revoked",
+        "invite_link",
+        &link.id,
+        diff(
+            &before,
+            &serde_json::json!(InviteLinkResponse::from(link.clone())),
+        ),
+    )
+    .await?;
+
+    Ok(Json(link.into()))
+}
+
+/// POST /api/invite-links/redeem
+/// Join an organization through an invite link, signed in from the admin login
+pub async fn redeem_invite_link(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Json(req): Json<RedeemInviteLinkRequest>,
+) -> Result<Json<RedeemInviteLinkResponse>> {
+    crate::middleware::ensure_interactive_session(&auth_user)?;
+    if auth_user.claims.service.is_some() {
+        return Err(AppError::Forbidden(
+            "Invite links must be redeemed from an admin login".to_string(),
+        ));
+    }
+
+    Ok(Json(
+        redeem(&state.pool, &audit, &auth_user.user, &req.token).await?,
+    ))
+}
+
+/// Add the user to the link's organization, claiming one of its uses
+async fn redeem(
+    pool: &SqlitePool,
+    audit: &AuditContext,
+    user: &User,
+    token: &str,
+) -> Result<RedeemInviteLinkResponse> {
+    let mut tx = pool.begin().await?;
+
+    let link = sqlx::query_as::<_, OrganizationInviteLink>(
+        "SELECT * FROM organization_invite_links WHERE token_hash = ?",
+    )
+    .bind(hash_invitation_token(token.trim()))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Invite link not found".to_string()))?;
+
+    if link.revoked_at.is_some() {
+        return Err(AppError::BadRequest(
+            "Invite link has been revoked".to_string(),
+        ));
+    }
+    if link.expires_at <= Utc::now() {
+        return Err(AppError::InvitationExpired);
+    }
+    if link.use_count >= link.max_uses {
+        return Err(AppError::BadRequest(
+            "Invite link has reached its usage limit".to_string(),
+        ));
+    }
+
+    // The account email must be on the domain and verified by a provider
+    if let Some(ref allowed_domain) = link.allowed_domain {
+        let verified: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM identities
+             WHERE user_id = ? AND email = ? COLLATE NOCASE AND email_verified = 1",
+        )
+        .bind(&user.id)
+        .bind(&user.email)
+        .fetch_one(&mut *tx)
+        .await?;
+        if DomainService::email_domain(&user.email).as_ref() != Some(allowed_domain)
+            || verified == 0
+        {
+            return Err(AppError::Forbidden(format!(
+                "This invite link is only for verified {} email addresses",
+                allowed_domain
+            )));
+        }
+    }
+
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE id = ?")
+        .bind(&link.org_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let already_member: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ? AND user_id = ?")
+            .bind(&org.id)
+            .bind(&user.id)
+            .fetch_one(&mut *tx)
+            .await?;
+    if already_member > 0 {
+        return Err(AppError::BadRequest(
+            "You are already a member of this organization".to_string(),
+        ));
+    }
+    if ScimService::is_deactivated(&mut *tx, &org.id, &user.id).await? {
+        return Err(AppError::Forbidden(
+            "Your account has been deactivated by your organization".to_string(),
+        ));
+    }
+
+    // A custom role cannot be deleted while a live link grants it, but check
+    // rather than hand out a role that no longer exists
+    if !OrgRoleService::role_exists(&mut *tx, &org.id, &link.role).await? {
+        return Err(AppError::BadRequest(format!(
+            "The role '{}' granted by this invite link no longer exists",
+            link.role
+        )));
+    }
+
+    // Same team limit check as accepting an invitation
+    let member_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM memberships WHERE org_id = ?")
+        .bind(&org.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if member_count >= TierService::member_limit(&mut *tx, &org).await? {
+        return Err(AppError::TeamLimitExceeded(
+            "Team limit reached".to_string(),
+        ));
+    }
+
+    // Claim a use; a concurrent redemption may have taken the last one
+    let claimed = sqlx::query(
+        "UPDATE organization_invite_links SET use_count = use_count + 1
+         WHERE id = ? AND use_count < max_uses",
+    )
+    .bind(&link.id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if claimed == 0 {
+        return Err(AppError::BadRequest(
+            "Invite link has reached its usage limit".to_string(),
+        ));
+    }
+
+    sqlx::query(
+        "INSERT INTO memberships (id, org_id, user_id, role, created_at) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&org.id)
+    .bind(&user.id)
+    .bind(&link.role)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO organization_invite_link_redemptions (id, link_id, user_id, created_at)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&link.id)
+    .bind(&user.id)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    create_org_audit_log(
+        &mut tx,
+        audit,
+        &org.id,
+        "invite_link_redeemed",
+        "invite_link",
+        &link.id,
+        diff(
+            &serde_json::json!({ "use_count": link.use_count }),
+            &serde_json::json!({
+                "use_count": link.use_count + 1,
+                "user_id": user.id,
+                "role": link.role,
+            }),
+        ),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        org_id = %org.id,
+        link_id = %link.id,
+        user_id = %user.id,
+        "Redeemed organization invite link"
+    );
+
+    Ok(RedeemInviteLinkResponse {
+        organization: org,
+        role: link.role,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support;
+
+    fn audit(user: &User) -> AuditContext {
+        AuditContext {
+            actor_id: user.id.clone(),
+            impersonator_id: None,
+            api_token_id: None,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+        }
+    }
+
+    async fn insert_link(
+        pool: &SqlitePool,
+        org: &Organization,
+        token: &str,
+        max_uses: i64,
+        allowed_domain: Option<&str>,
+    ) {
+        sqlx::query(
+            "INSERT INTO organization_invite_links
+                 (id, org_id, token_hash, role, max_uses, allowed_domain, expires_at, created_at)
+             VALUES (?, ?, ?, 'member', ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&org.id)
+        .bind(hash_invitation_token(token))
+        .bind(max_uses)
+        .bind(allowed_domain)
+        .bind(Utc::now() + Duration::days(1))
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn insert_identity(pool: &SqlitePool, user: &User, email: &str, verified: bool) {
+        sqlx::query(
+            "INSERT INTO identities (id, user_id, provider, provider_user_id, email, email_verified)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&user.id)
+        .bind(if verified { "google" } else { "github" })
+        .bind(Uuid::new_v4().to_string())
+        .bind(email)
+        .bind(verified)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_links_stop_at_their_use_limit() {
+        let pool = test_support::test_pool().await;
+        let owner = test_support::insert_user(&pool, "owner@example.com").await;
+        let org = test_support::insert_org(&pool, "acme", &owner).await;
+        insert_link(&pool, &org, "two-uses", 2, None).await;
+
+        for email in ["ada@example.com", "grace@example.com"] {
+            let user = test_support::insert_user(&pool, email).await;
+            let joined = redeem(&pool, &audit(&user), &user, "two-uses")
+                .await
+                .unwrap();
+            assert_eq!(joined.organization.id, org.id);
+            assert_eq!(joined.role, "member");
+        }
+
+        let late = test_support::insert_user(&pool, "late@example.com").await;
+        assert!(matches!(
+            redeem(&pool, &audit(&late), &late, "two-uses").await,
+            Err(AppError::BadRequest(_))
+        ));
+
+        let (use_count, members): (i64, i64) = sqlx::query_as(
+            "SELECT (SELECT use_count FROM organization_invite_links),
+                    (SELECT COUNT(*) FROM memberships WHERE org_id = ?)",
+        )
+        .bind(&org.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((use_count, members), (2, 3));
+    }
+
+    #[tokio::test]
+    async fn test_domain_links_need_a_provider_verified_email() {
+        let pool = test_support::test_pool().await;
+        let owner = test_support::insert_user(&pool, "owner@customer.com").await;
+        let org = test_support::insert_org(&pool, "customer", &owner).await;
+        insert_link(&pool, &org, "customers-only", 10, Some("customer.com")).await;
+
+        // On the domain, but no provider has verified the address
+        let ada = test_support::insert_user(&pool, "ada@customer.com").await;
+        insert_identity(&pool, &ada, "ada@customer.com", false).await;
+        assert!(matches!(
+            redeem(&pool, &audit(&ada), &ada, "customers-only").await,
+            Err(AppError::Forbidden(_))
+        ));
+
+        // Verified, but for another domain
+        let grace = test_support::insert_user(&pool, "grace@elsewhere.com").await;
+        insert_identity(&pool, &grace, "grace@elsewhere.com", true).await;
+        assert!(matches!(
+            redeem(&pool, &audit(&grace), &grace, "customers-only").await,
+            Err(AppError::Forbidden(_))
+        ));
+
+        insert_identity(&pool, &ada, "Ada@Customer.com", true).await;
+        redeem(&pool, &audit(&ada), &ada, "customers-only")
+            .await
+            .unwrap();
+    }
+}
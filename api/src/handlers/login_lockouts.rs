@@ -0,0 +1,224 @@
+use crate::auth::login_failures::{LoginFailureService, LOCKOUT_SCOPES};
+use crate::db::models::{LoginLockout, Organization};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::handlers::platform::create_audit_log;
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Deserialize)]
+pub struct LoginLockoutQuery {
+    pub scope: Option<String>,
+    pub user_id: Option<String>,
+    pub locked: Option<bool>, // only counters that are locked right now
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginLockoutResponse {
+    pub lockouts: Vec<LoginLockout>,
+    pub total: i64,
+}
+
+fn require_platform_owner(auth_user: &AuthUser) -> Result<()> {
+    if !auth_user.user.is_platform_owner {
+        return Err(AppError::Forbidden(
+            "Platform owner access required".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Resolve an organization and check the user has signed in to, or tried to
+/// sign in to, one of its services
+async fn find_org_end_user(
+    pool: &SqlitePool,
+    auth_user: &AuthUser,
+    org_slug: &str,
+    user_id: &str,
+    permission: &str,
+) -> Result<Organization> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(pool, &auth_user.user.id, &org.id, permission).await?;
+
+    let known: i64 = sqlx::query_scalar(
+        "SELECT
+             (SELECT COUNT(*) FROM subscriptions sub
+              JOIN services s ON sub.service_id = s.id
+              WHERE sub.user_id = ? AND s.org_id = ?)
+           + (SELECT COUNT(*) FROM identities WHERE user_id = ? AND issuing_org_id = ?)",
+    )
+    .bind(user_id)
+    .bind(&org.id)
+    .bind(user_id)
+    .bind(&org.id)
+    .fetch_one(pool)
+    .await?;
+
+    if known == 0 {
+        return Err(AppError::NotFound(
+            "User is not an end-user of this organization".to_string(),
+        ));
+    }
+
+    Ok(org)
+}
+
+/// GET /api/organizations/:org_slug/users/:user_id/lockouts
+/// Failed-login counters on an end-user and their identities
+pub async fn list_end_user_lockouts(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, user_id)): Path<(String, String)>,
+) -> Result<Json<Vec<LoginLockout>>> {
+    find_org_end_user(
+        &state.pool,
+        &auth_user,
+        &org_slug,
+        &user_id,
+        "view_end_users",
+    )
+    .await?;
+
+    Ok(Json(
+        LoginFailureService::list_for_user(&state.pool, &user_id).await?,
+    ))
+}
+
+/// DELETE /api/organizations/:org_slug/users/:user_id/lockouts
+/// Unlock an end-user and their identities. Lockouts on IP addresses are
+/// left to the platform owner.
+pub async fn unlock_end_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, user_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>> {
+    let org = find_org_end_user(
+        &state.pool,
+        &auth_user,
+        &org_slug,
+        &user_id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let cleared = LoginFailureService::unlock_user(&state.pool, &user_id).await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "end_user_unlocked",
+        "user",
+        &user_id,
+        diff(&json!({ "lockouts": cleared }), &json!({ "lockouts": 0 })),
+    )
+    .await?;
+
+    Ok(Json(json!({
+        "message": "User unlocked",
+        "cleared_count": cleared
+    })))
+}
+
+/// GET /api/platform/login-lockouts
+/// Failed-login counters across the platform, most recent failure first
+pub async fn list_login_lockouts(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<LoginLockoutQuery>,
+) -> Result<Json<LoginLockoutResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    if let Some(ref scope) = query.scope {
+        if !LOCKOUT_SCOPES.contains(&scope.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid scope: {} (expected user, identity or ip)",
+                scope
+            )));
+        }
+    }
+    let locked_only = query.locked.unwrap_or(false);
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let condition = "(? IS NULL OR scope = ?) AND (? IS NULL OR user_id = ?)
+                     AND (? = 0 OR locked_until > ?)";
+    let now = Utc::now();
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM login_lockouts WHERE {}",
+        condition
+    ))
+    .bind(&query.scope)
+    .bind(&query.scope)
+    .bind(&query.user_id)
+    .bind(&query.user_id)
+    .bind(locked_only)
+    .bind(now)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let lockouts = sqlx::query_as::<_, LoginLockout>(&format!(
+        "SELECT * FROM login_lockouts WHERE {} ORDER BY last_failure_at DESC LIMIT ? OFFSET ?",
+        condition
+    ))
+    .bind(&query.scope)
+    .bind(&query.scope)
+    .bind(&query.user_id)
+    .bind(&query.user_id)
+    .bind(locked_only)
+    .bind(now)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(LoginLockoutResponse { lockouts, total }))
+}
+
+/// DELETE /api/platform/login-lockouts/:scope/:subject
+/// Clear one counter, e.g. an office IP locked out by a misconfigured client
+pub async fn clear_login_lockout(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((scope, subject)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    require_platform_owner(&auth_user)?;
+
+    let lockout = LoginFailureService::find(&state.pool, &scope, &subject)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Login lockout not found".to_string()))?;
+    LoginFailureService::unlock(&state.pool, &scope, &subject).await?;
+
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "clear_login_lockout",
+        "login_lockout",
+        &format!("{}:{}", scope, subject),
+        Some(json!({
+            "failures": lockout.failures,
+            "locked_until": lockout.locked_until,
+            "user_id": lockout.user_id,
+        })),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
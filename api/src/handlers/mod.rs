@@ -1,10 +1,27 @@
+pub mod account_merge;
 pub mod analytics;
+pub mod api_tokens;
 pub mod auth;
+pub mod ciba;
+pub mod domains;
+pub mod email;
+pub mod groups;
 pub mod identities;
+pub mod impersonation;
 pub mod invitations;
+pub mod invite_links;
+pub mod login_lockouts;
+pub mod org_audit;
+pub mod org_roles;
 pub mod organizations;
 pub mod platform;
+pub mod privacy;
 pub mod provider_token;
+pub mod scim;
+pub mod service_roles;
 pub mod services;
+pub mod siem;
+pub mod sessions;
 pub mod subscription;
 pub mod webhook;
+pub mod webhook_endpoints;
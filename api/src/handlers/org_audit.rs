@@ -0,0 +1,323 @@
+use crate::auth::audit_chain::{AuditChain, AuditChainService, ChainVerification};
+use crate::db::models::{Organization, OrganizationAuditLog};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+/// Fields never written to the audit log; a change to them shows as redacted
+const REDACTED_FIELD_MARKERS: &[&str] = &["secret", "hash", "encrypted", "token"];
+
+/// Bookkeeping fields left out of diffs
+const IGNORED_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+#[derive(Debug, Deserialize)]
+pub struct OrgAuditLogQuery {
+    pub action: Option<String>,
+    pub actor_id: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub request_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct OrgAuditLogEntry {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub log: OrganizationAuditLog,
+    pub actor_email: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgAuditLogResponse {
+    pub logs: Vec<OrgAuditLogEntry>,
+    pub total: i64,
+}
+
+// ============================================================================
+// Audit Log Helpers
+// ============================================================================
+
+/// Create an organization audit log entry, linked to the end of the
+/// organization's audit chain
+pub async fn create_org_audit_log(
+    conn: &mut SqliteConnection,
+    audit: &AuditContext,
+    org_id: &str,
+    action: &str,
+    target_type: &str,
+    target_id: &str,
+    changes: Option<Value>,
+) -> Result<()> {
+    let chain = AuditChain::Organization(org_id.to_string());
+    let mut log = OrganizationAuditLog {
+        id: Uuid::new_v4().to_string(),
+        org_id: org_id.to_string(),
+        actor_id: audit.actor_id.clone(),
+        impersonator_id: audit.impersonator_id.clone(),
+        api_token_id: audit.api_token_id.clone(),
+        action: action.to_string(),
+        target_type: target_type.to_string(),
+        target_id: target_id.to_string(),
+        changes: changes.map(|c| c.to_string()),
+        ip_address: audit.ip_address.clone(),
+        user_agent: audit.user_agent.clone(),
+        request_id: audit.request_id.clone(),
+        created_at: AuditChainService::now(),
+        seq: None,
+        prev_hash: None,
+        entry_hash: None,
+    };
+
+    let mut attempt = 1;
+    loop {
+        AuditChainService::link(conn, &chain, &mut log).await?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO organization_audit_log
+                (id, org_id, actor_id, impersonator_id, api_token_id, action, target_type, target_id,
+                 changes, ip_address, user_agent, request_id, created_at, seq, prev_hash, entry_hash)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&log.id)
+        .bind(&log.org_id)
+        .bind(&log.actor_id)
+        .bind(&log.impersonator_id)
+        .bind(&log.api_token_id)
+        .bind(&log.action)
+        .bind(&log.target_type)
+        .bind(&log.target_id)
+        .bind(&log.changes)
+        .bind(&log.ip_address)
+        .bind(&log.user_agent)
+        .bind(&log.request_id)
+        .bind(log.created_at)
+        .bind(log.seq)
+        .bind(&log.prev_hash)
+        .bind(&log.entry_hash)
+        .execute(&mut *conn)
+        .await;
+
+        match inserted {
+            Ok(_) => return Ok(()),
+            Err(e) if AuditChainService::should_retry_append(&e, attempt) => attempt += 1,
+            Err(e) => return Err(AppError::Database(e)),
+        }
+    }
+}
+
+/// Field-level changes between two JSON objects, as
+/// `{"field": {"before": ..., "after": ...}}`. Pass `Value::Null` for the
+/// side of a record that does not exist (created or deleted). Returns `None`
+/// when nothing changed.
+pub fn diff(before: &Value, after: &Value) -> Option<Value> {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if changes.contains_key(key) || IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old == new {
+            continue;
+        }
+
+        let redact = |value: &Value| {
+            if value.is_null() {
+                Value::Null
+            } else {
+                Value::String("[redacted]".to_string())
+            }
+        };
+        let (old, new) = if REDACTED_FIELD_MARKERS.iter().any(|m| key.contains(m)) {
+            (redact(old), redact(new))
+        } else {
+            (old.clone(), new.clone())
+        };
+        changes.insert(
+            key.clone(),
+            serde_json::json!({ "before": old, "after": new }),
+        );
+    }
+
+    if changes.is_empty() {
+        None
+    } else {
+        Some(Value::Object(changes))
+    }
+}
+
+// ============================================================================
+// Audit Log Endpoints
+// ============================================================================
+
+/// GET /api/organizations/:org_slug/audit-log
+/// Changes made to the organization, newest first
+pub async fn list_org_audit_log(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+    Query(query): Query<OrgAuditLogQuery>,
+) -> Result<Json<OrgAuditLogResponse>> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(&org_slug)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "view_audit_log",
+    )
+    .await?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    // Build dynamic query based on filters
+    let mut conditions = vec!["l.org_id = ?"];
+    let mut bind_values: Vec<String> = vec![org.id.clone()];
+
+    for (column, value) in [
+        ("l.action = ?", &query.action),
+        ("l.actor_id = ?", &query.actor_id),
+        ("l.target_type = ?", &query.target_type),
+        ("l.target_id = ?", &query.target_id),
+        ("l.request_id = ?", &query.request_id),
+    ] {
+        if let Some(value) = value {
+            conditions.push(column);
+            bind_values.push(value.clone());
+        }
+    }
+    let where_clause = format!("WHERE {}", conditions.join(" AND "));
+    let range_clause = "AND (? IS NULL OR l.created_at >= ?) AND (? IS NULL OR l.created_at < ?)";
+
+    let count_query = format!(
+        "SELECT COUNT(*) FROM organization_audit_log l {} {}",
+        where_clause, range_clause
+    );
+    let mut count_q = sqlx::query_scalar::<_, i64>(&count_query);
+    for value in &bind_values {
+        count_q = count_q.bind(value);
+    }
+    let total = count_q
+        .bind(query.since)
+        .bind(query.since)
+        .bind(query.until)
+        .bind(query.until)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let list_query = format!(
+        "SELECT l.*, u.email AS actor_email
+         FROM organization_audit_log l
+         LEFT JOIN users u ON u.id = l.actor_id
+         {} {}
+         ORDER BY l.created_at DESC LIMIT ? OFFSET ?",
+        where_clause, range_clause
+    );
+    let mut list_q = sqlx::query_as::<_, OrgAuditLogEntry>(&list_query);
+    for value in &bind_values {
+        list_q = list_q.bind(value);
+    }
+    let logs = list_q
+        .bind(query.since)
+        .bind(query.since)
+        .bind(query.until)
+        .bind(query.until)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(OrgAuditLogResponse { logs, total }))
+}
+
+/// GET /api/organizations/:org_slug/audit-log/verify
+/// Walk the organization's audit hash chain and report the first break
+pub async fn verify_org_audit_log(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<ChainVerification>> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(&org_slug)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "view_audit_log",
+    )
+    .await?;
+
+    let verification = AuditChainService::verify::<OrganizationAuditLog>(
+        &state.pool,
+        &state.jwt_service,
+        &AuditChain::Organization(org.id),
+    )
+    .await?;
+
+    Ok(Json(verification))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff() {
+        let before = json!({
+            "name": "Acme",
+            "redirect_uris": ["https://a.example"],
+            "client_secret": "old",
+            "updated_at": "2025-01-01T00:00:00Z",
+        });
+        let after = json!({
+            "name": "Acme Inc",
+            "redirect_uris": ["https://a.example"],
+            "client_secret": "new",
+            "updated_at": "2025-01-02T00:00:00Z",
+        });
+
+        assert_eq!(
+            diff(&before, &after),
+            Some(json!({
+                "name": { "before": "Acme", "after": "Acme Inc" },
+                "client_secret": { "before": "[redacted]", "after": "[redacted]" },
+            }))
+        );
+
+        // Creations have no before, and unchanged records no changes
+        assert_eq!(
+            diff(&Value::Null, &json!({ "slug": "web" })),
+            Some(json!({ "slug": { "before": null, "after": "web" } }))
+        );
+        assert_eq!(diff(&before, &before), None);
+    }
+}
@@ -0,0 +1,419 @@
+use crate::auth::org_roles::OrgRoleService;
+use crate::constants::{
+    ADMIN_ORG_PERMISSIONS, MAX_NAME_LENGTH, MEMBER_ORG_PERMISSIONS, MIN_NAME_LENGTH,
+    ORG_PERMISSIONS, VALID_ORG_ROLES,
+};
+use crate::db::models::{OrgRole, Organization};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::handlers::service_roles::normalize_permissions;
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrgRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrgRoleRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgRoleResponse {
+    pub id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub built_in: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<OrgRole> for OrgRoleResponse {
+    fn from(role: OrgRole) -> Self {
+        Self {
+            permissions: OrgRoleService::parse_permissions(&role.permissions),
+            id: Some(role.id),
+            name: role.name,
+            description: role.description,
+            built_in: false,
+            created_at: Some(role.created_at),
+            updated_at: Some(role.updated_at),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgRolesResponse {
+    /// Catalogue of permissions custom roles can be built from
+    pub available_permissions: Vec<&'static str>,
+    pub roles: Vec<OrgRoleResponse>,
+}
+
+async fn find_organization(pool: &SqlitePool, org_slug: &str) -> Result<Organization> {
+    sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
+
+async fn find_role(pool: &SqlitePool, org_id: &str, role_id: &str) -> Result<OrgRole> {
+    sqlx::query_as::<_, OrgRole>("SELECT * FROM org_roles WHERE id = ? AND org_id = ?")
+        .bind(role_id)
+        .bind(org_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Role not found".to_string()))
+}
+
+fn builtin_role(name: &str, description: &str, permissions: &[&str]) -> OrgRoleResponse {
+    OrgRoleResponse {
+        id: None,
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        built_in: true,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+/// Role names are stored on memberships and invitations; built-in names are reserved
+async fn validate_role_name(
+    pool: &SqlitePool,
+    org_id: &str,
+    name: &str,
+    except_role_id: Option<&str>,
+) -> Result<()> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if name.len() < MIN_NAME_LENGTH || name.len() > MAX_NAME_LENGTH || !valid_chars {
+        return Err(AppError::BadRequest(format!(
+            "Role name must be {} to {} letters, digits, hyphens or underscores",
+            MIN_NAME_LENGTH, MAX_NAME_LENGTH
+        )));
+    }
+    if VALID_ORG_ROLES.contains(&name) {
+        return Err(AppError::BadRequest(
+            "Role name is reserved for a built-in role".to_string(),
+        ));
+    }
+
+    let exists: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM org_roles WHERE org_id = ? AND name = ? AND id != ?",
+    )
+    .bind(org_id)
+    .bind(name)
+    .bind(except_role_id.unwrap_or_default())
+    .fetch_one(pool)
+    .await?;
+    if exists > 0 {
+        return Err(AppError::BadRequest(
+            "A role with this name already exists".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check permissions against the catalogue, then sort and de-duplicate them
+fn normalize_org_permissions(permissions: Vec<String>) -> Result<Vec<String>> {
+    normalize_permissions(permissions, |permission| {
+        if ORG_PERMISSIONS.contains(&permission) {
+            Ok(())
+        } else {
+            Err(AppError::BadRequest(format!(
+                "Unknown permission '{}'",
+                permission
+            )))
+        }
+    })
+}
+
+/// GET /api/organizations/:org_slug/roles
+/// Built-in roles followed by the organization's custom roles
+pub async fn list_org_roles(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<OrgRolesResponse>> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[]).await?;
+
+    let custom =
+        sqlx::query_as::<_, OrgRole>("SELECT * FROM org_roles WHERE org_id = ? ORDER BY name")
+            .bind(&org.id)
+            .fetch_all(&state.pool)
+            .await?;
+
+    let mut roles = vec![
+        builtin_role("owner", "Full control of the organization", ORG_PERMISSIONS),
+        builtin_role(
+            "admin",
+            "Manages the organization, except roles, domains and service deletion",
+            ADMIN_ORG_PERMISSIONS,
+        ),
+        builtin_role(
+            "member",
+            "Read access to the organization",
+            MEMBER_ORG_PERMISSIONS,
+        ),
+    ];
+    roles.extend(custom.into_iter().map(Into::into));
+
+    Ok(Json(OrgRolesResponse {
+        available_permissions: ORG_PERMISSIONS.to_vec(),
+        roles,
+    }))
+}
+
+/// POST /api/organizations/:org_slug/roles
+pub async fn create_org_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Json(req): Json<CreateOrgRoleRequest>,
+) -> Result<(StatusCode, Json<OrgRoleResponse>)> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    let membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_roles",
+    )
+    .await?;
+
+    let name = req.name.trim();
+    validate_role_name(&state.pool, &org.id, name, None).await?;
+    let permissions = normalize_org_permissions(req.permissions)?;
+    OrgRoleService::ensure_can_grant(&state.pool, &org.id, &membership.role, &permissions).await?;
+
+    let now = Utc::now();
+    let role = sqlx::query_as::<_, OrgRole>(
+        r#"
+        INSERT INTO org_roles (id, org_id, name, description, permissions, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&org.id)
+    .bind(name)
+    .bind(&req.description)
+    .bind(serde_json::to_string(&permissions).unwrap_or_else(|_| "[]".to_string()))
+    .bind(now)
+    .bind(now)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "role_created",
+        "role",
+        &role.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!(OrgRoleResponse::from(role.clone())),
+        ),
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(role.into())))
+}
+
+/// PATCH /api/organizations/:org_slug/roles/:role_id
+/// Renaming a role carries its members, pending invitations and invite links along
+pub async fn update_org_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, role_id)): Path<(String, String)>,
+    Json(req): Json<UpdateOrgRoleRequest>,
+) -> Result<Json<OrgRoleResponse>> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    let membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_roles",
+    )
+    .await?;
+
+    let role = find_role(&state.pool, &org.id, &role_id).await?;
+    let before = serde_json::json!(OrgRoleResponse::from(role.clone()));
+
+    // Non-owners cannot edit roles that grant more than they hold, nor widen one
+    let current = OrgRoleService::parse_permissions(&role.permissions);
+    OrgRoleService::ensure_can_grant(&state.pool, &org.id, &membership.role, &current).await?;
+    if membership.role == role.name {
+        return Err(AppError::Forbidden("Cannot edit your own role".to_string()));
+    }
+
+    let name = match req.name {
+        Some(ref name) => {
+            let name = name.trim();
+            validate_role_name(&state.pool, &org.id, name, Some(&role.id)).await?;
+            name.to_string()
+        }
+        None => role.name.clone(),
+    };
+    let description = req.description.or(role.description);
+    let permissions = match req.permissions {
+        Some(permissions) => {
+            let permissions = normalize_org_permissions(permissions)?;
+            OrgRoleService::ensure_can_grant(&state.pool, &org.id, &membership.role, &permissions)
+                .await?;
+            permissions
+        }
+        None => current,
+    };
+
+    let mut tx = state.pool.begin().await?;
+
+    let updated = sqlx::query_as::<_, OrgRole>(
+        r#"
+        UPDATE org_roles SET name = ?, description = ?, permissions = ?, updated_at = ?
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(&name)
+    .bind(&description)
+    .bind(serde_json::to_string(&permissions).unwrap_or_else(|_| "[]".to_string()))
+    .bind(Utc::now())
+    .bind(&role.id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if name != role.name {
+        sqlx::query("UPDATE memberships SET role = ? WHERE org_id = ? AND role = ?")
+            .bind(&name)
+            .bind(&org.id)
+            .bind(&role.name)
+            .execute(&mut *tx)
+            .await?;
+        // Expired invitations can still be reopened, so they follow the rename too
+        sqlx::query(
+            "UPDATE organization_invitations SET role = ?
+             WHERE org_id = ? AND role = ? AND status IN ('pending', 'expired')",
+        )
+        .bind(&name)
+        .bind(&org.id)
+        .bind(&role.name)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE organization_invite_links SET role = ? WHERE org_id = ? AND role = ?")
+            .bind(&name)
+            .bind(&org.id)
+            .bind(&role.name)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    create_org_audit_log(
+        &mut tx,
+        &audit,
+        &org.id,
+        "role_updated",
+        "role",
+        &updated.id,
+        diff(
+            &before,
+            &serde_json::json!(OrgRoleResponse::from(updated.clone())),
+        ),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(updated.into()))
+}
+
+/// DELETE /api/organizations/:org_slug/roles/:role_id
+/// Refused while members, pending invitations or usable invite links still use the role
+pub async fn delete_org_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, role_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    let org = find_organization(&state.pool, &org_slug).await?;
+    let membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_roles",
+    )
+    .await?;
+
+    let role = find_role(&state.pool, &org.id, &role_id).await?;
+    let current = OrgRoleService::parse_permissions(&role.permissions);
+    OrgRoleService::ensure_can_grant(&state.pool, &org.id, &membership.role, &current).await?;
+
+    let in_use: i64 = sqlx::query_scalar(
+        r#"
+        SELECT (SELECT COUNT(*) FROM memberships WHERE org_id = ? AND role = ?)
+             + (SELECT COUNT(*) FROM organization_invitations
+                WHERE org_id = ? AND role = ? AND status IN ('pending', 'expired'))
+             + (SELECT COUNT(*) FROM organization_invite_links
+                WHERE org_id = ? AND role = ? AND revoked_at IS NULL
+                  AND expires_at > ? AND use_count < max_uses)
+        "#,
+    )
+    .bind(&org.id)
+    .bind(&role.name)
+    .bind(&org.id)
+    .bind(&role.name)
+    .bind(&org.id)
+    .bind(&role.name)
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+    if in_use > 0 {
+        return Err(AppError::BadRequest(
+            "Role is still assigned to members, open or expired invitations, or invite links"
+                .to_string(),
+        ));
+    }
+
+    sqlx::query("DELETE FROM org_roles WHERE id = ?")
+        .bind(&role.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "role_deleted",
+        "role",
+        &role.id,
+        diff(
+            &serde_json::json!(OrgRoleResponse::from(role.clone())),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
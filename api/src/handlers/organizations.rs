@@ -1,10 +1,14 @@
 use crate::constants::{
-    DEFAULT_MAX_USERS, DEFAULT_TIER_NAME, MAX_NAME_LENGTH, MAX_SLUG_LENGTH, MIN_NAME_LENGTH, MIN_SLUG_LENGTH, RESERVED_SLUGS,
+    DEFAULT_MAX_USERS, DEFAULT_TIER_NAME, MAX_NAME_LENGTH, MAX_SLUG_LENGTH, LOGIN_RISK_ACTIONS, MAX_SSO_SESSION_LIFETIME_MINUTES, MIN_NAME_LENGTH, MIN_SLUG_LENGTH, RESERVED_SLUGS,
+    WEBHOOK_EVENT_MEMBER_ROLE_CHANGED,
 };
+use crate::auth::org_roles::OrgRoleService;
+use crate::auth::webhooks::WebhookService;
 use crate::db::models::{Membership, Organization, OrganizationTier, User};
 use crate::error::{AppError, Result};
 use crate::handlers::auth::AppState;
-use crate::middleware::AuthUser;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
 use axum::{
     extract::{Path, Query, State},
     Json,
@@ -25,6 +29,9 @@ pub struct CreateOrganizationPublicRequest {
 #[derive(Debug, Deserialize)]
 pub struct UpdateOrganizationRequest {
     pub name: Option<String>,
+    pub sso_session_lifetime_minutes: Option<i64>,
+    pub login_risk_medium_action: Option<String>,
+    pub login_risk_high_action: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -226,10 +233,11 @@ pub async fn get_organization(
     }))
 }
 
-/// Update organization (owner/admin only)
+/// Update organization (requires manage_organization)
 pub async fn update_organization(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path(org_slug): Path<String>,
     Json(req): Json<UpdateOrganizationRequest>,
 ) -> Result<Json<OrganizationResponse>> {
@@ -244,9 +252,29 @@ pub async fn update_organization(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner or admin
-    let _membership =
-        crate::middleware::check_org_admin(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may change organization settings
+    let _membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_organization",
+    )
+    .await?;
+
+    let risk_actions = [
+        ("login_risk_medium_action", &req.login_risk_medium_action),
+        ("login_risk_high_action", &req.login_risk_high_action),
+    ];
+    for (column, action) in &risk_actions {
+        if let Some(action) = action {
+            if !LOGIN_RISK_ACTIONS.contains(&action.as_str()) {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid {}: {} (expected allow, notify, require_email_code or block)",
+                    column, action
+                )));
+            }
+        }
+    }
 
     // Simple update approach
     let now = Utc::now();
@@ -273,8 +301,46 @@ pub async fn update_organization(
         .map_err(AppError::Database)?;
     }
 
+    if let Some(lifetime) = req.sso_session_lifetime_minutes {
+        if !(0..=MAX_SSO_SESSION_LIFETIME_MINUTES).contains(&lifetime) {
+            return Err(AppError::BadRequest(format!(
+                "SSO session lifetime must be between 0 and {} minutes",
+                MAX_SSO_SESSION_LIFETIME_MINUTES
+            )));
+        }
+        sqlx::query("UPDATE organizations SET sso_session_lifetime_minutes = ? WHERE id = ?")
+            .bind(lifetime)
+            .bind(&organization.id)
+            .execute(&state.pool)
+            .await
+            .map_err(AppError::Database)?;
+    }
+
+    for (column, action) in &risk_actions {
+        let Some(action) = action else {
+            continue;
+        };
+        sqlx::query(&format!("UPDATE organizations SET {} = ? WHERE id = ?", column))
+            .bind(action)
+            .bind(&organization.id)
+            .execute(&state.pool)
+            .await
+            .map_err(AppError::Database)?;
+    }
+
     // Fetch updated organization
     let updated_org = get_organization_by_id(&state.pool, &organization.id).await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &organization.id,
+        "organization_updated",
+        "organization",
+        &organization.id,
+        diff(&serde_json::json!(organization), &serde_json::json!(updated_org)),
+    )
+    .await?;
     let (membership_count, service_count, tier) =
         get_organization_stats(&state.pool, &organization.id).await?;
 
@@ -313,7 +379,7 @@ pub async fn list_members(
 
     // Use simple query approach to avoid sqlx macro issues
     let members = if let Some(ref role_filter) = query.role {
-        sqlx::query("SELECT u.id, u.email, u.is_platform_owner, u.created_at, m.id as membership_id, m.role as membership_role, m.created_at as membership_created_at FROM users u JOIN memberships m ON u.id = m.user_id WHERE m.org_id = ? AND m.role = ? ORDER BY m.created_at ASC LIMIT ? OFFSET ?")
+        sqlx::query("SELECT u.*, m.id as membership_id, m.role as membership_role, m.created_at as membership_created_at FROM users u JOIN memberships m ON u.id = m.user_id WHERE m.org_id = ? AND m.role = ? ORDER BY m.created_at ASC LIMIT ? OFFSET ?")
             .bind(&organization.id)
             .bind(role_filter)
             .bind(limit)
@@ -322,7 +388,7 @@ pub async fn list_members(
             .await
             .map_err(AppError::Database)?
     } else {
-        sqlx::query("SELECT u.id, u.email, u.is_platform_owner, u.created_at, m.id as membership_id, m.role as membership_role, m.created_at as membership_created_at FROM users u JOIN memberships m ON u.id = m.user_id WHERE m.org_id = ? ORDER BY m.created_at ASC LIMIT ? OFFSET ?")
+        sqlx::query("SELECT u.*, m.id as membership_id, m.role as membership_role, m.created_at as membership_created_at FROM users u JOIN memberships m ON u.id = m.user_id WHERE m.org_id = ? ORDER BY m.created_at ASC LIMIT ? OFFSET ?")
             .bind(&organization.id)
             .bind(limit)
             .bind(offset)
@@ -339,6 +405,12 @@ pub async fn list_members(
                 email: row.get("email"),
                 is_platform_owner: row.get("is_platform_owner"),
                 created_at: row.get("created_at"),
+                name: row.get("name"),
+                given_name: row.get("given_name"),
+                family_name: row.get("family_name"),
+                avatar_url: row.get("avatar_url"),
+                locale: row.get("locale"),
+                primary_identity_id: row.get("primary_identity_id"),
             };
             let membership = crate::db::models::Membership {
                 id: row.get("membership_id"),
@@ -392,14 +464,16 @@ pub async fn list_members(
     }))
 }
 
-/// Update member role (owner only)
+/// Update member role (requires manage_roles)
 pub async fn update_member_role(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path((org_slug, user_id)): Path<(String, String)>,
     Json(req): Json<UpdateMemberRoleRequest>,
 ) -> Result<Json<OrganizationMember>> {
     let user = &auth_user.user;
+    crate::middleware::ensure_not_impersonating(&auth_user)?;
 
     // Find organization
     let organization =
@@ -410,13 +484,19 @@ pub async fn update_member_role(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner
-    crate::middleware::check_org_owner(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may assign roles
+    let caller_membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_roles",
+    )
+    .await?;
 
-    // Validate role
-    if !crate::constants::VALID_ORG_ROLES.contains(&req.role.as_str()) {
+    // Validate role (built-in or one of the organization's custom roles)
+    if !OrgRoleService::role_exists(&state.pool, &organization.id, &req.role).await? {
         return Err(AppError::BadRequest(
-            "Invalid role. Must be owner, admin, or member".to_string(),
+            "Invalid role. Must be owner, admin, member, or a custom role".to_string(),
         ));
     }
 
@@ -438,6 +518,23 @@ pub async fn update_member_role(
     .map_err(AppError::Database)?
     .ok_or_else(|| AppError::NotFound("User is not a member of this organization".to_string()))?;
 
+    // Non-owners can neither touch members at or above their level nor hand
+    // out a role that does so
+    OrgRoleService::ensure_can_manage(
+        &state.pool,
+        &organization.id,
+        &caller_membership.role,
+        &membership.role,
+    )
+    .await?;
+    OrgRoleService::ensure_can_manage(
+        &state.pool,
+        &organization.id,
+        &caller_membership.role,
+        &req.role,
+    )
+    .await?;
+
     // Update role
     sqlx::query!(
         "UPDATE memberships SET role = ? WHERE id = ?",
@@ -491,19 +588,79 @@ pub async fn update_member_role(
     .await
     .map_err(AppError::Database)?;
 
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &organization.id,
+        "member_role_changed",
+        "member",
+        &user_id,
+        diff(
+            &serde_json::json!({ "role": membership.role }),
+            &serde_json::json!({ "role": req.role }),
+        ),
+    )
+    .await?;
+    if req.role == "owner" {
+        create_org_audit_log(
+            &mut *state.pool.acquire().await?,
+            &audit,
+            &organization.id,
+            "member_role_changed",
+            "member",
+            &user.id,
+            diff(
+                &serde_json::json!({ "role": "owner" }),
+                &serde_json::json!({ "role": "admin" }),
+            ),
+        )
+        .await?;
+    }
+
+    let mut conn = state.pool.acquire().await.map_err(AppError::Database)?;
+    WebhookService::emit(
+        &mut conn,
+        &organization.id,
+        WEBHOOK_EVENT_MEMBER_ROLE_CHANGED,
+        serde_json::json!({
+            "user_id": user_id,
+            "old_role": membership.role,
+            "new_role": req.role,
+            "changed_by": user.id,
+        }),
+    )
+    .await?;
+    // Handing over ownership demotes the caller
+    if req.role == "owner" {
+        WebhookService::emit(
+            &mut conn,
+            &organization.id,
+            WEBHOOK_EVENT_MEMBER_ROLE_CHANGED,
+            serde_json::json!({
+                "user_id": user.id,
+                "old_role": "owner",
+                "new_role": "admin",
+                "changed_by": user.id,
+            }),
+        )
+        .await?;
+    }
+
     Ok(Json(OrganizationMember {
         user: target_user,
         membership: updated_membership,
     }))
 }
 
-/// Remove member from organization (owner/admin only)
+/// Remove member from organization (requires manage_members)
 pub async fn remove_member(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path((org_slug, user_id)): Path<(String, String)>,
 ) -> Result<Json<()>> {
     let user = &auth_user.user;
+    crate::middleware::ensure_not_impersonating(&auth_user)?;
 
     // Find organization
     let organization =
@@ -514,9 +671,14 @@ pub async fn remove_member(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner or admin (owner can remove anyone, admin can remove members)
-    let caller_membership =
-        crate::middleware::check_org_admin(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may remove members
+    let caller_membership = crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "manage_members",
+    )
+    .await?;
 
     // Cannot remove yourself
     if user_id == user.id {
@@ -536,12 +698,14 @@ pub async fn remove_member(
     .map_err(AppError::Database)?
     .ok_or_else(|| AppError::NotFound("User is not a member of this organization".to_string()))?;
 
-    // Check permissions: owner can remove anyone, admin can only remove members, not owners/admins
-    if caller_membership.role != "owner" && (target_membership.role == "owner" || target_membership.role == "admin") {
-        return Err(AppError::Forbidden(
-            "Only owners can remove other owners and admins".to_string(),
-        ));
-    }
+    // Check permissions: owner can remove anyone, others only members below their own role
+    OrgRoleService::ensure_can_manage(
+        &state.pool,
+        &organization.id,
+        &caller_membership.role,
+        &target_membership.role,
+    )
+    .await?;
 
     // Remove membership
     sqlx::query!("DELETE FROM memberships WHERE id = ?", target_membership.id)
@@ -549,6 +713,20 @@ pub async fn remove_member(
         .await
         .map_err(AppError::Database)?;
 
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &organization.id,
+        "member_removed",
+        "member",
+        &user_id,
+        diff(
+            &serde_json::json!({ "role": target_membership.role }),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
     Ok(Json(()))
 }
 
@@ -556,10 +734,12 @@ pub async fn remove_member(
 pub async fn transfer_ownership(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path(org_slug): Path<String>,
     Json(req): Json<TransferOwnershipRequest>,
 ) -> Result<Json<OrganizationMember>> {
     let user = &auth_user.user;
+    crate::middleware::ensure_not_impersonating(&auth_user)?;
 
     // Find organization
     let organization =
@@ -622,6 +802,20 @@ pub async fn transfer_ownership(
     .await
     .map_err(AppError::Database)?;
 
+    create_org_audit_log(
+        &mut tx,
+        &audit,
+        &organization.id,
+        "ownership_transferred",
+        "organization",
+        &organization.id,
+        diff(
+            &serde_json::json!({ "owner_user_id": user.id }),
+            &serde_json::json!({ "owner_user_id": new_owner.id }),
+        ),
+    )
+    .await?;
+
     tx.commit().await.map_err(AppError::Database)?;
 
     // Fetch updated membership
@@ -773,30 +967,27 @@ pub struct OAuthCredentialsResponse {
 pub async fn set_org_oauth_credentials(
     State(state): State<AppState>,
     user: AuthUser,
+    audit: AuditContext,
     Path((org_slug, provider)): Path<(String, String)>,
     Json(req): Json<SetOAuthCredentialsRequest>,
 ) -> Result<Json<OAuthCredentialsResponse>> {
+    crate::middleware::ensure_not_impersonating(&user)?;
+
     // Get organization
     let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
         .bind(&org_slug)
         .fetch_one(&state.pool)
         .await?;
 
-    // Verify user is admin or owner of the organization
-    let membership = sqlx::query_as::<_, Membership>(
-        "SELECT * FROM memberships WHERE org_id = ? AND user_id = ?",
+    // Verify user may manage the organization's OAuth credentials
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &user.user.id,
+        &org.id,
+        "manage_oauth_credentials",
     )
-    .bind(&org.id)
-    .bind(&user.user.id)
-    .fetch_one(&state.pool)
     .await?;
 
-    if membership.role != "owner" && membership.role != "admin" {
-        return Err(AppError::Forbidden(
-            "Must be an owner or admin to manage OAuth credentials".to_string(),
-        ));
-    }
-
     // Validate provider
     if provider != "github" && provider != "google" && provider != "microsoft" {
         return Err(AppError::BadRequest(
@@ -816,6 +1007,15 @@ pub async fn set_org_oauth_credentials(
 
     let encryption_key_id = encryption.key_id().to_string();
 
+    let previous = sqlx::query_as::<_, (String, String)>(
+        "SELECT client_id, client_secret_encrypted FROM organization_oauth_credentials
+         WHERE org_id = ? AND provider = ?",
+    )
+    .bind(&org.id)
+    .bind(&provider)
+    .fetch_optional(&state.pool)
+    .await?;
+
     // Upsert credentials
     let id = Uuid::new_v4().to_string();
     sqlx::query(
@@ -836,6 +1036,25 @@ pub async fn set_org_oauth_credentials(
     .execute(&state.pool)
     .await?;
 
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "oauth_credentials_set",
+        "oauth_credentials",
+        &provider,
+        diff(
+            &previous.map_or(serde_json::Value::Null, |(client_id, secret)| {
+                serde_json::json!({ "client_id": client_id, "client_secret": secret })
+            }),
+            &serde_json::json!({
+                "client_id": req.client_id,
+                "client_secret": client_secret_encrypted,
+            }),
+        ),
+    )
+    .await?;
+
     Ok(Json(OAuthCredentialsResponse {
         provider,
         client_id: req.client_id,
@@ -856,13 +1075,7 @@ pub async fn get_org_oauth_credentials(
         .await?;
 
     // Verify user is a member of the organization
-    let _membership = sqlx::query_as::<_, Membership>(
-        "SELECT * FROM memberships WHERE org_id = ? AND user_id = ?",
-    )
-    .bind(&org.id)
-    .bind(&user.user.id)
-    .fetch_one(&state.pool)
-    .await?;
+    crate::middleware::check_org_membership(&state.pool, &user.user.id, &org.id, &[]).await?;
 
     // Validate provider
     if provider != "github" && provider != "google" && provider != "microsoft" {
@@ -935,7 +1148,7 @@ fn validate_organization_name(name: &str) -> Result<()> {
     Ok(())
 }
 
-fn validate_email(email: &str) -> Result<()> {
+pub fn validate_email(email: &str) -> Result<()> {
     if !email.contains('@') || email.len() < 5 {
         return Err(AppError::BadRequest("Invalid email format".to_string()));
     }
@@ -976,6 +1189,9 @@ pub struct EndUserSubscription {
 pub struct EndUserIdentity {
     pub provider: String,
     pub provider_user_id: String,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
 }
 
@@ -1021,8 +1237,14 @@ pub async fn list_end_users(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is member (any role can view end-users)
-    crate::middleware::check_org_membership(&state.pool, &user.id, &organization.id, &[]).await?;
+    // Check if user may view end-users (members can by default)
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "view_end_users",
+    )
+    .await?;
 
     let page = query.page.unwrap_or(1).max(1);
     let limit = query.limit.unwrap_or(50).clamp(1, 100);
@@ -1043,7 +1265,7 @@ pub async fn list_end_users(
         .ok_or_else(|| AppError::NotFound(format!("Service '{}' not found", service_slug)))?;
 
         (
-            "SELECT DISTINCT u.id, u.email, u.is_platform_owner, u.created_at
+            "SELECT DISTINCT u.*
              FROM users u
              LEFT JOIN identities i ON u.id = i.user_id AND i.issuing_org_id = ? AND i.issuing_service_id = ?
              LEFT JOIN subscriptions sub ON u.id = sub.user_id AND sub.service_id = ?
@@ -1055,7 +1277,7 @@ pub async fn list_end_users(
     } else {
         // Show all users across all services in the organization
         (
-            "SELECT DISTINCT u.id, u.email, u.is_platform_owner, u.created_at
+            "SELECT DISTINCT u.*
              FROM users u
              LEFT JOIN identities i ON u.id = i.user_id AND i.issuing_org_id = ?
              LEFT JOIN subscriptions sub ON u.id = sub.user_id
@@ -1096,6 +1318,12 @@ pub async fn list_end_users(
             email: row.get("email"),
             is_platform_owner: row.get("is_platform_owner"),
             created_at: row.get("created_at"),
+            name: row.get("name"),
+            given_name: row.get("given_name"),
+            family_name: row.get("family_name"),
+            avatar_url: row.get("avatar_url"),
+            locale: row.get("locale"),
+            primary_identity_id: row.get("primary_identity_id"),
         })
         .collect();
 
@@ -1183,7 +1411,7 @@ pub async fn list_end_users(
     // Fetch identities for these users (optionally filtered by service)
     let identity_query = if service_id.is_some() {
         format!(
-            "SELECT user_id, provider, provider_user_id, created_at
+            "SELECT user_id, provider, provider_user_id, username, name, avatar_url, created_at
              FROM identities
              WHERE user_id IN ({}) AND issuing_org_id = ? AND issuing_service_id = ?
              ORDER BY created_at ASC",
@@ -1191,7 +1419,7 @@ pub async fn list_end_users(
         )
     } else {
         format!(
-            "SELECT user_id, provider, provider_user_id, created_at
+            "SELECT user_id, provider, provider_user_id, username, name, avatar_url, created_at
              FROM identities
              WHERE user_id IN ({}) AND issuing_org_id = ?
              ORDER BY created_at ASC",
@@ -1220,6 +1448,9 @@ pub async fn list_end_users(
         let identity = EndUserIdentity {
             provider: id_row.get("provider"),
             provider_user_id: id_row.get("provider_user_id"),
+            username: id_row.get("username"),
+            name: id_row.get("name"),
+            avatar_url: id_row.get("avatar_url"),
             created_at: id_row.get("created_at"),
         };
         identities_by_user
@@ -1301,8 +1532,14 @@ pub async fn get_end_user(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is member (any role can view end-users)
-    crate::middleware::check_org_membership(&state.pool, &user.id, &organization.id, &[]).await?;
+    // Check if user may view end-users (members can by default)
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "view_end_users",
+    )
+    .await?;
 
     // Get end-user
     let end_user_obj = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
@@ -1365,7 +1602,7 @@ pub async fn get_end_user(
     // Get identities that were created via this organization's services
     // Only show identities where issuing_org_id matches this organization
     let identity_rows = sqlx::query(
-        "SELECT provider, provider_user_id, created_at
+        "SELECT provider, provider_user_id, username, name, avatar_url, created_at
          FROM identities
          WHERE user_id = ? AND issuing_org_id = ?
          ORDER BY created_at ASC",
@@ -1381,6 +1618,9 @@ pub async fn get_end_user(
         .map(|id_row| EndUserIdentity {
             provider: id_row.get("provider"),
             provider_user_id: id_row.get("provider_user_id"),
+            username: id_row.get("username"),
+            name: id_row.get("name"),
+            avatar_url: id_row.get("avatar_url"),
             created_at: id_row.get("created_at"),
         })
         .collect();
@@ -1404,10 +1644,11 @@ pub async fn get_end_user(
     }))
 }
 
-/// Revoke all active sessions for an end-user (admin/owner only)
+/// Revoke all active sessions for an end-user (requires revoke_sessions)
 pub async fn revoke_end_user_sessions(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    audit: AuditContext,
     Path((org_slug, end_user_id)): Path<(String, String)>,
 ) -> Result<Json<serde_json::Value>> {
     let user = &auth_user.user;
@@ -1421,8 +1662,14 @@ pub async fn revoke_end_user_sessions(
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is admin or owner (required for session management)
-    crate::middleware::check_org_admin(&state.pool, &user.id, &organization.id).await?;
+    // Check if user may revoke end-user sessions
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &user.id,
+        &organization.id,
+        "revoke_sessions",
+    )
+    .await?;
 
     // Verify this user has subscriptions to this organization's services
     let subscription_count: i64 = sqlx::query_scalar(
@@ -1443,14 +1690,31 @@ pub async fn revoke_end_user_sessions(
         ));
     }
 
-    // Delete all active sessions for this user
-    let result = sqlx::query("DELETE FROM sessions WHERE user_id = ?")
-        .bind(&end_user_id)
-        .execute(&state.pool)
-        .await
-        .map_err(AppError::Database)?;
+    // Delete the user's sessions in this organization and notify its services
+    let revoked = crate::auth::logout::LogoutService::revoke_org_sessions(
+        &state.pool,
+        &state.jwt_service,
+        &state.base_url,
+        &end_user_id,
+        &organization,
+    )
+    .await?;
 
-    let revoked_count = result.rows_affected();
+    let revoked_count = revoked.len();
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &organization.id,
+        "end_user_sessions_revoked",
+        "user",
+        &end_user_id,
+        diff(
+            &serde_json::json!({ "active_sessions": revoked_count }),
+            &serde_json::json!({ "active_sessions": 0 }),
+        ),
+    )
+    .await?;
 
     Ok(Json(serde_json::json!({
         "message": "Sessions revoked successfully",
@@ -1,4 +1,10 @@
-use crate::db::models::{Organization, OrganizationTier, PlatformAuditLog, User};
+use crate::auth::audit_chain::{AuditChain, AuditChainService, ChainVerification};
+use crate::auth::email::{
+    EmailService, TEMPLATE_ORGANIZATION_APPROVED, TEMPLATE_ORGANIZATION_SUSPENDED,
+};
+use crate::db::models::{
+    Organization, OrganizationAuditLog, OrganizationTier, PlatformAuditLog, User,
+};
 use crate::error::{AppError, Result};
 use crate::handlers::auth::AppState;
 use crate::middleware::AuthUser;
@@ -9,7 +15,7 @@ use axum::{
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{Row, Sqlite};
+use sqlx::{Row, SqliteConnection};
 use uuid::Uuid;
 
 // ============================================================================
@@ -69,6 +75,11 @@ pub struct AuditLogQuery {
     pub offset: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyAuditLogQuery {
+    pub org_id: Option<String>, // verify this organization's chain instead of the platform's
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuditLogResponse {
     pub logs: Vec<PlatformAuditLog>,
@@ -79,39 +90,59 @@ pub struct AuditLogResponse {
 // Audit Log Helpers
 // ============================================================================
 
-/// Create an audit log entry
-pub async fn create_audit_log<'a, E>(
-    executor: E,
+/// Create an audit log entry, linked to the end of the platform audit chain
+pub async fn create_audit_log(
+    conn: &mut SqliteConnection,
     platform_owner_id: &str,
     action: &str,
     target_type: &str,
     target_id: &str,
     metadata: Option<serde_json::Value>,
-) -> Result<PlatformAuditLog>
-where
-    E: sqlx::Executor<'a, Database = Sqlite>,
-{
-    let id = Uuid::new_v4().to_string();
-    let metadata_str = metadata.map(|m| m.to_string());
-
-    let log = sqlx::query_as::<_, PlatformAuditLog>(
-        r#"
-        INSERT INTO platform_audit_log (id, platform_owner_id, action, target_type, target_id, metadata)
-        VALUES (?, ?, ?, ?, ?, ?)
-        RETURNING *
-        "#,
-    )
-    .bind(&id)
-    .bind(platform_owner_id)
-    .bind(action)
-    .bind(target_type)
-    .bind(target_id)
-    .bind(metadata_str)
-    .fetch_one(executor)
-    .await
-    .map_err(AppError::Database)?;
+) -> Result<PlatformAuditLog> {
+    let mut log = PlatformAuditLog {
+        id: Uuid::new_v4().to_string(),
+        platform_owner_id: platform_owner_id.to_string(),
+        action: action.to_string(),
+        target_type: target_type.to_string(),
+        target_id: target_id.to_string(),
+        metadata: metadata.map(|m| m.to_string()),
+        created_at: AuditChainService::now(),
+        seq: None,
+        prev_hash: None,
+        entry_hash: None,
+    };
 
-    Ok(log)
+    let mut attempt = 1;
+    loop {
+        AuditChainService::link(conn, &AuditChain::Platform, &mut log).await?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO platform_audit_log
+                (id, platform_owner_id, action, target_type, target_id, metadata, created_at,
+                 seq, prev_hash, entry_hash)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&log.id)
+        .bind(&log.platform_owner_id)
+        .bind(&log.action)
+        .bind(&log.target_type)
+        .bind(&log.target_id)
+        .bind(&log.metadata)
+        .bind(log.created_at)
+        .bind(log.seq)
+        .bind(&log.prev_hash)
+        .bind(&log.entry_hash)
+        .execute(&mut *conn)
+        .await;
+
+        match inserted {
+            Ok(_) => return Ok(log),
+            Err(e) if AuditChainService::should_retry_append(&e, attempt) => attempt += 1,
+            Err(e) => return Err(AppError::Database(e)),
+        }
+    }
 }
 
 // ============================================================================
@@ -195,10 +226,14 @@ pub async fn list_organizations(
         SELECT
             o.id, o.slug, o.name, o.owner_user_id, o.status, o.tier_id,
             o.max_services, o.max_users, o.approved_by, o.approved_at,
-            o.rejected_by, o.rejected_at, o.rejection_reason,
+            o.rejected_by, o.rejected_at, o.rejection_reason, o.sso_session_lifetime_minutes,
+            o.login_risk_medium_action, o.login_risk_high_action,
             o.created_at, o.updated_at,
             u.id as owner_id, u.email as owner_email,
-            u.is_platform_owner as owner_is_platform_owner, u.created_at as owner_created_at
+            u.is_platform_owner as owner_is_platform_owner, u.created_at as owner_created_at,
+            u.name as owner_name, u.given_name as owner_given_name,
+            u.family_name as owner_family_name, u.avatar_url as owner_avatar_url,
+            u.locale as owner_locale, u.primary_identity_id as owner_primary_identity_id
         FROM organizations o
         INNER JOIN users u ON o.owner_user_id = u.id
         {}
@@ -236,6 +271,9 @@ pub async fn list_organizations(
             rejected_by: row.get("rejected_by"),
             rejected_at: row.get("rejected_at"),
             rejection_reason: row.get("rejection_reason"),
+            sso_session_lifetime_minutes: row.get("sso_session_lifetime_minutes"),
+            login_risk_medium_action: row.get("login_risk_medium_action"),
+            login_risk_high_action: row.get("login_risk_high_action"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         };
@@ -245,6 +283,12 @@ pub async fn list_organizations(
             email: row.get("owner_email"),
             is_platform_owner: row.get("owner_is_platform_owner"),
             created_at: row.get("owner_created_at"),
+            name: row.get("owner_name"),
+            given_name: row.get("owner_given_name"),
+            family_name: row.get("owner_family_name"),
+            avatar_url: row.get("owner_avatar_url"),
+            locale: row.get("owner_locale"),
+            primary_identity_id: row.get("owner_primary_identity_id"),
         };
 
         // Fetch tier if present
@@ -342,9 +386,24 @@ pub async fn approve_organization(
     .await
     .map_err(AppError::Database)?;
 
+    // Let the owner know
+    let owner_email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(&updated_org.owner_user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    EmailService::enqueue(
+        &mut tx,
+        Some(&updated_org.id),
+        TEMPLATE_ORGANIZATION_APPROVED,
+        &owner_email,
+        &[("org_name", &updated_org.name), ("org_slug", &updated_org.slug)],
+    )
+    .await?;
+
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "approve_organization",
         "organization",
@@ -416,7 +475,7 @@ pub async fn reject_organization(
 
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "reject_organization",
         "organization",
@@ -479,9 +538,24 @@ pub async fn suspend_organization(
     .await
     .map_err(AppError::Database)?;
 
+    // Let the owner know
+    let owner_email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(&updated_org.owner_user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    EmailService::enqueue(
+        &mut tx,
+        Some(&updated_org.id),
+        TEMPLATE_ORGANIZATION_SUSPENDED,
+        &owner_email,
+        &[("org_name", &updated_org.name), ("org_slug", &updated_org.slug)],
+    )
+    .await?;
+
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "suspend_organization",
         "organization",
@@ -545,7 +619,7 @@ pub async fn activate_organization(
 
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "activate_organization",
         "organization",
@@ -617,7 +691,7 @@ pub async fn update_organization_tier(
 
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "update_organization_tier",
         "organization",
@@ -681,7 +755,7 @@ pub async fn promote_platform_owner(
 
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "promote_platform_owner",
         "user",
@@ -761,7 +835,7 @@ pub async fn demote_platform_owner(
 
     // Create audit log
     create_audit_log(
-        &mut *tx,
+        &mut tx,
         &auth_user.user.id,
         "demote_platform_owner",
         "user",
@@ -849,6 +923,41 @@ pub async fn get_audit_log(
     Ok(Json(AuditLogResponse { logs, total }))
 }
 
+/// GET /api/platform/audit-log/verify
+/// Walk the platform audit hash chain, or an organization's, and report the first break
+pub async fn verify_audit_log(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<VerifyAuditLogQuery>,
+) -> Result<Json<ChainVerification>> {
+    if !auth_user.user.is_platform_owner {
+        return Err(AppError::Forbidden(
+            "Platform owner access required".to_string(),
+        ));
+    }
+
+    let verification = match query.org_id {
+        Some(org_id) => {
+            AuditChainService::verify::<OrganizationAuditLog>(
+                &state.pool,
+                &state.jwt_service,
+                &AuditChain::Organization(org_id),
+            )
+            .await?
+        }
+        None => {
+            AuditChainService::verify::<PlatformAuditLog>(
+                &state.pool,
+                &state.jwt_service,
+                &AuditChain::Platform,
+            )
+            .await?
+        }
+    };
+
+    Ok(Json(verification))
+}
+
 // ============================================================================
 // Platform Analytics Endpoints
 // ============================================================================
@@ -954,7 +1063,8 @@ pub async fn get_platform_overview(
 
     // Get logins in last 24 hours
     let total_logins_24h: i64 = sqlx::query_scalar(
-        "SELECT COUNT(*) FROM login_events WHERE created_at >= datetime('now', '-1 day')",
+        "SELECT COUNT(*) FROM login_events
+         WHERE outcome = 'success' AND created_at >= datetime('now', '-1 day')",
     )
     .fetch_one(&state.pool)
     .await
@@ -962,7 +1072,8 @@ pub async fn get_platform_overview(
 
     // Get logins in last 30 days
     let total_logins_30d: i64 = sqlx::query_scalar(
-        "SELECT COUNT(*) FROM login_events WHERE created_at >= datetime('now', '-30 days')",
+        "SELECT COUNT(*) FROM login_events
+         WHERE outcome = 'success' AND created_at >= datetime('now', '-30 days')",
     )
     .fetch_one(&state.pool)
     .await
@@ -1150,7 +1261,8 @@ pub async fn get_login_activity(
             DATE(created_at) as date,
             COUNT(*) as count
         FROM login_events
-        WHERE DATE(created_at) >= DATE(?)
+        WHERE outcome = 'success'
+          AND DATE(created_at) >= DATE(?)
           AND DATE(created_at) <= DATE(?)
         GROUP BY DATE(created_at)
         ORDER BY date ASC
@@ -1192,14 +1304,14 @@ pub async fn get_top_organizations(
                 SELECT COUNT(DISTINCT le.user_id)
                 FROM login_events le
                 JOIN services s ON le.service_id = s.id
-                WHERE s.org_id = o.id
+                WHERE s.org_id = o.id AND le.outcome = 'success'
             ), 0) as user_count,
             COALESCE((SELECT COUNT(*) FROM services WHERE org_id = o.id), 0) as service_count,
             COALESCE((
                 SELECT COUNT(*)
                 FROM login_events le
                 JOIN services s ON le.service_id = s.id
-                WHERE s.org_id = o.id
+                WHERE s.org_id = o.id AND le.outcome = 'success'
                   AND le.created_at >= datetime('now', '-30 days')
             ), 0) as login_count_30d
         FROM organizations o
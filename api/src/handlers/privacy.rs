@@ -0,0 +1,167 @@
+use crate::auth::privacy::{DataExport, ErasureSummary, PrivacyService};
+use crate::db::models::{Organization, User};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::create_org_audit_log;
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use sqlx::SqlitePool;
+
+/// Serve an export as a downloadable JSON file
+fn export_download(export: DataExport) -> impl IntoResponse {
+    let disposition = format!("attachment; filename=\"user-data-{}.json\"", export.user.id);
+    ([(header::CONTENT_DISPOSITION, disposition)], Json(export))
+}
+
+/// Resolve an organization and the end-user the caller acts on. Members of the
+/// organization are managed through the member endpoints instead.
+async fn find_org_end_user(
+    pool: &SqlitePool,
+    auth_user: &AuthUser,
+    org_slug: &str,
+    user_id: &str,
+    permission: &str,
+) -> Result<(Organization, User)> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(pool, &auth_user.user.id, &org.id, permission).await?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("End-user not found".to_string()))?;
+
+    let (subscriptions, memberships): (i64, i64) = sqlx::query_as(
+        "SELECT
+             (SELECT COUNT(*) FROM subscriptions sub
+              JOIN services s ON sub.service_id = s.id
+              WHERE sub.user_id = ? AND s.org_id = ?),
+             (SELECT COUNT(*) FROM memberships WHERE user_id = ? AND org_id = ?)",
+    )
+    .bind(&user.id)
+    .bind(&org.id)
+    .bind(&user.id)
+    .bind(&org.id)
+    .fetch_one(pool)
+    .await?;
+
+    if subscriptions == 0 {
+        return Err(AppError::NotFound(
+            "User is not an end-user of this organization".to_string(),
+        ));
+    }
+    if memberships > 0 {
+        return Err(AppError::BadRequest(
+            "User is a member of this organization; remove the membership instead".to_string(),
+        ));
+    }
+
+    Ok((org, user))
+}
+
+/// GET /api/user/export
+/// Download everything the platform holds about the caller
+pub async fn export_user_data(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<impl IntoResponse> {
+    let export = PrivacyService::export(&state.pool, &auth_user.user, None).await?;
+
+    Ok(export_download(export))
+}
+
+/// DELETE /api/user
+/// Erase the caller's account and data
+pub async fn delete_user_account(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<ErasureSummary>> {
+    crate::middleware::ensure_interactive_session(&auth_user)?;
+
+    let summary = PrivacyService::erase(
+        &state.pool,
+        &state.stripe_service,
+        &state.jwt_service,
+        &state.base_url,
+        &auth_user.user,
+        None,
+        &auth_user.user.id,
+    )
+    .await?;
+
+    Ok(Json(summary))
+}
+
+/// GET /api/organizations/:org_slug/users/:user_id/export
+/// Download the data the organization holds about one of its end-users
+pub async fn export_end_user_data(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, user_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let (org, user) = find_org_end_user(
+        &state.pool,
+        &auth_user,
+        &org_slug,
+        &user_id,
+        "view_end_users",
+    )
+    .await?;
+
+    let export = PrivacyService::export(&state.pool, &user, Some(&org)).await?;
+
+    Ok(export_download(export))
+}
+
+/// DELETE /api/organizations/:org_slug/users/:user_id
+/// Erase the data the organization holds about one of its end-users
+pub async fn erase_end_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, user_id)): Path<(String, String)>,
+) -> Result<Json<ErasureSummary>> {
+    let (org, user) = find_org_end_user(
+        &state.pool,
+        &auth_user,
+        &org_slug,
+        &user_id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let summary = PrivacyService::erase(
+        &state.pool,
+        &state.stripe_service,
+        &state.jwt_service,
+        &state.base_url,
+        &user,
+        Some(&org),
+        &auth_user.user.id,
+    )
+    .await?;
+
+    // Nothing about the erased user beyond their id is recorded
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "end_user_erased",
+        "user",
+        &user.id,
+        None,
+    )
+    .await?;
+
+    Ok(Json(summary))
+}
@@ -0,0 +1,1613 @@
+use crate::auth::api_tokens::ApiTokenService;
+use crate::auth::org_roles::OrgRoleService;
+use crate::auth::profiles::{ProfileService, ProfileUpdate};
+use crate::auth::scim::{
+    ScimService, ERROR_SCHEMA, GROUP_SCHEMA, LIST_RESPONSE_SCHEMA, SERVICE_PROVIDER_CONFIG_SCHEMA,
+    USER_EXTENSION_SCHEMA, USER_SCHEMA, USER_TYPE_END_USER, USER_TYPE_MEMBER,
+};
+use crate::constants::{MAX_NAME_LENGTH, MIN_NAME_LENGTH, ORG_API_KEY_PREFIX};
+use crate::db::models::{Organization, ScimUser, User, UserGroup};
+use crate::error::AppError;
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::handlers::organizations::ensure_organization_active;
+use crate::middleware::{AuditContext, ClientInfo, RequestId};
+use axum::{
+    body::Bytes,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// Error in the SCIM wire format (RFC 7644 section 3.12)
+#[derive(Debug)]
+pub struct ScimError {
+    status: StatusCode,
+    scim_type: Option<&'static str>,
+    detail: String,
+}
+
+impl ScimError {
+    fn bad_request(scim_type: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            scim_type: Some(scim_type),
+            detail: detail.into(),
+        }
+    }
+
+    fn with_type(mut self, scim_type: &'static str) -> Self {
+        self.scim_type = Some(scim_type);
+        self
+    }
+
+    fn conflict(detail: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            scim_type: Some("uniqueness"),
+            detail: detail.into(),
+        }
+    }
+}
+
+impl From<AppError> for ScimError {
+    fn from(error: AppError) -> Self {
+        let (status, detail) = match error {
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired".to_string()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::BadRequest(msg) | AppError::TeamLimitExceeded(msg) => {
+                (StatusCode::BAD_REQUEST, msg)
+            }
+            other => {
+                tracing::error!("SCIM request failed: {}", other);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
+        };
+
+        Self {
+            status,
+            scim_type: None,
+            detail,
+        }
+    }
+}
+
+impl From<sqlx::Error> for ScimError {
+    fn from(error: sqlx::Error) -> Self {
+        AppError::Database(error).into()
+    }
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        let mut body = json!({
+            "schemas": [ERROR_SCHEMA],
+            "status": self.status.as_u16().to_string(),
+            "detail": self.detail,
+        });
+        if let Some(scim_type) = self.scim_type {
+            body["scimType"] = json!(scim_type);
+        }
+        scim_response(self.status, body)
+    }
+}
+
+type ScimResult<T> = std::result::Result<T, ScimError>;
+
+fn scim_response(status: StatusCode, body: Value) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/scim+json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn created_response(body: Value) -> Response {
+    let location = body["meta"]["location"].as_str().map(HeaderValue::from_str);
+    let mut response = scim_response(StatusCode::CREATED, body);
+    if let Some(Ok(location)) = location {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    response
+}
+
+/// SCIM clients send `application/scim+json`, which the `Json` extractor rejects
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> ScimResult<T> {
+    serde_json::from_slice(body).map_err(|e| {
+        ScimError::bad_request("invalidSyntax", format!("Invalid request body: {}", e))
+    })
+}
+
+fn parse_value<T: DeserializeOwned>(value: Value) -> ScimResult<T> {
+    serde_json::from_value(value)
+        .map_err(|e| ScimError::bad_request("invalidValue", format!("Invalid value: {}", e)))
+}
+
+/// The parts of a SCIM request besides its body: the headers carrying the
+/// API key and the client details recorded in the organization audit log
+pub struct ScimRequest {
+    headers: HeaderMap,
+    client: ClientInfo,
+    request_id: Option<String>,
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ScimRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let Ok(client) = ClientInfo::from_request_parts(parts, state).await;
+
+        Ok(ScimRequest {
+            headers: parts.headers.clone(),
+            client,
+            request_id: parts
+                .extensions
+                .get::<RequestId>()
+                .map(|RequestId(id)| id.clone()),
+        })
+    }
+}
+
+/// The organization and the API key role a SCIM request acts with
+struct ScimContext {
+    org: Organization,
+    actor_id: String,
+    role: String,
+    base: String,
+    audit: AuditContext,
+}
+
+impl ScimContext {
+    async fn require(&self, pool: &SqlitePool, permission: &str) -> ScimResult<()> {
+        crate::middleware::check_org_permission(pool, &self.actor_id, &self.org.id, permission)
+            .await?;
+        Ok(())
+    }
+}
+
+/// SCIM clients authenticate with an organization API key that has the
+/// `scim` scope and a role allowed to manage members
+async fn authenticate(
+    state: &AppState,
+    req: &ScimRequest,
+    org_slug: &str,
+) -> ScimResult<ScimContext> {
+    let token = req
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| {
+            AppError::Unauthorized("Missing or invalid Authorization header".to_string())
+        })?;
+    if !token.starts_with(ORG_API_KEY_PREFIX) {
+        return Err(
+            AppError::Unauthorized("SCIM requires an organization API key".to_string()).into(),
+        );
+    }
+
+    let (api_token, user, _) = ApiTokenService::authenticate(&state.pool, token).await?;
+
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+    if api_token.org_id.as_deref() != Some(org.id.as_str()) {
+        return Err(
+            AppError::Forbidden("API key belongs to a different organization".to_string()).into(),
+        );
+    }
+    if !ApiTokenService::parse_scopes(&api_token.scopes)
+        .iter()
+        .any(|s| s == "scim")
+    {
+        return Err(AppError::Forbidden("API key does not have the scim scope".to_string()).into());
+    }
+    ensure_organization_active(&state.pool, &org.id).await?;
+
+    let membership =
+        crate::middleware::check_org_permission(&state.pool, &user.id, &org.id, "manage_members")
+            .await?;
+
+    Ok(ScimContext {
+        base: format!("{}/scim/v2/{}", state.base_url, org.slug),
+        org,
+        audit: AuditContext {
+            actor_id: user.id.clone(),
+            impersonator_id: None,
+            api_token_id: Some(api_token.id),
+            ip_address: req.client.ip_address.clone(),
+            user_agent: req.client.user_agent.clone(),
+            request_id: req.request_id.clone(),
+        },
+        actor_id: user.id,
+        role: membership.role,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListQuery {
+    pub filter: Option<String>,
+    pub start_index: Option<i64>,
+    pub count: Option<i64>,
+    pub excluded_attributes: Option<String>,
+}
+
+impl ScimListQuery {
+    /// 1-based start index and page size
+    fn page(&self) -> (i64, i64) {
+        (
+            self.start_index.unwrap_or(1).max(1),
+            self.count
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(0, MAX_PAGE_SIZE),
+        )
+    }
+
+    fn excludes(&self, attribute: &str) -> bool {
+        self.excluded_attributes.as_deref().is_some_and(|excluded| {
+            excluded
+                .split(',')
+                .any(|a| a.trim().eq_ignore_ascii_case(attribute))
+        })
+    }
+}
+
+/// Turn a filter into an SQL condition with one placeholder, using `column`
+/// to map the attribute, and the value to bind to it
+fn filter_condition(
+    filter: Option<&str>,
+    column: impl Fn(&str, &str) -> Option<(String, Option<String>)>,
+) -> ScimResult<(String, Option<String>)> {
+    let Some(filter) = filter else {
+        // Keeps one placeholder so callers always bind the value
+        return Ok(("? IS NULL".to_string(), None));
+    };
+    let filter = ScimService::parse_filter(filter)
+        .map_err(|e| ScimError::from(e).with_type("invalidFilter"))?;
+
+    column(&filter.attribute.to_ascii_lowercase(), &filter.value).ok_or_else(|| {
+        ScimError::bad_request(
+            "invalidFilter",
+            format!("Filtering on '{}' is not supported", filter.attribute),
+        )
+    })
+}
+
+fn list_response(start_index: i64, total: i64, resources: Vec<Value>) -> Response {
+    scim_response(
+        StatusCode::OK,
+        json!({
+            "schemas": [LIST_RESPONSE_SCHEMA],
+            "totalResults": total,
+            "startIndex": start_index,
+            "itemsPerPage": resources.len(),
+            "Resources": resources,
+        }),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchRequest {
+    #[serde(rename = "Operations")]
+    pub operations: Vec<PatchOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchOperation {
+    pub op: String,
+    pub path: Option<String>,
+    pub value: Option<Value>,
+}
+
+// ============================================================================
+// Users
+// ============================================================================
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimName {
+    pub formatted: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScimEmail {
+    pub value: String,
+    pub primary: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ScimUserExtension {
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUserRequest {
+    pub user_name: Option<String>,
+    pub external_id: Option<String>,
+    pub name: Option<ScimName>,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub emails: Vec<ScimEmail>,
+    pub active: Option<Value>,
+    pub user_type: Option<String>,
+    #[serde(rename = "urn:ietf:params:scim:schemas:extension:sso:2.0:User")]
+    pub extension: Option<ScimUserExtension>,
+}
+
+impl ScimUserRequest {
+    /// The sign-in email: `userName` when it is an address, else the primary email
+    fn email(&self) -> Option<String> {
+        self.user_name
+            .as_deref()
+            .filter(|name| name.contains('@'))
+            .or_else(|| {
+                self.emails
+                    .iter()
+                    .find(|email| email.primary == Some(true))
+                    .or(self.emails.first())
+                    .map(|email| email.value.as_str())
+            })
+            .map(|email| email.trim().to_string())
+            .filter(|email| email.contains('@'))
+    }
+
+    fn into_changes(self) -> ScimResult<UserChanges> {
+        let active = match self.active {
+            Some(ref value) => Some(ScimService::parse_bool(value).ok_or_else(|| {
+                ScimError::bad_request("invalidValue", "active must be a boolean")
+            })?),
+            None => None,
+        };
+        let name = self.name.unwrap_or_default();
+
+        Ok(UserChanges {
+            user_name: self.user_name,
+            external_id: self.external_id.map(Some),
+            profile: ProfileUpdate {
+                name: name.formatted.or(self.display_name),
+                given_name: name.given_name,
+                family_name: name.family_name,
+                ..Default::default()
+            },
+            user_type: self.user_type,
+            role: self.extension.and_then(|extension| extension.role),
+            active,
+        })
+    }
+}
+
+/// Attribute changes from a create, replace or patch request. Absent fields
+/// are left as they are.
+#[derive(Debug, Default)]
+struct UserChanges {
+    user_name: Option<String>,
+    external_id: Option<Option<String>>,
+    profile: ProfileUpdate,
+    user_type: Option<String>,
+    role: Option<String>,
+    active: Option<bool>,
+}
+
+impl UserChanges {
+    fn merge(&mut self, other: UserChanges) {
+        self.user_name = other.user_name.or(self.user_name.take());
+        self.external_id = other.external_id.or(self.external_id.take());
+        self.profile.name = other.profile.name.or(self.profile.name.take());
+        self.profile.given_name = other.profile.given_name.or(self.profile.given_name.take());
+        self.profile.family_name = other
+            .profile
+            .family_name
+            .or(self.profile.family_name.take());
+        self.user_type = other.user_type.or(self.user_type.take());
+        self.role = other.role.or(self.role.take());
+        self.active = other.active.or(self.active);
+    }
+
+    /// Apply one patch operation on a path; `None` removes the attribute.
+    /// Attributes this server does not store are ignored.
+    fn set(&mut self, path: &str, value: Option<Value>) -> ScimResult<()> {
+        let string = || {
+            value
+                .as_ref()
+                .and_then(Value::as_str)
+                .map(|s| s.to_string())
+        };
+        let role_path = format!("{}:role", USER_EXTENSION_SCHEMA).to_ascii_lowercase();
+
+        match path.to_ascii_lowercase().as_str() {
+            "active" => {
+                let active = value
+                    .as_ref()
+                    .and_then(ScimService::parse_bool)
+                    .ok_or_else(|| {
+                        ScimError::bad_request("invalidValue", "active must be a boolean")
+                    })?;
+                self.active = Some(active);
+            }
+            "externalid" => self.external_id = Some(string()),
+            "username" => {
+                self.user_name = Some(string().ok_or_else(|| {
+                    ScimError::bad_request("mutability", "userName cannot be removed")
+                })?)
+            }
+            "name" => {
+                let name: ScimName = match value {
+                    Some(value) => parse_value(value)?,
+                    None => ScimName::default(),
+                };
+                self.profile.name = Some(name.formatted.unwrap_or_default());
+                self.profile.given_name = Some(name.given_name.unwrap_or_default());
+                self.profile.family_name = Some(name.family_name.unwrap_or_default());
+            }
+            "displayname" | "name.formatted" => {
+                self.profile.name = Some(string().unwrap_or_default())
+            }
+            "name.givenname" => self.profile.given_name = Some(string().unwrap_or_default()),
+            "name.familyname" => self.profile.family_name = Some(string().unwrap_or_default()),
+            "usertype" => self.user_type = string(),
+            path if path == role_path => self.role = string(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn from_patch(operations: Vec<PatchOperation>) -> ScimResult<Self> {
+        let mut changes = Self::default();
+        for operation in operations {
+            match (operation.op.to_ascii_lowercase().as_str(), operation.path) {
+                ("add" | "replace", None) => {
+                    let value = operation.value.ok_or_else(|| {
+                        ScimError::bad_request("invalidValue", "Operation requires a value")
+                    })?;
+                    let request: ScimUserRequest = parse_value(value)?;
+                    changes.merge(request.into_changes()?);
+                }
+                ("add" | "replace", Some(path)) => {
+                    let value = operation.value.ok_or_else(|| {
+                        ScimError::bad_request("invalidValue", "Operation requires a value")
+                    })?;
+                    changes.set(&path, Some(value))?;
+                }
+                ("remove", Some(path)) => changes.set(&path, None)?,
+                ("remove", None) => {
+                    return Err(ScimError::bad_request("noTarget", "remove requires a path"))
+                }
+                (op, _) => {
+                    return Err(ScimError::bad_request(
+                        "invalidSyntax",
+                        format!("Unsupported operation '{}'", op),
+                    ))
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+async fn find_scim_user(pool: &SqlitePool, org_id: &str, user_id: &str) -> ScimResult<ScimUser> {
+    let scim_user =
+        sqlx::query_as::<_, ScimUser>("SELECT * FROM scim_users WHERE org_id = ? AND user_id = ?")
+            .bind(org_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    Ok(scim_user)
+}
+
+async fn ensure_external_id_free(
+    pool: &SqlitePool,
+    org_id: &str,
+    external_id: &str,
+    user_id: Option<&str>,
+) -> ScimResult<()> {
+    let taken: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM scim_users WHERE org_id = ? AND external_id = ? AND user_id IS NOT ?",
+    )
+    .bind(org_id)
+    .bind(external_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    if taken > 0 {
+        return Err(ScimError::conflict(format!(
+            "externalId '{}' is already in use",
+            external_id
+        )));
+    }
+
+    Ok(())
+}
+
+/// Members cannot be given the owner role or more than the API key holds
+async fn validate_role(pool: &SqlitePool, ctx: &ScimContext, role: &str) -> ScimResult<()> {
+    if role == "owner" || !OrgRoleService::role_exists(pool, &ctx.org.id, role).await? {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            format!("Invalid role '{}'", role),
+        ));
+    }
+    let permissions = OrgRoleService::permissions_for_role(pool, &ctx.org.id, role).await?;
+    OrgRoleService::ensure_can_grant(pool, &ctx.org.id, &ctx.role, &permissions).await?;
+
+    Ok(())
+}
+
+async fn member_role(pool: &SqlitePool, org_id: &str, user_id: &str) -> ScimResult<Option<String>> {
+    let role = sqlx::query_scalar("SELECT role FROM memberships WHERE org_id = ? AND user_id = ?")
+        .bind(org_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(role)
+}
+
+async fn deactivate_user(
+    state: &AppState,
+    ctx: &ScimContext,
+    scim_user: &ScimUser,
+) -> ScimResult<()> {
+    if scim_user.user_type == USER_TYPE_MEMBER {
+        if let Some(role) = member_role(&state.pool, &ctx.org.id, &scim_user.user_id).await? {
+            OrgRoleService::ensure_can_manage(&state.pool, &ctx.org.id, &ctx.role, &role).await?;
+        }
+    }
+
+    ScimService::deactivate(
+        &state.pool,
+        &state.jwt_service,
+        &state.base_url,
+        &ctx.org,
+        scim_user,
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn apply_user_changes(
+    state: &AppState,
+    ctx: &ScimContext,
+    scim_user: &ScimUser,
+    user: &User,
+    changes: UserChanges,
+) -> ScimResult<()> {
+    let pool = &state.pool;
+
+    // Validate everything first so a rejected request changes nothing
+    if let Some(ref user_type) = changes.user_type {
+        if *user_type != scim_user.user_type {
+            return Err(ScimError::bad_request(
+                "mutability",
+                "userType cannot be changed",
+            ));
+        }
+    }
+    // The email is the user's sign-in identity across every organization
+    if let Some(ref user_name) = changes.user_name {
+        if user_name.contains('@') && !user_name.trim().eq_ignore_ascii_case(&user.email) {
+            return Err(ScimError::bad_request(
+                "mutability",
+                "userName cannot be changed",
+            ));
+        }
+    }
+
+    if let Some(Some(ref external_id)) = changes.external_id {
+        ensure_external_id_free(pool, &ctx.org.id, external_id, Some(&user.id)).await?;
+    }
+    let current_role = member_role(pool, &ctx.org.id, &user.id).await?;
+    if let Some(ref role) = changes.role {
+        if scim_user.user_type != USER_TYPE_MEMBER {
+            return Err(ScimError::bad_request(
+                "invalidValue",
+                "Only members have an organization role",
+            ));
+        }
+        validate_role(pool, ctx, role).await?;
+    }
+    let changes_membership = changes.role.is_some() || changes.active == Some(false);
+    if let Some(ref current) = current_role {
+        if changes_membership && scim_user.user_type == USER_TYPE_MEMBER {
+            OrgRoleService::ensure_can_manage(pool, &ctx.org.id, &ctx.role, current).await?;
+        }
+    }
+
+    if let Some(external_id) = changes.external_id {
+        sqlx::query("UPDATE scim_users SET external_id = ? WHERE org_id = ? AND user_id = ?")
+            .bind(&external_id)
+            .bind(&ctx.org.id)
+            .bind(&user.id)
+            .execute(pool)
+            .await?;
+    }
+
+    // The organization keeps the name its identity provider sends. It only
+    // becomes the account's own profile for accounts its SCIM client created,
+    // and a profile the user picked from one of their own identities wins.
+    if !changes.profile.is_empty() {
+        let merged = |new: &Option<String>, current: &Option<String>| match new {
+            Some(value) if value.trim().is_empty() => None,
+            Some(value) => Some(value.trim().to_string()),
+            None => current.clone(),
+        };
+        sqlx::query(
+            "UPDATE scim_users SET name = ?, given_name = ?, family_name = ?
+             WHERE org_id = ? AND user_id = ?",
+        )
+        .bind(merged(&changes.profile.name, &scim_user.name))
+        .bind(merged(&changes.profile.given_name, &scim_user.given_name))
+        .bind(merged(&changes.profile.family_name, &scim_user.family_name))
+        .bind(&ctx.org.id)
+        .bind(&user.id)
+        .execute(pool)
+        .await?;
+
+        if user.primary_identity_id.is_none()
+            && ScimService::created_account(pool, &ctx.org.id, &user.id).await?
+        {
+            ProfileService::update(pool, user, changes.profile).await?;
+        }
+    }
+
+    if let Some(ref role) = changes.role {
+        if current_role.is_some() {
+            sqlx::query("UPDATE memberships SET role = ? WHERE org_id = ? AND user_id = ?")
+                .bind(role)
+                .bind(&ctx.org.id)
+                .bind(&user.id)
+                .execute(pool)
+                .await?;
+        }
+        sqlx::query("UPDATE scim_users SET role = ? WHERE org_id = ? AND user_id = ?")
+            .bind(role)
+            .bind(&ctx.org.id)
+            .bind(&user.id)
+            .execute(pool)
+            .await?;
+    }
+
+    match changes.active {
+        Some(false) if scim_user.active => {
+            let scim_user = find_scim_user(pool, &ctx.org.id, &user.id).await?;
+            deactivate_user(state, ctx, &scim_user).await?;
+        }
+        Some(true) if !scim_user.active => {
+            let scim_user = find_scim_user(pool, &ctx.org.id, &user.id).await?;
+            ScimService::reactivate(pool, &ctx.org, &scim_user).await?;
+        }
+        _ => {}
+    }
+
+    sqlx::query("UPDATE scim_users SET updated_at = ? WHERE org_id = ? AND user_id = ?")
+        .bind(Utc::now())
+        .bind(&ctx.org.id)
+        .bind(&user.id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn user_resource(pool: &SqlitePool, ctx: &ScimContext, user_id: &str) -> ScimResult<Value> {
+    let scim_user = find_scim_user(pool, &ctx.org.id, user_id).await?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    let role = match member_role(pool, &ctx.org.id, user_id).await? {
+        Some(role) => Some(role),
+        None => scim_user.role.clone(),
+    };
+    let groups: Vec<Value> = sqlx::query(
+        "SELECT g.id, g.name
+         FROM user_group_members gm
+         JOIN user_groups g ON gm.group_id = g.id
+         WHERE gm.user_id = ? AND g.org_id = ?
+         ORDER BY g.name",
+    )
+    .bind(user_id)
+    .bind(&ctx.org.id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let id: String = row.get("id");
+        json!({
+            "value": id,
+            "display": row.get::<String, _>("name"),
+            "$ref": format!("{}/Groups/{}", ctx.base, id),
+        })
+    })
+    .collect();
+
+    Ok(json!({
+        "schemas": [USER_SCHEMA, USER_EXTENSION_SCHEMA],
+        "id": user.id,
+        "externalId": scim_user.external_id,
+        "userName": user.email,
+        "name": {
+            "formatted": scim_user.name.as_ref().or(user.name.as_ref()),
+            "givenName": scim_user.given_name.as_ref().or(user.given_name.as_ref()),
+            "familyName": scim_user.family_name.as_ref().or(user.family_name.as_ref()),
+        },
+        "displayName": scim_user.name.as_ref().or(user.name.as_ref()),
+        "emails": [{ "value": user.email, "primary": true }],
+        "active": scim_user.active,
+        "userType": scim_user.user_type,
+        "groups": groups,
+        USER_EXTENSION_SCHEMA: { "role": role },
+        "meta": {
+            "resourceType": "User",
+            "created": scim_user.created_at,
+            "lastModified": scim_user.updated_at,
+            "location": format!("{}/Users/{}", ctx.base, user.id),
+        },
+    }))
+}
+
+/// GET /scim/v2/:org_slug/Users
+pub async fn list_scim_users(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path(org_slug): Path<String>,
+    Query(query): Query<ScimListQuery>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    let (start_index, count) = query.page();
+
+    let (condition, value) =
+        filter_condition(
+            query.filter.as_deref(),
+            |attribute, value| match attribute {
+                "username" | "emails" | "emails.value" => Some((
+                    "u.email = ? COLLATE NOCASE".to_string(),
+                    Some(value.to_string()),
+                )),
+                "externalid" => Some(("su.external_id = ?".to_string(), Some(value.to_string()))),
+                "id" => Some(("su.user_id = ?".to_string(), Some(value.to_string()))),
+                "usertype" => Some(("su.user_type = ?".to_string(), Some(value.to_string()))),
+                "active" => Some((
+                    "su.active = ?".to_string(),
+                    Some(if value == "true" { "1" } else { "0" }.to_string()),
+                )),
+                _ => None,
+            },
+        )?;
+
+    let from = format!(
+        "FROM scim_users su JOIN users u ON su.user_id = u.id WHERE su.org_id = ? AND {}",
+        condition
+    );
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {}", from))
+        .bind(&ctx.org.id)
+        .bind(&value)
+        .fetch_one(&state.pool)
+        .await?;
+    let user_ids: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT su.user_id {} ORDER BY su.created_at, su.user_id LIMIT ? OFFSET ?",
+        from
+    ))
+    .bind(&ctx.org.id)
+    .bind(&value)
+    .bind(count)
+    .bind(start_index - 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut resources = Vec::with_capacity(user_ids.len());
+    for user_id in &user_ids {
+        resources.push(user_resource(&state.pool, &ctx, user_id).await?);
+    }
+
+    Ok(list_response(start_index, total, resources))
+}
+
+/// POST /scim/v2/:org_slug/Users
+/// Provision a member (the default) or, with `userType: "end_user"`, an end user.
+/// An existing account with the same email is reused when it is already a
+/// member or end user of the organization; existing members keep their role.
+pub async fn create_scim_user(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path(org_slug): Path<String>,
+    body: Bytes,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    let pool = &state.pool;
+    let request: ScimUserRequest = parse_body(&body)?;
+
+    let email = request.email().ok_or_else(|| {
+        ScimError::bad_request("invalidValue", "userName must be an email address")
+    })?;
+    let user_type = request
+        .user_type
+        .clone()
+        .unwrap_or_else(|| USER_TYPE_MEMBER.to_string());
+    if user_type != USER_TYPE_MEMBER && user_type != USER_TYPE_END_USER {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            format!(
+                "userType must be '{}' or '{}'",
+                USER_TYPE_MEMBER, USER_TYPE_END_USER
+            ),
+        ));
+    }
+    if user_type == USER_TYPE_END_USER {
+        ctx.require(pool, "manage_end_users").await?;
+    }
+
+    let mut changes = request.into_changes()?;
+    if let Some(Some(ref external_id)) = changes.external_id {
+        ensure_external_id_free(pool, &ctx.org.id, external_id, None).await?;
+    }
+
+    let user = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ? COLLATE NOCASE")
+        .bind(&email)
+        .fetch_optional(pool)
+        .await?
+    {
+        // Binding gives the identity provider control of the account's
+        // membership and sessions in the organization
+        Some(user) if ScimService::may_bind(pool, &ctx.org.id, &user.id).await? => user,
+        Some(_) => {
+            return Err(ScimError::conflict(format!(
+                "An account for '{}' exists outside the organization; invite the user instead",
+                email
+            )))
+        }
+        None => {
+            sqlx::query_as::<_, User>(
+                "INSERT INTO users (id, email, scim_org_id, created_at) VALUES (?, ?, ?, ?) RETURNING *",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&email)
+            .bind(&ctx.org.id)
+            .bind(Utc::now())
+            .fetch_one(pool)
+            .await?
+        }
+    };
+
+    let provisioned: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM scim_users WHERE org_id = ? AND user_id = ?")
+            .bind(&ctx.org.id)
+            .bind(&user.id)
+            .fetch_one(pool)
+            .await?;
+    if provisioned > 0 {
+        return Err(ScimError::conflict(format!(
+            "User '{}' already exists",
+            email
+        )));
+    }
+
+    let active = changes.active.take().unwrap_or(true);
+    let role = if user_type == USER_TYPE_MEMBER {
+        match member_role(pool, &ctx.org.id, &user.id).await? {
+            Some(existing) => Some(existing),
+            None => {
+                let role = changes.role.take().unwrap_or_else(|| "member".to_string());
+                validate_role(pool, &ctx, &role).await?;
+                if active {
+                    ScimService::grant_membership(pool, &ctx.org, &user.id, &role).await?;
+                }
+                Some(role)
+            }
+        }
+    } else {
+        if changes.role.is_some() {
+            return Err(ScimError::bad_request(
+                "invalidValue",
+                "Only members have an organization role",
+            ));
+        }
+        None
+    };
+
+    let now = Utc::now();
+    let scim_user = sqlx::query_as::<_, ScimUser>(
+        r#"
+        INSERT INTO scim_users (org_id, user_id, external_id, user_type, role, active, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, 1, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(&ctx.org.id)
+    .bind(&user.id)
+    .bind(changes.external_id.take().flatten())
+    .bind(&user_type)
+    .bind(&role)
+    .bind(now)
+    .bind(now)
+    .fetch_one(pool)
+    .await?;
+
+    // Only the profile and an initial deactivation are left to apply
+    changes.role = None;
+    changes.active = Some(active);
+    apply_user_changes(&state, &ctx, &scim_user, &user, changes).await?;
+
+    let scim_user = find_scim_user(pool, &ctx.org.id, &user.id).await?;
+    create_org_audit_log(
+        &mut *pool.acquire().await?,
+        &ctx.audit,
+        &ctx.org.id,
+        "scim_user_created",
+        "user",
+        &user.id,
+        diff(&Value::Null, &json!(scim_user)),
+    )
+    .await?;
+
+    Ok(created_response(user_resource(pool, &ctx, &user.id).await?))
+}
+
+/// GET /scim/v2/:org_slug/Users/:user_id
+pub async fn get_scim_user(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, user_id)): Path<(String, String)>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        user_resource(&state.pool, &ctx, &user_id).await?,
+    ))
+}
+
+async fn update_user(
+    state: &AppState,
+    ctx: &ScimContext,
+    user_id: &str,
+    changes: UserChanges,
+) -> ScimResult<Response> {
+    let scim_user = find_scim_user(&state.pool, &ctx.org.id, user_id).await?;
+    if scim_user.user_type == USER_TYPE_END_USER {
+        ctx.require(&state.pool, "manage_end_users").await?;
+    }
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    apply_user_changes(state, ctx, &scim_user, &user, changes).await?;
+
+    let updated = find_scim_user(&state.pool, &ctx.org.id, user_id).await?;
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &ctx.audit,
+        &ctx.org.id,
+        "scim_user_updated",
+        "user",
+        user_id,
+        diff(&json!(scim_user), &json!(updated)),
+    )
+    .await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        user_resource(&state.pool, ctx, user_id).await?,
+    ))
+}
+
+/// PUT /scim/v2/:org_slug/Users/:user_id
+/// Replace the attributes the request carries
+pub async fn replace_scim_user(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, user_id)): Path<(String, String)>,
+    body: Bytes,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    let request: ScimUserRequest = parse_body(&body)?;
+
+    update_user(&state, &ctx, &user_id, request.into_changes()?).await
+}
+
+/// PATCH /scim/v2/:org_slug/Users/:user_id
+/// `active: false` deprovisions the user and revokes their sessions
+pub async fn patch_scim_user(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, user_id)): Path<(String, String)>,
+    body: Bytes,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    let request: PatchRequest = parse_body(&body)?;
+
+    update_user(
+        &state,
+        &ctx,
+        &user_id,
+        UserChanges::from_patch(request.operations)?,
+    )
+    .await
+}
+
+/// DELETE /scim/v2/:org_slug/Users/:user_id
+/// Deprovision the user and forget the SCIM record; the account itself is kept
+pub async fn delete_scim_user(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, user_id)): Path<(String, String)>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    let scim_user = find_scim_user(&state.pool, &ctx.org.id, &user_id).await?;
+    if scim_user.user_type == USER_TYPE_END_USER {
+        ctx.require(&state.pool, "manage_end_users").await?;
+    }
+
+    if scim_user.active {
+        deactivate_user(&state, &ctx, &scim_user).await?;
+    }
+
+    sqlx::query(
+        "DELETE FROM user_group_members
+         WHERE user_id = ? AND group_id IN (SELECT id FROM user_groups WHERE org_id = ?)",
+    )
+    .bind(&user_id)
+    .bind(&ctx.org.id)
+    .execute(&state.pool)
+    .await?;
+    sqlx::query("DELETE FROM scim_users WHERE org_id = ? AND user_id = ?")
+        .bind(&ctx.org.id)
+        .bind(&user_id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &ctx.audit,
+        &ctx.org.id,
+        "scim_user_deleted",
+        "user",
+        &user_id,
+        diff(&json!(scim_user), &Value::Null),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// ============================================================================
+// Groups
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct ScimMemberRef {
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroupRequest {
+    pub display_name: Option<String>,
+    pub external_id: Option<String>,
+    pub members: Option<Vec<ScimMemberRef>>,
+}
+
+async fn find_group(pool: &SqlitePool, org_id: &str, group_id: &str) -> ScimResult<UserGroup> {
+    Ok(crate::handlers::groups::find_group(pool, org_id, group_id).await?)
+}
+
+/// Check a group name is valid and not used by another group of the organization
+async fn validate_group_name(
+    pool: &SqlitePool,
+    org_id: &str,
+    name: &str,
+    group_id: Option<&str>,
+) -> ScimResult<String> {
+    let name = name.trim();
+    if name.len() < MIN_NAME_LENGTH || name.len() > MAX_NAME_LENGTH {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            format!(
+                "displayName must be between {} and {} characters",
+                MIN_NAME_LENGTH, MAX_NAME_LENGTH
+            ),
+        ));
+    }
+
+    let taken: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM user_groups WHERE org_id = ? AND name = ? AND id IS NOT ?",
+    )
+    .bind(org_id)
+    .bind(name)
+    .bind(group_id)
+    .fetch_one(pool)
+    .await?;
+    if taken > 0 {
+        return Err(ScimError::conflict(format!(
+            "Group '{}' already exists",
+            name
+        )));
+    }
+
+    Ok(name.to_string())
+}
+
+fn member_ids(value: Option<Value>) -> ScimResult<Vec<String>> {
+    let members: Vec<ScimMemberRef> = match value {
+        Some(value) => parse_value(value)?,
+        None => Vec::new(),
+    };
+    Ok(members.into_iter().map(|member| member.value).collect())
+}
+
+/// Only users provisioned in this organization can be group members
+async fn add_group_members(
+    pool: &SqlitePool,
+    org_id: &str,
+    group_id: &str,
+    user_ids: &[String],
+) -> ScimResult<()> {
+    for user_id in user_ids {
+        find_scim_user(pool, org_id, user_id).await.map_err(|_| {
+            ScimError::bad_request("invalidValue", format!("Unknown member '{}'", user_id))
+        })?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO user_group_members (group_id, user_id, created_at) VALUES (?, ?, ?)",
+        )
+        .bind(group_id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn remove_group_members(
+    pool: &SqlitePool,
+    group_id: &str,
+    user_ids: Option<&[String]>,
+) -> ScimResult<()> {
+    match user_ids {
+        Some(user_ids) => {
+            for user_id in user_ids {
+                sqlx::query("DELETE FROM user_group_members WHERE group_id = ? AND user_id = ?")
+                    .bind(group_id)
+                    .bind(user_id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        None => {
+            sqlx::query("DELETE FROM user_group_members WHERE group_id = ?")
+                .bind(group_id)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The group as recorded in the organization audit log
+async fn group_snapshot(pool: &SqlitePool, group: &UserGroup) -> ScimResult<Value> {
+    let members: Vec<String> = sqlx::query_scalar(
+        "SELECT user_id FROM user_group_members WHERE group_id = ? ORDER BY user_id",
+    )
+    .bind(&group.id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(json!({
+        "name": group.name,
+        "external_id": group.external_id,
+        "members": members,
+    }))
+}
+
+async fn group_resource(
+    pool: &SqlitePool,
+    ctx: &ScimContext,
+    group: &UserGroup,
+    include_members: bool,
+) -> ScimResult<Value> {
+    let mut resource = json!({
+        "schemas": [GROUP_SCHEMA],
+        "id": group.id,
+        "externalId": group.external_id,
+        "displayName": group.name,
+        "meta": {
+            "resourceType": "Group",
+            "created": group.created_at,
+            "location": format!("{}/Groups/{}", ctx.base, group.id),
+        },
+    });
+
+    if include_members {
+        let members: Vec<Value> = sqlx::query(
+            "SELECT u.id, u.email
+             FROM user_group_members gm
+             JOIN users u ON gm.user_id = u.id
+             WHERE gm.group_id = ?
+             ORDER BY u.email",
+        )
+        .bind(&group.id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let id: String = row.get("id");
+            json!({
+                "value": id,
+                "display": row.get::<String, _>("email"),
+                "$ref": format!("{}/Users/{}", ctx.base, id),
+            })
+        })
+        .collect();
+        resource["members"] = json!(members);
+    }
+
+    Ok(resource)
+}
+
+/// GET /scim/v2/:org_slug/Groups
+pub async fn list_scim_groups(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path(org_slug): Path<String>,
+    Query(query): Query<ScimListQuery>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    ctx.require(&state.pool, "manage_end_users").await?;
+    let (start_index, count) = query.page();
+
+    let (condition, value) = filter_condition(query.filter.as_deref(), |attribute, value| {
+        let column = match attribute {
+            "displayname" => "name",
+            "externalid" => "external_id",
+            "id" => "id",
+            _ => return None,
+        };
+        Some((format!("{} = ?", column), Some(value.to_string())))
+    })?;
+
+    let from = format!("FROM user_groups WHERE org_id = ? AND {}", condition);
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {}", from))
+        .bind(&ctx.org.id)
+        .bind(&value)
+        .fetch_one(&state.pool)
+        .await?;
+    let groups = sqlx::query_as::<_, UserGroup>(&format!(
+        "SELECT * {} ORDER BY created_at, id LIMIT ? OFFSET ?",
+        from
+    ))
+    .bind(&ctx.org.id)
+    .bind(&value)
+    .bind(count)
+    .bind(start_index - 1)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let include_members = !query.excludes("members");
+    let mut resources = Vec::with_capacity(groups.len());
+    for group in &groups {
+        resources.push(group_resource(&state.pool, &ctx, group, include_members).await?);
+    }
+
+    Ok(list_response(start_index, total, resources))
+}
+
+/// POST /scim/v2/:org_slug/Groups
+pub async fn create_scim_group(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path(org_slug): Path<String>,
+    body: Bytes,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    ctx.require(&state.pool, "manage_end_users").await?;
+    let request: ScimGroupRequest = parse_body(&body)?;
+
+    let display_name = request
+        .display_name
+        .ok_or_else(|| ScimError::bad_request("invalidValue", "displayName is required"))?;
+    let name = validate_group_name(&state.pool, &ctx.org.id, &display_name, None).await?;
+
+    let group = sqlx::query_as::<_, UserGroup>(
+        r#"
+        INSERT INTO user_groups (id, org_id, name, external_id, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&ctx.org.id)
+    .bind(&name)
+    .bind(&request.external_id)
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    let members: Vec<String> = request
+        .members
+        .unwrap_or_default()
+        .into_iter()
+        .map(|member| member.value)
+        .collect();
+    add_group_members(&state.pool, &ctx.org.id, &group.id, &members).await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &ctx.audit,
+        &ctx.org.id,
+        "scim_group_created",
+        "group",
+        &group.id,
+        diff(&Value::Null, &group_snapshot(&state.pool, &group).await?),
+    )
+    .await?;
+
+    Ok(created_response(
+        group_resource(&state.pool, &ctx, &group, true).await?,
+    ))
+}
+
+/// GET /scim/v2/:org_slug/Groups/:group_id
+pub async fn get_scim_group(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, group_id)): Path<(String, String)>,
+    Query(query): Query<ScimListQuery>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    ctx.require(&state.pool, "manage_end_users").await?;
+    let group = find_group(&state.pool, &ctx.org.id, &group_id).await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        group_resource(&state.pool, &ctx, &group, !query.excludes("members")).await?,
+    ))
+}
+
+/// PATCH /scim/v2/:org_slug/Groups/:group_id
+/// Rename the group and add, remove or replace its members
+pub async fn patch_scim_group(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, group_id)): Path<(String, String)>,
+    body: Bytes,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    ctx.require(&state.pool, "manage_end_users").await?;
+    let pool = &state.pool;
+    let group = find_group(pool, &ctx.org.id, &group_id).await?;
+    let before = group_snapshot(pool, &group).await?;
+    let request: PatchRequest = parse_body(&body)?;
+
+    for operation in request.operations {
+        let op = operation.op.to_ascii_lowercase();
+        let path = operation.path.as_deref().map(str::to_ascii_lowercase);
+
+        match (op.as_str(), path.as_deref()) {
+            ("add", Some("members")) => {
+                add_group_members(pool, &ctx.org.id, &group.id, &member_ids(operation.value)?)
+                    .await?
+            }
+            ("replace", Some("members")) => {
+                let members = member_ids(operation.value)?;
+                remove_group_members(pool, &group.id, None).await?;
+                add_group_members(pool, &ctx.org.id, &group.id, &members).await?;
+            }
+            ("remove", Some("members")) => match operation.value {
+                Some(value) => {
+                    let members = member_ids(Some(value))?;
+                    remove_group_members(pool, &group.id, Some(&members)).await?;
+                }
+                None => remove_group_members(pool, &group.id, None).await?,
+            },
+            // members[value eq "<user id>"]
+            ("remove", Some(path)) if path.starts_with("members[") && path.ends_with(']') => {
+                let original = operation.path.as_deref().unwrap_or_default();
+                let filter = ScimService::parse_filter(&original[8..original.len() - 1])
+                    .map_err(|e| ScimError::from(e).with_type("invalidPath"))?;
+                if !filter.attribute.eq_ignore_ascii_case("value") {
+                    return Err(ScimError::bad_request(
+                        "invalidPath",
+                        format!("Unsupported path '{}'", original),
+                    ));
+                }
+                remove_group_members(pool, &group.id, Some(&[filter.value])).await?;
+            }
+            ("add" | "replace", Some("displayname")) => {
+                let name = operation
+                    .value
+                    .as_ref()
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| {
+                        ScimError::bad_request("invalidValue", "displayName must be a string")
+                    })?;
+                rename_group(pool, &ctx, &group, name).await?;
+            }
+            ("add" | "replace", Some("externalid")) => {
+                let external_id = operation.value.as_ref().and_then(Value::as_str);
+                set_group_external_id(pool, &group, external_id).await?;
+            }
+            ("remove", Some("externalid")) => set_group_external_id(pool, &group, None).await?,
+            ("add" | "replace", None) => {
+                let value = operation.value.ok_or_else(|| {
+                    ScimError::bad_request("invalidValue", "Operation requires a value")
+                })?;
+                let changes: ScimGroupRequest = parse_value(value)?;
+                if let Some(ref name) = changes.display_name {
+                    rename_group(pool, &ctx, &group, name).await?;
+                }
+                if let Some(ref external_id) = changes.external_id {
+                    set_group_external_id(pool, &group, Some(external_id)).await?;
+                }
+                if let Some(members) = changes.members {
+                    let members: Vec<String> = members.into_iter().map(|m| m.value).collect();
+                    if op == "replace" {
+                        remove_group_members(pool, &group.id, None).await?;
+                    }
+                    add_group_members(pool, &ctx.org.id, &group.id, &members).await?;
+                }
+            }
+            ("add" | "replace" | "remove", _) => {
+                return Err(ScimError::bad_request(
+                    "invalidPath",
+                    format!("Unsupported path '{}'", operation.path.unwrap_or_default()),
+                ))
+            }
+            (op, _) => {
+                return Err(ScimError::bad_request(
+                    "invalidSyntax",
+                    format!("Unsupported operation '{}'", op),
+                ))
+            }
+        }
+    }
+
+    let group = find_group(pool, &ctx.org.id, &group.id).await?;
+    create_org_audit_log(
+        &mut *pool.acquire().await?,
+        &ctx.audit,
+        &ctx.org.id,
+        "scim_group_updated",
+        "group",
+        &group.id,
+        diff(&before, &group_snapshot(pool, &group).await?),
+    )
+    .await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        group_resource(pool, &ctx, &group, true).await?,
+    ))
+}
+
+async fn rename_group(
+    pool: &SqlitePool,
+    ctx: &ScimContext,
+    group: &UserGroup,
+    name: &str,
+) -> ScimResult<()> {
+    let name = validate_group_name(pool, &ctx.org.id, name, Some(&group.id)).await?;
+    sqlx::query("UPDATE user_groups SET name = ? WHERE id = ?")
+        .bind(&name)
+        .bind(&group.id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn set_group_external_id(
+    pool: &SqlitePool,
+    group: &UserGroup,
+    external_id: Option<&str>,
+) -> ScimResult<()> {
+    sqlx::query("UPDATE user_groups SET external_id = ? WHERE id = ?")
+        .bind(external_id)
+        .bind(&group.id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// DELETE /scim/v2/:org_slug/Groups/:group_id
+/// Role assignments made through the group are removed with it
+pub async fn delete_scim_group(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path((org_slug, group_id)): Path<(String, String)>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+    ctx.require(&state.pool, "manage_end_users").await?;
+    let group = find_group(&state.pool, &ctx.org.id, &group_id).await?;
+    let before = group_snapshot(&state.pool, &group).await?;
+
+    sqlx::query("DELETE FROM user_groups WHERE id = ?")
+        .bind(&group.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &ctx.audit,
+        &ctx.org.id,
+        "scim_group_deleted",
+        "group",
+        &group.id,
+        diff(&before, &Value::Null),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// ============================================================================
+// Discovery
+// ============================================================================
+
+/// GET /scim/v2/:org_slug/ServiceProviderConfig
+pub async fn get_service_provider_config(
+    State(state): State<AppState>,
+    req: ScimRequest,
+    Path(org_slug): Path<String>,
+) -> ScimResult<Response> {
+    let ctx = authenticate(&state, &req, &org_slug).await?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        json!({
+            "schemas": [SERVICE_PROVIDER_CONFIG_SCHEMA],
+            "patch": { "supported": true },
+            "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+            "filter": { "supported": true, "maxResults": MAX_PAGE_SIZE },
+            "changePassword": { "supported": false },
+            "sort": { "supported": false },
+            "etag": { "supported": false },
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "Organization API key",
+                "description": "An organization API key with the scim scope",
+                "primary": true,
+            }],
+            "meta": {
+                "resourceType": "ServiceProviderConfig",
+                "location": format!("{}/ServiceProviderConfig", ctx.base),
+            },
+        }),
+    ))
+}
@@ -0,0 +1,529 @@
+use crate::auth::service_roles::ServiceRoleService;
+use crate::constants::MAX_NAME_LENGTH;
+use crate::db::models::{Organization, Service, ServiceRole, ServiceRoleAssignment};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::groups::{find_group, find_org_user};
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateServiceRoleRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
+
+/// Assign a role to exactly one of a user or a group
+#[derive(Debug, Deserialize)]
+pub struct AssignServiceRoleRequest {
+    pub user_id: Option<String>,
+    pub group_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceRoleResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ServiceRole> for ServiceRoleResponse {
+    fn from(role: ServiceRole) -> Self {
+        Self {
+            permissions: ServiceRoleService::parse_permissions(&role.permissions),
+            id: role.id,
+            name: role.name,
+            description: role.description,
+            created_at: role.created_at,
+            updated_at: role.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RoleAssignmentResponse {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub user_email: Option<String>,
+    pub group_id: Option<String>,
+    pub group_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Resolve the organization and service from the path
+async fn find_service(
+    pool: &SqlitePool,
+    org_slug: &str,
+    service_slug: &str,
+) -> Result<(Organization, Service)> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    let service =
+        sqlx::query_as::<_, Service>("SELECT * FROM services WHERE org_id = ? AND slug = ?")
+            .bind(&org.id)
+            .bind(service_slug)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Service not found".to_string()))?;
+
+    Ok((org, service))
+}
+
+async fn find_role(pool: &SqlitePool, service_id: &str, role_id: &str) -> Result<ServiceRole> {
+    sqlx::query_as::<_, ServiceRole>("SELECT * FROM service_roles WHERE id = ? AND service_id = ?")
+        .bind(role_id)
+        .bind(service_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Role not found".to_string()))
+}
+
+/// Role names and permissions end up in tokens, so keep them to simple identifiers
+fn validate_claim_value(kind: &str, value: &str) -> Result<()> {
+    let valid_chars = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '/'));
+
+    if value.is_empty() || value.len() > MAX_NAME_LENGTH || !valid_chars {
+        return Err(AppError::BadRequest(format!(
+            "Invalid {} '{}': use up to {} letters, digits or _ - . : /",
+            kind, value, MAX_NAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Trim, check, sort and de-duplicate role permissions. Organization roles use
+/// this too; `check` rejects the values their kind of role does not allow.
+pub(crate) fn normalize_permissions(
+    permissions: Vec<String>,
+    check: impl Fn(&str) -> Result<()>,
+) -> Result<Vec<String>> {
+    let mut permissions: Vec<String> = permissions
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .collect();
+    for permission in &permissions {
+        check(permission)?;
+    }
+    permissions.sort();
+    permissions.dedup();
+
+    Ok(permissions)
+}
+
+/// Service role permissions are stored as a JSON array
+fn permissions_json(permissions: Vec<String>) -> Result<String> {
+    let permissions =
+        normalize_permissions(permissions, |p| validate_claim_value("permission", p))?;
+    Ok(serde_json::to_string(&permissions).unwrap_or_else(|_| "[]".to_string()))
+}
+
+async fn ensure_unique_role_name(
+    pool: &SqlitePool,
+    service_id: &str,
+    name: &str,
+    except_role_id: Option<&str>,
+) -> Result<()> {
+    let exists: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM service_roles WHERE service_id = ? AND name = ? AND id != ?",
+    )
+    .bind(service_id)
+    .bind(name)
+    .bind(except_role_id.unwrap_or_default())
+    .fetch_one(pool)
+    .await?;
+
+    if exists > 0 {
+        return Err(AppError::BadRequest(
+            "A role with this name already exists for this service".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/organizations/:org_slug/services/:service_slug/roles
+pub async fn list_service_roles(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, service_slug)): Path<(String, String)>,
+) -> Result<Json<Vec<ServiceRoleResponse>>> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[]).await?;
+
+    let roles = sqlx::query_as::<_, ServiceRole>(
+        "SELECT * FROM service_roles WHERE service_id = ? ORDER BY name",
+    )
+    .bind(&service.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(roles.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/organizations/:org_slug/services/:service_slug/roles
+pub async fn create_service_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, service_slug)): Path<(String, String)>,
+    Json(req): Json<CreateServiceRoleRequest>,
+) -> Result<(StatusCode, Json<ServiceRoleResponse>)> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_services",
+    )
+    .await?;
+
+    let name = req.name.trim();
+    validate_claim_value("role name", name)?;
+    ensure_unique_role_name(&state.pool, &service.id, name, None).await?;
+    let permissions = permissions_json(req.permissions.unwrap_or_default())?;
+
+    let now = Utc::now();
+    let role = sqlx::query_as::<_, ServiceRole>(
+        r#"
+        INSERT INTO service_roles (id, service_id, name, description, permissions, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&service.id)
+    .bind(name)
+    .bind(&req.description)
+    .bind(&permissions)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "service_role_created",
+        "service_role",
+        &role.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!(ServiceRoleResponse::from(role.clone())),
+        ),
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(role.into())))
+}
+
+/// PATCH /api/organizations/:org_slug/services/:service_slug/roles/:role_id
+/// Changes reach tokens on the next login or refresh
+pub async fn update_service_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, service_slug, role_id)): Path<(String, String, String)>,
+    Json(req): Json<UpdateServiceRoleRequest>,
+) -> Result<Json<ServiceRoleResponse>> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_services",
+    )
+    .await?;
+
+    let role = find_role(&state.pool, &service.id, &role_id).await?;
+    let before = serde_json::json!(ServiceRoleResponse::from(role.clone()));
+
+    let name = match req.name {
+        Some(ref name) => {
+            let name = name.trim();
+            validate_claim_value("role name", name)?;
+            ensure_unique_role_name(&state.pool, &service.id, name, Some(&role.id)).await?;
+            name.to_string()
+        }
+        None => role.name,
+    };
+    let description = req.description.or(role.description);
+    let permissions = match req.permissions {
+        Some(permissions) => permissions_json(permissions)?,
+        None => role.permissions,
+    };
+
+    let role = sqlx::query_as::<_, ServiceRole>(
+        r#"
+        UPDATE service_roles SET name = ?, description = ?, permissions = ?, updated_at = ?
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(&name)
+    .bind(&description)
+    .bind(&permissions)
+    .bind(Utc::now())
+    .bind(&role.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "service_role_updated",
+        "service_role",
+        &role.id,
+        diff(
+            &before,
+            &serde_json::json!(ServiceRoleResponse::from(role.clone())),
+        ),
+    )
+    .await?;
+
+    Ok(Json(role.into()))
+}
+
+/// DELETE /api/organizations/:org_slug/services/:service_slug/roles/:role_id
+pub async fn delete_service_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, service_slug, role_id)): Path<(String, String, String)>,
+) -> Result<StatusCode> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_services",
+    )
+    .await?;
+
+    let role = find_role(&state.pool, &service.id, &role_id).await?;
+
+    sqlx::query("DELETE FROM service_roles WHERE id = ?")
+        .bind(&role.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "service_role_deleted",
+        "service_role",
+        &role.id,
+        diff(
+            &serde_json::json!(ServiceRoleResponse::from(role.clone())),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/organizations/:org_slug/services/:service_slug/roles/:role_id/assignments
+pub async fn list_role_assignments(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, service_slug, role_id)): Path<(String, String, String)>,
+) -> Result<Json<Vec<RoleAssignmentResponse>>> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[]).await?;
+
+    let role = find_role(&state.pool, &service.id, &role_id).await?;
+
+    let assignments = sqlx::query_as::<_, RoleAssignmentResponse>(
+        r#"
+        SELECT a.id, a.user_id, u.email as user_email, a.group_id, g.name as group_name, a.created_at
+        FROM service_role_assignments a
+        LEFT JOIN users u ON a.user_id = u.id
+        LEFT JOIN user_groups g ON a.group_id = g.id
+        WHERE a.role_id = ?
+        ORDER BY a.created_at
+        "#,
+    )
+    .bind(&role.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(assignments))
+}
+
+/// POST /api/organizations/:org_slug/services/:service_slug/roles/:role_id/assignments
+pub async fn assign_service_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, service_slug, role_id)): Path<(String, String, String)>,
+    Json(req): Json<AssignServiceRoleRequest>,
+) -> Result<(StatusCode, Json<ServiceRoleAssignment>)> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let role = find_role(&state.pool, &service.id, &role_id).await?;
+
+    let (user_id, group_id) = match (req.user_id, req.group_id) {
+        (Some(user_id), None) => {
+            let user = find_org_user(&state.pool, &org.id, &user_id).await?;
+            (Some(user.id), None)
+        }
+        (None, Some(group_id)) => {
+            let group = find_group(&state.pool, &org.id, &group_id).await?;
+            (None, Some(group.id))
+        }
+        _ => {
+            return Err(AppError::BadRequest(
+                "Provide exactly one of user_id or group_id".to_string(),
+            ))
+        }
+    };
+
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM service_role_assignments WHERE role_id = ? AND (user_id = ? OR group_id = ?)",
+    )
+    .bind(&role.id)
+    .bind(&user_id)
+    .bind(&group_id)
+    .fetch_one(&state.pool)
+    .await?;
+    if existing > 0 {
+        return Err(AppError::BadRequest("Role is already assigned".to_string()));
+    }
+
+    let assignment = sqlx::query_as::<_, ServiceRoleAssignment>(
+        r#"
+        INSERT INTO service_role_assignments (id, role_id, user_id, group_id, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&role.id)
+    .bind(&user_id)
+    .bind(&group_id)
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "service_role_assigned",
+        "service_role",
+        &role.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!({
+                "assignment_id": assignment.id,
+                "user_id": assignment.user_id,
+                "group_id": assignment.group_id,
+            }),
+        ),
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(assignment)))
+}
+
+/// DELETE /api/organizations/:org_slug/services/:service_slug/roles/:role_id/assignments/:assignment_id
+pub async fn unassign_service_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, service_slug, role_id, assignment_id)): Path<(String, String, String, String)>,
+) -> Result<StatusCode> {
+    let (org, service) = find_service(&state.pool, &org_slug, &service_slug).await?;
+    crate::middleware::check_org_permission(
+        &state.pool,
+        &auth_user.user.id,
+        &org.id,
+        "manage_end_users",
+    )
+    .await?;
+
+    let role = find_role(&state.pool, &service.id, &role_id).await?;
+
+    let assignment = sqlx::query_as::<_, ServiceRoleAssignment>(
+        "DELETE FROM service_role_assignments WHERE id = ? AND role_id = ? RETURNING *",
+    )
+    .bind(&assignment_id)
+    .bind(&role.id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Assignment not found".to_string()))?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "service_role_unassigned",
+        "service_role",
+        &role.id,
+        diff(
+            &serde_json::json!({
+                "assignment_id": assignment.id,
+                "user_id": assignment.user_id,
+                "group_id": assignment.group_id,
+            }),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_permissions() {
+        let permissions = permissions_json(vec![
+            "reports:write".to_string(),
+            " reports:read ".to_string(),
+            "reports:write".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(permissions, r#"["reports:read","reports:write"]"#);
+
+        assert!(permissions_json(vec!["has space".to_string()]).is_err());
+        assert!(permissions_json(vec!["".to_string()]).is_err());
+    }
+}
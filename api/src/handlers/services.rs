@@ -1,8 +1,11 @@
+use crate::auth::org_roles::OrgRoleService;
+use crate::auth::webhooks::WebhookService;
 use crate::constants::{DEFAULT_MAX_SERVICES, DEFAULT_TIER_NAME, VALID_SERVICE_TYPES};
-use crate::db::models::{Membership, Organization, Plan, Service, ServiceResponse};
+use crate::db::models::{Organization, Plan, Service, ServiceResponse};
 use crate::error::Result;
 use crate::handlers::auth::AppState;
-use crate::middleware::AuthUser;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -34,6 +37,11 @@ pub struct UpdateServiceRequest {
     pub google_scopes: Option<Vec<String>>,
     pub redirect_uris: Option<Vec<String>>,
     pub device_activation_uri: Option<String>,
+    pub ciba_notification_endpoint: Option<String>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    pub post_logout_redirect_uris: Option<Vec<String>>,
+    pub require_verified_email: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -88,25 +96,25 @@ pub struct PlanResponse {
     pub subscription_count: i64,
 }
 
-// Helper function to check if user has permission to manage services
-async fn can_manage_service(state: &AppState, user_id: &str, org_id: &str) -> Result<bool> {
-    let membership = sqlx::query_as::<_, Membership>(
-        r#"
-        SELECT m.* FROM memberships m
-        WHERE m.org_id = ? AND m.user_id = ?
-        "#,
-    )
-    .bind(org_id)
-    .bind(user_id)
-    .fetch_optional(&state.pool)
-    .await?;
+// Helper function to check if the user's org role grants a permission
+async fn has_org_permission(
+    state: &AppState,
+    user_id: &str,
+    org_id: &str,
+    permission: &str,
+) -> Result<bool> {
+    let membership =
+        match crate::middleware::check_org_membership(&state.pool, user_id, org_id, &[]).await {
+            Ok(membership) => membership,
+            Err(crate::error::AppError::Forbidden(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
 
-    Ok(membership
-        .map(|m| m.role == "owner" || m.role == "admin")
-        .unwrap_or(false))
+    let permissions =
+        OrgRoleService::permissions_for_role(&state.pool, org_id, &membership.role).await?;
+    Ok(permissions.iter().any(|p| p == permission))
 }
 
-// Helper function to calculate service limits
 async fn get_service_limits(state: &AppState, org: &Organization) -> Result<(i64, String)> {
     let max_services = if let Some(custom_limit) = org.max_services {
         custom_limit
@@ -148,6 +156,7 @@ pub async fn create_service(
     State(state): State<AppState>,
     Path(org_slug): Path<String>,
     auth_user: axum::Extension<AuthUser>,
+    audit: AuditContext,
     Json(req): Json<CreateServiceRequest>,
 ) -> Result<Json<ServiceWithGrantsResponse>> {
     // Validate service type
@@ -170,8 +179,8 @@ pub async fn create_service(
     let org =
         crate::handlers::organizations::ensure_organization_active(&state.pool, &org.id).await?;
 
-    // 4. AUTHORIZE: user is member with role in ('owner', 'admin')
-    if !can_manage_service(&state, &auth_user.user.id, &org.id).await? {
+    // 4. AUTHORIZE: user's role grants manage_services
+    if !has_org_permission(&state, &auth_user.user.id, &org.id, "manage_services").await? {
         return Err(crate::error::AppError::Forbidden(
             "Insufficient permissions to create services".to_string(),
         ));
@@ -269,6 +278,20 @@ pub async fn create_service(
     .fetch_one(&mut *tx)
     .await?;
 
+    create_org_audit_log(
+        &mut tx,
+        &audit,
+        &org.id,
+        "service_created",
+        "service",
+        &service.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!(ServiceResponse::from(service.clone())),
+        ),
+    )
+    .await?;
+
     // 9. COMMIT
     tx.commit().await?;
 
@@ -301,16 +324,8 @@ pub async fn list_organization_services(
         .ok_or_else(|| crate::error::AppError::NotFound("Organization not found".to_string()))?;
 
     // Check if user is member
-    let _membership = sqlx::query_as::<_, Membership>(
-        "SELECT * FROM memberships WHERE org_id = ? AND user_id = ?",
-    )
-    .bind(&org.id)
-    .bind(&auth_user.user.id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| {
-        crate::error::AppError::Forbidden("Not a member of this organization".to_string())
-    })?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[])
+        .await?;
 
     // Build query with filters
     let mut sql = "SELECT * FROM services WHERE org_id = ?".to_string();
@@ -406,16 +421,8 @@ pub async fn get_service(
         .ok_or_else(|| crate::error::AppError::NotFound("Organization not found".to_string()))?;
 
     // Check if user is member
-    let _membership = sqlx::query_as::<_, Membership>(
-        "SELECT * FROM memberships WHERE org_id = ? AND user_id = ?",
-    )
-    .bind(&org.id)
-    .bind(&auth_user.user.id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| {
-        crate::error::AppError::Forbidden("Not a member of this organization".to_string())
-    })?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[])
+        .await?;
 
     let service =
         sqlx::query_as::<_, Service>("SELECT * FROM services WHERE org_id = ? AND slug = ?")
@@ -433,6 +440,7 @@ pub async fn update_service(
     State(state): State<AppState>,
     Path((org_slug, service_slug)): Path<(String, String)>,
     auth_user: axum::Extension<AuthUser>,
+    audit: AuditContext,
     Json(req): Json<UpdateServiceRequest>,
 ) -> Result<Json<ServiceResponse>> {
     // Get organization
@@ -443,14 +451,14 @@ pub async fn update_service(
         .ok_or_else(|| crate::error::AppError::NotFound("Organization not found".to_string()))?;
 
     // Check if user has permission
-    if !can_manage_service(&state, &auth_user.user.id, &org.id).await? {
+    if !has_org_permission(&state, &auth_user.user.id, &org.id, "manage_services").await? {
         return Err(crate::error::AppError::Forbidden(
             "Insufficient permissions to update services".to_string(),
         ));
     }
 
     // Get existing service
-    let _existing_service =
+    let existing_service =
         sqlx::query_as::<_, Service>("SELECT * FROM services WHERE org_id = ? AND slug = ?")
             .bind(&org.id)
             .bind(&service_slug)
@@ -511,6 +519,44 @@ pub async fn update_service(
         values.push(device_activation_uri.clone());
     }
 
+    if let Some(ciba_notification_endpoint) = &req.ciba_notification_endpoint {
+        // Pinged from inside the network, so held to the same rules as webhooks
+        WebhookService::validate_url(ciba_notification_endpoint).map_err(|_| {
+            crate::error::AppError::BadRequest(
+                "CIBA notification endpoint must be an HTTPS URL on a public host".to_string(),
+            )
+        })?;
+        updates.push("ciba_notification_endpoint = ?");
+        values.push(ciba_notification_endpoint.clone());
+    }
+
+    if let Some(backchannel_logout_uri) = &req.backchannel_logout_uri {
+        validate_logout_uri(backchannel_logout_uri)?;
+        updates.push("backchannel_logout_uri = ?");
+        values.push(backchannel_logout_uri.clone());
+    }
+
+    if let Some(frontchannel_logout_uri) = &req.frontchannel_logout_uri {
+        validate_logout_uri(frontchannel_logout_uri)?;
+        updates.push("frontchannel_logout_uri = ?");
+        values.push(frontchannel_logout_uri.clone());
+    }
+
+    if let Some(post_logout_redirect_uris) = &req.post_logout_redirect_uris {
+        for uri in post_logout_redirect_uris {
+            validate_logout_uri(uri)?;
+        }
+        updates.push("post_logout_redirect_uris = ?");
+        let uris_json = serde_json::to_string(post_logout_redirect_uris).unwrap();
+        values.push(uris_json.clone());
+        scope_strings.push(uris_json);
+    }
+
+    if let Some(require_verified_email) = req.require_verified_email {
+        updates.push("require_verified_email = ?");
+        values.push(if require_verified_email { "1" } else { "0" }.to_string());
+    }
+
     if updates.is_empty() {
         return Err(crate::error::AppError::BadRequest(
             "No fields to update".to_string(),
@@ -540,14 +586,41 @@ pub async fn update_service(
             .fetch_one(&state.pool)
             .await?;
 
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "service_updated",
+        "service",
+        &updated_service.id,
+        diff(
+            &serde_json::json!(ServiceResponse::from(existing_service)),
+            &serde_json::json!(ServiceResponse::from(updated_service.clone())),
+        ),
+    )
+    .await?;
+
     Ok(Json(ServiceResponse::from(updated_service)))
 }
 
+fn validate_logout_uri(uri: &str) -> Result<()> {
+    let parsed = oauth2::url::Url::parse(uri).map_err(|_| {
+        crate::error::AppError::BadRequest(format!("Invalid logout URI: {}", uri))
+    })?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return Err(crate::error::AppError::BadRequest(
+            "Logout URIs must be HTTP(S) URLs".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 // Delete service
 pub async fn delete_service(
     State(state): State<AppState>,
     Path((org_slug, service_slug)): Path<(String, String)>,
     auth_user: axum::Extension<AuthUser>,
+    audit: AuditContext,
 ) -> Result<StatusCode> {
     // Get organization
     let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
@@ -556,27 +629,26 @@ pub async fn delete_service(
         .await?
         .ok_or_else(|| crate::error::AppError::NotFound("Organization not found".to_string()))?;
 
-    // Check if user is owner (only owners can delete services)
-    let membership = sqlx::query_as::<_, Membership>(
-        "SELECT * FROM memberships WHERE org_id = ? AND user_id = ?",
-    )
-    .bind(&org.id)
-    .bind(&auth_user.user.id)
-    .fetch_optional(&state.pool)
-    .await?;
-
-    if membership.map(|m| m.role != "owner").unwrap_or(true) {
+    // Check if user may delete services (owner-only unless granted to a custom role)
+    if !has_org_permission(&state, &auth_user.user.id, &org.id, "delete_services").await? {
         return Err(crate::error::AppError::Forbidden(
-            "Only organization owners can delete services".to_string(),
+            "Insufficient permissions to delete services".to_string(),
         ));
     }
 
+    let service =
+        sqlx::query_as::<_, Service>("SELECT * FROM services WHERE org_id = ? AND slug = ?")
+            .bind(&org.id)
+            .bind(&service_slug)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("Service not found".to_string()))?;
+
     // Check if service has active subscriptions
     let subscription_count: i64 = sqlx::query_scalar(
-        "SELECT COUNT(*) FROM subscriptions WHERE service_id = (SELECT id FROM services WHERE org_id = ? AND slug = ?) AND status = 'active'",
+        "SELECT COUNT(*) FROM subscriptions WHERE service_id = ? AND status = 'active'",
     )
-    .bind(&org.id)
-    .bind(&service_slug)
+    .bind(&service.id)
     .fetch_one(&state.pool)
     .await?;
 
@@ -586,17 +658,28 @@ pub async fn delete_service(
         ));
     }
 
-    let result = sqlx::query("DELETE FROM services WHERE org_id = ? AND slug = ?")
-        .bind(&org.id)
-        .bind(&service_slug)
-        .execute(&state.pool)
+    let mut tx = state.pool.begin().await?;
+
+    sqlx::query("DELETE FROM services WHERE id = ?")
+        .bind(&service.id)
+        .execute(&mut *tx)
         .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(crate::error::AppError::NotFound(
-            "Service not found".to_string(),
-        ));
-    }
+    create_org_audit_log(
+        &mut tx,
+        &audit,
+        &org.id,
+        "service_deleted",
+        "service",
+        &service.id,
+        diff(
+            &serde_json::json!(ServiceResponse::from(service.clone())),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    tx.commit().await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -606,8 +689,11 @@ pub async fn create_plan(
     State(state): State<AppState>,
     Path((org_slug, service_slug)): Path<(String, String)>,
     auth_user: axum::Extension<AuthUser>,
+    audit: AuditContext,
     Json(req): Json<CreatePlanRequest>,
 ) -> Result<Json<PlanResponse>> {
+    crate::middleware::ensure_not_impersonating(&auth_user)?;
+
     // Get organization
     let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
         .bind(&org_slug)
@@ -616,7 +702,7 @@ pub async fn create_plan(
         .ok_or_else(|| crate::error::AppError::NotFound("Organization not found".to_string()))?;
 
     // Check if user has permission
-    if !can_manage_service(&state, &auth_user.user.id, &org.id).await? {
+    if !has_org_permission(&state, &auth_user.user.id, &org.id, "manage_billing").await? {
         return Err(crate::error::AppError::Forbidden(
             "Insufficient permissions to create plans".to_string(),
         ));
@@ -652,6 +738,17 @@ pub async fn create_plan(
     .fetch_one(&state.pool)
     .await?;
 
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "plan_created",
+        "plan",
+        &plan.id,
+        diff(&serde_json::Value::Null, &serde_json::json!(plan)),
+    )
+    .await?;
+
     // Get subscription count (should be 0 for new plan)
     let subscription_count: i64 = sqlx::query_scalar(
         "SELECT COUNT(*) FROM subscriptions WHERE plan_id = ? AND status = 'active'",
@@ -680,16 +777,8 @@ pub async fn list_service_plans(
         .ok_or_else(|| crate::error::AppError::NotFound("Organization not found".to_string()))?;
 
     // Check if user is member
-    let _membership = sqlx::query_as::<_, Membership>(
-        "SELECT * FROM memberships WHERE org_id = ? AND user_id = ?",
-    )
-    .bind(&org.id)
-    .bind(&auth_user.user.id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or_else(|| {
-        crate::error::AppError::Forbidden("Not a member of this organization".to_string())
-    })?;
+    crate::middleware::check_org_membership(&state.pool, &auth_user.user.id, &org.id, &[])
+        .await?;
 
     // Get service
     let service =
@@ -0,0 +1,299 @@
+use crate::auth::impersonation::ImpersonationService;
+use crate::auth::logout::LogoutService;
+use crate::db::models::{PlatformAuditLog, Session};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Debug, Serialize)]
+pub struct UserSessionResponse {
+    pub id: String,
+    pub org_slug: Option<String>,
+    pub service_slug: Option<String>,
+    pub service_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub device: String,
+    pub browser: String,
+    pub current: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<String>, // email of the user acting as the caller
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impersonation_reason: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct SessionRow {
+    id: String,
+    org_slug: Option<String>,
+    service_slug: Option<String>,
+    service_name: Option<String>,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    impersonated_by: Option<String>,
+    impersonation_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonationEventResponse {
+    pub id: String,
+    pub action: String,
+    pub actor_id: String,
+    pub actor_email: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ImpersonationEventRow {
+    #[sqlx(flatten)]
+    log: PlatformAuditLog,
+    actor_email: Option<String>,
+}
+
+/// GET /api/user/sessions
+/// List the caller's active sessions across all services
+pub async fn list_user_sessions(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<UserSessionResponse>>> {
+    let rows = sqlx::query_as::<_, SessionRow>(
+        r#"
+        SELECT
+            ses.id,
+            ses.org_slug,
+            s.slug as service_slug,
+            s.name as service_name,
+            ses.created_at,
+            ses.last_used_at,
+            ses.ip_address,
+            ses.user_agent,
+            actor.email as impersonated_by,
+            ses.impersonation_reason
+        FROM sessions ses
+        LEFT JOIN services s ON ses.service_id = s.id
+        LEFT JOIN users actor ON ses.impersonator_id = actor.id
+        WHERE ses.user_id = ?
+          AND (ses.expires_at > ? OR ses.refresh_token_expires_at > ?)
+        ORDER BY COALESCE(ses.last_used_at, ses.created_at) DESC
+        "#,
+    )
+    .bind(&auth_user.user.id)
+    .bind(Utc::now())
+    .bind(Utc::now())
+    .fetch_all(&state.pool)
+    .await?;
+
+    let sessions = rows
+        .into_iter()
+        .map(|row| {
+            let (device, browser) = parse_user_agent(row.user_agent.as_deref());
+            UserSessionResponse {
+                current: row.id == auth_user.session_id,
+                id: row.id,
+                org_slug: row.org_slug,
+                service_slug: row.service_slug,
+                service_name: row.service_name,
+                created_at: row.created_at,
+                last_used_at: row.last_used_at,
+                ip_address: row.ip_address,
+                user_agent: row.user_agent,
+                device,
+                browser,
+                impersonated_by: row.impersonated_by,
+                impersonation_reason: row.impersonation_reason,
+            }
+        })
+        .collect();
+
+    Ok(Json(sessions))
+}
+
+/// DELETE /api/user/sessions/:session_id
+/// Revoke one of the caller's sessions
+pub async fn revoke_user_session(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    audit: AuditContext,
+    Path(session_id): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    // Only allow revoking the caller's own sessions
+    let owned =
+        sqlx::query_scalar::<_, String>("SELECT id FROM sessions WHERE id = ? AND user_id = ?")
+            .bind(&session_id)
+            .bind(&auth_user.user.id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+
+    if let Some(session) =
+        LogoutService::revoke_session(&state.pool, &state.jwt_service, &state.base_url, &owned)
+            .await?
+    {
+        ImpersonationService::log_stopped(&state.pool, &session, &auth_user.user.id, &audit)
+            .await?;
+    }
+
+    Ok(Json(json!({
+        "message": "Session revoked successfully",
+        "revoked_count": 1
+    })))
+}
+
+/// DELETE /api/user/sessions
+/// Revoke all of the caller's sessions except the one making the request
+pub async fn revoke_other_user_sessions(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    audit: AuditContext,
+) -> Result<Json<serde_json::Value>> {
+    let revoked = sqlx::query_as::<_, Session>(
+        "DELETE FROM sessions WHERE user_id = ? AND id != ? RETURNING *",
+    )
+    .bind(&auth_user.user.id)
+    .bind(&auth_user.session_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    LogoutService::notify_services(&state.pool, &state.jwt_service, &state.base_url, &revoked)
+        .await?;
+    for session in &revoked {
+        ImpersonationService::log_stopped(&state.pool, session, &auth_user.user.id, &audit)
+            .await?;
+    }
+
+    Ok(Json(json!({
+        "message": "Other sessions revoked successfully",
+        "revoked_count": revoked.len()
+    })))
+}
+
+/// GET /api/user/impersonations
+/// Audit trail of every time someone impersonated the caller
+pub async fn list_user_impersonations(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<ImpersonationEventResponse>>> {
+    let rows = sqlx::query_as::<_, ImpersonationEventRow>(
+        r#"
+        SELECT pal.*, actor.email as actor_email
+        FROM platform_audit_log pal
+        LEFT JOIN users actor ON pal.platform_owner_id = actor.id
+        WHERE pal.target_type = 'user' AND pal.target_id = ?
+          AND pal.action IN ('impersonation_started', 'impersonation_stopped')
+        ORDER BY pal.created_at DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(&auth_user.user.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let events = rows
+        .into_iter()
+        .map(|row| ImpersonationEventResponse {
+            metadata: row
+                .log
+                .metadata
+                .as_deref()
+                .and_then(|m| serde_json::from_str(m).ok()),
+            id: row.log.id,
+            action: row.log.action,
+            actor_id: row.log.platform_owner_id,
+            actor_email: row.actor_email,
+            created_at: row.log.created_at,
+        })
+        .collect();
+
+    Ok(Json(events))
+}
+
+/// Best-effort split of a User-Agent header into (device, browser) labels
+fn parse_user_agent(user_agent: Option<&str>) -> (String, String) {
+    let Some(ua) = user_agent else {
+        return ("Unknown".to_string(), "Unknown".to_string());
+    };
+
+    let device = if ua.contains("iPhone") {
+        "iPhone"
+    } else if ua.contains("iPad") {
+        "iPad"
+    } else if ua.contains("Android") {
+        if ua.contains("Mobile") {
+            "Android phone"
+        } else {
+            "Android tablet"
+        }
+    } else if ua.contains("Windows") {
+        "Windows PC"
+    } else if ua.contains("Macintosh") || ua.contains("Mac OS X") {
+        "Mac"
+    } else if ua.contains("CrOS") {
+        "Chromebook"
+    } else if ua.contains("Linux") {
+        "Linux PC"
+    } else {
+        "Unknown"
+    };
+
+    // Order matters: Edge and Opera also advertise Chrome, Chrome advertises Safari
+    let browser = if ua.contains("Edg/") {
+        "Edge"
+    } else if ua.contains("OPR/") {
+        "Opera"
+    } else if ua.contains("Firefox/") {
+        "Firefox"
+    } else if ua.contains("Chrome/") || ua.contains("CriOS/") {
+        "Chrome"
+    } else if ua.contains("Safari/") {
+        "Safari"
+    } else if ua.starts_with("curl/") {
+        "curl"
+    } else {
+        "Unknown"
+    };
+
+    (device.to_string(), browser.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_agent() {
+        let chrome_mac = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+        assert_eq!(
+            parse_user_agent(Some(chrome_mac)),
+            ("Mac".to_string(), "Chrome".to_string())
+        );
+
+        let safari_iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+        assert_eq!(
+            parse_user_agent(Some(safari_iphone)),
+            ("iPhone".to_string(), "Safari".to_string())
+        );
+
+        let edge_windows = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0";
+        assert_eq!(
+            parse_user_agent(Some(edge_windows)),
+            ("Windows PC".to_string(), "Edge".to_string())
+        );
+
+        assert_eq!(
+            parse_user_agent(None),
+            ("Unknown".to_string(), "Unknown".to_string())
+        );
+    }
+}
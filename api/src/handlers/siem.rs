@@ -0,0 +1,453 @@
+use crate::auth::security_events::SECURITY_SEVERITIES;
+use crate::db::models::{SecurityEvent, SiemSink, SiemSinkCursor};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::platform::create_audit_log;
+use crate::middleware::AuthUser;
+use crate::siem::{self, sinks::redact_config, SiemEvent, SiemSource};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSiemSinkRequest {
+    pub name: String,
+    pub kind: String,
+    pub config: Value,
+    pub sources: Option<Vec<String>>, // all sources when omitted
+    pub enabled: Option<bool>,
+    pub start_from: Option<String>, // 'beginning' (default) or 'now'
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSiemSinkRequest {
+    pub name: Option<String>,
+    pub config: Option<Value>, // replaces the whole config, header values included
+    pub sources: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetCursorsRequest {
+    pub source: Option<String>, // every source when omitted
+    pub start_from: String,     // 'beginning' or 'now'
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiemSinkResponse {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub config: Value,
+    pub sources: Vec<String>,
+    pub enabled: bool,
+    pub last_error: Option<String>,
+    pub last_exported_at: Option<DateTime<Utc>>,
+    pub cursors: Vec<SiemSinkCursor>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestSiemSinkResponse {
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SecurityEventQuery {
+    pub event_type: Option<String>,
+    pub severity: Option<String>,
+    pub user_id: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecurityEventResponse {
+    pub events: Vec<SecurityEvent>,
+    pub total: i64,
+}
+
+fn require_platform_owner(auth_user: &AuthUser) -> Result<()> {
+    if !auth_user.user.is_platform_owner {
+        return Err(AppError::Forbidden(
+            "Platform owner access required".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn file_dir() -> Result<String> {
+    crate::config::Config::from_env()
+        .map(|config| config.siem_file_dir)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+fn parse_stored_config(sink: &SiemSink) -> Result<Value> {
+    serde_json::from_str(&sink.config).map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+async fn find_sink(pool: &SqlitePool, sink_id: &str) -> Result<SiemSink> {
+    sqlx::query_as::<_, SiemSink>("SELECT * FROM siem_sinks WHERE id = ?")
+        .bind(sink_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("SIEM sink not found".to_string()))
+}
+
+async fn sink_response(pool: &SqlitePool, sink: SiemSink) -> Result<SiemSinkResponse> {
+    let cursors = sqlx::query_as::<_, SiemSinkCursor>(
+        "SELECT * FROM siem_sink_cursors WHERE sink_id = ? ORDER BY source",
+    )
+    .bind(&sink.id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(SiemSinkResponse {
+        config: redact_config(&sink.kind, &parse_stored_config(&sink)?),
+        sources: serde_json::from_str(&sink.sources).unwrap_or_default(),
+        id: sink.id,
+        name: sink.name,
+        kind: sink.kind,
+        enabled: sink.enabled,
+        last_error: sink.last_error,
+        last_exported_at: sink.last_exported_at,
+        cursors,
+        created_by: sink.created_by,
+        created_at: sink.created_at,
+        updated_at: sink.updated_at,
+    })
+}
+
+/// Move a sink's cursors to the start or the current end of each source
+async fn position_cursors(
+    pool: &SqlitePool,
+    sink_id: &str,
+    sources: &[SiemSource],
+    start_from: &str,
+) -> Result<()> {
+    for &source in sources {
+        let position = match start_from {
+            "beginning" => 0,
+            "now" => source.head(pool).await?,
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid start_from: {} (expected beginning or now)",
+                    other
+                )))
+            }
+        };
+        siem::set_cursor_position(pool, sink_id, source, position).await?;
+    }
+    Ok(())
+}
+
+/// GET /api/platform/siem-sinks
+pub async fn list_siem_sinks(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<SiemSinkResponse>>> {
+    require_platform_owner(&auth_user)?;
+
+    let sinks = sqlx::query_as::<_, SiemSink>("SELECT * FROM siem_sinks ORDER BY created_at")
+        .fetch_all(&state.pool)
+        .await?;
+
+    let mut responses = Vec::with_capacity(sinks.len());
+    for sink in sinks {
+        responses.push(sink_response(&state.pool, sink).await?);
+    }
+
+    Ok(Json(responses))
+}
+
+/// POST /api/platform/siem-sinks
+/// Register a sink; it starts exporting on the next run of the export job
+pub async fn create_siem_sink(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CreateSiemSinkRequest>,
+) -> Result<(StatusCode, Json<SiemSinkResponse>)> {
+    require_platform_owner(&auth_user)?;
+
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Sink name is required".to_string()));
+    }
+    siem::build_sink(&req.kind, &req.config, &file_dir()?)?;
+    let source_names = req.sources.unwrap_or_else(|| {
+        SiemSource::ALL
+            .iter()
+            .map(|s| s.name().to_string())
+            .collect()
+    });
+    let sources = SiemSource::to_json(&source_names)?;
+    let start_from = req.start_from.as_deref().unwrap_or("beginning");
+
+    let sink = sqlx::query_as::<_, SiemSink>(
+        r#"
+        INSERT INTO siem_sinks
+            (id, name, kind, config, sources, enabled, created_by, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(&req.kind)
+    .bind(req.config.to_string())
+    .bind(&sources)
+    .bind(req.enabled.unwrap_or(true))
+    .bind(&auth_user.user.id)
+    .bind(Utc::now())
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    if let Err(e) = position_cursors(
+        &state.pool,
+        &sink.id,
+        &SiemSource::parse_list(&sources),
+        start_from,
+    )
+    .await
+    {
+        sqlx::query("DELETE FROM siem_sinks WHERE id = ?")
+            .bind(&sink.id)
+            .execute(&state.pool)
+            .await?;
+        return Err(e);
+    }
+
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "create_siem_sink",
+        "siem_sink",
+        &sink.id,
+        Some(json!({ "name": sink.name, "kind": sink.kind, "sources": source_names })),
+    )
+    .await?;
+
+    tracing::info!(sink_id = %sink.id, kind = %sink.kind, "Created SIEM sink");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(sink_response(&state.pool, sink).await?),
+    ))
+}
+
+/// GET /api/platform/siem-sinks/:sink_id
+pub async fn get_siem_sink(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(sink_id): Path<String>,
+) -> Result<Json<SiemSinkResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    let sink = find_sink(&state.pool, &sink_id).await?;
+
+    Ok(Json(sink_response(&state.pool, sink).await?))
+}
+
+/// PATCH /api/platform/siem-sinks/:sink_id
+pub async fn update_siem_sink(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(sink_id): Path<String>,
+    Json(req): Json<UpdateSiemSinkRequest>,
+) -> Result<Json<SiemSinkResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    let sink = find_sink(&state.pool, &sink_id).await?;
+    if let Some(ref config) = req.config {
+        siem::build_sink(&sink.kind, config, &file_dir()?)?;
+    }
+    let sources = req
+        .sources
+        .as_deref()
+        .map(SiemSource::to_json)
+        .transpose()?;
+    let name = req.name.as_deref().map(str::trim);
+    if name == Some("") {
+        return Err(AppError::BadRequest("Sink name is required".to_string()));
+    }
+
+    let updated = sqlx::query_as::<_, SiemSink>(
+        r#"
+        UPDATE siem_sinks
+        SET name = ?, config = ?, sources = ?, enabled = ?, last_error = NULL, updated_at = ?
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(name.unwrap_or(&sink.name))
+    .bind(
+        req.config
+            .as_ref()
+            .map(Value::to_string)
+            .unwrap_or(sink.config.clone()),
+    )
+    .bind(sources.as_ref().unwrap_or(&sink.sources))
+    .bind(req.enabled.unwrap_or(sink.enabled))
+    .bind(Utc::now())
+    .bind(&sink.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "update_siem_sink",
+        "siem_sink",
+        &sink.id,
+        Some(json!({
+            "name": updated.name,
+            "config_changed": req.config.is_some(),
+            "sources": req.sources,
+            "enabled": updated.enabled,
+        })),
+    )
+    .await?;
+
+    Ok(Json(sink_response(&state.pool, updated).await?))
+}
+
+/// DELETE /api/platform/siem-sinks/:sink_id
+pub async fn delete_siem_sink(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(sink_id): Path<String>,
+) -> Result<StatusCode> {
+    require_platform_owner(&auth_user)?;
+
+    let sink = find_sink(&state.pool, &sink_id).await?;
+    sqlx::query("DELETE FROM siem_sinks WHERE id = ?")
+        .bind(&sink.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "delete_siem_sink",
+        "siem_sink",
+        &sink.id,
+        Some(json!({ "name": sink.name, "kind": sink.kind })),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/platform/siem-sinks/:sink_id/test
+/// Send one test event straight to the sink, bypassing the cursors
+pub async fn test_siem_sink(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(sink_id): Path<String>,
+) -> Result<Json<TestSiemSinkResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    let sink = find_sink(&state.pool, &sink_id).await?;
+    let target = siem::build_sink(&sink.kind, &parse_stored_config(&sink)?, &file_dir()?)?;
+
+    let result = target.send(&[SiemEvent::test()]).await;
+
+    Ok(Json(TestSiemSinkResponse {
+        delivered: result.is_ok(),
+        error: result.err(),
+    }))
+}
+
+/// POST /api/platform/siem-sinks/:sink_id/cursors/reset
+/// Replay a sink's sources from the beginning, or skip to now
+pub async fn reset_siem_sink_cursors(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(sink_id): Path<String>,
+    Json(req): Json<ResetCursorsRequest>,
+) -> Result<Json<SiemSinkResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    let sink = find_sink(&state.pool, &sink_id).await?;
+    let sources = match req.source.as_deref() {
+        Some(name) => vec![SiemSource::from_name(name)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown SIEM source: {}", name)))?],
+        None => SiemSource::parse_list(&sink.sources),
+    };
+    position_cursors(&state.pool, &sink.id, &sources, &req.start_from).await?;
+
+    create_audit_log(
+        &mut *state.pool.acquire().await?,
+        &auth_user.user.id,
+        "reset_siem_sink_cursors",
+        "siem_sink",
+        &sink.id,
+        Some(json!({ "source": req.source, "start_from": req.start_from })),
+    )
+    .await?;
+
+    Ok(Json(sink_response(&state.pool, sink).await?))
+}
+
+/// GET /api/platform/security-events
+pub async fn list_security_events(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<SecurityEventQuery>,
+) -> Result<Json<SecurityEventResponse>> {
+    require_platform_owner(&auth_user)?;
+
+    if let Some(ref severity) = query.severity {
+        if !SECURITY_SEVERITIES.contains(&severity.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid severity: {} (expected low, medium or high)",
+                severity
+            )));
+        }
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let condition = "(? IS NULL OR event_type = ?) AND (? IS NULL OR severity = ?)
+                     AND (? IS NULL OR user_id = ?)";
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM security_events WHERE {}",
+        condition
+    ))
+    .bind(&query.event_type)
+    .bind(&query.event_type)
+    .bind(&query.severity)
+    .bind(&query.severity)
+    .bind(&query.user_id)
+    .bind(&query.user_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let events = sqlx::query_as::<_, SecurityEvent>(&format!(
+        "SELECT * FROM security_events WHERE {} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        condition
+    ))
+    .bind(&query.event_type)
+    .bind(&query.event_type)
+    .bind(&query.severity)
+    .bind(&query.severity)
+    .bind(&query.user_id)
+    .bind(&query.user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(SecurityEventResponse { events, total }))
+}
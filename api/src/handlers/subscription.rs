@@ -1,4 +1,5 @@
 use crate::auth::jwt::Claims;
+use crate::auth::profiles::{ProfileService, ProfileUpdate};
 use crate::constants::DEFAULT_TIER_NAME;
 use crate::db::models::User;
 use crate::error::{AppError, Result};
@@ -12,11 +13,41 @@ pub struct UserResponse {
     pub email: String,
     pub org: String,
     pub service: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    pub primary_identity_id: Option<String>,
+}
+
+impl UserResponse {
+    fn new(user: User, claims: Claims) -> Self {
+        Self {
+            id: user.id,
+            email: user.email,
+            org: claims.org.unwrap_or_default(),
+            service: claims.service.unwrap_or_default(),
+            name: user.name,
+            given_name: user.given_name,
+            family_name: user.family_name,
+            avatar_url: user.avatar_url,
+            locale: user.locale,
+            primary_identity_id: user.primary_identity_id,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserRequest {
     pub email: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    /// Follow the profile of one of the user's identities instead
+    pub primary_identity_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,12 +89,7 @@ pub async fn get_user(
         }
     }
 
-    Ok(Json(UserResponse {
-        id: auth_user.user.id.clone(),
-        email: auth_user.user.email.clone(),
-        org: auth_user.claims.org.unwrap_or_default(),
-        service: auth_user.claims.service.unwrap_or_default(),
-    }))
+    Ok(Json(UserResponse::new(auth_user.user, auth_user.claims)))
 }
 
 /// Get current subscription
@@ -167,6 +193,8 @@ pub async fn update_user(
     let auth_user = auth_user
         .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string()))?
         .0;
+    // Changing the email would hand the account to whoever controls the new address
+    crate::middleware::ensure_not_impersonating(&auth_user)?;
 
     // Get current user
     let mut user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
@@ -180,6 +208,7 @@ pub async fn update_user(
         if !new_email.contains('@') || new_email.len() < 5 {
             return Err(AppError::BadRequest("Invalid email format".to_string()));
         }
+        ProfileService::ensure_verified_email(&state.pool, &user.id, &new_email).await?;
 
         // Check if email is already taken
         let existing =
@@ -205,6 +234,29 @@ pub async fn update_user(
         user.email = new_email;
     }
 
+    // Update the primary profile, either by hand or from an identity
+    let profile = ProfileUpdate {
+        name: req.name,
+        given_name: req.given_name,
+        family_name: req.family_name,
+        avatar_url: req.avatar_url,
+        locale: req.locale,
+    };
+    match (req.primary_identity_id, profile.is_empty()) {
+        (Some(_), false) => {
+            return Err(AppError::BadRequest(
+                "Set either primary_identity_id or profile fields, not both".to_string(),
+            ));
+        }
+        (Some(identity_id), true) => {
+            user = ProfileService::set_primary(&state.pool, &user.id, &identity_id).await?;
+        }
+        (None, false) => {
+            user = ProfileService::update(&state.pool, &user, profile).await?;
+        }
+        (None, true) => {}
+    }
+
     // Verify user is still member of org if org claim exists
     if let Some(ref org_slug) = auth_user.claims.org {
         let is_member = sqlx::query_scalar::<_, i64>(
@@ -226,10 +278,5 @@ pub async fn update_user(
         }
     }
 
-    Ok(Json(UserResponse {
-        id: user.id,
-        email: user.email,
-        org: auth_user.claims.org.unwrap_or_default(),
-        service: auth_user.claims.service.unwrap_or_default(),
-    }))
+    Ok(Json(UserResponse::new(user, auth_user.claims)))
 }
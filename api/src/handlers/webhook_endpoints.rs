@@ -0,0 +1,506 @@
+use crate::auth::webhooks::WebhookService;
+use crate::constants::{MAX_WEBHOOK_ENDPOINTS_PER_ORG, WEBHOOK_SECRET_ROTATION_GRACE_HOURS};
+use crate::db::models::{Organization, WebhookDelivery, WebhookEndpoint};
+use crate::error::{AppError, Result};
+use crate::handlers::auth::AppState;
+use crate::handlers::org_audit::{create_org_audit_log, diff};
+use crate::middleware::{AuditContext, AuthUser};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookEndpointRequest {
+    pub url: String,
+    pub description: Option<String>,
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookEndpointRequest {
+    pub url: Option<String>,
+    pub description: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookEndpointResponse {
+    pub id: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub event_types: Vec<String>,
+    pub enabled: bool,
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<WebhookEndpoint> for WebhookEndpointResponse {
+    fn from(endpoint: WebhookEndpoint) -> Self {
+        Self {
+            event_types: WebhookService::parse_event_types(&endpoint.event_types),
+            id: endpoint.id,
+            url: endpoint.url,
+            description: endpoint.description,
+            enabled: endpoint.enabled,
+            previous_secret_expires_at: endpoint
+                .previous_secret_expires_at
+                .filter(|expires_at| *expires_at > Utc::now()),
+            created_by: endpoint.created_by,
+            created_at: endpoint.created_at,
+            updated_at: endpoint.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookSecretResponse {
+    pub endpoint: WebhookEndpointResponse,
+    pub secret: String, // Signing secret (only returned here)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryLogQuery {
+    pub status: Option<String>,
+    pub event_type: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DeliveryLogEntry {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub event_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryLogResponse {
+    pub deliveries: Vec<DeliveryLogEntry>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryDetailResponse {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+}
+
+async fn find_organization(
+    pool: &SqlitePool,
+    auth_user: &AuthUser,
+    org_slug: &str,
+) -> Result<Organization> {
+    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
+        .bind(org_slug)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+
+    crate::middleware::check_org_permission(pool, &auth_user.user.id, &org.id, "manage_webhooks")
+        .await?;
+
+    Ok(org)
+}
+
+async fn find_endpoint(
+    pool: &SqlitePool,
+    org_id: &str,
+    endpoint_id: &str,
+) -> Result<WebhookEndpoint> {
+    sqlx::query_as::<_, WebhookEndpoint>(
+        "SELECT * FROM webhook_endpoints WHERE id = ? AND org_id = ?",
+    )
+    .bind(endpoint_id)
+    .bind(org_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Webhook endpoint not found".to_string()))
+}
+
+fn event_types_json(event_types: &[String]) -> Result<String> {
+    WebhookService::validate_event_types(event_types)?;
+    serde_json::to_string(event_types).map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// GET /api/organizations/:org_slug/webhooks
+pub async fn list_webhook_endpoints(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(org_slug): Path<String>,
+) -> Result<Json<Vec<WebhookEndpointResponse>>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+
+    let endpoints = sqlx::query_as::<_, WebhookEndpoint>(
+        "SELECT * FROM webhook_endpoints WHERE org_id = ? ORDER BY created_at DESC",
+    )
+    .bind(&org.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(endpoints.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/organizations/:org_slug/webhooks
+/// Register an endpoint (requires manage_webhooks)
+pub async fn create_webhook_endpoint(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path(org_slug): Path<String>,
+    Json(req): Json<CreateWebhookEndpointRequest>,
+) -> Result<(StatusCode, Json<WebhookSecretResponse>)> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    WebhookService::validate_url(&req.url)?;
+    let event_types = event_types_json(&req.event_types)?;
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_endpoints WHERE org_id = ?")
+        .bind(&org.id)
+        .fetch_one(&state.pool)
+        .await?;
+    if count >= MAX_WEBHOOK_ENDPOINTS_PER_ORG {
+        return Err(AppError::BadRequest(format!(
+            "An organization can have at most {} webhook endpoints",
+            MAX_WEBHOOK_ENDPOINTS_PER_ORG
+        )));
+    }
+
+    let secret = WebhookService::generate_secret();
+    let endpoint = sqlx::query_as::<_, WebhookEndpoint>(
+        r#"
+        INSERT INTO webhook_endpoints
+            (id, org_id, url, description, event_types, secret, enabled, created_by, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?, ?)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&org.id)
+    .bind(&req.url)
+    .bind(&req.description)
+    .bind(&event_types)
+    .bind(&secret)
+    .bind(&auth_user.user.id)
+    .bind(Utc::now())
+    .bind(Utc::now())
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "webhook_endpoint_created",
+        "webhook_endpoint",
+        &endpoint.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!(WebhookEndpointResponse::from(endpoint.clone())),
+        ),
+    )
+    .await?;
+
+    tracing::info!(
+        org_slug = %org_slug,
+        endpoint_id = %endpoint.id,
+        url = %endpoint.url,
+        "Created webhook endpoint"
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WebhookSecretResponse {
+            endpoint: endpoint.into(),
+            secret,
+        }),
+    ))
+}
+
+/// GET /api/organizations/:org_slug/webhooks/:endpoint_id
+pub async fn get_webhook_endpoint(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, endpoint_id)): Path<(String, String)>,
+) -> Result<Json<WebhookEndpointResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+
+    Ok(Json(endpoint.into()))
+}
+
+/// PATCH /api/organizations/:org_slug/webhooks/:endpoint_id
+pub async fn update_webhook_endpoint(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, endpoint_id)): Path<(String, String)>,
+    Json(req): Json<UpdateWebhookEndpointRequest>,
+) -> Result<Json<WebhookEndpointResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+    let before = serde_json::json!(WebhookEndpointResponse::from(endpoint.clone()));
+
+    if let Some(ref url) = req.url {
+        WebhookService::validate_url(url)?;
+    }
+    let event_types = req
+        .event_types
+        .as_deref()
+        .map(event_types_json)
+        .transpose()?;
+
+    let endpoint = sqlx::query_as::<_, WebhookEndpoint>(
+        r#"
+        UPDATE webhook_endpoints
+        SET url = ?, description = ?, event_types = ?, enabled = ?, updated_at = ?
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(req.url.as_ref().unwrap_or(&endpoint.url))
+    .bind(req.description.as_ref().or(endpoint.description.as_ref()))
+    .bind(event_types.as_ref().unwrap_or(&endpoint.event_types))
+    .bind(req.enabled.unwrap_or(endpoint.enabled))
+    .bind(Utc::now())
+    .bind(&endpoint.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "webhook_endpoint_updated",
+        "webhook_endpoint",
+        &endpoint.id,
+        diff(
+            &before,
+            &serde_json::json!(WebhookEndpointResponse::from(endpoint.clone())),
+        ),
+    )
+    .await?;
+
+    Ok(Json(endpoint.into()))
+}
+
+/// DELETE /api/organizations/:org_slug/webhooks/:endpoint_id
+/// Remove an endpoint together with its delivery log
+pub async fn delete_webhook_endpoint(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, endpoint_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+
+    sqlx::query("DELETE FROM webhook_endpoints WHERE id = ?")
+        .bind(&endpoint.id)
+        .execute(&state.pool)
+        .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "webhook_endpoint_deleted",
+        "webhook_endpoint",
+        &endpoint.id,
+        diff(
+            &serde_json::json!(WebhookEndpointResponse::from(endpoint.clone())),
+            &serde_json::Value::Null,
+        ),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/organizations/:org_slug/webhooks/:endpoint_id/rotate-secret
+/// Issue a new signing secret. The old one keeps signing alongside it for a
+/// grace period so receivers can switch without dropping events.
+pub async fn rotate_webhook_secret(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, endpoint_id)): Path<(String, String)>,
+) -> Result<Json<WebhookSecretResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+    let before = serde_json::json!(endpoint);
+
+    let secret = WebhookService::generate_secret();
+    let endpoint = sqlx::query_as::<_, WebhookEndpoint>(
+        r#"
+        UPDATE webhook_endpoints
+        SET secret = ?, previous_secret = secret, previous_secret_expires_at = ?, updated_at = ?
+        WHERE id = ?
+        RETURNING *
+        "#,
+    )
+    .bind(&secret)
+    .bind(Utc::now() + Duration::hours(WEBHOOK_SECRET_ROTATION_GRACE_HOURS))
+    .bind(Utc::now())
+    .bind(&endpoint.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    create_org_audit_log(
+        &mut *state.pool.acquire().await?,
+        &audit,
+        &org.id,
+        "webhook_secret_rotated",
+        "webhook_endpoint",
+        &endpoint.id,
+        diff(&before, &serde_json::json!(endpoint)),
+    )
+    .await?;
+
+    tracing::info!(
+        org_slug = %org_slug,
+        endpoint_id = %endpoint.id,
+        user_id = %auth_user.user.id,
+        "Rotated webhook signing secret"
+    );
+
+    Ok(Json(WebhookSecretResponse {
+        endpoint: endpoint.into(),
+        secret,
+    }))
+}
+
+/// GET /api/organizations/:org_slug/webhooks/:endpoint_id/deliveries
+/// Delivery log, newest first
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, endpoint_id)): Path<(String, String)>,
+    Query(query): Query<DeliveryLogQuery>,
+) -> Result<Json<DeliveryLogResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let condition =
+        "d.endpoint_id = ? AND (? IS NULL OR d.status = ?) AND (? IS NULL OR e.event_type = ?)";
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM webhook_deliveries d JOIN webhook_events e ON d.event_id = e.id WHERE {}",
+        condition
+    ))
+    .bind(&endpoint.id)
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(&query.event_type)
+    .bind(&query.event_type)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let deliveries = sqlx::query_as::<_, DeliveryLogEntry>(&format!(
+        "SELECT d.*, e.event_type FROM webhook_deliveries d
+         JOIN webhook_events e ON d.event_id = e.id
+         WHERE {}
+         ORDER BY d.created_at DESC LIMIT ? OFFSET ?",
+        condition
+    ))
+    .bind(&endpoint.id)
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(&query.event_type)
+    .bind(&query.event_type)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(DeliveryLogResponse { deliveries, total }))
+}
+
+/// GET /api/organizations/:org_slug/webhooks/:endpoint_id/deliveries/:delivery_id
+/// One delivery with the payload that was sent
+pub async fn get_webhook_delivery(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((org_slug, endpoint_id, delivery_id)): Path<(String, String, String)>,
+) -> Result<Json<DeliveryDetailResponse>> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+
+    let delivery = sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT * FROM webhook_deliveries WHERE id = ? AND endpoint_id = ?",
+    )
+    .bind(&delivery_id)
+    .bind(&endpoint.id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Delivery not found".to_string()))?;
+
+    let (event_type, payload): (String, String) =
+        sqlx::query_as("SELECT event_type, payload FROM webhook_events WHERE id = ?")
+            .bind(&delivery.event_id)
+            .fetch_one(&state.pool)
+            .await?;
+
+    Ok(Json(DeliveryDetailResponse {
+        delivery,
+        event_type,
+        payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+    }))
+}
+
+/// POST /api/organizations/:org_slug/webhooks/:endpoint_id/deliveries/:delivery_id/redeliver
+/// Send the same event again as a new delivery
+pub async fn redeliver_webhook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    audit: AuditContext,
+    Path((org_slug, endpoint_id, delivery_id)): Path<(String, String, String)>,
+) -> Result<(StatusCode, Json<WebhookDelivery>)> {
+    let org = find_organization(&state.pool, &auth_user, &org_slug).await?;
+    let endpoint = find_endpoint(&state.pool, &org.id, &endpoint_id).await?;
+
+    let event_id: String = sqlx::query_scalar(
+        "SELECT event_id FROM webhook_deliveries WHERE id = ? AND endpoint_id = ?",
+    )
+    .bind(&delivery_id)
+    .bind(&endpoint.id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Delivery not found".to_string()))?;
+
+    let mut conn = state.pool.acquire().await?;
+    let id = WebhookService::queue_delivery(&mut conn, &endpoint.id, &event_id).await?;
+    let delivery =
+        sqlx::query_as::<_, WebhookDelivery>("SELECT * FROM webhook_deliveries WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    create_org_audit_log(
+        &mut conn,
+        &audit,
+        &org.id,
+        "webhook_redelivered",
+        "webhook_endpoint",
+        &endpoint.id,
+        diff(
+            &serde_json::Value::Null,
+            &serde_json::json!({ "delivery_id": delivery.id, "event_id": event_id }),
+        ),
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(delivery)))
+}
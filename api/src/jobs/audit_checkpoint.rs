@@ -0,0 +1,70 @@
+use crate::auth::audit_chain::{AuditChain, AuditChainService};
+use crate::auth::jwt::JwtService;
+use crate::constants::AUDIT_CHECKPOINT_INTERVAL_MINUTES;
+use crate::db::models::{OrganizationAuditLog, PlatformAuditLog};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+pub struct AuditCheckpointJob {
+    pool: SqlitePool,
+    jwt_service: Arc<JwtService>,
+}
+
+impl AuditCheckpointJob {
+    pub fn new(pool: SqlitePool, jwt_service: Arc<JwtService>) -> Self {
+        Self { pool, jwt_service }
+    }
+
+    pub async fn start(self) {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            AUDIT_CHECKPOINT_INTERVAL_MINUTES as u64 * 60,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.seal_unsealed().await {
+                tracing::error!("Audit log sealing failed: {}", e);
+            }
+            if let Err(e) = self.create_checkpoints().await {
+                tracing::error!("Audit checkpoint job failed: {}", e);
+            }
+        }
+    }
+
+    /// Link entries written before the hash chain existed. New entries are
+    /// linked when written, so this finds nothing once the backlog is sealed.
+    async fn seal_unsealed(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for chain in AuditChainService::unsealed_chains(&self.pool).await? {
+            let sealed = match chain {
+                AuditChain::Platform => {
+                    AuditChainService::seal::<PlatformAuditLog>(&self.pool, &chain).await?
+                }
+                AuditChain::Organization(_) => {
+                    AuditChainService::seal::<OrganizationAuditLog>(&self.pool, &chain).await?
+                }
+            };
+            tracing::info!(
+                "Sealed {} audit log entries into chain {}",
+                sealed,
+                chain.name()
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn create_checkpoints(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for chain in AuditChainService::chains_needing_checkpoint(&self.pool).await? {
+            let checkpoint =
+                AuditChainService::checkpoint(&self.pool, &self.jwt_service, &chain).await?;
+            tracing::debug!(
+                "Checkpointed audit chain {} at seq {}",
+                chain.name(),
+                checkpoint.seq
+            );
+        }
+
+        Ok(())
+    }
+}
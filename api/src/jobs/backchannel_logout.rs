@@ -0,0 +1,112 @@
+use crate::constants::{BACKCHANNEL_LOGOUT_BACKOFF_SECONDS, BACKCHANNEL_LOGOUT_MAX_ATTEMPTS};
+use crate::db::models::BackchannelLogoutDelivery;
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+pub struct BackchannelLogoutJob {
+    pool: SqlitePool,
+    client: reqwest::Client,
+}
+
+impl BackchannelLogoutJob {
+    pub fn new(pool: SqlitePool) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self { pool, client }
+    }
+
+    pub async fn start(self) {
+        // Deliveries are time-sensitive, so poll frequently
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.deliver_pending().await {
+                tracing::error!("Back-channel logout job failed: {}", e);
+            }
+        }
+    }
+
+    async fn deliver_pending(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let deliveries = sqlx::query_as::<_, BackchannelLogoutDelivery>(
+            r#"
+            SELECT * FROM backchannel_logout_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= ?
+            ORDER BY next_attempt_at ASC
+            LIMIT 100
+            "#,
+        )
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await?;
+
+        for delivery in deliveries {
+            let result = self
+                .client
+                .post(&delivery.logout_uri)
+                .form(&[("logout_token", delivery.logout_token.as_str())])
+                .send()
+                .await;
+
+            let error = match result {
+                Ok(response) if response.status().is_success() => None,
+                Ok(response) => Some(format!("HTTP {}", response.status())),
+                Err(e) => Some(e.to_string()),
+            };
+
+            self.record_attempt(&delivery, error).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn record_attempt(
+        &self,
+        delivery: &BackchannelLogoutDelivery,
+        error: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let attempts = delivery.attempts + 1;
+
+        let Some(error) = error else {
+            sqlx::query(
+                "UPDATE backchannel_logout_deliveries SET status = 'delivered', attempts = ?, last_error = NULL WHERE id = ?",
+            )
+            .bind(attempts)
+            .bind(&delivery.id)
+            .execute(&self.pool)
+            .await?;
+            return Ok(());
+        };
+
+        // Exponential backoff: 30s, 60s, 120s, ...
+        let status = if attempts >= BACKCHANNEL_LOGOUT_MAX_ATTEMPTS {
+            tracing::warn!(
+                "Giving up on back-channel logout to {} after {} attempts: {}",
+                delivery.logout_uri,
+                attempts,
+                error
+            );
+            "failed"
+        } else {
+            "pending"
+        };
+        let next_attempt_at =
+            Utc::now() + Duration::seconds(BACKCHANNEL_LOGOUT_BACKOFF_SECONDS << (attempts - 1));
+
+        sqlx::query(
+            "UPDATE backchannel_logout_deliveries SET status = ?, attempts = ?, last_error = ?, next_attempt_at = ? WHERE id = ?",
+        )
+        .bind(status)
+        .bind(attempts)
+        .bind(&error)
+        .bind(next_attempt_at)
+        .bind(&delivery.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use crate::auth::email::EmailService;
+use crate::constants::{EMAIL_DELIVERY_BACKOFF_SECONDS, EMAIL_DELIVERY_MAX_ATTEMPTS};
+use crate::db::models::EmailOutboxMessage;
+use crate::email::{compose, DeliveryError, MailTransport};
+use chrono::{Duration, Utc};
+use lettre::message::Mailbox;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+pub struct EmailDeliveryJob {
+    pool: SqlitePool,
+    transport: Arc<dyn MailTransport>,
+    from: Mailbox,
+}
+
+impl EmailDeliveryJob {
+    pub fn new(pool: SqlitePool, transport: Arc<dyn MailTransport>, from: Mailbox) -> Self {
+        Self {
+            pool,
+            transport,
+            from,
+        }
+    }
+
+    pub async fn start(self) {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.deliver_pending().await {
+                tracing::error!("Email delivery job failed: {}", e);
+            }
+        }
+    }
+
+    async fn deliver_pending(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let messages = sqlx::query_as::<_, EmailOutboxMessage>(
+            r#"
+            SELECT * FROM email_outbox
+            WHERE status = 'pending' AND next_attempt_at <= ?
+            ORDER BY next_attempt_at ASC
+            LIMIT 50
+            "#,
+        )
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await?;
+
+        for message in messages {
+            if EmailService::is_suppressed(&self.pool, &message.to_address).await? {
+                self.finish(&message, "suppressed", message.attempts, None)
+                    .await?;
+                continue;
+            }
+
+            let result = match compose(
+                &self.from,
+                &message.to_address,
+                &message.subject,
+                &message.body_text,
+            ) {
+                Ok(email) => self.transport.send(&email).await,
+                Err(e) => Err(e),
+            };
+
+            self.record_attempt(&message, result.err()).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn record_attempt(
+        &self,
+        message: &EmailOutboxMessage,
+        error: Option<DeliveryError>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let attempts = message.attempts + 1;
+
+        let Some(error) = error else {
+            return self.finish(message, "sent", attempts, None).await;
+        };
+
+        // A hard bounce means the address is dead: stop sending to it
+        if error.permanent {
+            tracing::warn!("Email to {} bounced: {}", message.to_address, error.message);
+            EmailService::suppress(&self.pool, &message.to_address, &error.message).await?;
+            return self
+                .finish(message, "bounced", attempts, Some(&error.message))
+                .await;
+        }
+
+        if attempts >= EMAIL_DELIVERY_MAX_ATTEMPTS {
+            tracing::warn!(
+                "Giving up on email to {} after {} attempts: {}",
+                message.to_address,
+                attempts,
+                error.message
+            );
+            return self
+                .finish(message, "failed", attempts, Some(&error.message))
+                .await;
+        }
+
+        // Exponential backoff: 1m, 2m, 4m, ...
+        let next_attempt_at =
+            Utc::now() + Duration::seconds(EMAIL_DELIVERY_BACKOFF_SECONDS << (attempts - 1));
+
+        sqlx::query(
+            "UPDATE email_outbox SET attempts = ?, last_error = ?, next_attempt_at = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(attempts)
+        .bind(&error.message)
+        .bind(next_attempt_at)
+        .bind(Utc::now())
+        .bind(&message.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        message: &EmailOutboxMessage,
+        status: &str,
+        attempts: i64,
+        error: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sent_at = (status == "sent").then(Utc::now);
+
+        sqlx::query(
+            "UPDATE email_outbox SET status = ?, attempts = ?, last_error = ?, sent_at = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(status)
+        .bind(attempts)
+        .bind(error)
+        .bind(sent_at)
+        .bind(Utc::now())
+        .bind(&message.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::auth::invitations::InvitationService;
+use crate::db::models::OrganizationInvitation;
+use crate::encryption::EncryptionService;
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+pub struct InvitationLifecycleJob {
+    pool: SqlitePool,
+    base_url: String,
+    reminder_hours: Option<i64>,
+    encryption: Option<Arc<EncryptionService>>,
+}
+
+impl InvitationLifecycleJob {
+    pub fn new(
+        pool: SqlitePool,
+        base_url: String,
+        reminder_hours: Option<i64>,
+        encryption: Option<Arc<EncryptionService>>,
+    ) -> Self {
+        Self {
+            pool,
+            base_url,
+            reminder_hours,
+            encryption,
+        }
+    }
+
+    pub async fn start(self) {
+        // Run every 5 minutes
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.expire_invitations().await {
+                tracing::error!("Invitation expiry job failed: {}", e);
+            }
+            if let (Some(hours), Some(encryption)) = (self.reminder_hours, &self.encryption) {
+                if let Err(e) = self.send_reminders(hours, encryption).await {
+                    tracing::error!("Invitation reminder job failed: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn expire_invitations(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let result = sqlx::query(
+            "UPDATE organization_invitations SET status = 'expired'
+             WHERE status = 'pending' AND expires_at <= ?",
+        )
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        let expired_count = result.rows_affected();
+
+        if expired_count > 0 {
+            tracing::info!("Marked {} invitations as expired", expired_count);
+        }
+
+        Ok(())
+    }
+
+    /// Remind invitees whose invitation expires within `hours`. The reminder
+    /// carries the link the invitee already has; invitations whose token was
+    /// not kept are skipped. One failing invitation does not hold up the rest.
+    async fn send_reminders(
+        &self,
+        hours: i64,
+        encryption: &EncryptionService,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Utc::now();
+        let invitations = sqlx::query_as::<_, OrganizationInvitation>(
+            r#"
+            SELECT * FROM organization_invitations
+            WHERE status = 'pending' AND reminder_sent_at IS NULL
+              AND token_encrypted IS NOT NULL
+              AND expires_at > ? AND expires_at <= ?
+            LIMIT 100
+            "#,
+        )
+        .bind(now)
+        .bind(now + Duration::hours(hours))
+        .fetch_all(&self.pool)
+        .await?;
+
+        for invitation in invitations {
+            if let Err(e) = self.remind(encryption, &invitation).await {
+                tracing::error!("Reminder for invitation {} failed: {}", invitation.id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remind(
+        &self,
+        encryption: &EncryptionService,
+        invitation: &OrganizationInvitation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+        InvitationService::remind(&mut tx, &self.base_url, encryption, invitation).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -1,2 +1,8 @@
+pub mod audit_checkpoint;
+pub mod backchannel_logout;
+pub mod email_delivery;
+pub mod invitation_lifecycle;
 pub mod oauth_state_cleanup;
+pub mod siem_export;
 pub mod token_refresh;
+pub mod webhook_delivery;
@@ -0,0 +1,72 @@
+use crate::constants::SIEM_EXPORT_INTERVAL_SECONDS;
+use crate::db::models::SiemSink;
+use crate::siem;
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+pub struct SiemExportJob {
+    pool: SqlitePool,
+    file_dir: String,
+}
+
+impl SiemExportJob {
+    pub fn new(pool: SqlitePool, file_dir: String) -> Self {
+        Self { pool, file_dir }
+    }
+
+    pub async fn start(self) {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            SIEM_EXPORT_INTERVAL_SECONDS,
+        ));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.export_all().await {
+                tracing::error!("SIEM export job failed: {}", e);
+            }
+        }
+    }
+
+    async fn export_all(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let sinks = sqlx::query_as::<_, SiemSink>("SELECT * FROM siem_sinks WHERE enabled = 1")
+            .fetch_all(&self.pool)
+            .await?;
+
+        for sink in sinks {
+            let result = match serde_json::from_str(&sink.config)
+                .map_err(|e| e.to_string())
+                .and_then(|config| {
+                    siem::build_sink(&sink.kind, &config, &self.file_dir).map_err(|e| e.to_string())
+                }) {
+                Ok(target) => siem::export_sink(&self.pool, &sink, target.as_ref()).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(0) => {}
+                Ok(exported) => {
+                    tracing::debug!("Exported {} events to SIEM sink {}", exported, sink.name);
+                    sqlx::query(
+                        "UPDATE siem_sinks SET last_error = NULL, last_exported_at = ? WHERE id = ?",
+                    )
+                    .bind(Utc::now())
+                    .bind(&sink.id)
+                    .execute(&self.pool)
+                    .await?;
+                }
+                Err(error) => {
+                    // The cursor did not move; the same events are sent on the next run
+                    tracing::warn!("SIEM export to sink {} failed: {}", sink.name, error);
+                    sqlx::query("UPDATE siem_sinks SET last_error = ? WHERE id = ?")
+                        .bind(&error)
+                        .bind(&sink.id)
+                        .execute(&self.pool)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::auth::webhooks::{WebhookService, SIGNATURE_HEADER};
+use crate::constants::{WEBHOOK_DELIVERY_BACKOFF_SECONDS, WEBHOOK_DELIVERY_MAX_ATTEMPTS};
+use crate::db::models::{WebhookDelivery, WebhookEndpoint, WebhookEvent};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+pub struct WebhookDeliveryJob {
+    pool: SqlitePool,
+}
+
+impl WebhookDeliveryJob {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn start(self) {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.deliver_pending().await {
+                tracing::error!("Webhook delivery job failed: {}", e);
+            }
+        }
+    }
+
+    async fn deliver_pending(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= ?
+            ORDER BY next_attempt_at ASC
+            LIMIT 100
+            "#,
+        )
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await?;
+
+        for delivery in deliveries {
+            let endpoint = sqlx::query_as::<_, WebhookEndpoint>(
+                "SELECT * FROM webhook_endpoints WHERE id = ?",
+            )
+            .bind(&delivery.endpoint_id)
+            .fetch_one(&self.pool)
+            .await?;
+            let event =
+                sqlx::query_as::<_, WebhookEvent>("SELECT * FROM webhook_events WHERE id = ?")
+                    .bind(&delivery.event_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+            // Deliveries queued before the endpoint was disabled wait for it
+            if !endpoint.enabled {
+                continue;
+            }
+
+            let client = match WebhookService::pinned_client(&endpoint.url).await {
+                Ok(client) => client,
+                Err(e) => {
+                    self.record_attempt(&delivery, &endpoint, None, Some(e.to_string()))
+                        .await?;
+                    continue;
+                }
+            };
+
+            let timestamp = Utc::now().timestamp();
+            let result = client
+                .post(&endpoint.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-SSO-Event", &event.event_type)
+                .header("X-SSO-Delivery", &delivery.id)
+                .header(
+                    SIGNATURE_HEADER,
+                    WebhookService::signature_header(&endpoint, timestamp, &event.payload),
+                )
+                .body(event.payload.clone())
+                .send()
+                .await;
+
+            let (response_status, error) = match result {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16() as i64), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16() as i64),
+                    Some(format!("HTTP {}", response.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+
+            self.record_attempt(&delivery, &endpoint, response_status, error)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn record_attempt(
+        &self,
+        delivery: &WebhookDelivery,
+        endpoint: &WebhookEndpoint,
+        response_status: Option<i64>,
+        error: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let attempts = delivery.attempts + 1;
+
+        let Some(error) = error else {
+            sqlx::query(
+                "UPDATE webhook_deliveries
+                 SET status = 'delivered', attempts = ?, response_status = ?, last_error = NULL,
+                     delivered_at = ?, updated_at = ?
+                 WHERE id = ?",
+            )
+            .bind(attempts)
+            .bind(response_status)
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .bind(&delivery.id)
+            .execute(&self.pool)
+            .await?;
+            return Ok(());
+        };
+
+        // Exponential backoff: 30s, 60s, 120s, ...
+        let status = if attempts >= WEBHOOK_DELIVERY_MAX_ATTEMPTS {
+            tracing::warn!(
+                "Giving up on webhook delivery {} to {} after {} attempts: {}",
+                delivery.id,
+                endpoint.url,
+                attempts,
+                error
+            );
+            "failed"
+        } else {
+            "pending"
+        };
+        let next_attempt_at =
+            Utc::now() + Duration::seconds(WEBHOOK_DELIVERY_BACKOFF_SECONDS << (attempts - 1));
+
+        sqlx::query(
+            "UPDATE webhook_deliveries
+             SET status = ?, attempts = ?, response_status = ?, last_error = ?, next_attempt_at = ?,
+                 updated_at = ?
+             WHERE id = ?",
+        )
+        .bind(status)
+        .bind(attempts)
+        .bind(response_status)
+        .bind(&error)
+        .bind(next_attempt_at)
+        .bind(Utc::now())
+        .bind(&delivery.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -3,63 +3,138 @@ mod billing;
 mod config;
 mod constants;
 mod db;
+mod dns;
+mod email;
 mod encryption;
 mod error;
+mod geoip;
 mod handlers;
 mod jobs;
 mod middleware;
+mod siem;
 
+use crate::auth::invitations::INVITATION_ACCEPT_PATH;
 use crate::auth::jwt::JwtService;
+use crate::auth::login_failures::LockoutPolicy;
 use crate::auth::sso::OAuthClient;
 use crate::billing::stripe::StripeService;
 use crate::config::Config;
 use crate::constants::DEVICE_CODE_EXPIRE_MINUTES;
 use crate::db::models::DeviceCode;
+use crate::dns::DnsResolver;
 use crate::encryption::EncryptionService;
+use crate::geoip::{GeoIpLookup, MaxMindGeoIp, NoGeoIp};
 use crate::handlers::analytics::{
-    get_login_trends, get_logins_by_provider, get_logins_by_service, get_recent_logins,
-    AnalyticsState,
+    get_failed_logins, get_login_trends, get_logins_by_provider, get_logins_by_service,
+    get_recent_logins, get_risky_logins, AnalyticsState,
 };
 use crate::handlers::auth::{
     auth_admin_callback, auth_admin_provider, auth_callback, auth_provider, device_code,
-    device_verify, logout, refresh_token, token_exchange, AppState, DbRequest,
+    device_verify, end_session, logout, refresh_token, token_exchange, verify_login_challenge,
+    AppState, DbRequest,
+};
+use crate::handlers::api_tokens::{
+    create_org_api_key, create_personal_token, list_org_api_keys, list_personal_tokens,
+    revoke_org_api_key, revoke_personal_token,
+};
+use crate::handlers::ciba::{
+    approve_backchannel_request, backchannel_authorize, deny_backchannel_request,
+    list_backchannel_requests,
+};
+use crate::handlers::domains::{
+    add_domain, delete_domain, list_domains, update_domain, verify_domain,
+};
+use crate::handlers::email::{
+    create_suppression, delete_suppression, get_email_template, list_email_templates,
+    list_outbox, list_suppressions, reset_email_template, retry_outbox_message,
+    update_email_template,
+};
+use crate::handlers::groups::{
+    add_group_member, create_group, delete_group, list_group_members, list_groups,
+    remove_group_member,
 };
 use crate::handlers::identities::{list_identities, start_link, unlink_identity};
 use crate::handlers::invitations::{
-    accept_invitation, accept_invitation_redirect, cancel_invitation, create_invitation,
-    decline_invitation, list_invitations, list_user_invitations,
+    accept_invitation, accept_invitation_redirect, bulk_create_invitations, cancel_invitation,
+    create_invitation, decline_invitation, extend_invitation, list_invitations,
+    list_user_invitations, resend_invitation,
+};
+use crate::handlers::invite_links::{
+    create_invite_link, list_invite_link_redemptions, list_invite_links, redeem_invite_link,
+    revoke_invite_link,
 };
+use crate::handlers::login_lockouts::{
+    clear_login_lockout, list_end_user_lockouts, list_login_lockouts, unlock_end_user,
+};
+use crate::handlers::org_audit::{list_org_audit_log, verify_org_audit_log};
+use crate::handlers::org_roles::{create_org_role, delete_org_role, list_org_roles, update_org_role};
 use crate::handlers::organizations::{
     create_organization_public, get_end_user, get_org_oauth_credentials, get_organization,
     list_end_users, list_members, list_user_organizations, remove_member, revoke_end_user_sessions,
     set_org_oauth_credentials, transfer_ownership, update_member_role, update_organization,
 };
 use crate::handlers::platform::{
-    activate_organization, approve_organization, demote_platform_owner, get_audit_log,
+    activate_organization, approve_organization, demote_platform_owner, get_audit_log, verify_audit_log,
     get_growth_trends, get_login_activity, get_organization_status_breakdown,
     get_platform_overview, get_recent_organizations, get_top_organizations, list_organizations,
     list_tiers, promote_platform_owner, reject_organization, suspend_organization,
     update_organization_tier,
 };
 use crate::handlers::provider_token::get_provider_token;
+use crate::handlers::scim::{
+    create_scim_group, create_scim_user, delete_scim_group, delete_scim_user,
+    get_scim_group, get_scim_user, get_service_provider_config, list_scim_groups,
+    list_scim_users, patch_scim_group, patch_scim_user, replace_scim_user,
+};
+use crate::handlers::service_roles::{
+    assign_service_role, create_service_role, delete_service_role, list_role_assignments,
+    list_service_roles, unassign_service_role, update_service_role,
+};
 use crate::handlers::services::{
     create_plan, create_service, delete_service, get_service, list_organization_services,
     list_service_plans, update_service,
 };
+use crate::handlers::account_merge::{create_merge_token, merge_account};
+use crate::handlers::impersonation::{
+    start_org_impersonation, start_platform_impersonation, stop_impersonation,
+};
+use crate::handlers::privacy::{
+    delete_user_account, erase_end_user, export_end_user_data, export_user_data,
+};
+use crate::handlers::sessions::{
+    list_user_impersonations, list_user_sessions, revoke_other_user_sessions, revoke_user_session,
+};
+use crate::handlers::siem::{
+    create_siem_sink, delete_siem_sink, get_siem_sink, list_security_events, list_siem_sinks,
+    reset_siem_sink_cursors, test_siem_sink, update_siem_sink,
+};
 use crate::handlers::subscription::{get_subscription, get_user, update_user};
 use crate::handlers::webhook::{stripe_webhook, WebhookState};
+use crate::handlers::webhook_endpoints::{
+    create_webhook_endpoint, delete_webhook_endpoint, get_webhook_delivery, get_webhook_endpoint,
+    list_webhook_deliveries, list_webhook_endpoints, redeliver_webhook, rotate_webhook_secret,
+    update_webhook_endpoint,
+};
+use crate::jobs::audit_checkpoint::AuditCheckpointJob;
+use crate::jobs::backchannel_logout::BackchannelLogoutJob;
+use crate::jobs::email_delivery::EmailDeliveryJob;
+use crate::jobs::invitation_lifecycle::InvitationLifecycleJob;
 use crate::jobs::oauth_state_cleanup::OAuthStateCleanupJob;
+use crate::jobs::siem_export::SiemExportJob;
 use crate::jobs::token_refresh::TokenRefreshJob;
+use crate::jobs::webhook_delivery::WebhookDeliveryJob;
+use crate::middleware::ClientIpKeyExtractor;
 use axum::{
+    http::{HeaderValue, Method},
     middleware as axum_middleware,
     routing::{delete, get, patch, post},
-    Router,
+    Extension, Router,
 };
 use sqlx::{Sqlite, SqlitePool, Transaction};
 use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use axum::Json;
 use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
@@ -68,7 +143,6 @@ use rsa::traits::PublicKeyParts;
 use serde::Serialize;
 use tower_governor::{
     governor::GovernorConfigBuilder,
-    key_extractor::SmartIpKeyExtractor,
     GovernorLayer,
 };
 
@@ -84,15 +158,16 @@ async fn handle_create_device_code_batch(
         return;
     }
 
-    let values_placeholder = "(?, ?, ?, ?, ?, ?, ?, 'pending')";
+    let values_placeholder = "(?, ?, ?, ?, ?, ?, ?, 'pending', ?)";
     let placeholders: Vec<&str> = (0..batch.len()).map(|_| values_placeholder).collect();
     let sql = format!(
-        "INSERT INTO device_codes (id, device_code, user_code, client_id, org_slug, service_slug, expires_at, status) VALUES {}",
+        "INSERT INTO device_codes (id, device_code, user_code, client_id, org_slug, service_slug, expires_at, status, created_at) VALUES {}",
         placeholders.join(", ")
     );
 
     let mut query_builder = sqlx::query(&sql);
-    let expires_at = chrono::Utc::now() + chrono::Duration::minutes(DEVICE_CODE_EXPIRE_MINUTES);
+    let created_at = chrono::Utc::now();
+    let expires_at = created_at + chrono::Duration::minutes(DEVICE_CODE_EXPIRE_MINUTES);
 
     // Bind all pre-generated values from the batch
     for req in &batch {
@@ -112,7 +187,8 @@ async fn handle_create_device_code_batch(
             .bind(client_id)
             .bind(org_slug)
             .bind(service_slug)
-            .bind(expires_at);
+            .bind(expires_at)
+            .bind(created_at);
     }
 
     // Execute the single, large query. We don't need RETURNING anymore.
@@ -138,6 +214,13 @@ async fn handle_create_device_code_batch(
                     expires_at,
                     user_id: None,
                     status: "pending".to_string(),
+                    flow_type: "device".to_string(),
+                    login_hint_user_id: None,
+                    binding_message: None,
+                    client_notification_token: None,
+                    created_at: Some(created_at),
+                    provider: None,
+                    authenticated_at: None,
                 };
                 let _ = responder.send(Ok(response_code));
             }
@@ -278,6 +361,43 @@ async fn jwks_handler() -> Result<Json<JwksResponse>, axum::http::StatusCode> {
     Ok(Json(JwksResponse { keys: vec![jwk] }))
 }
 
+/// Credentialed CORS for the configured app origins, so cross-origin calls such as
+/// starting an identity link can set the browser-binding cookie. Without any
+/// configured origins every origin is allowed, but without credentials.
+fn cors_layer(allowed_origins: &[String]) -> anyhow::Result<CorsLayer> {
+    if allowed_origins.is_empty() {
+        tracing::warn!(
+            "CORS_ALLOWED_ORIGINS is not set; identity linking only works from the API's own origin"
+        );
+        return Ok(CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any));
+    }
+
+    let origins = allowed_origins
+        .iter()
+        .map(|origin| {
+            origin
+                .parse::<HeaderValue>()
+                .map_err(|_| anyhow::anyhow!("Invalid CORS origin: {}", origin))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true))
+}
+
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing
@@ -341,6 +461,71 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("OAuth state cleanup job started");
     }
 
+    // Start background back-channel logout delivery job
+    {
+        let logout_pool = pool.clone();
+        tokio::spawn(async move {
+            let job = BackchannelLogoutJob::new(logout_pool);
+            job.start().await;
+        });
+        tracing::info!("Back-channel logout job started");
+    }
+
+    // Start background email delivery job
+    {
+        let transport =
+            email::transport_from_config(&config).expect("Failed to initialize email transport");
+        let from = config.email_from.parse().expect("EMAIL_FROM must be a valid mailbox");
+        let email_pool = pool.clone();
+        tokio::spawn(async move {
+            let job = EmailDeliveryJob::new(email_pool, transport, from);
+            job.start().await;
+        });
+        tracing::info!("Email delivery job started ({} transport)", config.email_transport);
+    }
+
+    // Start background webhook delivery job
+    {
+        let webhook_pool = pool.clone();
+        tokio::spawn(async move {
+            let job = WebhookDeliveryJob::new(webhook_pool);
+            job.start().await;
+        });
+        tracing::info!("Webhook delivery job started");
+    }
+
+    // Start background invitation expiry and reminder job
+    {
+        let invitation_pool = pool.clone();
+        let base_url = config.base_url.clone();
+        let reminder_hours = config.invitation_reminder_hours;
+        let invitation_encryption = encryption.clone().map(Arc::new);
+        if reminder_hours.is_some() && invitation_encryption.is_none() {
+            tracing::warn!("ENCRYPTION_KEY not set, invitation reminders are disabled");
+        }
+        tokio::spawn(async move {
+            let job = InvitationLifecycleJob::new(
+                invitation_pool,
+                base_url,
+                reminder_hours,
+                invitation_encryption,
+            );
+            job.start().await;
+        });
+        tracing::info!("Invitation lifecycle job started");
+    }
+
+    // Start background SIEM export job
+    {
+        let siem_pool = pool.clone();
+        let file_dir = config.siem_file_dir.clone();
+        tokio::spawn(async move {
+            let job = SiemExportJob::new(siem_pool, file_dir);
+            job.start().await;
+        });
+        tracing::info!("SIEM export job started");
+    }
+
     // Initialize services
     let oauth_client =
         Arc::new(OAuthClient::new(&config).expect("Failed to initialize OAuth client"));
@@ -353,10 +538,37 @@ async fn main() -> anyhow::Result<()> {
         JwtService::new(&private_key, &public_key, config.jwt_expiration_hours, &key_id)
             .expect("Failed to initialize JWT service")
     );
+
+    // Start background audit log sealing and checkpoint job
+    {
+        let audit_pool = pool.clone();
+        let audit_jwt_service = jwt_service.clone();
+        tokio::spawn(async move {
+            let job = AuditCheckpointJob::new(audit_pool, audit_jwt_service);
+            job.start().await;
+        });
+        tracing::info!("Audit checkpoint job started");
+    }
     let stripe_service = Arc::new(StripeService::new(
         config.stripe_secret_key.clone(),
         config.stripe_webhook_secret.clone(),
     ));
+    let dns_resolver = Arc::new(
+        DnsResolver::new(config.dns_resolver.as_deref()).expect("Failed to initialize DNS resolver"),
+    );
+    let geoip: Arc<dyn GeoIpLookup> = match config.geoip_database_path.as_deref() {
+        Some(path) => {
+            Arc::new(MaxMindGeoIp::open(path).expect("Failed to open GeoIP database"))
+        }
+        None => {
+            tracing::warn!("GEOIP_DATABASE_PATH not set, login location checks are disabled");
+            Arc::new(NoGeoIp)
+        }
+    };
+
+    if config.invitation_accept_uri.is_none() {
+        tracing::warn!("INVITATION_ACCEPT_URI not set, invitation email links cannot be opened");
+    }
 
     // Create application state
     let app_state = AppState {
@@ -366,6 +578,11 @@ async fn main() -> anyhow::Result<()> {
         base_url: config.base_url.clone(),
         db_tx: tx, // Add the channel sender to the state
         encryption: encryption.clone().map(Arc::new),
+        stripe_service: stripe_service.clone(),
+        dns_resolver,
+        geoip,
+        lockout_policy: LockoutPolicy::from_config(&config),
+        invitation_accept_uri: config.invitation_accept_uri.clone(),
     };
 
     let webhook_state = WebhookState {
@@ -389,16 +606,79 @@ async fn main() -> anyhow::Result<()> {
             "/api/organizations/:org_slug/oauth-credentials/:provider",
             get(get_org_oauth_credentials),
         )
+        // Domain verification, auto-join and enforced sign-in
+        .route(
+            "/api/organizations/:org_slug/domains",
+            get(list_domains).post(add_domain),
+        )
+        .route(
+            "/api/organizations/:org_slug/domains/:domain",
+            patch(update_domain).delete(delete_domain),
+        )
+        .route(
+            "/api/organizations/:org_slug/domains/:domain/verify",
+            post(verify_domain),
+        )
+        // Organization overrides of transactional email templates
+        .route(
+            "/api/organizations/:org_slug/email-templates",
+            get(list_email_templates),
+        )
+        .route(
+            "/api/organizations/:org_slug/email-templates/:template",
+            get(get_email_template)
+                .put(update_email_template)
+                .delete(reset_email_template),
+        )
+        // Outbound event webhooks
+        .route(
+            "/api/organizations/:org_slug/webhooks",
+            get(list_webhook_endpoints).post(create_webhook_endpoint),
+        )
+        .route(
+            "/api/organizations/:org_slug/webhooks/:endpoint_id",
+            get(get_webhook_endpoint)
+                .patch(update_webhook_endpoint)
+                .delete(delete_webhook_endpoint),
+        )
+        .route(
+            "/api/organizations/:org_slug/webhooks/:endpoint_id/rotate-secret",
+            post(rotate_webhook_secret),
+        )
+        .route(
+            "/api/organizations/:org_slug/webhooks/:endpoint_id/deliveries",
+            get(list_webhook_deliveries),
+        )
+        .route(
+            "/api/organizations/:org_slug/webhooks/:endpoint_id/deliveries/:delivery_id",
+            get(get_webhook_delivery),
+        )
+        .route(
+            "/api/organizations/:org_slug/webhooks/:endpoint_id/deliveries/:delivery_id/redeliver",
+            post(redeliver_webhook),
+        )
         // End-user management routes
         .route("/api/organizations/:org_slug/users", get(list_end_users))
         .route(
             "/api/organizations/:org_slug/users/:user_id",
-            get(get_end_user),
+            get(get_end_user).delete(erase_end_user),
+        )
+        .route(
+            "/api/organizations/:org_slug/users/:user_id/export",
+            get(export_end_user_data),
         )
         .route(
             "/api/organizations/:org_slug/users/:user_id/sessions",
             delete(revoke_end_user_sessions),
         )
+        .route(
+            "/api/organizations/:org_slug/users/:user_id/lockouts",
+            get(list_end_user_lockouts).delete(unlock_end_user),
+        )
+        .route(
+            "/api/organizations/:org_slug/users/:user_id/impersonate",
+            post(start_org_impersonation),
+        )
         // Service management routes - combine methods for the same path
         .route("/api/organizations/:org_slug/services/:service_slug/plans",
                 get(list_service_plans).post(create_plan))
@@ -406,6 +686,37 @@ async fn main() -> anyhow::Result<()> {
                 get(get_service).patch(update_service).delete(delete_service))
         .route("/api/organizations/:org_slug/services",
                 get(list_organization_services).post(create_service))
+        // End-user roles per service
+        .route(
+            "/api/organizations/:org_slug/services/:service_slug/roles",
+            get(list_service_roles).post(create_service_role),
+        )
+        .route(
+            "/api/organizations/:org_slug/services/:service_slug/roles/:role_id",
+            patch(update_service_role).delete(delete_service_role),
+        )
+        .route(
+            "/api/organizations/:org_slug/services/:service_slug/roles/:role_id/assignments",
+            get(list_role_assignments).post(assign_service_role),
+        )
+        .route(
+            "/api/organizations/:org_slug/services/:service_slug/roles/:role_id/assignments/:assignment_id",
+            delete(unassign_service_role),
+        )
+        // End-user groups
+        .route(
+            "/api/organizations/:org_slug/groups",
+            get(list_groups).post(create_group),
+        )
+        .route("/api/organizations/:org_slug/groups/:group_id", delete(delete_group))
+        .route(
+            "/api/organizations/:org_slug/groups/:group_id/members",
+            get(list_group_members).post(add_group_member),
+        )
+        .route(
+            "/api/organizations/:org_slug/groups/:group_id/members/:user_id",
+            delete(remove_group_member),
+        )
         // Apply active organization check middleware
         .route_layer(axum_middleware::from_fn_with_state(
             app_state.clone(),
@@ -430,6 +741,14 @@ async fn main() -> anyhow::Result<()> {
             "/api/organizations/:org_slug/analytics/recent-logins",
             get(get_recent_logins),
         )
+        .route(
+            "/api/organizations/:org_slug/analytics/risky-logins",
+            get(get_risky_logins),
+        )
+        .route(
+            "/api/organizations/:org_slug/analytics/failed-logins",
+            get(get_failed_logins),
+        )
         .with_state(analytics_state)
         .route_layer(axum_middleware::from_fn_with_state(
             (app_state.pool.clone(), app_state.jwt_service.clone()),
@@ -438,14 +757,63 @@ async fn main() -> anyhow::Result<()> {
 
     // Build protected routes (require JWT)
     let protected_routes = Router::new()
-        .route("/api/user", get(get_user))
-        .route("/api/user", patch(update_user))
-        .route("/api/subscription", get(get_subscription))
         .route("/api/provider-token/:provider", get(get_provider_token))
         // Identity linking routes
         .route("/api/user/identities", get(list_identities))
         .route("/api/user/identities/:provider/link", post(start_link))
         .route("/api/user/identities/:provider", delete(unlink_identity))
+        // Self-service session management
+        .route(
+            "/api/user/sessions",
+            get(list_user_sessions).delete(revoke_other_user_sessions),
+        )
+        .route("/api/user/sessions/:session_id", delete(revoke_user_session))
+        .route("/api/user/impersonations", get(list_user_impersonations))
+        .route("/api/user/merge", post(merge_account))
+        .route("/api/user/merge/token", post(create_merge_token))
+        .route("/api/user/export", get(export_user_data))
+        // Backchannel (CIBA) approval routes
+        .route("/api/user/backchannel-requests", get(list_backchannel_requests))
+        .route(
+            "/api/user/backchannel-requests/:id/approve",
+            post(approve_backchannel_request),
+        )
+        .route(
+            "/api/user/backchannel-requests/:id/deny",
+            post(deny_backchannel_request),
+        )
+        // Personal access tokens and organization API keys
+        .route(
+            "/api/user/tokens",
+            get(list_personal_tokens).post(create_personal_token),
+        )
+        .route("/api/user/tokens/:token_id", delete(revoke_personal_token))
+        .route(
+            "/api/organizations/:org_slug/api-keys",
+            get(list_org_api_keys).post(create_org_api_key),
+        )
+        .route(
+            "/api/organizations/:org_slug/api-keys/:key_id",
+            delete(revoke_org_api_key),
+        )
+        // Profile changes (including email), role changes and ownership transfer
+        .route("/api/user", patch(update_user))
+        .route(
+            "/api/organizations/:org_slug/members/:user_id",
+            patch(update_member_role),
+        )
+        .route(
+            "/api/organizations/:org_slug/transfer-ownership",
+            post(transfer_ownership),
+        )
+        // Routes above need an interactive login; API tokens are rejected
+        .route_layer(axum_middleware::from_fn(
+            crate::middleware::require_interactive_session,
+        ))
+        .route("/api/impersonation/stop", post(stop_impersonation))
+        .route("/api/user", get(get_user))
+        .route("/api/user", delete(delete_user_account))
+        .route("/api/subscription", get(get_subscription))
         // Organization routes (not restricted by org status)
         .route("/api/organizations", get(list_user_organizations))
         .route("/api/organizations/:org_slug", get(get_organization))
@@ -453,15 +821,24 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/organizations/:org_slug/members", get(list_members))
         .route(
             "/api/organizations/:org_slug/members/:user_id",
-            patch(update_member_role),
+            post(remove_member),
         )
         .route(
-            "/api/organizations/:org_slug/members/:user_id",
-            post(remove_member),
+            "/api/organizations/:org_slug/audit-log",
+            get(list_org_audit_log),
         )
         .route(
-            "/api/organizations/:org_slug/transfer-ownership",
-            post(transfer_ownership),
+            "/api/organizations/:org_slug/audit-log/verify",
+            get(verify_org_audit_log),
+        )
+        // Custom organization roles
+        .route(
+            "/api/organizations/:org_slug/roles",
+            get(list_org_roles).post(create_org_role),
+        )
+        .route(
+            "/api/organizations/:org_slug/roles/:role_id",
+            patch(update_org_role).delete(delete_org_role),
         )
         // Invitation routes (not restricted by org status)
         .route(
@@ -472,14 +849,38 @@ async fn main() -> anyhow::Result<()> {
             "/api/organizations/:org_slug/invitations",
             get(list_invitations),
         )
+        .route(
+            "/api/organizations/:org_slug/invitations/bulk",
+            post(bulk_create_invitations),
+        )
         .route(
             "/api/organizations/:org_slug/invitations/:invitation_id",
             post(cancel_invitation),
         )
+        .route(
+            "/api/organizations/:org_slug/invitations/:invitation_id/resend",
+            post(resend_invitation),
+        )
+        .route(
+            "/api/organizations/:org_slug/invitations/:invitation_id/extend",
+            post(extend_invitation),
+        )
+        .route(
+            "/api/organizations/:org_slug/invite-links",
+            get(list_invite_links).post(create_invite_link),
+        )
+        .route(
+            "/api/organizations/:org_slug/invite-links/:link_id",
+            delete(revoke_invite_link),
+        )
+        .route(
+            "/api/organizations/:org_slug/invite-links/:link_id/redemptions",
+            get(list_invite_link_redemptions),
+        )
+        .route("/api/invite-links/redeem", post(redeem_invite_link))
         .route("/api/invitations", get(list_user_invitations))
         .route("/api/invitations/accept", post(accept_invitation))
         .route("/api/invitations/decline", post(decline_invitation))
-        .route("/invitations/accept", get(accept_invitation_redirect)) // For email links
         // Merge active org routes
         .merge(active_org_routes)
         .route_layer(axum_middleware::from_fn_with_state(
@@ -517,6 +918,45 @@ async fn main() -> anyhow::Result<()> {
             delete(demote_platform_owner),
         )
         .route("/api/platform/audit-log", get(get_audit_log))
+        .route("/api/platform/audit-log/verify", get(verify_audit_log))
+        .route(
+            "/api/platform/siem-sinks",
+            get(list_siem_sinks).post(create_siem_sink),
+        )
+        .route(
+            "/api/platform/siem-sinks/:sink_id",
+            get(get_siem_sink)
+                .patch(update_siem_sink)
+                .delete(delete_siem_sink),
+        )
+        .route("/api/platform/siem-sinks/:sink_id/test", post(test_siem_sink))
+        .route(
+            "/api/platform/siem-sinks/:sink_id/cursors/reset",
+            post(reset_siem_sink_cursors),
+        )
+        .route("/api/platform/security-events", get(list_security_events))
+        .route("/api/platform/login-lockouts", get(list_login_lockouts))
+        .route(
+            "/api/platform/login-lockouts/:scope/:subject",
+            delete(clear_login_lockout),
+        )
+        .route("/api/platform/email/outbox", get(list_outbox))
+        .route(
+            "/api/platform/email/outbox/:id/retry",
+            post(retry_outbox_message),
+        )
+        .route(
+            "/api/platform/email/suppressions",
+            get(list_suppressions).post(create_suppression),
+        )
+        .route(
+            "/api/platform/email/suppressions/:email",
+            delete(delete_suppression),
+        )
+        .route(
+            "/api/platform/users/:user_id/impersonate",
+            post(start_platform_impersonation),
+        )
         // Platform analytics routes
         .route(
             "/api/platform/analytics/overview",
@@ -555,7 +995,7 @@ async fn main() -> anyhow::Result<()> {
         GovernorConfigBuilder::default()
             .per_second(60)
             .burst_size(20)
-            .key_extractor(SmartIpKeyExtractor)
+            .key_extractor(ClientIpKeyExtractor)
             .finish()
             .expect("Failed to build auth rate limiter"),
     ));
@@ -564,7 +1004,7 @@ async fn main() -> anyhow::Result<()> {
         GovernorConfigBuilder::default()
             .per_second(60)
             .burst_size(10)
-            .key_extractor(SmartIpKeyExtractor)
+            .key_extractor(ClientIpKeyExtractor)
             .finish()
             .expect("Failed to build device rate limiter"),
     ));
@@ -576,6 +1016,11 @@ async fn main() -> anyhow::Result<()> {
         .route("/auth/:provider/callback", get(auth_callback))
         .route("/api/auth/logout", post(logout))
         .route("/api/auth/refresh", post(refresh_token))
+        .route(
+            "/api/auth/login-challenges/:challenge_id/verify",
+            post(verify_login_challenge),
+        )
+        .route("/auth/end-session", get(end_session))
         // Admin authentication routes
         .route("/auth/admin/:provider", get(auth_admin_provider))
         .route("/auth/admin/:provider/callback", get(auth_admin_callback))
@@ -587,6 +1032,7 @@ async fn main() -> anyhow::Result<()> {
     let device_routes = Router::new()
         .route("/auth/device/code", post(device_code))
         .route("/auth/device/verify", post(device_verify))
+        .route("/auth/bc-authorize", post(backchannel_authorize))
         .route("/auth/token", post(token_exchange))
         .layer(GovernorLayer {
             config: device_rate_limiter_config,
@@ -598,6 +1044,34 @@ async fn main() -> anyhow::Result<()> {
         .route("/.well-known/jwks.json", get(jwks_handler))
         // Public organization creation
         .route("/api/organizations", post(create_organization_public))
+        // Invitation email links, opened signed out
+        .route(INVITATION_ACCEPT_PATH, get(accept_invitation_redirect))
+        // SCIM 2.0 provisioning (authenticated by organization API keys in the handlers)
+        .route(
+            "/scim/v2/:org_slug/ServiceProviderConfig",
+            get(get_service_provider_config),
+        )
+        .route(
+            "/scim/v2/:org_slug/Users",
+            get(list_scim_users).post(create_scim_user),
+        )
+        .route(
+            "/scim/v2/:org_slug/Users/:user_id",
+            get(get_scim_user)
+                .put(replace_scim_user)
+                .patch(patch_scim_user)
+                .delete(delete_scim_user),
+        )
+        .route(
+            "/scim/v2/:org_slug/Groups",
+            get(list_scim_groups).post(create_scim_group),
+        )
+        .route(
+            "/scim/v2/:org_slug/Groups/:group_id",
+            get(get_scim_group)
+                .patch(patch_scim_group)
+                .delete(delete_scim_group),
+        )
         .merge(auth_routes)
         .merge(device_routes);
 
@@ -612,13 +1086,12 @@ async fn main() -> anyhow::Result<()> {
         // Webhook routes (separate state)
         .route("/webhooks/stripe", post(stripe_webhook))
         .with_state(webhook_state)
+        // Request ids for logs and the organization audit log
+        .layer(axum_middleware::from_fn(crate::middleware::assign_request_id))
+        // Proxies whose forwarding headers ClientInfo and the rate limiters believe
+        .layer(Extension(Arc::new(config.trusted_proxies.clone())))
         // CORS
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        );
+        .layer(cors_layer(&config.cors_allowed_origins)?);
 
     // --- More Aggressive WAL Checkpointing ---
     let checkpoint_pool = pool.clone();
@@ -653,6 +1126,8 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("  - POST /auth/device/code");
     tracing::info!("  - GET /activate");
     tracing::info!("  - POST /auth/token");
+    tracing::info!("CIBA endpoints:");
+    tracing::info!("  - POST /auth/bc-authorize");
     tracing::info!("Protected API endpoints:");
     tracing::info!("  - GET /api/user");
     tracing::info!("  - GET /api/subscription");
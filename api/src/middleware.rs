@@ -1,20 +1,31 @@
+use crate::auth::api_tokens::ApiTokenService;
 use crate::auth::jwt::{Claims, JwtService};
-use crate::db::models::{Membership, Organization, User};
+use crate::constants::SESSION_LAST_USED_RESOLUTION_MINUTES;
+use crate::db::models::{ApiToken, Membership, Organization, User};
 use crate::error::{AppError, Result};
 use axum::{
-    extract::{FromRequestParts, Path, Request, State},
-    http::{request::Parts, StatusCode},
+    extract::{ConnectInfo, FromRequestParts, Path, Request, State},
+    http::{request::Parts, HeaderValue, StatusCode},
     middleware::Next,
     response::Response,
 };
+use chrono::{Duration, Utc};
 use serde::Deserialize;
 use sqlx::SqlitePool;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use tower_governor::{key_extractor::KeyExtractor, GovernorError};
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
 /// Extension type for storing authenticated user claims
 #[derive(Clone, Debug)]
 pub struct AuthUser {
     pub claims: Claims,
     pub user: User,
+    pub session_id: String, // session id, or the API token id for token requests
+    pub api_token: Option<ApiToken>, // set when authenticated with a PAT or org API key
 }
 
 #[axum::async_trait]
@@ -33,7 +44,263 @@ where
     }
 }
 
-/// Extract and validate JWT from Authorization header
+/// Reverse proxies whose X-Forwarded-For and X-Real-IP headers are believed.
+/// Requests from any other peer are attributed to the peer address.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Parse a comma-separated list of addresses and CIDR ranges
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        let networks = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (address, prefix) = match entry.split_once('/') {
+                    Some((address, prefix)) => (address, Some(prefix)),
+                    None => (entry, None),
+                };
+                let address: IpAddr = address
+                    .parse()
+                    .map_err(|_| format!("Invalid trusted proxy address: {}", entry))?;
+                let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|prefix| *prefix <= max_prefix)
+                        .ok_or_else(|| format!("Invalid trusted proxy range: {}", entry))?,
+                    None => max_prefix,
+                };
+                Ok((address, prefix))
+            })
+            .collect::<std::result::Result<_, String>>()?;
+
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical_ip(ip);
+        self.networks
+            .iter()
+            .any(|(network, prefix)| match (canonical_ip(*network), ip) {
+                (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                    prefix_matches(u32::from(network).into(), u32::from(ip).into(), *prefix, 32)
+                }
+                (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                    prefix_matches(u128::from(network), u128::from(ip), *prefix, 128)
+                }
+                _ => false,
+            })
+    }
+
+    /// The client address for a request from `peer`: the nearest
+    /// X-Forwarded-For hop that is not one of our proxies, or X-Real-IP, but
+    /// only when the peer itself is a trusted proxy
+    pub fn client_ip(
+        &self,
+        peer: Option<IpAddr>,
+        headers: &axum::http::HeaderMap,
+    ) -> Option<IpAddr> {
+        let peer = peer?;
+        if !self.contains(peer) {
+            return Some(peer);
+        }
+
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        if let Some(forwarded_for) = header("x-forwarded-for") {
+            let hops: Vec<IpAddr> = forwarded_for
+                .split(',')
+                .filter_map(|hop| hop.trim().parse().ok())
+                .collect();
+            if let Some(client) = hops
+                .iter()
+                .rev()
+                .find(|hop| !self.contains(**hop))
+                .or_else(|| hops.first())
+            {
+                return Some(*client);
+            }
+        }
+
+        header("x-real-ip")
+            .and_then(|ip| ip.trim().parse().ok())
+            .or(Some(peer))
+    }
+}
+
+/// IPv4-mapped IPv6 peers (dual-stack listeners) compare as IPv4
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+
+fn prefix_matches(network: u128, ip: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = u32::from(bits - prefix);
+    (network >> shift) == (ip >> shift)
+}
+
+/// Client address of a request, from the peer address and the trusted
+/// proxies installed as a request extension
+fn request_client_ip(
+    extensions: &axum::http::Extensions,
+    headers: &axum::http::HeaderMap,
+) -> Option<IpAddr> {
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    match extensions.get::<Arc<TrustedProxies>>() {
+        Some(proxies) => proxies.client_ip(peer, headers),
+        None => peer,
+    }
+}
+
+/// Rate limiter key: the same client address sessions and audit entries record
+#[derive(Clone, Copy, Debug)]
+pub struct ClientIpKeyExtractor;
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+
+    fn extract<T>(
+        &self,
+        req: &axum::http::Request<T>,
+    ) -> std::result::Result<Self::Key, GovernorError> {
+        request_client_ip(req.extensions(), req.headers()).ok_or(GovernorError::UnableToExtractKey)
+    }
+}
+
+/// Client metadata (IP address and user agent) recorded on sessions
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        // Proxy headers count only from trusted proxies (same as the rate limiter)
+        let ip_address =
+            request_client_ip(&parts.extensions, &parts.headers).map(|ip| ip.to_string());
+
+        Ok(ClientInfo {
+            ip_address,
+            user_agent: header("user-agent"),
+        })
+    }
+}
+
+/// Request id, taken from a well-formed `X-Request-Id` header or generated,
+/// and echoed on the response
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= 128
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+        })
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    let mut response = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Who made an organization-level change and from where, for the
+/// organization audit log
+#[derive(Clone, Debug)]
+pub struct AuditContext {
+    pub actor_id: String,
+    pub impersonator_id: Option<String>,
+    pub api_token_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for AuditContext
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let auth_user = AuthUser::from_request_parts(parts, state).await?;
+        let Ok(client) = ClientInfo::from_request_parts(parts, state).await;
+
+        Ok(AuditContext {
+            actor_id: auth_user.user.id,
+            impersonator_id: auth_user.claims.act.map(|act| act.sub),
+            api_token_id: auth_user.api_token.map(|token| token.id),
+            ip_address: client.ip_address,
+            user_agent: client.user_agent,
+            request_id: parts
+                .extensions
+                .get::<RequestId>()
+                .map(|RequestId(id)| id.clone()),
+        })
+    }
+}
+
+/// Read a cookie value from the request headers
+pub fn read_cookie(headers: &axum::http::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Extract and validate JWT (or API token) from Authorization header
 pub async fn extract_user_from_jwt(
     State((pool, jwt_service)): State<(SqlitePool, Arc<JwtService>)>,
     mut req: Request,
@@ -49,39 +316,62 @@ pub async fn extract_user_from_jwt(
             AppError::Unauthorized("Missing or invalid Authorization header".to_string())
         })?;
 
+    // Personal access tokens and org API keys, limited by their scopes
+    if ApiTokenService::is_api_token(token) {
+        let (api_token, user, claims) = ApiTokenService::authenticate(&pool, token).await?;
+        ApiTokenService::check_method_scope(&api_token, req.method())?;
+
+        req.extensions_mut().insert(AuthUser {
+            claims,
+            user,
+            session_id: api_token.id.clone(),
+            api_token: Some(api_token),
+        });
+
+        return Ok(next.run(req).await);
+    }
+
     // Validate token
     let claims = jwt_service.validate_token(token)?;
 
     // Check if session is still valid (not revoked)
     let token_hash = JwtService::hash_token(token);
-    let session = sqlx::query!(
+    let session_id = sqlx::query_scalar::<_, String>(
         "SELECT id FROM sessions WHERE token_hash = ? AND expires_at > datetime('now')",
-        token_hash
     )
+    .bind(&token_hash)
     .fetch_optional(&pool)
     .await
-    .map_err(AppError::Database)?;
-
-    if session.is_none() {
-        return Err(AppError::Unauthorized(
-            "Session revoked or expired".to_string(),
-        ));
-    }
+    .map_err(AppError::Database)?
+    .ok_or_else(|| AppError::Unauthorized("Session revoked or expired".to_string()))?;
 
-    // Load user from database
-    let user = sqlx::query_as::<_, User>(
-        "SELECT id, email, is_platform_owner, created_at FROM users WHERE id = ?",
+    // Track last activity, throttled so busy clients don't write on every request
+    let now = Utc::now();
+    sqlx::query(
+        "UPDATE sessions SET last_used_at = ?
+         WHERE id = ? AND (last_used_at IS NULL OR last_used_at < ?)",
     )
-    .bind(&claims.sub)
-    .fetch_optional(&pool)
+    .bind(now)
+    .bind(&session_id)
+    .bind(now - Duration::minutes(SESSION_LAST_USED_RESOLUTION_MINUTES))
+    .execute(&pool)
     .await
-    .map_err(AppError::Database)?
-    .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+    .map_err(AppError::Database)?;
+
+    // Load user from database
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(&claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
 
     // Store user in request extensions
     req.extensions_mut().insert(AuthUser {
         claims: claims.clone(),
         user,
+        session_id,
+        api_token: None,
     });
 
     Ok(next.run(req).await)
@@ -104,9 +394,58 @@ pub async fn require_platform_owner(
         ));
     }
 
+    // API tokens only reach platform routes with the explicit `platform` scope
+    if let Some(ref api_token) = auth_user.api_token {
+        if !ApiTokenService::parse_scopes(&api_token.scopes)
+            .iter()
+            .any(|s| s == "platform")
+        {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "API token does not have the platform scope".to_string(),
+            ));
+        }
+    }
+
     Ok(next.run(req).await)
 }
 
+/// Middleware to reject API tokens and impersonation tokens on account and
+/// credential management routes, which need the user's own interactive login
+pub async fn require_interactive_session(
+    req: Request,
+    next: Next,
+) -> std::result::Result<Response, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<AuthUser>()
+        .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string()))?;
+    ensure_interactive_session(auth_user)?;
+
+    Ok(next.run(req).await)
+}
+
+/// Helper function behind `require_interactive_session`, for handlers
+/// mounted outside the interactive route group
+pub fn ensure_interactive_session(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.api_token.is_some() {
+        return Err(AppError::Forbidden(
+            "This endpoint cannot be used with an API token".to_string(),
+        ));
+    }
+    ensure_not_impersonating(auth_user)
+}
+
+/// Helper function to block sensitive actions while impersonating a user
+pub fn ensure_not_impersonating(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.claims.act.is_some() {
+        return Err(AppError::Forbidden(
+            "This action is not allowed while impersonating a user".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// Middleware to require organization membership with specific roles
 #[allow(dead_code)] // Currently unused, kept for future middleware implementation
 pub async fn require_org_member(
@@ -157,8 +496,26 @@ pub async fn check_org_membership(
     .bind(user_id)
     .fetch_optional(pool)
     .await
-    .map_err(AppError::Database)?
-    .ok_or_else(|| AppError::Forbidden("Not a member of this organization".to_string()))?;
+    .map_err(AppError::Database)?;
+
+    // Organization API keys act as a service account that holds the key's
+    // role without being a listed member
+    let membership = match membership {
+        Some(membership) => membership,
+        None => sqlx::query_as::<_, Membership>(
+            "SELECT id, org_id, user_id, role, created_at
+             FROM api_tokens
+             WHERE org_id = ? AND user_id = ? AND kind = 'org'
+               AND revoked_at IS NULL AND expires_at > ?",
+        )
+        .bind(org_id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::Forbidden("Not a member of this organization".to_string()))?,
+    };
 
     if !required_roles.is_empty() && !required_roles.contains(&membership.role.as_str()) {
         return Err(AppError::Forbidden(format!(
@@ -176,9 +533,29 @@ pub async fn check_org_owner(pool: &SqlitePool, user_id: &str, org_id: &str) ->
     Ok(())
 }
 
-/// Helper function to check if user is organization admin or owner
-pub async fn check_org_admin(pool: &SqlitePool, user_id: &str, org_id: &str) -> Result<Membership> {
-    check_org_membership(pool, user_id, org_id, &["owner", "admin"]).await
+/// Helper function to check that a member's role grants an organization permission
+pub async fn check_org_permission(
+    pool: &SqlitePool,
+    user_id: &str,
+    org_id: &str,
+    permission: &str,
+) -> Result<Membership> {
+    let membership = check_org_membership(pool, user_id, org_id, &[]).await?;
+
+    let permissions = crate::auth::org_roles::OrgRoleService::permissions_for_role(
+        pool,
+        org_id,
+        &membership.role,
+    )
+    .await?;
+    if !permissions.iter().any(|p| p == permission) {
+        return Err(AppError::Forbidden(format!(
+            "Requires the '{}' permission",
+            permission
+        )));
+    }
+
+    Ok(membership)
 }
 
 /// Context for organization member operations
@@ -234,42 +611,6 @@ pub async fn extract_org_member_context(
     Ok(next.run(request).await)
 }
 
-/// Middleware to require organization admin or owner role
-#[allow(dead_code)] // Currently unused, kept for future middleware implementation
-pub async fn require_org_admin_or_owner(
-    State(state): State<crate::handlers::auth::AppState>,
-    Path(org_slug): Path<String>,
-    mut req: Request,
-    next: Next,
-) -> std::result::Result<Response, AppError> {
-    // Get authenticated user
-    let auth_user = req
-        .extensions()
-        .get::<AuthUser>()
-        .cloned()
-        .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string()))?;
-
-    // Get organization
-    let org = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = ?")
-        .bind(&org_slug)
-        .fetch_optional(&state.pool)
-        .await
-        .map_err(AppError::Database)?
-        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
-
-    // Check if user is admin or owner
-    check_org_admin(&state.pool, &auth_user.user.id, &org.id).await?;
-
-    // Store context in request extensions
-    req.extensions_mut().insert(OrgMemberContext {
-        org: org.clone(),
-        user: auth_user.user.clone(),
-        membership: check_org_admin(&state.pool, &auth_user.user.id, &org.id).await?,
-    });
-
-    Ok(next.run(req).await)
-}
-
 /// Extractor struct for organization slug path parameter
 #[derive(Deserialize)]
 pub struct OrgSlugParam {
@@ -303,3 +644,61 @@ pub async fn require_active_organization(
 
     Ok(next.run(req).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_trusted_proxy_ranges() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8, 127.0.0.1, fd00::/8").unwrap();
+        assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+        assert!(proxies.contains("127.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("fd12::1".parse().unwrap()));
+        assert!(!proxies.contains("11.0.0.1".parse().unwrap()));
+        assert!(!proxies.contains("127.0.0.2".parse().unwrap()));
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("proxy.internal").is_err());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_headers_from_untrusted_peers() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let spoofed = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "1.2.3.4")]);
+
+        let peer = "203.0.113.9".parse().ok();
+        assert_eq!(proxies.client_ip(peer, &spoofed), peer);
+        assert_eq!(TrustedProxies::default().client_ip(peer, &spoofed), peer);
+    }
+
+    #[test]
+    fn test_client_ip_takes_nearest_untrusted_hop() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let peer = "10.0.0.1".parse().ok();
+
+        // The client can prepend anything; only hops our proxies added count
+        let forwarded = headers(&[("x-forwarded-for", "1.2.3.4, 198.51.100.7, 10.0.0.2")]);
+        assert_eq!(
+            proxies.client_ip(peer, &forwarded),
+            "198.51.100.7".parse().ok()
+        );
+
+        let real_ip = headers(&[("x-real-ip", "198.51.100.7")]);
+        assert_eq!(
+            proxies.client_ip(peer, &real_ip),
+            "198.51.100.7".parse().ok()
+        );
+
+        assert_eq!(proxies.client_ip(peer, &HeaderMap::new()), peer);
+    }
+}
@@ -0,0 +1,214 @@
+pub mod sinks;
+pub mod sources;
+
+use crate::constants::{SIEM_EXPORT_BATCH_SIZE, SIEM_EXPORT_MAX_BATCHES};
+use crate::db::models::SiemSink as SiemSinkRow;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+pub use sinks::build_sink;
+pub use sources::SiemSource;
+
+/// ECS version the exported events follow
+pub const ECS_VERSION: &str = "8.11.0";
+
+/// An exported event. Field names follow the Elastic Common Schema; fields
+/// ECS has no place for live under `sso`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SiemEvent {
+    #[serde(rename = "@timestamp")]
+    pub timestamp: DateTime<Utc>,
+    pub event: EventInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization: Option<IdInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<UserAgentInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpInfo>,
+    pub sso: Value,
+    pub ecs: EcsInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventInfo {
+    pub id: String,
+    pub kind: &'static str, // 'event', or 'alert' for security events
+    pub category: Vec<&'static str>,
+    #[serde(rename = "type")]
+    pub event_type: Vec<&'static str>,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<&'static str>,
+    pub severity: u8, // syslog severity: 6 informational down to 2 critical
+    pub dataset: &'static str,
+    pub module: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserInfo {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<IdInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdInfo {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceInfo {
+    pub ip: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoInfo {
+    pub country_iso_code: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserAgentInfo {
+    pub original: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpInfo {
+    pub request: IdInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EcsInfo {
+    pub version: &'static str,
+}
+
+impl SiemEvent {
+    /// Event sent by the sink test endpoint
+    pub fn test() -> Self {
+        Self {
+            timestamp: Utc::now(),
+            event: EventInfo {
+                id: uuid::Uuid::new_v4().to_string(),
+                kind: "event",
+                category: vec!["configuration"],
+                event_type: vec!["info"],
+                action: "siem_sink_test".to_string(),
+                outcome: Some("success"),
+                severity: 6,
+                dataset: "sso.test",
+                module: "sso",
+                sequence: None,
+            },
+            user: None,
+            organization: None,
+            source: None,
+            user_agent: None,
+            http: None,
+            sso: serde_json::json!({}),
+            ecs: EcsInfo {
+                version: ECS_VERSION,
+            },
+        }
+    }
+}
+
+/// Destination for exported events. The export job goes through this trait
+/// so file, syslog and HTTP sinks are interchangeable.
+pub trait SiemSink: Send + Sync {
+    /// Deliver a batch in order; on error the whole batch is sent again later
+    fn send<'a>(
+        &'a self,
+        events: &'a [SiemEvent],
+    ) -> BoxFuture<'a, std::result::Result<(), String>>;
+}
+
+/// Where a sink has read up to in a source; 0 before its first export
+pub async fn cursor_position(pool: &SqlitePool, sink_id: &str, source: SiemSource) -> Result<i64> {
+    let position = sqlx::query_scalar::<_, i64>(
+        "SELECT position FROM siem_sink_cursors WHERE sink_id = ? AND source = ?",
+    )
+    .bind(sink_id)
+    .bind(source.name())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(position.unwrap_or(0))
+}
+
+pub async fn set_cursor_position(
+    pool: &SqlitePool,
+    sink_id: &str,
+    source: SiemSource,
+    position: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO siem_sink_cursors (sink_id, source, position, updated_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(sink_id, source) DO UPDATE SET
+            position = excluded.position,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(sink_id)
+    .bind(source.name())
+    .bind(position)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Send everything a sink has not seen yet, a bounded number of batches per
+/// source. The cursor only moves after a batch is delivered, so delivery is
+/// at least once. Returns the number of events sent.
+pub async fn export_sink(
+    pool: &SqlitePool,
+    sink: &SiemSinkRow,
+    target: &dyn SiemSink,
+) -> std::result::Result<usize, String> {
+    let mut exported = 0;
+
+    for source in SiemSource::parse_list(&sink.sources) {
+        for _ in 0..SIEM_EXPORT_MAX_BATCHES {
+            let position = cursor_position(pool, &sink.id, source)
+                .await
+                .map_err(|e| e.to_string())?;
+            let batch = source
+                .fetch_after(pool, position, SIEM_EXPORT_BATCH_SIZE)
+                .await
+                .map_err(|e| e.to_string())?;
+            let Some(&(last_position, _)) = batch.last() else {
+                break;
+            };
+
+            let events: Vec<SiemEvent> = batch.into_iter().map(|(_, event)| event).collect();
+            target
+                .send(&events)
+                .await
+                .map_err(|e| format!("{}: {}", source.name(), e))?;
+            set_cursor_position(pool, &sink.id, source, last_position)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            exported += events.len();
+            if (events.len() as i64) < SIEM_EXPORT_BATCH_SIZE {
+                break;
+            }
+        }
+    }
+
+    Ok(exported)
+}
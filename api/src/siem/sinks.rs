@@ -0,0 +1,399 @@
+use super::{SiemEvent, SiemSink};
+use crate::error::{AppError, Result};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// JSON lines written to a file under `SIEM_FILE_DIR`, rotated by size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileSinkConfig {
+    pub filename: String,
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    #[serde(default = "default_max_files")]
+    pub max_files: u32, // rotated files kept next to the live one
+}
+
+fn default_max_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_max_files() -> u32 {
+    5
+}
+
+/// RFC 5424 messages over UDP, or TCP with octet-counting framing (RFC 6587)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyslogSinkConfig {
+    pub host: String,
+    #[serde(default = "default_syslog_port")]
+    pub port: u16,
+    #[serde(default = "default_syslog_protocol")]
+    pub protocol: String, // 'udp' or 'tcp'
+    #[serde(default = "default_facility")]
+    pub facility: u8, // 0-23; 10 is authpriv
+    #[serde(default = "default_app_name")]
+    pub app_name: String,
+    pub hostname: Option<String>, // HOSTNAME field; $HOSTNAME or '-' when unset
+}
+
+fn default_syslog_port() -> u16 {
+    514
+}
+
+fn default_syslog_protocol() -> String {
+    "udp".to_string()
+}
+
+fn default_facility() -> u8 {
+    10
+}
+
+fn default_app_name() -> String {
+    "sso".to_string()
+}
+
+/// Batches POSTed as newline-delimited JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpSinkConfig {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>, // e.g. Authorization for the collector
+    #[serde(default = "default_http_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_http_timeout() -> u64 {
+    10
+}
+
+fn parse_config<T: serde::de::DeserializeOwned>(kind: &str, config: &Value) -> Result<T> {
+    serde_json::from_value(config.clone())
+        .map_err(|e| AppError::BadRequest(format!("Invalid {} sink config: {}", kind, e)))
+}
+
+/// Validate a sink's config and build it. File sinks write inside `file_dir`.
+pub fn build_sink(kind: &str, config: &Value, file_dir: &str) -> Result<Box<dyn SiemSink>> {
+    match kind {
+        "file" => {
+            let config: FileSinkConfig = parse_config(kind, config)?;
+            Ok(Box::new(FileSink::new(file_dir, config)?))
+        }
+        "syslog" => {
+            let config: SyslogSinkConfig = parse_config(kind, config)?;
+            Ok(Box::new(SyslogSink::new(config)?))
+        }
+        "http" => {
+            let config: HttpSinkConfig = parse_config(kind, config)?;
+            Ok(Box::new(HttpSink::new(config)?))
+        }
+        other => Err(AppError::BadRequest(format!(
+            "Unknown SIEM sink kind: {} (expected file, syslog or http)",
+            other
+        ))),
+    }
+}
+
+/// Sink config as shown through the API: HTTP header values are credentials
+pub fn redact_config(kind: &str, config: &Value) -> Value {
+    let mut config = config.clone();
+    if kind == "http" {
+        if let Some(headers) = config.get_mut("headers").and_then(Value::as_object_mut) {
+            for value in headers.values_mut() {
+                *value = Value::String("[redacted]".to_string());
+            }
+        }
+    }
+    config
+}
+
+pub struct FileSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+}
+
+impl FileSink {
+    pub fn new(dir: &str, config: FileSinkConfig) -> Result<Self> {
+        let valid_name = !config.filename.is_empty()
+            && !config.filename.starts_with('.')
+            && config
+                .filename
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid_name {
+            return Err(AppError::BadRequest(
+                "File sink filename may only contain letters, digits, '-', '_' and '.'".to_string(),
+            ));
+        }
+        if config.max_bytes == 0 || config.max_files == 0 {
+            return Err(AppError::BadRequest(
+                "File sink max_bytes and max_files must be positive".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            path: Path::new(dir).join(&config.filename),
+            max_bytes: config.max_bytes,
+            max_files: config.max_files,
+        })
+    }
+
+    fn rotated(&self, index: u32) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// events.jsonl -> events.jsonl.1 -> events.jsonl.2 ...; the oldest is overwritten
+    async fn rotate(&self) -> std::io::Result<()> {
+        for index in (1..self.max_files).rev() {
+            match tokio::fs::rename(self.rotated(index), self.rotated(index + 1)).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        tokio::fs::rename(&self.path, self.rotated(1)).await
+    }
+}
+
+impl SiemSink for FileSink {
+    fn send<'a>(
+        &'a self,
+        events: &'a [SiemEvent],
+    ) -> BoxFuture<'a, std::result::Result<(), String>> {
+        Box::pin(async move {
+            let mut lines = Vec::new();
+            for event in events {
+                serde_json::to_writer(&mut lines, event).map_err(|e| e.to_string())?;
+                lines.push(b'\n');
+            }
+
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            let size = tokio::fs::metadata(&self.path)
+                .await
+                .map(|m| m.len())
+                .unwrap_or(0);
+            if size > 0 && size + lines.len() as u64 > self.max_bytes {
+                self.rotate().await.map_err(|e| e.to_string())?;
+            }
+
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .map_err(|e| e.to_string())?;
+            file.write_all(&lines).await.map_err(|e| e.to_string())?;
+            file.sync_data().await.map_err(|e| e.to_string())
+        })
+    }
+}
+
+pub struct SyslogSink {
+    config: SyslogSinkConfig,
+    hostname: String,
+}
+
+impl SyslogSink {
+    pub fn new(config: SyslogSinkConfig) -> Result<Self> {
+        if config.host.is_empty() {
+            return Err(AppError::BadRequest(
+                "Syslog sink host is required".to_string(),
+            ));
+        }
+        if !matches!(config.protocol.as_str(), "udp" | "tcp") {
+            return Err(AppError::BadRequest(
+                "Syslog sink protocol must be udp or tcp".to_string(),
+            ));
+        }
+        if config.facility > 23 {
+            return Err(AppError::BadRequest(
+                "Syslog facility must be between 0 and 23".to_string(),
+            ));
+        }
+
+        let hostname = config
+            .hostname
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .filter(|h| !h.is_empty())
+            .unwrap_or_else(|| "-".to_string());
+
+        Ok(Self { config, hostname })
+    }
+
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID - MSG`, MSG being the JSON event
+    pub fn format(&self, event: &SiemEvent) -> std::result::Result<String, String> {
+        let json = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        let msgid = event.event.dataset.trim_start_matches("sso.");
+
+        Ok(format!(
+            "<{}>1 {} {} {} {} {} - {}",
+            self.config.facility as u16 * 8 + event.event.severity as u16,
+            event
+                .timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            self.hostname,
+            self.config.app_name,
+            std::process::id(),
+            msgid,
+            json
+        ))
+    }
+
+    async fn send_udp(&self, messages: &[String]) -> std::io::Result<()> {
+        let target = tokio::net::lookup_host((self.config.host.as_str(), self.config.port))
+            .await?
+            .next()
+            .ok_or_else(|| std::io::Error::other("Syslog host did not resolve"))?;
+        let bind = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = tokio::net::UdpSocket::bind(bind).await?;
+        socket.connect(target).await?;
+        for message in messages {
+            socket.send(message.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_tcp(&self, messages: &[String]) -> std::io::Result<()> {
+        let mut stream =
+            tokio::net::TcpStream::connect((self.config.host.as_str(), self.config.port)).await?;
+        for message in messages {
+            let frame = format!("{} {}", message.len(), message);
+            stream.write_all(frame.as_bytes()).await?;
+        }
+        stream.flush().await?;
+        stream.shutdown().await
+    }
+}
+
+impl SiemSink for SyslogSink {
+    fn send<'a>(
+        &'a self,
+        events: &'a [SiemEvent],
+    ) -> BoxFuture<'a, std::result::Result<(), String>> {
+        Box::pin(async move {
+            let messages = events
+                .iter()
+                .map(|event| self.format(event))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            let sent = match self.config.protocol.as_str() {
+                "tcp" => self.send_tcp(&messages).await,
+                _ => self.send_udp(&messages).await,
+            };
+            sent.map_err(|e| {
+                format!(
+                    "syslog {}:{} ({}): {}",
+                    self.config.host, self.config.port, self.config.protocol, e
+                )
+            })
+        })
+    }
+}
+
+pub struct HttpSink {
+    config: HttpSinkConfig,
+    client: reqwest::Client,
+}
+
+impl HttpSink {
+    pub fn new(config: HttpSinkConfig) -> Result<Self> {
+        let url = oauth2::url::Url::parse(&config.url)
+            .map_err(|_| AppError::BadRequest(format!("Invalid HTTP sink URL: {}", config.url)))?;
+        if url.scheme() != "https" && url.scheme() != "http" {
+            return Err(AppError::BadRequest(
+                "HTTP sink URLs must be HTTP(S) URLs".to_string(),
+            ));
+        }
+        for (name, value) in &config.headers {
+            let valid = reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok()
+                && reqwest::header::HeaderValue::from_str(value).is_ok();
+            if !valid {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid HTTP sink header: {}",
+                    name
+                )));
+            }
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout_seconds))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        Ok(Self { config, client })
+    }
+}
+
+impl SiemSink for HttpSink {
+    fn send<'a>(
+        &'a self,
+        events: &'a [SiemEvent],
+    ) -> BoxFuture<'a, std::result::Result<(), String>> {
+        Box::pin(async move {
+            let mut body = Vec::new();
+            for event in events {
+                serde_json::to_writer(&mut body, event).map_err(|e| e.to_string())?;
+                body.push(b'\n');
+            }
+
+            let mut request = self
+                .client
+                .post(&self.config.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson");
+            for (name, value) in &self.config.headers {
+                request = request.header(name, value);
+            }
+
+            let response = request.body(body).send().await.map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("HTTP {}", response.status()));
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syslog_format() {
+        let sink = SyslogSink::new(SyslogSinkConfig {
+            host: "127.0.0.1".to_string(),
+            port: 514,
+            protocol: "udp".to_string(),
+            facility: 10,
+            app_name: "sso".to_string(),
+            hostname: Some("sso-1".to_string()),
+        })
+        .unwrap();
+        let mut event = SiemEvent::test();
+        event.timestamp = "2025-11-09T10:00:00Z".parse().unwrap();
+
+        let message = sink.format(&event).unwrap();
+        let prefix = format!(
+            "<86>1 2025-11-09T10:00:00.000000Z sso-1 sso {} test - {{",
+            std::process::id()
+        );
+        assert!(message.starts_with(&prefix), "{}", message);
+    }
+}
@@ -0,0 +1,425 @@
+use super::{
+    EcsInfo, EventInfo, GeoInfo, HttpInfo, IdInfo, SiemEvent, SourceInfo, UserAgentInfo, UserInfo,
+    ECS_VERSION,
+};
+use crate::db::models::{
+    LoginEvent, LoginFailure, OrganizationAuditLog, PlatformAuditLog, SecurityEvent,
+};
+use crate::error::{AppError, Result};
+use serde_json::{json, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{FromRow, Row, SqlitePool};
+
+/// A table the export pipeline reads, in rowid order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiemSource {
+    PlatformAudit,
+    OrgAudit,
+    Login,
+    LoginFailure,
+    Security,
+}
+
+impl SiemSource {
+    pub const ALL: [SiemSource; 5] = [
+        SiemSource::PlatformAudit,
+        SiemSource::OrgAudit,
+        SiemSource::Login,
+        SiemSource::LoginFailure,
+        SiemSource::Security,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SiemSource::PlatformAudit => "platform_audit",
+            SiemSource::OrgAudit => "org_audit",
+            SiemSource::Login => "login",
+            SiemSource::LoginFailure => "login_failure",
+            SiemSource::Security => "security",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|source| source.name() == name)
+    }
+
+    /// Validate a list of source names into the JSON stored on the sink
+    pub fn to_json(names: &[String]) -> Result<String> {
+        if names.is_empty() {
+            return Err(AppError::BadRequest(
+                "A SIEM sink needs at least one source".to_string(),
+            ));
+        }
+        if let Some(unknown) = names.iter().find(|name| Self::from_name(name).is_none()) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown SIEM source: {} (expected platform_audit, org_audit, login, login_failure or security)",
+                unknown
+            )));
+        }
+        serde_json::to_string(names).map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Sources stored on a sink; unknown names are skipped
+    pub fn parse_list(json: &str) -> Vec<SiemSource> {
+        serde_json::from_str::<Vec<String>>(json)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| Self::from_name(name))
+            .collect()
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            SiemSource::PlatformAudit => "platform_audit_log",
+            SiemSource::OrgAudit => "organization_audit_log",
+            SiemSource::Login => "login_events",
+            SiemSource::LoginFailure => "login_failures",
+            SiemSource::Security => "security_events",
+        }
+    }
+
+    /// Highest rowid in the source, for sinks that start from now
+    pub async fn head(&self, pool: &SqlitePool) -> Result<i64> {
+        let head = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COALESCE(MAX(rowid), 0) FROM {}",
+            self.table()
+        ))
+        .fetch_one(pool)
+        .await?;
+
+        Ok(head)
+    }
+
+    /// Up to `limit` events after `position`, with the rowid of each
+    pub async fn fetch_after(
+        &self,
+        pool: &SqlitePool,
+        position: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, SiemEvent)>> {
+        let query = match self {
+            SiemSource::Login => "SELECT l.rowid AS export_position, l.*, s.org_id AS export_org_id
+                 FROM login_events l
+                 LEFT JOIN services s ON s.id = l.service_id
+                 WHERE l.rowid > ? ORDER BY l.rowid ASC LIMIT ?"
+                .to_string(),
+            _ => format!(
+                "SELECT rowid AS export_position, * FROM {}
+                 WHERE rowid > ? ORDER BY rowid ASC LIMIT ?",
+                self.table()
+            ),
+        };
+        let rows = sqlx::query(&query)
+            .bind(position)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| Ok((row.try_get("export_position")?, self.map_row(row)?)))
+            .collect()
+    }
+
+    fn map_row(&self, row: &SqliteRow) -> Result<SiemEvent> {
+        Ok(match self {
+            SiemSource::PlatformAudit => platform_audit_event(PlatformAuditLog::from_row(row)?),
+            SiemSource::OrgAudit => org_audit_event(OrganizationAuditLog::from_row(row)?),
+            SiemSource::Login => {
+                login_event(LoginEvent::from_row(row)?, row.try_get("export_org_id")?)
+            }
+            SiemSource::LoginFailure => login_failure_event(LoginFailure::from_row(row)?),
+            SiemSource::Security => security_event(SecurityEvent::from_row(row)?),
+        })
+    }
+}
+
+fn parse_json(value: Option<&str>) -> Value {
+    value
+        .and_then(|v| serde_json::from_str(v).ok())
+        .unwrap_or(Value::Null)
+}
+
+fn client_fields(
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+) -> (Option<SourceInfo>, Option<UserAgentInfo>) {
+    (
+        ip_address.map(|ip| SourceInfo { ip, geo: None }),
+        user_agent.map(|original| UserAgentInfo { original }),
+    )
+}
+
+fn event_info(
+    id: String,
+    action: String,
+    dataset: &'static str,
+    category: &'static str,
+    event_type: &'static str,
+) -> EventInfo {
+    EventInfo {
+        id,
+        kind: "event",
+        category: vec![category],
+        event_type: vec![event_type],
+        action,
+        outcome: Some("success"),
+        severity: 6,
+        dataset,
+        module: "sso",
+        sequence: None,
+    }
+}
+
+/// Creations, deletions or changes by action name. Organization actions end
+/// in a past participle (`group_created`); platform ones start with a verb
+/// (`create_siem_sink`).
+fn change_type(action: &str) -> &'static str {
+    if action.ends_with("_created") || action.ends_with("_added") || action.starts_with("create_") {
+        "creation"
+    } else if action.ends_with("_deleted")
+        || action.ends_with("_removed")
+        || action.starts_with("delete_")
+    {
+        "deletion"
+    } else {
+        "change"
+    }
+}
+
+fn platform_audit_event(log: PlatformAuditLog) -> SiemEvent {
+    let mut event = event_info(
+        log.id,
+        log.action.clone(),
+        "sso.platform_audit",
+        "configuration",
+        change_type(&log.action),
+    );
+    event.sequence = log.seq;
+    let target = (log.target_type == "user").then(|| IdInfo {
+        id: log.target_id.clone(),
+    });
+
+    SiemEvent {
+        timestamp: log.created_at,
+        event,
+        user: Some(UserInfo {
+            id: log.platform_owner_id,
+            target,
+        }),
+        organization: (log.target_type == "organization").then(|| IdInfo {
+            id: log.target_id.clone(),
+        }),
+        source: None,
+        user_agent: None,
+        http: None,
+        sso: json!({
+            "target_type": log.target_type,
+            "target_id": log.target_id,
+            "metadata": parse_json(log.metadata.as_deref()),
+            "entry_hash": log.entry_hash,
+        }),
+        ecs: EcsInfo {
+            version: ECS_VERSION,
+        },
+    }
+}
+
+fn org_audit_event(log: OrganizationAuditLog) -> SiemEvent {
+    let mut event = event_info(
+        log.id,
+        log.action.clone(),
+        "sso.org_audit",
+        "configuration",
+        change_type(&log.action),
+    );
+    event.sequence = log.seq;
+    let target = matches!(log.target_type.as_str(), "user" | "member").then(|| IdInfo {
+        id: log.target_id.clone(),
+    });
+    let (source, user_agent) = client_fields(log.ip_address, log.user_agent);
+
+    SiemEvent {
+        timestamp: log.created_at,
+        event,
+        user: Some(UserInfo {
+            id: log.actor_id,
+            target,
+        }),
+        organization: Some(IdInfo { id: log.org_id }),
+        source,
+        user_agent,
+        http: log.request_id.map(|id| HttpInfo {
+            request: IdInfo { id },
+        }),
+        sso: json!({
+            "target_type": log.target_type,
+            "target_id": log.target_id,
+            "changes": parse_json(log.changes.as_deref()),
+            "impersonator_id": log.impersonator_id,
+            "api_token_id": log.api_token_id,
+            "entry_hash": log.entry_hash,
+        }),
+        ecs: EcsInfo {
+            version: ECS_VERSION,
+        },
+    }
+}
+
+fn login_event(login: LoginEvent, org_id: Option<String>) -> SiemEvent {
+    let (mut source, user_agent) = client_fields(login.ip_address, login.user_agent);
+    if let (Some(source), Some(country)) = (source.as_mut(), login.country) {
+        source.geo = Some(GeoInfo {
+            country_iso_code: country,
+        });
+    }
+    let mut event = event_info(
+        login.id,
+        "login".to_string(),
+        "sso.login",
+        "authentication",
+        "start",
+    );
+    // A challenged login that is completed gets a separate 'success' row
+    event.outcome = Some(match login.outcome.as_str() {
+        "success" => "success",
+        "blocked" => "failure",
+        _ => "unknown",
+    });
+    if login.outcome == "blocked" {
+        event.severity = 4;
+    }
+
+    SiemEvent {
+        timestamp: login.created_at,
+        event,
+        user: Some(UserInfo {
+            id: login.user_id,
+            target: None,
+        }),
+        organization: org_id.map(|id| IdInfo { id }),
+        source,
+        user_agent,
+        http: None,
+        sso: json!({
+            "service_id": login.service_id,
+            "provider": login.provider,
+            "outcome": login.outcome,
+            "risk_score": login.risk_score,
+            "risk_reasons": parse_json(login.risk_reasons.as_deref()),
+            "risk_action": login.risk_action,
+            "device_fingerprint": login.device_fingerprint,
+        }),
+        ecs: EcsInfo {
+            version: ECS_VERSION,
+        },
+    }
+}
+
+fn login_failure_event(failure: LoginFailure) -> SiemEvent {
+    let (source, user_agent) = client_fields(failure.ip_address, failure.user_agent);
+    let mut event = event_info(
+        failure.id,
+        "login_failed".to_string(),
+        "sso.login",
+        "authentication",
+        "start",
+    );
+    event.outcome = Some("failure");
+    event.severity = 5;
+
+    SiemEvent {
+        timestamp: failure.created_at,
+        event,
+        user: failure.user_id.map(|id| UserInfo { id, target: None }),
+        organization: failure.org_id.map(|id| IdInfo { id }),
+        source,
+        user_agent,
+        http: None,
+        sso: json!({
+            "flow": failure.flow,
+            "reason": failure.reason,
+            "service_id": failure.service_id,
+            "provider": failure.provider,
+            "provider_user_id": failure.provider_user_id,
+            "detail": failure.detail,
+        }),
+        ecs: EcsInfo {
+            version: ECS_VERSION,
+        },
+    }
+}
+
+fn security_event(security: SecurityEvent) -> SiemEvent {
+    let mut event = event_info(
+        security.id,
+        security.event_type,
+        "sso.security",
+        "threat",
+        "indicator",
+    );
+    event.kind = "alert";
+    event.outcome = None;
+    event.severity = match security.severity.as_str() {
+        "high" => 2,
+        "medium" => 4,
+        _ => 5,
+    };
+    let (source, user_agent) = client_fields(security.ip_address, security.user_agent);
+
+    SiemEvent {
+        timestamp: security.created_at,
+        event,
+        user: security.user_id.map(|id| UserInfo { id, target: None }),
+        organization: security.org_id.map(|id| IdInfo { id }),
+        source,
+        user_agent,
+        http: None,
+        sso: json!({
+            "severity": security.severity,
+            "details": parse_json(security.details.as_deref()),
+        }),
+        ecs: EcsInfo {
+            version: ECS_VERSION,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_org_audit_event_schema() {
+        let log = OrganizationAuditLog {
+            id: "log-1".to_string(),
+            org_id: "org-1".to_string(),
+            actor_id: "user-1".to_string(),
+            impersonator_id: None,
+            api_token_id: None,
+            action: "member_removed".to_string(),
+            target_type: "member".to_string(),
+            target_id: "user-2".to_string(),
+            changes: Some(r#"{"role":{"before":"admin","after":null}}"#.to_string()),
+            ip_address: Some("203.0.113.7".to_string()),
+            user_agent: None,
+            request_id: Some("req-1".to_string()),
+            created_at: "2025-11-09T10:00:00Z".parse().unwrap(),
+            seq: Some(7),
+            prev_hash: None,
+            entry_hash: Some("abc".to_string()),
+        };
+
+        let event = serde_json::to_value(org_audit_event(log)).unwrap();
+        assert_eq!(event["@timestamp"], "2025-11-09T10:00:00Z");
+        assert_eq!(event["event"]["dataset"], "sso.org_audit");
+        assert_eq!(event["event"]["type"], json!(["deletion"]));
+        assert_eq!(event["event"]["sequence"], 7);
+        assert_eq!(event["user"]["id"], "user-1");
+        assert_eq!(event["user"]["target"]["id"], "user-2");
+        assert_eq!(event["organization"]["id"], "org-1");
+        assert_eq!(event["source"]["ip"], "203.0.113.7");
+        assert_eq!(event["http"]["request"]["id"], "req-1");
+        assert_eq!(event["sso"]["changes"]["role"]["before"], "admin");
+        assert!(event.get("user_agent").is_none());
+    }
+}
@@ -9,6 +9,7 @@ interface RequestOptions {
   body?: any;
   params?: Record<string, any>;
   timeout?: number;
+  credentials?: 'omit' | 'same-origin' | 'include';
 }
 
 /**
@@ -100,6 +101,7 @@ export class HttpClient {
         headers,
         body: options.body ? JSON.stringify(options.body) : undefined,
         signal: controller.signal,
+        credentials: options.credentials,
       });
 
       clearTimeout(timeoutId);
@@ -188,12 +190,13 @@ export class HttpClient {
   public async post<T = any>(
     path: string,
     data?: any,
-    config?: { headers?: Record<string, string> }
+    config?: { headers?: Record<string, string>; credentials?: 'omit' | 'same-origin' | 'include' }
   ): Promise<HttpResponse<T>> {
     return this.request<T>(path, {
       method: 'POST',
       body: data,
       headers: config?.headers,
+      credentials: config?.credentials,
     });
   }
 
@@ -26,6 +26,9 @@ class IdentitiesModule {
   /**
    * Start linking a new social account to the authenticated user.
    * Returns an authorization URL that the user should be redirected to.
+   * The request is sent with credentials so the API can set the cookie that ties
+   * the callback to this browser; the API must list your app's origin in
+   * `CORS_ALLOWED_ORIGINS`.
    *
    * @param provider The OAuth provider to link (e.g., 'github', 'google', 'microsoft')
    * @returns Object containing the authorization URL
@@ -37,7 +40,11 @@ class IdentitiesModule {
    * ```
    */
   public async startLink(provider: string): Promise<StartLinkResponse> {
-    const response = await this.http.post<StartLinkResponse>(`/api/user/identities/${provider}/link`, {});
+    const response = await this.http.post<StartLinkResponse>(
+      `/api/user/identities/${provider}/link`,
+      {},
+      { credentials: 'include' }
+    );
     return response.data;
   }
 
@@ -86,6 +93,7 @@ export class UserModule {
 
   /**
    * Update the authenticated user's profile.
+   * A new email must be one a linked provider has verified for the user.
    *
    * @param payload Update payload
    * @returns Updated user profile